@@ -0,0 +1,29 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use rs64_rom::{calculate_cart_checksum_from_reader, calculate_cart_checksum_with, CicVariant, ROM_LEN};
+
+fn test_image() -> Vec<u8> {
+    (0..ROM_LEN).map(|ii| (ii * 7 + 3) as u8).collect()
+}
+
+fn bench_checksum(c: &mut Criterion) {
+    let image = test_image();
+
+    let mut group = c.benchmark_group("checksum");
+    group.throughput(Throughput::Bytes(ROM_LEN as u64));
+
+    group.bench_function("6102", |b| b.iter(|| {
+        calculate_cart_checksum_with(CicVariant::Cic6102, &image).unwrap()
+    }));
+    group.bench_function("6105", |b| b.iter(|| {
+        calculate_cart_checksum_with(CicVariant::Cic6105, &image).unwrap()
+    }));
+    group.bench_function("6102_reader", |b| b.iter(|| {
+        calculate_cart_checksum_from_reader(CicVariant::Cic6102, &mut &image[..]).unwrap()
+    }));
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_checksum);
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use rs64_rom::{convert_swapping, ByteSwapping};
+
+const IMAGE_LEN: usize = 8 * 1024 * 1024;
+
+fn bytewise_u16(buffer: &mut [u8]) {
+    for half in buffer.chunks_exact_mut(2) {
+        half.swap(0, 1);
+    }
+}
+
+fn bench_swap(c: &mut Criterion) {
+    let mut buffer = vec![0x5au8; IMAGE_LEN];
+
+    let mut group = c.benchmark_group("swap");
+    group.throughput(Throughput::Bytes(IMAGE_LEN as u64));
+
+    group.bench_function("bytewise_u16", |b| b.iter(|| bytewise_u16(&mut buffer)));
+    group.bench_function("u16", |b| b.iter(|| {
+        convert_swapping(ByteSwapping::Native, ByteSwapping::U16LittleEndian, &mut buffer).unwrap()
+    }));
+    group.bench_function("u32", |b| b.iter(|| {
+        convert_swapping(ByteSwapping::Native, ByteSwapping::U32LittleEndian, &mut buffer).unwrap()
+    }));
+    group.bench_function("u16_to_u32", |b| b.iter(|| {
+        convert_swapping(ByteSwapping::U16LittleEndian, ByteSwapping::U32LittleEndian, &mut buffer).unwrap()
+    }));
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_swap);
+criterion_main!(benches);
@@ -0,0 +1,130 @@
+#ifndef RS64_ROM_H
+#define RS64_ROM_H
+
+/* Generated by cbindgen from src/capi.rs; do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define RS64_OK 0
+
+/**
+ * A required pointer was null.
+ */
+#define RS64_ERR_NULL -1
+
+/**
+ * The image's byte order couldn't be determined.
+ */
+#define RS64_ERR_UNKNOWN_SWAPPING -2
+
+/**
+ * The image isn't a whole number of swapping units.
+ */
+#define RS64_ERR_UNALIGNED -3
+
+/**
+ * The image is too short for the operation.
+ */
+#define RS64_ERR_TOO_SHORT -4
+
+/**
+ * An argument was out of range.
+ */
+#define RS64_ERR_INVALID_ARGUMENT -5
+
+#define RS64_SWAP_NATIVE 0
+
+#define RS64_SWAP_U16_LITTLE_ENDIAN 1
+
+#define RS64_SWAP_U32_LITTLE_ENDIAN 2
+
+/**
+ * Passed as the CIC to checksum functions to use the one the bootcode
+ * implies, falling back to 6102.
+ */
+#define RS64_CIC_AUTO -1
+
+#define RS64_CIC_6101 0
+
+#define RS64_CIC_6102 1
+
+#define RS64_CIC_6103 2
+
+#define RS64_CIC_6105 3
+
+#define RS64_CIC_6106 4
+
+#define RS64_CIC_7102 5
+
+#define RS64_HEADER_NAME_LEN 20
+
+/**
+ * The cart header with its fields decoded to host integers.
+ */
+typedef struct Rs64RomHeader {
+  uint32_t cart_timing;
+  uint32_t clock_rate;
+  uint32_t load_addr;
+  uint32_t release;
+  uint32_t crc1;
+  uint32_t crc2;
+  uint32_t rsvd_18;
+  uint32_t rsvd_1c;
+  uint8_t name[RS64_HEADER_NAME_LEN];
+  uint32_t rsvd_34;
+  uint32_t manuf_id;
+  uint16_t cart_id;
+  uint16_t country_code;
+} Rs64RomHeader;
+
+/**
+ * Returns the byte order of the image as an `RS64_SWAP_*` value, or
+ * `RS64_ERR_UNKNOWN_SWAPPING`.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable bytes.
+ */
+int32_t rs64_detect_swapping(const uint8_t *data, size_t len);
+
+/**
+ * Converts the image in place to the `RS64_SWAP_*` order `to`.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` writable bytes.
+ */
+int32_t rs64_convert(uint8_t *data, size_t len, int32_t to);
+
+/**
+ * Calculates the CRC pair of a native byte order image for the `RS64_CIC_*`
+ * variant `cic`, storing it through `crc1` and `crc2`.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable bytes, and `crc1` and `crc2` must be
+ * writable.
+ */
+int32_t rs64_checksum(const uint8_t *data, size_t len, int32_t cic, uint32_t *crc1, uint32_t *crc2);
+
+/**
+ * Recalculates the image's CRCs for the CIC its bootcode implies and writes
+ * them into its header, leaving the byte order as it was.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` writable bytes.
+ */
+int32_t rs64_fix_crc(uint8_t *data, size_t len);
+
+/**
+ * Decodes the image's header into `header`, whose fields are host integers.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable bytes, and `header` must be writable.
+ */
+int32_t rs64_parse_header(const uint8_t *data, size_t len, struct Rs64RomHeader *header);
+
+#endif  /* RS64_ROM_H */
@@ -0,0 +1,166 @@
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use zip::ZipArchive;
+
+use crate::{analyze, resolve_swapping, Error, RomInfo};
+
+/// Extensions taken as images without looking inside, matched ignoring case.
+const ROM_EXTENSIONS: [&str; 5] = ["z64", "v64", "n64", "rom", "u64"];
+
+// Entries larger than the biggest cart plus generous headroom for overdumps
+// aren't worth inflating to check.
+const MAX_ENTRY_LEN: u64 = 128 * 1024 * 1024;
+
+/// A zip archive of images, read without extracting it to disk.
+///
+/// Only zip is supported; 7z archives need to be converted or extracted
+/// first.
+pub struct RomArchive<R> {
+    zip: ZipArchive<R>,
+}
+
+impl RomArchive<File> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<RomArchive<File>, Error> {
+        RomArchive::new(File::open(path)?)
+    }
+}
+
+impl<R: Read + Seek> RomArchive<R> {
+    pub fn new(reader: R) -> Result<RomArchive<R>, Error> {
+        Ok(RomArchive { zip: ZipArchive::new(reader)? })
+    }
+
+    /// Total number of entries, images or not.
+    pub fn entry_count(&self) -> usize {
+        self.zip.len()
+    }
+
+    /// Iterates the entries that are images: those with an image extension,
+    /// plus any other file that starts with a recognizable header.  Each
+    /// yields its name and contents, in the order stored, with read errors
+    /// reported per entry.
+    pub fn roms(&mut self) -> Roms<'_, R> {
+        Roms { archive: self, index: 0 }
+    }
+
+    /// Runs [`analyze`] over every image entry.
+    pub fn analyze_all(&mut self) -> Vec<(String, Result<RomInfo, Error>)> {
+        self.roms()
+            .map(|(name, data)| {
+                let info = data.and_then(|data| analyze(&data));
+                (name, info)
+            })
+            .collect()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.zip.into_inner()
+    }
+
+    // The entry's name and contents if it's an image, `None` if it isn't.
+    fn read_rom(&mut self, index: usize) -> Option<(String, Result<Vec<u8>, Error>)> {
+        let name = self.zip.name_for_index(index).unwrap_or_default().to_string();
+        let mut entry = match self.zip.by_index(index) {
+            Ok(entry) => entry,
+            Err(err) => return Some((name, Err(err.into()))),
+        };
+        if entry.is_dir() || entry.size() > MAX_ENTRY_LEN {
+            return None;
+        }
+
+        let named_rom = Path::new(&name).extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| ROM_EXTENSIONS.iter().any(|rom| rom.eq_ignore_ascii_case(extension)));
+
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        if let Err(err) = entry.read_to_end(&mut data) {
+            return Some((name, Err(err.into())));
+        }
+
+        if named_rom || resolve_swapping(&data).is_some() {
+            Some((name, Ok(data)))
+        } else {
+            None
+        }
+    }
+}
+
+/// The iterator [`RomArchive::roms`] returns.
+pub struct Roms<'a, R> {
+    archive: &'a mut RomArchive<R>,
+    index: usize,
+}
+
+impl<'a, R: Read + Seek> Iterator for Roms<'a, R> {
+    type Item = (String, Result<Vec<u8>, Error>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.archive.zip.len() {
+            let index = self.index;
+            self.index += 1;
+
+            if let Some(rom) = self.archive.read_rom(index) {
+                return Some(rom);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{Cursor, Write};
+
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    use crate::tests::test_cart;
+    use crate::{swap_cart_to, ByteSwapping};
+
+    fn build_zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        writer.add_directory("saves/", options).unwrap();
+        for (name, data) in entries {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn finds_roms_by_extension_and_content() {
+        let native = test_cart();
+        let mut swapped = native.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut swapped).unwrap();
+
+        let zip = build_zip(&[
+            ("Game (USA).v64", &swapped),
+            ("readme.txt", b"not a rom"),
+            ("dump.bin", &native),
+            ("broken.z64", &[0u8; 32]),
+        ]);
+
+        let mut archive = RomArchive::new(Cursor::new(zip)).unwrap();
+        assert_eq!(archive.entry_count(), 5);
+
+        let roms: Vec<_> = archive.roms().collect();
+        let names: Vec<&str> = roms.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["Game (USA).v64", "dump.bin", "broken.z64"]);
+        assert_eq!(roms[0].1.as_ref().unwrap(), &swapped);
+
+        let infos = archive.analyze_all();
+        assert_eq!(infos[0].1.as_ref().unwrap().swapping.swapping, ByteSwapping::U16LittleEndian);
+        assert_eq!(infos[1].1.as_ref().unwrap().swapping.swapping, ByteSwapping::Native);
+        assert!(infos[2].1.is_err());
+    }
+
+    #[test]
+    fn rejects_non_zip_data() {
+        assert!(matches!(RomArchive::new(Cursor::new(test_cart())), Err(Error::InvalidArchive(_))));
+    }
+}
@@ -0,0 +1,167 @@
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::checksum::CHECKSUM_END;
+use crate::{
+    convert_swapping, resolve_swapping, ByteSwapping, CartChecksum, ChecksumError, CicVariant, Error, RomHeader,
+    HEADER_LEN, SWAP_CHUNK_LEN,
+};
+
+impl RomHeader {
+    /// Async version of [`RomHeader::deserialize`].
+    pub async fn deserialize_async<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<RomHeader> {
+        let mut bytes = [0u8; HEADER_LEN as usize];
+        reader.read_exact(&mut bytes).await?;
+        Ok(RomHeader::from_bytes(&bytes))
+    }
+
+    /// Async version of [`RomHeader::serialize`].
+    pub async fn serialize_async<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_bytes()).await
+    }
+}
+
+/// Async version of [`swap_stream_to`](crate::swap_stream_to).
+pub async fn swap_stream_to_async<R, W>(new_swapping: ByteSwapping, reader: &mut R, writer: &mut W) -> Result<u64, Error>
+    where R: AsyncRead + Unpin,
+          W: AsyncWrite + Unpin,
+{
+    let mut chunk = vec![0u8; SWAP_CHUNK_LEN];
+    let len = fill_chunk_async(reader, &mut chunk).await?;
+
+    let original_swapping = match resolve_swapping(&chunk[..len]) {
+        Some(swapping) => swapping,
+        None => {
+            return Err(Error::UnknownByteSwapping);
+        },
+    };
+
+    convert_chunks_async(original_swapping, new_swapping, chunk, len, reader, writer).await
+}
+
+/// Async version of [`convert_stream`](crate::convert_stream).
+pub async fn convert_stream_async<R, W>(from: ByteSwapping, to: ByteSwapping, reader: &mut R, writer: &mut W) -> Result<u64, Error>
+    where R: AsyncRead + Unpin,
+          W: AsyncWrite + Unpin,
+{
+    let mut chunk = vec![0u8; SWAP_CHUNK_LEN];
+    let len = fill_chunk_async(reader, &mut chunk).await?;
+
+    convert_chunks_async(from, to, chunk, len, reader, writer).await
+}
+
+/// Async version of
+/// [`calculate_cart_checksum_from_reader`](crate::calculate_cart_checksum_from_reader):
+/// reads exactly the checksummed region of a native byte order image.
+pub async fn calculate_cart_checksum_from_async_reader<R: AsyncRead + Unpin>(variant: CicVariant, reader: &mut R)
+                                                                              -> Result<(u32, u32), Error> {
+    let mut digest = CartChecksum::new(variant);
+    let mut chunk = [0u8; 4096];
+    let mut remaining = CHECKSUM_END;
+
+    while remaining != 0 {
+        let want = core::cmp::min(remaining, chunk.len());
+        let count = reader.read(&mut chunk[..want]).await?;
+        if count == 0 {
+            return Err(ChecksumError::ErrorReadingBuffer { offset: CHECKSUM_END - remaining }.into());
+        }
+        digest.update(&chunk[..count]);
+        remaining -= count;
+    }
+
+    Ok(digest.finalize()?)
+}
+
+async fn convert_chunks_async<R, W>(from: ByteSwapping, to: ByteSwapping, mut chunk: Vec<u8>, mut len: usize,
+                                    reader: &mut R, writer: &mut W) -> Result<u64, Error>
+    where R: AsyncRead + Unpin,
+          W: AsyncWrite + Unpin,
+{
+    let mut total = 0u64;
+
+    while len != 0 {
+        convert_swapping(from, to, &mut chunk[..len])?;
+        writer.write_all(&chunk[..len]).await?;
+        total += len as u64;
+
+        len = fill_chunk_async(reader, &mut chunk).await?;
+    }
+
+    writer.flush().await?;
+    Ok(total)
+}
+
+// Same as `fill_chunk`: short reads must never split a swapping unit.
+async fn fill_chunk_async<R: AsyncRead + Unpin>(reader: &mut R, chunk: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < chunk.len() {
+        match reader.read(&mut chunk[len..]).await {
+            Ok(0) => break,
+            Ok(count) => len += count,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {},
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+    use crate::{calculate_cart_checksum_with, swap_cart_to};
+
+    fn block_on<F: core::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn round_trips_headers() {
+        let mut header = RomHeader::new();
+        header.load_addr = 0x80000400;
+        header.set_name("ASYNC").unwrap();
+
+        let mut bytes = Vec::new();
+        block_on(header.serialize_async(&mut bytes)).unwrap();
+        assert_eq!(bytes, header.to_bytes());
+
+        let parsed = block_on(RomHeader::deserialize_async(&mut &bytes[..])).unwrap();
+        assert_eq!(parsed.to_bytes(), header.to_bytes());
+        assert!(block_on(RomHeader::deserialize_async(&mut &bytes[..10])).is_err());
+    }
+
+    #[test]
+    fn swaps_streams() {
+        let native = test_cart();
+        let mut swapped = native.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut swapped).unwrap();
+
+        let mut out = Vec::new();
+        let written = block_on(swap_stream_to_async(ByteSwapping::Native, &mut &swapped[..], &mut out)).unwrap();
+        assert_eq!(written, native.len() as u64);
+        assert_eq!(out, native);
+
+        let mut out = Vec::new();
+        block_on(convert_stream_async(ByteSwapping::Native, ByteSwapping::U16LittleEndian, &mut &native[..], &mut out)).unwrap();
+        assert_eq!(out, swapped);
+
+        let blank = vec![0u8; 4096];
+        assert!(matches!(block_on(swap_stream_to_async(ByteSwapping::Native, &mut &blank[..], &mut Vec::new())),
+                         Err(Error::UnknownByteSwapping)));
+    }
+
+    #[test]
+    fn checksums_streams() {
+        let cart = test_cart();
+        for variant in CicVariant::ALL.iter() {
+            assert_eq!(block_on(calculate_cart_checksum_from_async_reader(*variant, &mut &cart[..])).unwrap(),
+                       calculate_cart_checksum_with(*variant, &cart).unwrap());
+        }
+
+        let short = &cart[..CHECKSUM_END - 1];
+        assert!(matches!(block_on(calculate_cart_checksum_from_async_reader(CicVariant::Cic6102, &mut &short[..])),
+                         Err(Error::Checksum(ChecksumError::ErrorReadingBuffer { offset: 0x100fff }))));
+    }
+}
@@ -0,0 +1,284 @@
+//! Analyzing whole collections of images at once.
+//!
+//! [`scan`] walks files and directories and yields a [`RomInfo`] for every
+//! image it finds, each paired with its path and carrying its own error, so
+//! one unreadable or corrupt file never stops the rest.  [`scan_with`] does
+//! the same through a callback and, with the `rayon` feature and
+//! [`ScanOptions::parallel`] set, analyzes images on the rayon thread pool.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{analyze, Error, RomInfo};
+
+/// How [`scan`] and [`scan_with`] pick the files to analyze.
+#[derive(Clone, Debug)]
+pub struct ScanOptions {
+    /// Descend into subdirectories of the directories given.
+    pub recursive: bool,
+    /// Extensions, without the dot and matched ignoring case, of the files
+    /// to pick up while walking a directory.  Files named directly are
+    /// always analyzed.  Empty picks up every file.
+    pub extensions: Vec<String>,
+    /// Analyze images on the rayon thread pool in [`scan_with`].  Results
+    /// then arrive in whatever order they finish.
+    #[cfg(feature = "rayon")]
+    pub parallel: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> ScanOptions {
+        ScanOptions {
+            recursive: true,
+            extensions: vec!["z64".into(), "v64".into(), "n64".into()],
+            #[cfg(feature = "rayon")]
+            parallel: false,
+        }
+    }
+}
+
+/// Lazily analyzes every image under `paths`, in path order with each
+/// directory's entries sorted by name.  Symlinked directories aren't
+/// followed.
+pub fn scan<I>(paths: I, options: &ScanOptions) -> Scan
+    where I: IntoIterator<Item = PathBuf>
+{
+    Scan { walk: Walk::new(paths, options) }
+}
+
+/// Analyzes every image under `paths`, handing each result to `callback` as
+/// it's ready.
+pub fn scan_with<I, F>(paths: I, options: &ScanOptions, mut callback: F)
+    where I: IntoIterator<Item = PathBuf>,
+          F: FnMut(PathBuf, Result<RomInfo, Error>),
+{
+    #[cfg(feature = "rayon")]
+    {
+        if options.parallel {
+            return scan_parallel(Walk::new(paths, options), callback);
+        }
+    }
+
+    for (path, result) in scan(paths, options) {
+        callback(path, result);
+    }
+}
+
+// Walks on the calling thread, analyzes on the pool, and runs the callback
+// back on the calling thread as results arrive.
+#[cfg(feature = "rayon")]
+fn scan_parallel<F>(walk: Walk, mut callback: F)
+    where F: FnMut(PathBuf, Result<RomInfo, Error>)
+{
+    use rayon::prelude::*;
+    use std::sync::mpsc;
+
+    let mut files = Vec::new();
+    for found in walk {
+        match found {
+            Ok(path) => files.push(path),
+            Err((path, err)) => callback(path, Err(err)),
+        }
+    }
+
+    log_debug!("analyzing {} files in parallel", files.len());
+    let (sender, receiver) = mpsc::channel();
+    std::thread::scope(|scope| {
+        scope.spawn(move || {
+            files.into_par_iter().for_each_with(sender, |sender, path| {
+                let result = analyze_path(&path);
+                // The receiver outlives every sender.
+                sender.send((path, result)).unwrap();
+            });
+        });
+
+        for (path, result) in receiver {
+            callback(path, result);
+        }
+    });
+}
+
+/// The iterator [`scan`] returns.
+pub struct Scan {
+    walk: Walk,
+}
+
+impl Iterator for Scan {
+    type Item = (PathBuf, Result<RomInfo, Error>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.walk.next()? {
+            Ok(path) => {
+                let result = analyze_path(&path);
+                Some((path, result))
+            },
+            Err((path, err)) => Some((path, Err(err))),
+        }
+    }
+}
+
+fn analyze_path(path: &Path) -> Result<RomInfo, Error> {
+    let image = fs::read(path)?;
+    let result = analyze(&image);
+    match result {
+        Ok(ref info) => log_debug!("analyzed {} bytes of {}: {}", info.size, path.display(), info.header.game_id()),
+        Err(ref err) => log_debug!("couldn't analyze {}: {}", path.display(), err),
+    }
+    result
+}
+
+// Depth first walk yielding the files to analyze, or the directories that
+// couldn't be read.
+struct Walk {
+    pending: VecDeque<Pending>,
+    recursive: bool,
+    extensions: Vec<String>,
+}
+
+enum Pending {
+    /// A path given by the caller, which may be either.
+    Named(PathBuf),
+    File(PathBuf),
+    Dir(PathBuf),
+}
+
+impl Walk {
+    fn new<I>(paths: I, options: &ScanOptions) -> Walk
+        where I: IntoIterator<Item = PathBuf>
+    {
+        Walk {
+            pending: paths.into_iter().map(Pending::Named).collect(),
+            recursive: options.recursive,
+            extensions: options.extensions.clone(),
+        }
+    }
+
+    fn wanted(&self, path: &Path) -> bool {
+        if self.extensions.is_empty() {
+            return true;
+        }
+        let extension = match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) => extension,
+            None => return false,
+        };
+        self.extensions.iter().any(|wanted| wanted.eq_ignore_ascii_case(extension))
+    }
+
+    fn read_dir(&mut self, dir: &Path) -> Result<(), Error> {
+        let mut children = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let path = entry.path();
+
+            if file_type.is_dir() {
+                if self.recursive {
+                    children.push((path, true));
+                }
+            } else if (file_type.is_file() || path.is_file()) && self.wanted(&path) {
+                children.push((path, false));
+            }
+        }
+
+        children.sort();
+        for (child, is_dir) in children.into_iter().rev() {
+            self.pending.push_front(if is_dir { Pending::Dir(child) } else { Pending::File(child) });
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Walk {
+    type Item = Result<PathBuf, (PathBuf, Error)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let dir = match self.pending.pop_front()? {
+                Pending::Named(path) if path.is_dir() => path,
+                Pending::Named(path) | Pending::File(path) => return Some(Ok(path)),
+                Pending::Dir(path) => path,
+            };
+
+            if let Err(err) = self.read_dir(&dir) {
+                return Some(Err((dir, err)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    use crate::tests::test_cart;
+    use crate::{fix_cart_checksum, swap_cart_to, ByteSwapping};
+
+    fn make_collection(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rs64-rom-batch-{}-{}", name, std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+
+        let mut good = test_cart();
+        fix_cart_checksum(&mut good).unwrap();
+        fs::write(dir.join("a.z64"), &good).unwrap();
+
+        let mut swapped = good.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut swapped).unwrap();
+        fs::write(dir.join("nested").join("b.V64"), &swapped).unwrap();
+
+        fs::write(dir.join("c.n64"), [0u8; 16]).unwrap();
+        fs::write(dir.join("notes.txt"), b"not a rom").unwrap();
+        dir
+    }
+
+    fn names(results: &[(PathBuf, Result<RomInfo, Error>)]) -> Vec<String> {
+        results.iter().map(|(path, _)| path.file_name().unwrap().to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn scans_directories_in_order() {
+        let dir = make_collection("order");
+
+        let results: Vec<_> = scan(vec![dir.clone()], &ScanOptions::default()).collect();
+        assert_eq!(names(&results), ["a.z64", "c.n64", "b.V64"]);
+        assert!(results[0].1.as_ref().unwrap().checksum.as_ref().unwrap().is_valid());
+        assert!(results[1].1.is_err());
+        assert_eq!(results[2].1.as_ref().unwrap().swapping.swapping, ByteSwapping::U16LittleEndian);
+
+        let mut flat = ScanOptions { recursive: false, ..ScanOptions::default() };
+        flat.extensions.clear();
+        let results: Vec<_> = scan(vec![dir.clone()], &flat).collect();
+        assert_eq!(names(&results), ["a.z64", "c.n64", "notes.txt"]);
+
+        // Named files skip the extension filter; missing paths surface as
+        // errors of their own.
+        let results: Vec<_> = scan(vec![dir.join("notes.txt"), dir.join("missing")], &ScanOptions::default()).collect();
+        assert_eq!(names(&results), ["notes.txt", "missing"]);
+        assert!(matches!(results[1].1, Err(Error::Io(_))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn callback_sees_every_file() {
+        let dir = make_collection("callback");
+
+        let mut seen = Vec::new();
+        scan_with(vec![dir.clone()], &ScanOptions::default(), |path, result| seen.push((path, result)));
+        assert_eq!(names(&seen), ["a.z64", "c.n64", "b.V64"]);
+
+        #[cfg(feature = "rayon")]
+        {
+            let options = ScanOptions { parallel: true, ..ScanOptions::default() };
+            let mut seen = Vec::new();
+            scan_with(vec![dir.clone()], &options, |path, result| seen.push((path, result.is_ok())));
+            seen.sort_by(|a, b| a.0.cmp(&b.0));
+            let ok: Vec<bool> = seen.iter().map(|(_, ok)| *ok).collect();
+            assert_eq!(ok, [true, false, true]);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
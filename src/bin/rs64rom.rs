@@ -0,0 +1,295 @@
+//! Command line front end to rs64-rom, built only on the library's public API.
+
+use std::convert::TryFrom;
+use std::env;
+use std::error::Error as StdError;
+use std::fs;
+use std::process;
+
+use rs64_rom::{
+    analyze, fix_cart_checksum, pad_rom, pad_rom_to, patch, swap_cart_to, trimmed, ByteSwapping, CartSizeClass, PadFill,
+};
+
+const USAGE: &str = "\
+usage: rs64rom <command> [options]
+
+commands:
+    info <rom>                          print what can be worked out about an image
+    swap <rom> <z64|v64|n64> [-o out]   convert an image's byte order
+    fix-crc <rom> [-o out]              recalculate the header CRCs
+    pad <rom> [--size MIB] [--fill BYTE|repeat] [-o out]
+                                        pad to a standard cart size and fix the CRCs
+    trim <rom> [-o out]                 strip tail padding and mirrored data
+    patch <rom> <patch> [-o out]        apply an IPS, BPS, or xdelta patch
+
+Commands that modify an image write it back in place unless -o is given.";
+
+type CliResult<T> = Result<T, Box<dyn StdError>>;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(output) => print!("{}", output),
+        Err(err) => {
+            eprintln!("rs64rom: {}", err);
+            process::exit(1);
+        },
+    }
+}
+
+// Runs one command, returning what it prints so tests can check it.
+fn run(args: &[String]) -> CliResult<String> {
+    let (command, rest) = match args.split_first() {
+        Some((command, rest)) => (command.as_str(), rest),
+        None => return Err(USAGE.into()),
+    };
+    let args = Args::parse(rest)?;
+
+    match command {
+        "info" => {
+            args.expect_positional(1)?;
+            info(&fs::read(&args.positional[0])?)
+        },
+        "swap" => {
+            args.expect_positional(2)?;
+            let swapping = ByteSwapping::from_file_extension(&args.positional[1])
+                .ok_or_else(|| format!("unknown byte order {:?}", args.positional[1]))?;
+            args.modify(|rom| {
+                swap_cart_to(swapping, rom)?;
+                Ok(format!("converted to {}\n", swapping))
+            })
+        },
+        "fix-crc" => {
+            args.expect_positional(1)?;
+            args.modify(|rom| {
+                let (crc1, crc2) = fix_cart_checksum(rom).map_err(rs64_rom::Error::from)?;
+                Ok(format!("CRC1 0x{:08X} CRC2 0x{:08X}\n", crc1, crc2))
+            })
+        },
+        "pad" => {
+            args.expect_positional(1)?;
+            let fill = match args.fill.as_deref() {
+                None => PadFill::Byte(0xff),
+                Some("repeat") => PadFill::Repeat,
+                Some(text) => {
+                    let byte = u8::try_from(parse_number(text)?);
+                    PadFill::Byte(byte.map_err(|_| format!("fill byte {:?} is over 0xff", text))?)
+                },
+            };
+            let size = match args.size.as_deref() {
+                Some(mib) => {
+                    let size = parse_number(mib)?.checked_mul(1024 * 1024);
+                    Some(size.ok_or_else(|| format!("size of {:?} MiB is too large", mib))?)
+                },
+                None => None,
+            };
+            args.modify(|rom| {
+                let (crc1, crc2) = match size {
+                    Some(size) => pad_rom_to(rom, size, fill)?,
+                    None => pad_rom(rom, fill)?,
+                };
+                Ok(format!("padded to {} bytes, CRC1 0x{:08X} CRC2 0x{:08X}\n", rom.len(), crc1, crc2))
+            })
+        },
+        "trim" => {
+            args.expect_positional(1)?;
+            args.modify(|rom| {
+                let before = rom.len();
+                let len = trimmed(rom).len();
+                rom.truncate(len);
+                Ok(format!("trimmed {} bytes to {}\n", before, len))
+            })
+        },
+        "patch" => {
+            args.expect_positional(2)?;
+            let patch_data = fs::read(&args.positional[1])?;
+            args.modify(|rom| {
+                let format = patch::apply(&patch_data, rom)?;
+                Ok(format!("applied {:?} patch\n", format))
+            })
+        },
+        "help" | "-h" | "--help" => Ok(format!("{}\n", USAGE)),
+        other => Err(format!("unknown command {:?}\n\n{}", other, USAGE).into()),
+    }
+}
+
+fn info(data: &[u8]) -> CliResult<String> {
+    let info = analyze(data)?;
+    let mut out = format!("{}\n", info.header);
+
+    out += &format!("Byte order:   {} ({:?} confidence)\n", info.swapping.swapping, info.swapping.confidence);
+    out += &format!("Platform:     {}\n", info.platform);
+    match info.ipl3 {
+        Some(ipl3) => out += &format!("IPL3:         {}\n", ipl3),
+        None => out += "IPL3:         unknown\n",
+    }
+    match info.size_class {
+        CartSizeClass::Retail(cart_size) => {
+            out += &format!("Size:         {} bytes ({} MiB cart)\n", info.size, cart_size / (1024 * 1024))
+        },
+        CartSizeClass::Extended => out += &format!("Size:         {} bytes (SC64 extended ROM)\n", info.size),
+        CartSizeClass::TooLarge => out += &format!("Size:         {} bytes (too large for any cart)\n", info.size),
+    }
+    if let Some(save) = info.save {
+        out += &format!("Save type:    {} ({:?} confidence)\n", save.save_type, save.confidence);
+    }
+    match info.checksum {
+        Some(ref report) if report.is_valid() => out += &format!("Checksum:     ok ({})\n", report.variant),
+        Some(ref report) => {
+            out += &format!("Checksum:     bad, expected 0x{:08X} 0x{:08X} for {}\n",
+                            report.expected_crc1, report.expected_crc2, report.variant);
+        },
+        None => out += "Checksum:     image too short\n",
+    }
+
+    Ok(out)
+}
+
+/// Positional arguments plus the few options any command takes.
+#[derive(Default)]
+struct Args {
+    positional: Vec<String>,
+    output: Option<String>,
+    size: Option<String>,
+    fill: Option<String>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> CliResult<Args> {
+        let mut parsed = Args::default();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let slot = match arg.as_str() {
+                "-o" | "--output" => &mut parsed.output,
+                "--size" => &mut parsed.size,
+                "--fill" => &mut parsed.fill,
+                _ => {
+                    parsed.positional.push(arg.clone());
+                    continue;
+                },
+            };
+            *slot = Some(args.next().ok_or_else(|| format!("{} needs a value", arg))?.clone());
+        }
+
+        Ok(parsed)
+    }
+
+    fn expect_positional(&self, count: usize) -> CliResult<()> {
+        if self.positional.len() != count {
+            return Err(format!("expected {} arguments, got {}\n\n{}", count, self.positional.len(), USAGE).into());
+        }
+        Ok(())
+    }
+
+    // Reads the image named by the first argument, lets `f` change it, and
+    // writes it to the output path or back over the input.
+    fn modify<F>(&self, f: F) -> CliResult<String>
+        where F: FnOnce(&mut Vec<u8>) -> CliResult<String>
+    {
+        let input = &self.positional[0];
+        let mut rom = fs::read(input)?;
+        let message = f(&mut rom)?;
+        fs::write(self.output.as_ref().unwrap_or(input), &rom)?;
+        Ok(message)
+    }
+}
+
+fn parse_number(text: &str) -> CliResult<usize> {
+    let parsed = match text.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("invalid number {:?}", text).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use rs64_rom::{calculate_cart_checksum, detect_swapping, RomHeader, LOAD_START};
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("rs64rom-cli-{}-{}", process::id(), name))
+    }
+
+    fn run_args(args: &[&str]) -> CliResult<String> {
+        run(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    fn write_test_rom(path: &PathBuf) -> Vec<u8> {
+        let mut rom = vec![0u8; LOAD_START + 0x100000];
+        let mut header = RomHeader::new();
+        header.set_name("CLI TEST").unwrap();
+        rom[..64].copy_from_slice(&header.to_bytes());
+        for (ii, byte) in rom[LOAD_START..].iter_mut().enumerate().take(0x20000) {
+            *byte = (ii * 7) as u8;
+        }
+        fs::write(path, &rom).unwrap();
+        rom
+    }
+
+    #[test]
+    fn commands_round_trip_an_image() {
+        let rom_path = temp_path("rom.z64");
+        let swapped_path = temp_path("rom.v64");
+        let original = write_test_rom(&rom_path);
+        let rom = rom_path.to_str().unwrap();
+        let swapped = swapped_path.to_str().unwrap();
+
+        assert!(run_args(&["info", rom]).unwrap().contains("CLI TEST"));
+
+        run_args(&["swap", rom, "v64", "-o", swapped]).unwrap();
+        assert_eq!(detect_swapping(&fs::read(&swapped_path).unwrap()), Some(ByteSwapping::U16LittleEndian));
+        assert_eq!(fs::read(&rom_path).unwrap(), original);
+
+        run_args(&["fix-crc", rom]).unwrap();
+        let fixed = fs::read(&rom_path).unwrap();
+        let header = RomHeader::from_slice(&fixed).unwrap();
+        assert_eq!((header.crc1, header.crc2), calculate_cart_checksum(&fixed).unwrap());
+        assert!(run_args(&["info", rom]).unwrap().contains("Checksum:     ok"));
+
+        run_args(&["trim", rom]).unwrap();
+        let trimmed_len = fs::read(&rom_path).unwrap().len();
+        assert!(trimmed_len < original.len());
+
+        run_args(&["pad", rom, "--fill", "0"]).unwrap();
+        assert_eq!(fs::read(&rom_path).unwrap().len(), 8 * 1024 * 1024);
+
+        fs::remove_file(&rom_path).unwrap();
+        fs::remove_file(&swapped_path).unwrap();
+    }
+
+    #[test]
+    fn applies_patches() {
+        let rom_path = temp_path("patch.z64");
+        let patch_path = temp_path("patch.ips");
+        write_test_rom(&rom_path);
+
+        let mut ips = b"PATCH".to_vec();
+        ips.extend_from_slice(&[0x00, 0x20, 0x00, 0x00, 0x02, 0xaa, 0xbb]);
+        ips.extend_from_slice(b"EOF");
+        fs::write(&patch_path, &ips).unwrap();
+
+        let output = run_args(&["patch", rom_path.to_str().unwrap(), patch_path.to_str().unwrap()]).unwrap();
+        assert_eq!(output, "applied Ips patch\n");
+        assert_eq!(&fs::read(&rom_path).unwrap()[0x2000..0x2002], &[0xaa, 0xbb]);
+
+        fs::remove_file(&rom_path).unwrap();
+        fs::remove_file(&patch_path).unwrap();
+    }
+
+    #[test]
+    fn rejects_bad_invocations() {
+        assert!(run_args(&[]).is_err());
+        assert!(run_args(&["frobnicate"]).is_err());
+        assert!(run_args(&["swap", "rom.z64"]).is_err());
+        assert!(run_args(&["swap", "rom.z64", "rom", "-o"]).is_err());
+        assert!(run_args(&["pad", "rom.z64", "--fill", "0x1ff"]).unwrap_err().to_string().contains("fill byte"));
+        let huge = usize::MAX.to_string();
+        assert!(run_args(&["pad", "rom.z64", "--size", &huge]).unwrap_err().to_string().contains("too large"));
+        assert!(parse_number("0x10").unwrap() == 16 && parse_number("ten").is_err());
+    }
+}
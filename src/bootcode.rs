@@ -0,0 +1,128 @@
+use core::fmt;
+
+use crate::crc32::crc32;
+use crate::{resolve_swapping, swap_to_native, CicVariant, Error, BOOTCODE_END, BOOTCODE_LEN, BOOTCODE_START};
+
+/// The IPL3 region of an image, in native byte order.
+pub type Bootcode = [u8; BOOTCODE_LEN as usize];
+
+/// The banner libdragon links into its IPL3.  Every build of it differs, so
+/// it's recognized by this rather than by hash.
+const LIBDRAGON_BANNER: &[u8] = b" Libdragon IPL3 ";
+
+/// A recognized IPL3.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Ipl3 {
+    /// The IPL3 Nintendo shipped for a CIC, matched by CRC-32.
+    Retail(CicVariant),
+    /// libdragon's open source IPL3, which pairs with a 6102 CIC and doesn't
+    /// check the header CRCs.
+    Libdragon,
+}
+
+impl Ipl3 {
+    /// The lockout chip a cart booting this IPL3 needs.
+    pub fn cic(self) -> CicVariant {
+        match self {
+            Ipl3::Retail(variant) => variant,
+            Ipl3::Libdragon => CicVariant::Cic6102,
+        }
+    }
+
+    /// Whether this IPL3 refuses to boot an image whose header CRCs are wrong.
+    pub fn verifies_crcs(self) -> bool {
+        match self {
+            Ipl3::Retail(_) => true,
+            Ipl3::Libdragon => false,
+        }
+    }
+}
+
+impl fmt::Display for Ipl3 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Ipl3::Retail(variant) => write!(f, "retail {} IPL3", variant),
+            Ipl3::Libdragon => write!(f, "libdragon IPL3"),
+        }
+    }
+}
+
+/// Known IPL3s by the CRC-32 of their bootcode region.
+pub const KNOWN_IPL3S: [(u32, Ipl3); 6] = [
+    (0x6170a4a1, Ipl3::Retail(CicVariant::Cic6101)),
+    (0x90bb6cb5, Ipl3::Retail(CicVariant::Cic6102)),
+    (0x0b050ee0, Ipl3::Retail(CicVariant::Cic6103)),
+    (0x98bc2c86, Ipl3::Retail(CicVariant::Cic6105)),
+    (0xacc8580a, Ipl3::Retail(CicVariant::Cic6106)),
+    (0x009e9ea3, Ipl3::Retail(CicVariant::Cic7102)),
+];
+
+/// Whether `ipl3`, padded out to a whole bootcode region, is libdragon's.
+pub fn is_libdragon_ipl3(ipl3: &[u8]) -> bool {
+    ipl3.windows(LIBDRAGON_BANNER.len()).any(|window| window == LIBDRAGON_BANNER)
+}
+
+/// Recognizes a native byte order bootcode region, returning `None` for
+/// anything unknown.
+pub fn identify_bootcode(bootcode: &Bootcode) -> Option<Ipl3> {
+    let crc = crc32(bootcode);
+    KNOWN_IPL3S.iter()
+        .find(|(known, _)| *known == crc)
+        .map(|(_, ipl3)| *ipl3)
+        .or_else(|| is_libdragon_ipl3(bootcode).then_some(Ipl3::Libdragon))
+}
+
+/// Copies the bootcode out of an image in any byte order, for example to
+/// reuse a donor ROM's IPL3.
+pub fn extract_bootcode(rom: &[u8]) -> Result<Bootcode, Error> {
+    let swapping = resolve_swapping(rom).ok_or(Error::UnknownByteSwapping)?;
+    if rom.len() < BOOTCODE_END {
+        return Err(Error::BufferTooShort { needed: BOOTCODE_END, actual: rom.len() });
+    }
+
+    let mut bootcode = [0u8; BOOTCODE_LEN as usize];
+    bootcode.copy_from_slice(&rom[BOOTCODE_START..BOOTCODE_END]);
+    swap_to_native(swapping, &mut bootcode);
+    Ok(bootcode)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use crate::tests::{stamp_cic, test_cart};
+    use crate::{swap_cart_to, ByteSwapping};
+
+    pub(crate) fn fake_libdragon_ipl3() -> [u8; 0x100] {
+        let mut ipl3 = [0u8; 0x100];
+        ipl3[0x20..0x20 + LIBDRAGON_BANNER.len()].copy_from_slice(LIBDRAGON_BANNER);
+        ipl3
+    }
+
+    #[test]
+    fn table_matches_cic_fingerprints() {
+        for variant in CicVariant::ALL.iter() {
+            assert!(KNOWN_IPL3S.contains(&(variant.bootcode_crc32(), Ipl3::Retail(*variant))));
+        }
+    }
+
+    #[test]
+    fn identifies_donor_bootcode() {
+        let mut donor = test_cart();
+        stamp_cic(&mut donor, CicVariant::Cic6105);
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut donor).unwrap();
+
+        let bootcode = extract_bootcode(&donor).unwrap();
+        assert_eq!(identify_bootcode(&bootcode), Some(Ipl3::Retail(CicVariant::Cic6105)));
+        assert_eq!(identify_bootcode(&[0; BOOTCODE_LEN as usize]), None);
+
+        let mut libdragon = [0u8; BOOTCODE_LEN as usize];
+        libdragon[..0x100].copy_from_slice(&fake_libdragon_ipl3());
+        assert_eq!(identify_bootcode(&libdragon), Some(Ipl3::Libdragon));
+        assert_eq!(Ipl3::Libdragon.cic(), CicVariant::Cic6102);
+        assert!(!Ipl3::Libdragon.verifies_crcs());
+
+        assert!(matches!(extract_bootcode(&[0u8; 64]), Err(Error::UnknownByteSwapping)));
+    }
+}
@@ -0,0 +1,554 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::{
+    calculate_cart_checksum_with, extract_bootcode, BuildInfo, identify_bootcode, is_libdragon_ipl3, CicVariant, Error, Ipl3,
+    NameError, RomHeader, BOOTCODE_END, BOOTCODE_LEN, BOOTCODE_START,
+    HEADER_CRC_END, HEADER_CRC_START, HEADER_END, HEADER_START, LOAD_START, ROM_LEN, SC64_MAX_ROM_LEN,
+};
+
+struct Segment {
+    data: Vec<u8>,
+    align: usize,
+    offset: Option<usize>,
+    name: Option<String>,
+    load_addr: Option<u32>,
+}
+
+/// Where [`RomBuilder::build_with_map`] put one payload segment.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SegmentPlacement {
+    pub name: String,
+    /// Offset of the segment from the start of the image.
+    pub rom_offset: usize,
+    pub len: usize,
+    /// The address the segment runs at.  Unless one was given, segments
+    /// follow the previous one in memory as they do in the image, and the
+    /// first one sits where the IPL3 copies the payload to.
+    pub load_addr: u32,
+}
+
+impl SegmentPlacement {
+    pub fn rom_end(&self) -> usize {
+        self.rom_offset + self.len
+    }
+
+    pub fn load_end(&self) -> u32 {
+        self.load_addr.wrapping_add(self.len as u32)
+    }
+}
+
+/// The layout of a built image, for keeping a project's link layout in
+/// step with it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MemoryMap {
+    /// Where the IPL3 jumps to, after any CIC offset.
+    pub entry_point: u32,
+    /// Length of the whole image.
+    pub rom_len: usize,
+    /// Payload segments in the order they were added.
+    pub segments: Vec<SegmentPlacement>,
+}
+
+impl MemoryMap {
+    /// A table of every segment's ROM and RAM ranges, after the header and
+    /// IPL3.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "entry point  0x{:08x}", self.entry_point);
+        let _ = writeln!(text, "image length 0x{:08x}", self.rom_len);
+        let _ = writeln!(text);
+        let _ = writeln!(text, "{:<21}  {:<21}  {:<10}  name", "rom", "ram", "length");
+
+        let mut row = |rom_start: usize, rom_end: usize, ram: &str, name: &str| {
+            let rom = format!("0x{:08x}-0x{:08x}", rom_start, rom_end);
+            let len = format!("0x{:x}", rom_end - rom_start);
+            let _ = writeln!(text, "{:<21}  {:<21}  {:<10}  {}", rom, ram, len, name);
+        };
+        row(HEADER_START, HEADER_END, "-", "header");
+        row(BOOTCODE_START, BOOTCODE_END, "-", "ipl3");
+        for segment in &self.segments {
+            let ram = format!("0x{:08x}-0x{:08x}", segment.load_addr, segment.load_end());
+            row(segment.rom_offset, segment.rom_end(), &ram, &segment.name);
+        }
+        text
+    }
+
+    /// A GNU ld script fragment, for `INCLUDE` in a project's own script,
+    /// that places each segment's `.<name>` input sections at its address
+    /// and ROM offset and defines `<name>_ROM_START`, `<name>_ROM_END` and
+    /// `<name>_VRAM` for code that loads segments itself.
+    pub fn to_ld_script(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "/* Segment layout of the built image. */");
+        let _ = writeln!(text, "__entry_point = 0x{:08x};", self.entry_point);
+        let _ = writeln!(text, "SECTIONS");
+        let _ = writeln!(text, "{{");
+        for segment in &self.segments {
+            let name = &segment.name;
+            let _ = writeln!(text, "    .{} 0x{:08x} : AT(0x{:08x}) {{ *(.{} .{}.*) }}",
+                             name, segment.load_addr, segment.rom_offset, name, name);
+            let _ = writeln!(text, "    {}_ROM_START = 0x{:08x};", name, segment.rom_offset);
+            let _ = writeln!(text, "    {}_ROM_END = 0x{:08x};", name, segment.rom_end());
+            let _ = writeln!(text, "    {}_VRAM = 0x{:08x};", name, segment.load_addr);
+        }
+        let _ = writeln!(text, "}}");
+        text
+    }
+}
+
+/// Assembles a header, an IPL3, and payload segments into a native byte order
+/// image with valid CRCs, in the spirit of makerom.
+pub struct RomBuilder {
+    header: RomHeader,
+    bootcode: Vec<u8>,
+    cic: Option<CicVariant>,
+    segments: Vec<Segment>,
+    fill: u8,
+    size: Option<usize>,
+    entry_point: Option<u32>,
+}
+
+impl RomBuilder {
+    pub fn new() -> RomBuilder {
+        Default::default()
+    }
+
+    pub fn header(mut self, header: RomHeader) -> RomBuilder {
+        self.header = header;
+        self
+    }
+
+    /// Sets the internal name; see [`RomHeader::set_name`].
+    pub fn name(mut self, name: &str) -> Result<RomBuilder, NameError> {
+        self.header.set_name(name)?;
+        Ok(self)
+    }
+
+    /// Stamps a commit hash and Unix timestamp into the header's reserved
+    /// words; see [`BuildInfo::new`].
+    pub fn with_build_info(mut self, commit_hash: &str, timestamp: u64) -> Result<RomBuilder, Error> {
+        let info = BuildInfo::new(commit_hash, timestamp)
+            .ok_or(Error::InvalidArgument("build info needs an 8 digit hex hash and a 32 bit timestamp"))?;
+        self.header.set_build_info(info);
+        Ok(self)
+    }
+
+    /// Sets the address the IPL3 copies the payload to and jumps to.
+    pub fn load_addr(mut self, load_addr: u32) -> RomBuilder {
+        self.header.load_addr = load_addr;
+        self
+    }
+
+    /// Sets the address the payload should run from, storing whatever load
+    /// address makes the CIC's IPL3 land there; see
+    /// [`RomHeader::effective_entry_point`].  This wins over
+    /// [`RomBuilder::load_addr`] and uses the CIC the image ends up with.
+    pub fn entry_point(mut self, entry_point: u32) -> RomBuilder {
+        self.entry_point = Some(entry_point);
+        self
+    }
+
+    /// Sets the IPL3 blob; shorter blobs are padded with the fill byte.
+    pub fn bootcode(mut self, bootcode: &[u8]) -> RomBuilder {
+        self.bootcode = bootcode.to_vec();
+        self
+    }
+
+    /// Uses libdragon's open source IPL3, as built by its toolchain.  The
+    /// header CRCs are still filled in for the 6102, so retail tools see a
+    /// valid image.
+    pub fn libdragon_ipl3(mut self, ipl3: &[u8]) -> Result<RomBuilder, Error> {
+        if !is_libdragon_ipl3(ipl3) {
+            return Err(Error::InvalidArgument("bootcode is not a libdragon IPL3"));
+        }
+        self.bootcode = ipl3.to_vec();
+        self.cic = Some(Ipl3::Libdragon.cic());
+        Ok(self)
+    }
+
+    /// Uses the IPL3 of a donor image in any byte order, along with the CIC
+    /// it needs if it's a known one.
+    pub fn bootcode_from(mut self, donor: &[u8]) -> Result<RomBuilder, Error> {
+        let bootcode = extract_bootcode(donor)?;
+        if let Some(ipl3) = identify_bootcode(&bootcode) {
+            self.cic = Some(ipl3.cic());
+        }
+        self.bootcode = bootcode.to_vec();
+        Ok(self)
+    }
+
+    /// Forces the CIC used for the CRCs instead of detecting it from the bootcode.
+    pub fn cic(mut self, cic: CicVariant) -> RomBuilder {
+        self.cic = Some(cic);
+        self
+    }
+
+    /// Appends a payload segment directly after the previous one.
+    pub fn segment(self, data: &[u8]) -> RomBuilder {
+        self.aligned_segment(data, 1)
+    }
+
+    /// Appends a payload segment starting at the next multiple of `align`
+    /// bytes from the start of the image.
+    pub fn aligned_segment(mut self, data: &[u8], align: usize) -> RomBuilder {
+        self.segments.push(Segment {
+            data: data.to_vec(),
+            align: core::cmp::max(align, 1),
+            offset: None,
+            name: None,
+            load_addr: None,
+        });
+        self
+    }
+
+    /// Appends a payload segment at a fixed offset from the start of the
+    /// image, which must not overlap anything before it.
+    pub fn segment_at(mut self, data: &[u8], offset: usize) -> RomBuilder {
+        self.segments.push(Segment {
+            data: data.to_vec(),
+            align: 1,
+            offset: Some(offset),
+            name: None,
+            load_addr: None,
+        });
+        self
+    }
+
+    /// Names the segment added last in the [`MemoryMap`], where segments are
+    /// otherwise `segment0`, `segment1` and so on.  The name ends up in
+    /// linker symbols, so it has to be a C identifier, can't be taken by
+    /// another segment, and can't be one of those default names.
+    pub fn segment_name(mut self, name: &str) -> Result<RomBuilder, Error> {
+        let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            return Err(Error::InvalidArgument("segment name must be a C identifier"));
+        }
+        let index = name.strip_prefix("segment");
+        if index.is_some_and(|index| !index.is_empty() && index.bytes().all(|c| c.is_ascii_digit())) {
+            return Err(Error::InvalidArgument("segmentN names are kept for unnamed segments"));
+        }
+        let others = &self.segments[..self.segments.len().saturating_sub(1)];
+        if others.iter().any(|segment| segment.name.as_deref() == Some(name)) {
+            return Err(Error::InvalidArgument("another segment already has that name"));
+        }
+        self.last_segment()?.name = Some(name.into());
+        Ok(self)
+    }
+
+    /// Sets the address the segment added last runs at, for segments the
+    /// game copies to memory itself.
+    pub fn segment_load_addr(mut self, load_addr: u32) -> Result<RomBuilder, Error> {
+        self.last_segment()?.load_addr = Some(load_addr);
+        Ok(self)
+    }
+
+    fn last_segment(&mut self) -> Result<&mut Segment, Error> {
+        self.segments.last_mut().ok_or(Error::InvalidArgument("no segment added yet"))
+    }
+
+    /// Byte used for alignment gaps and padding.
+    pub fn fill(mut self, fill: u8) -> RomBuilder {
+        self.fill = fill;
+        self
+    }
+
+    /// Pads the image to exactly `size` bytes.  Without this the image is only
+    /// padded up to the minimum checksummed length.
+    pub fn pad_to(mut self, size: usize) -> RomBuilder {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn build(&self) -> Result<Vec<u8>, Error> {
+        self.build_with_map().map(|(image, _)| image)
+    }
+
+    /// Like [`RomBuilder::build`], also returning where each segment went.
+    pub fn build_with_map(&self) -> Result<(Vec<u8>, MemoryMap), Error> {
+        if self.bootcode.len() > BOOTCODE_LEN as usize {
+            return Err(Error::InvalidLayout("bootcode larger than the bootcode region"));
+        }
+
+        let mut image = alloc::vec![self.fill; LOAD_START];
+        image[BOOTCODE_START..BOOTCODE_START + self.bootcode.len()].copy_from_slice(&self.bootcode);
+
+        let mut offsets = Vec::with_capacity(self.segments.len());
+        for segment in self.segments.iter() {
+            let start = match segment.offset {
+                Some(offset) if offset < image.len() => {
+                    return Err(Error::InvalidLayout("segment overlaps the previous contents"));
+                },
+                Some(offset) => offset,
+                None => round_up(image.len(), segment.align)
+                    .ok_or(Error::InvalidArgument("segment alignment is larger than any cart"))?,
+            };
+            if start.checked_add(segment.data.len()).is_none_or(|end| end > SC64_MAX_ROM_LEN) {
+                return Err(Error::InvalidArgument("segment lies past the end of the largest cart"));
+            }
+            image.resize(start, self.fill);
+            image.extend_from_slice(&segment.data);
+            offsets.push(start);
+        }
+
+        let size = match self.size {
+            Some(size) => {
+                if size > SC64_MAX_ROM_LEN {
+                    return Err(Error::InvalidArgument("target size larger than the largest cart"));
+                }
+                if size < ROM_LEN {
+                    return Err(Error::InvalidLayout("target size smaller than the checksummed region"));
+                }
+                if size < image.len() {
+                    return Err(Error::InvalidLayout("segments do not fit in the target size"));
+                }
+                size
+            },
+            None => core::cmp::max(image.len(), ROM_LEN),
+        };
+        image.resize(size, self.fill);
+
+        let mut bootcode = [0u8; BOOTCODE_LEN as usize];
+        bootcode.copy_from_slice(&image[BOOTCODE_START..BOOTCODE_END]);
+        let cic = self.cic
+            .or_else(|| identify_bootcode(&bootcode).map(Ipl3::cic))
+            .unwrap_or(CicVariant::Cic6102);
+
+        let mut header = self.header;
+        if let Some(entry_point) = self.entry_point {
+            header.load_addr = entry_point.wrapping_add(cic.entry_offset());
+        }
+        image[HEADER_START..HEADER_END].copy_from_slice(&header.to_bytes());
+        let (crc1, crc2) = calculate_cart_checksum_with(cic, &image)?;
+
+        image[HEADER_CRC_START..HEADER_CRC_START + 4].copy_from_slice(&crc1.to_be_bytes());
+        image[HEADER_CRC_START + 4..HEADER_CRC_END].copy_from_slice(&crc2.to_be_bytes());
+
+        let entry_point = header.effective_entry_point(cic);
+        let mut segments: Vec<SegmentPlacement> = Vec::with_capacity(self.segments.len());
+        for (index, (segment, &rom_offset)) in self.segments.iter().zip(offsets.iter()).enumerate() {
+            let load_addr = segment.load_addr.unwrap_or_else(|| match segments.last() {
+                Some(previous) => previous.load_addr.wrapping_add((rom_offset - previous.rom_offset) as u32),
+                None => entry_point.wrapping_add((rom_offset - LOAD_START) as u32),
+            });
+            segments.push(SegmentPlacement {
+                name: segment.name.clone().unwrap_or_else(|| format!("segment{}", index)),
+                rom_offset,
+                len: segment.data.len(),
+                load_addr,
+            });
+        }
+
+        let map = MemoryMap { entry_point, rom_len: image.len(), segments };
+        Ok((image, map))
+    }
+}
+
+impl core::default::Default for RomBuilder {
+    fn default() -> Self {
+        RomBuilder {
+            header: RomHeader::new(),
+            bootcode: Vec::new(),
+            cic: None,
+            segments: Vec::new(),
+            fill: 0xff,
+            size: None,
+            entry_point: None,
+        }
+    }
+}
+
+fn round_up(value: usize, align: usize) -> Option<usize> {
+    (value.checked_add(align - 1)? / align).checked_mul(align)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{detect_cic, verify_cart_checksum};
+
+    #[test]
+    fn builds_checksummed_image() {
+        let mut header = RomHeader::new();
+        header.load_addr = 0x80000400;
+
+        let image = RomBuilder::new()
+            .header(header)
+            .bootcode(&[0x11; 16])
+            .segment(&[1, 2, 3])
+            .aligned_segment(&[4, 5], 16)
+            .fill(0x00)
+            .build()
+            .unwrap();
+
+        assert_eq!(image.len(), ROM_LEN);
+        assert_eq!(&image[BOOTCODE_START..BOOTCODE_START + 16], &[0x11; 16]);
+        assert_eq!(image[BOOTCODE_START + 16], 0x00);
+        assert_eq!(&image[LOAD_START..LOAD_START + 3], &[1, 2, 3]);
+        assert_eq!(&image[LOAD_START + 3..LOAD_START + 16], &[0u8; 13]);
+        assert_eq!(&image[LOAD_START + 16..LOAD_START + 18], &[4, 5]);
+
+        let parsed = RomHeader::from_slice(&image).unwrap();
+        assert_eq!(parsed.load_addr, 0x80000400);
+        assert!(verify_cart_checksum(&image).unwrap().is_valid());
+    }
+
+    #[test]
+    fn places_segments_at_fixed_offsets() {
+        let image = RomBuilder::new()
+            .load_addr(0x80000400)
+            .segment_at(&[7, 8], LOAD_START + 0x20)
+            .build()
+            .unwrap();
+
+        assert_eq!(RomHeader::from_slice(&image).unwrap().load_addr, 0x80000400);
+        assert_eq!(image[LOAD_START + 0x1f], 0xff);
+        assert_eq!(&image[LOAD_START + 0x20..LOAD_START + 0x22], &[7, 8]);
+
+        assert!(RomBuilder::new().segment_at(&[0], HEADER_END).build().is_err());
+    }
+
+    #[test]
+    fn offsets_the_entry_point_for_the_cic() {
+        let image = RomBuilder::new()
+            .cic(CicVariant::Cic6103)
+            .load_addr(0x80000000)
+            .entry_point(0x80000400)
+            .build()
+            .unwrap();
+
+        let header = RomHeader::from_slice(&image).unwrap();
+        assert_eq!(header.load_addr, 0x80100400);
+        assert_eq!(header.effective_entry_point(CicVariant::Cic6103), 0x80000400);
+        assert_eq!(header.effective_entry_point(CicVariant::Cic6102), 0x80100400);
+    }
+
+    #[test]
+    fn sets_name() {
+        let image = RomBuilder::new().name("BUILT").unwrap().build().unwrap();
+        assert_eq!(&RomHeader::from_slice(&image).unwrap().name, b"BUILT               ");
+
+        assert!(RomBuilder::new().name("\u{3042}").is_err());
+    }
+
+    #[test]
+    fn stamps_build_info() {
+        let image = RomBuilder::new().with_build_info("0123abcd", 1_600_000_000).unwrap().build().unwrap();
+        let info = RomHeader::from_slice(&image).unwrap().build_info().unwrap();
+        assert_eq!(info.commit, 0x0123abcd);
+        assert_eq!(info.timestamp, 1_600_000_000);
+        assert!(verify_cart_checksum(&image).unwrap().is_valid());
+
+        assert!(matches!(RomBuilder::new().with_build_info("xyz", 0), Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn pads_to_target_size() {
+        let image = RomBuilder::new().pad_to(2 * ROM_LEN).build().unwrap();
+        assert_eq!(image.len(), 2 * ROM_LEN);
+        assert_eq!(image[image.len() - 1], 0xff);
+    }
+
+    #[test]
+    fn borrows_donor_bootcode() {
+        let mut donor = crate::tests::test_cart();
+        crate::tests::stamp_cic(&mut donor, CicVariant::Cic6106);
+        crate::swap_cart_to(crate::ByteSwapping::U32LittleEndian, &mut donor).unwrap();
+
+        let image = RomBuilder::new().bootcode_from(&donor).unwrap().build().unwrap();
+        assert_eq!(detect_cic(&image), Some(CicVariant::Cic6106));
+        let report = verify_cart_checksum(&image).unwrap();
+        assert_eq!(report.variant, CicVariant::Cic6106);
+        assert!(report.is_valid());
+
+        assert!(RomBuilder::new().bootcode_from(&[0u8; 64]).is_err());
+    }
+
+    #[test]
+    fn builds_libdragon_images() {
+        let ipl3 = crate::bootcode::tests::fake_libdragon_ipl3();
+        let mut image = RomBuilder::new().libdragon_ipl3(&ipl3).unwrap().build().unwrap();
+
+        let report = verify_cart_checksum(&image).unwrap();
+        assert_eq!(report.ipl3, Some(Ipl3::Libdragon));
+        assert!(report.crcs_match());
+
+        image[HEADER_CRC_START] ^= 0xff;
+        let report = verify_cart_checksum(&image).unwrap();
+        assert!(!report.crcs_match());
+        assert!(report.is_valid());
+
+        assert!(matches!(RomBuilder::new().libdragon_ipl3(&[0; 16]), Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn maps_segments() {
+        let (image, map) = RomBuilder::new()
+            .entry_point(0x80000400)
+            .segment(&[0; 0x10]).segment_name("boot").unwrap()
+            .aligned_segment(&[1; 0x20], 0x100)
+            .segment_at(&[2; 8], 0x20000).segment_name("overlay").unwrap().segment_load_addr(0x80400000).unwrap()
+            .segment(&[3; 4])
+            .build_with_map()
+            .unwrap();
+
+        assert_eq!((map.entry_point, map.rom_len), (0x80000400, image.len()));
+        let placed: Vec<(&str, usize, usize, u32)> = map.segments.iter()
+            .map(|segment| (&segment.name[..], segment.rom_offset, segment.len, segment.load_addr))
+            .collect();
+        assert_eq!(placed, [
+            ("boot", LOAD_START, 0x10, 0x80000400),
+            ("segment1", LOAD_START + 0x100, 0x20, 0x80000500),
+            ("overlay", 0x20000, 8, 0x80400000),
+            ("segment3", 0x20008, 4, 0x80400008),
+        ]);
+        assert_eq!(&image[0x20000..0x20008], &[2; 8]);
+
+        let text = map.to_text();
+        assert!(text.contains("0x00001000-0x00001010  0x80000400-0x80000410  0x10        boot\n"));
+        assert!(text.contains("0x00000040-0x00001000  -                      0xfc0       ipl3\n"));
+
+        let script = map.to_ld_script();
+        assert!(script.contains("    .overlay 0x80400000 : AT(0x00020000) { *(.overlay .overlay.*) }\n"));
+        assert!(script.contains("    overlay_ROM_END = 0x00020008;\n"));
+        assert!(script.contains("    segment1_VRAM = 0x80000500;\n"));
+    }
+
+    #[test]
+    fn rejects_bad_segment_names() {
+        assert!(matches!(RomBuilder::new().segment_name("code"), Err(Error::InvalidArgument(_))));
+        assert!(matches!(RomBuilder::new().segment_load_addr(0x80000000), Err(Error::InvalidArgument(_))));
+        assert!(matches!(RomBuilder::new().segment(&[0]).segment_name("1st"), Err(Error::InvalidArgument(_))));
+        assert!(matches!(RomBuilder::new().segment(&[0]).segment_name("has space"), Err(Error::InvalidArgument(_))));
+        assert!(RomBuilder::new().segment(&[0]).segment_name("_ok_2").is_ok());
+        assert!(matches!(RomBuilder::new().segment(&[0]).segment_name("segment1"), Err(Error::InvalidArgument(_))));
+        assert!(RomBuilder::new().segment(&[0]).segment_name("segments").is_ok());
+
+        let named = RomBuilder::new().segment(&[0]).segment_name("code").unwrap();
+        assert!(matches!(named.segment(&[1]).segment_name("code"), Err(Error::InvalidArgument(_))));
+        let renamed = RomBuilder::new().segment(&[0]).segment_name("code").unwrap().segment_name("code");
+        assert!(renamed.is_ok());
+    }
+
+    #[test]
+    fn rejects_impossible_layouts() {
+        assert!(RomBuilder::new().bootcode(&vec![0; BOOTCODE_LEN as usize + 1]).build().is_err());
+        assert!(RomBuilder::new().pad_to(LOAD_START).build().is_err());
+        assert!(RomBuilder::new().segment(&vec![0; ROM_LEN]).pad_to(ROM_LEN).build().is_err());
+    }
+
+    #[test]
+    fn rejects_offsets_and_sizes_past_the_largest_cart() {
+        assert!(matches!(RomBuilder::new().segment_at(&[1], usize::MAX).build(), Err(Error::InvalidArgument(_))));
+        assert!(matches!(RomBuilder::new().segment_at(&[1], SC64_MAX_ROM_LEN).build(), Err(Error::InvalidArgument(_))));
+        assert!(matches!(RomBuilder::new().aligned_segment(&[1], usize::MAX).build(), Err(Error::InvalidArgument(_))));
+        assert!(matches!(RomBuilder::new().aligned_segment(&[1], SC64_MAX_ROM_LEN).build(),
+                         Err(Error::InvalidArgument(_))));
+        assert!(matches!(RomBuilder::new().pad_to(usize::MAX).build(), Err(Error::InvalidArgument(_))));
+        assert!(matches!(RomBuilder::new().pad_to(SC64_MAX_ROM_LEN + 4).build(), Err(Error::InvalidArgument(_))));
+    }
+}
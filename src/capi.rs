@@ -0,0 +1,333 @@
+//! A small C API over the core operations, for emulators and flashcart tools
+//! written in C or C++.  Build a library with, for example,
+//! `cargo rustc --release --features capi --crate-type staticlib` and include
+//! `include/rs64_rom.h`, which is generated from this file alone by
+//! cbindgen, so everything the header declares has to be defined here.
+//!
+//! Every function returns `RS64_OK` or a negative `RS64_ERR_*` code, and
+//! takes image buffers in any supported byte order unless noted.
+
+use core::slice;
+
+use crate::{
+    calculate_cart_checksum_with, detect_cic, fix_cart_checksum, resolve_swapping, swap_cart_to, swap_to_native,
+    ByteSwapping, ChecksumError, CicVariant, Error, RomHeader, HEADER_LEN,
+};
+
+pub const RS64_OK: i32 = 0;
+/// A required pointer was null.
+pub const RS64_ERR_NULL: i32 = -1;
+/// The image's byte order couldn't be determined.
+pub const RS64_ERR_UNKNOWN_SWAPPING: i32 = -2;
+/// The image isn't a whole number of swapping units.
+pub const RS64_ERR_UNALIGNED: i32 = -3;
+/// The image is too short for the operation.
+pub const RS64_ERR_TOO_SHORT: i32 = -4;
+/// An argument was out of range.
+pub const RS64_ERR_INVALID_ARGUMENT: i32 = -5;
+
+pub const RS64_SWAP_NATIVE: i32 = 0;
+pub const RS64_SWAP_U16_LITTLE_ENDIAN: i32 = 1;
+pub const RS64_SWAP_U32_LITTLE_ENDIAN: i32 = 2;
+
+/// Passed as the CIC to checksum functions to use the one the bootcode
+/// implies, falling back to 6102.
+pub const RS64_CIC_AUTO: i32 = -1;
+pub const RS64_CIC_6101: i32 = 0;
+pub const RS64_CIC_6102: i32 = 1;
+pub const RS64_CIC_6103: i32 = 2;
+pub const RS64_CIC_6105: i32 = 3;
+pub const RS64_CIC_6106: i32 = 4;
+pub const RS64_CIC_7102: i32 = 5;
+
+pub const RS64_HEADER_NAME_LEN: usize = 20;
+
+/// The cart header with its fields decoded to host integers.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Rs64RomHeader {
+    pub cart_timing: u32,
+    pub clock_rate: u32,
+    pub load_addr: u32,
+    pub release: u32,
+    pub crc1: u32,
+    pub crc2: u32,
+    pub rsvd_18: u32,
+    pub rsvd_1c: u32,
+    pub name: [u8; RS64_HEADER_NAME_LEN],
+    pub rsvd_34: u32,
+    pub manuf_id: u32,
+    pub cart_id: u16,
+    pub country_code: u16,
+}
+
+impl From<RomHeader> for Rs64RomHeader {
+    fn from(header: RomHeader) -> Rs64RomHeader {
+        Rs64RomHeader {
+            cart_timing: header.cart_timing,
+            clock_rate: header.clock_rate,
+            load_addr: header.load_addr,
+            release: header.release,
+            crc1: header.crc1,
+            crc2: header.crc2,
+            rsvd_18: header.rsvd_18,
+            rsvd_1c: header.rsvd_1c,
+            name: header.name,
+            rsvd_34: header.rsvd_34,
+            manuf_id: header.manuf_id,
+            cart_id: header.cart_id,
+            country_code: header.country_code,
+        }
+    }
+}
+
+/// Returns the byte order of the image as an `RS64_SWAP_*` value, or
+/// `RS64_ERR_UNKNOWN_SWAPPING`.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rs64_detect_swapping(data: *const u8, len: usize) -> i32 {
+    let buffer = match input(data, len) {
+        Some(buffer) => buffer,
+        None => return RS64_ERR_NULL,
+    };
+
+    match resolve_swapping(buffer) {
+        Some(swapping) => swapping_code(swapping),
+        None => RS64_ERR_UNKNOWN_SWAPPING,
+    }
+}
+
+/// Converts the image in place to the `RS64_SWAP_*` order `to`.
+///
+/// # Safety
+///
+/// `data` must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rs64_convert(data: *mut u8, len: usize, to: i32) -> i32 {
+    let buffer = match output(data, len) {
+        Some(buffer) => buffer,
+        None => return RS64_ERR_NULL,
+    };
+    let to = match swapping_from_code(to) {
+        Some(to) => to,
+        None => return RS64_ERR_INVALID_ARGUMENT,
+    };
+
+    status(swap_cart_to(to, buffer))
+}
+
+/// Calculates the CRC pair of a native byte order image for the `RS64_CIC_*`
+/// variant `cic`, storing it through `crc1` and `crc2`.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, and `crc1` and `crc2` must be
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn rs64_checksum(data: *const u8, len: usize, cic: i32, crc1: *mut u32, crc2: *mut u32) -> i32 {
+    let buffer = match input(data, len) {
+        Some(buffer) => buffer,
+        None => return RS64_ERR_NULL,
+    };
+    if crc1.is_null() || crc2.is_null() {
+        return RS64_ERR_NULL;
+    }
+
+    let variant = if cic == RS64_CIC_AUTO {
+        detect_cic(buffer).unwrap_or(CicVariant::Cic6102)
+    } else {
+        match CicVariant::ALL.get(cic as usize) {
+            Some(variant) if cic >= 0 => *variant,
+            _ => return RS64_ERR_INVALID_ARGUMENT,
+        }
+    };
+
+    match calculate_cart_checksum_with(variant, buffer) {
+        Ok((crc1_value, crc2_value)) => {
+            *crc1 = crc1_value;
+            *crc2 = crc2_value;
+            RS64_OK
+        },
+        Err(err) => error_code(&err.into()),
+    }
+}
+
+/// Recalculates the image's CRCs for the CIC its bootcode implies and writes
+/// them into its header, leaving the byte order as it was.
+///
+/// # Safety
+///
+/// `data` must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rs64_fix_crc(data: *mut u8, len: usize) -> i32 {
+    let buffer = match output(data, len) {
+        Some(buffer) => buffer,
+        None => return RS64_ERR_NULL,
+    };
+
+    match fix_cart_checksum(buffer) {
+        Ok(_) => RS64_OK,
+        Err(err) => error_code(&err.into()),
+    }
+}
+
+/// Decodes the image's header into `header`, whose fields are host integers.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, and `header` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn rs64_parse_header(data: *const u8, len: usize, header: *mut Rs64RomHeader) -> i32 {
+    let buffer = match input(data, len) {
+        Some(buffer) => buffer,
+        None => return RS64_ERR_NULL,
+    };
+    if header.is_null() {
+        return RS64_ERR_NULL;
+    }
+    if buffer.len() < HEADER_LEN as usize {
+        return RS64_ERR_TOO_SHORT;
+    }
+
+    let swapping = match resolve_swapping(buffer) {
+        Some(swapping) => swapping,
+        None => return RS64_ERR_UNKNOWN_SWAPPING,
+    };
+
+    let mut bytes = [0u8; HEADER_LEN as usize];
+    bytes.copy_from_slice(&buffer[..HEADER_LEN as usize]);
+    swap_to_native(swapping, &mut bytes);
+    *header = RomHeader::from_bytes(&bytes).into();
+
+    RS64_OK
+}
+
+unsafe fn input<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    if data.is_null() {
+        return None;
+    }
+    Some(slice::from_raw_parts(data, len))
+}
+
+unsafe fn output<'a>(data: *mut u8, len: usize) -> Option<&'a mut [u8]> {
+    if data.is_null() {
+        return None;
+    }
+    Some(slice::from_raw_parts_mut(data, len))
+}
+
+fn swapping_code(swapping: ByteSwapping) -> i32 {
+    match swapping {
+        ByteSwapping::Native => RS64_SWAP_NATIVE,
+        ByteSwapping::U16LittleEndian => RS64_SWAP_U16_LITTLE_ENDIAN,
+        ByteSwapping::U32LittleEndian => RS64_SWAP_U32_LITTLE_ENDIAN,
+    }
+}
+
+fn swapping_from_code(code: i32) -> Option<ByteSwapping> {
+    match code {
+        RS64_SWAP_NATIVE => Some(ByteSwapping::Native),
+        RS64_SWAP_U16_LITTLE_ENDIAN => Some(ByteSwapping::U16LittleEndian),
+        RS64_SWAP_U32_LITTLE_ENDIAN => Some(ByteSwapping::U32LittleEndian),
+        _ => None,
+    }
+}
+
+fn status(result: Result<(), Error>) -> i32 {
+    match result {
+        Ok(()) => RS64_OK,
+        Err(err) => error_code(&err),
+    }
+}
+
+fn error_code(err: &Error) -> i32 {
+    match *err {
+        Error::UnknownByteSwapping | Error::Checksum(ChecksumError::UnknownByteSwapping) => RS64_ERR_UNKNOWN_SWAPPING,
+        Error::UnalignedLength { .. } | Error::Checksum(ChecksumError::UnalignedRegion) => RS64_ERR_UNALIGNED,
+        Error::BufferTooShort { .. }
+        | Error::Checksum(ChecksumError::NotLongEnough { .. })
+        | Error::Checksum(ChecksumError::ErrorReadingBuffer { .. }) => RS64_ERR_TOO_SHORT,
+        _ => RS64_ERR_INVALID_ARGUMENT,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::{stamp_cic, test_cart};
+    use crate::calculate_cart_checksum;
+
+    #[test]
+    fn detects_and_converts() {
+        let mut cart = test_cart();
+        unsafe {
+            assert_eq!(rs64_detect_swapping(cart.as_ptr(), cart.len()), RS64_SWAP_NATIVE);
+            assert_eq!(rs64_convert(cart.as_mut_ptr(), cart.len(), RS64_SWAP_U32_LITTLE_ENDIAN), RS64_OK);
+            assert_eq!(rs64_detect_swapping(cart.as_ptr(), cart.len()), RS64_SWAP_U32_LITTLE_ENDIAN);
+            assert_eq!(rs64_convert(cart.as_mut_ptr(), cart.len(), 7), RS64_ERR_INVALID_ARGUMENT);
+            assert_eq!(rs64_convert(cart.as_mut_ptr(), 6, RS64_SWAP_NATIVE), RS64_ERR_UNALIGNED);
+
+            let mut blank = [0u8; 64];
+            assert_eq!(rs64_convert(blank.as_mut_ptr(), blank.len(), RS64_SWAP_NATIVE), RS64_ERR_UNKNOWN_SWAPPING);
+            assert_eq!(rs64_detect_swapping(core::ptr::null(), 0), RS64_ERR_NULL);
+        }
+        assert_eq!(cart.len(), test_cart().len());
+    }
+
+    #[test]
+    fn checksums_and_fixes_crcs() {
+        let mut cart = test_cart();
+        stamp_cic(&mut cart, CicVariant::Cic6103);
+        let expected = calculate_cart_checksum(&cart).unwrap();
+
+        let (mut crc1, mut crc2) = (0, 0);
+        unsafe {
+            assert_eq!(rs64_checksum(cart.as_ptr(), cart.len(), RS64_CIC_AUTO, &mut crc1, &mut crc2), RS64_OK);
+            assert_eq!((crc1, crc2), expected);
+            assert_eq!(rs64_checksum(cart.as_ptr(), cart.len(), RS64_CIC_6103, &mut crc1, &mut crc2), RS64_OK);
+            assert_eq!((crc1, crc2), expected);
+            assert_eq!(rs64_checksum(cart.as_ptr(), cart.len(), 6, &mut crc1, &mut crc2), RS64_ERR_INVALID_ARGUMENT);
+            assert_eq!(rs64_checksum(cart.as_ptr(), 0x1000, RS64_CIC_6102, &mut crc1, &mut crc2), RS64_ERR_TOO_SHORT);
+
+            swap_cart_to(ByteSwapping::U16LittleEndian, &mut cart).unwrap();
+            assert_eq!(rs64_fix_crc(cart.as_mut_ptr(), cart.len()), RS64_OK);
+
+            let mut header = Rs64RomHeader::from(RomHeader::new());
+            assert_eq!(rs64_parse_header(cart.as_ptr(), cart.len(), &mut header), RS64_OK);
+            assert_eq!((header.crc1, header.crc2), expected);
+            assert_eq!(rs64_parse_header(cart.as_ptr(), cart.len(), core::ptr::null_mut()), RS64_ERR_NULL);
+        }
+    }
+
+    // The header is generated from this file, so every constant and function
+    // here has to be in it with the same value, and nothing else.
+    #[test]
+    fn header_is_up_to_date() {
+        let header = include_str!("../include/rs64_rom.h");
+        let source = include_str!("capi.rs");
+
+        let mut defines: Vec<(&str, &str)> = header.lines()
+            .filter_map(|line| line.strip_prefix("#define "))
+            .filter_map(|define| define.split_once(' '))
+            .collect();
+        let mut consts: Vec<(&str, &str)> = source.lines()
+            .filter_map(|line| line.strip_prefix("pub const "))
+            .filter_map(|item| Some((item.split_once(':')?.0, item.split_once(" = ")?.1.strip_suffix(';')?)))
+            .collect();
+        defines.sort_unstable();
+        consts.sort_unstable();
+        assert_eq!(defines, consts);
+
+        let functions = source.lines()
+            .filter_map(|line| line.strip_prefix("pub unsafe extern \"C\" fn "))
+            .filter_map(|item| item.split_once('('));
+        for (name, _) in functions {
+            assert!(header.contains(&format!(" {}(", name)), "{} is missing from the header", name);
+        }
+        assert!(header.contains("typedef struct Rs64RomHeader {"));
+    }
+}
@@ -0,0 +1,387 @@
+//! Emulator cheat files, read into and written from one model: games keyed
+//! by their header CRC pair, each with named cheats made of [`CheatCode`]s.
+//!
+//! Project64's `Project64.cht` and mupen64plus's `mupencheat.txt` cover
+//! many games each; RetroArch's `.cht` files hold a single game's cheats and
+//! say nothing about which game, so they convert to and from a cheat list.
+//! Codes with `????` placeholders, which those emulators fill in from a
+//! list of options, have no fixed value and are skipped.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::{CheatCode, Error, RomHeader};
+
+/// One named cheat: codes applied together.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Cheat {
+    pub name: String,
+    pub note: Option<String>,
+    pub codes: Vec<CheatCode>,
+    /// Whether the cheat was switched on, as far as the format records it.
+    pub enabled: bool,
+}
+
+/// A game's cheats.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct CheatGame {
+    pub crc1: u32,
+    pub crc2: u32,
+    /// The header's region byte, which both formats key a game by along
+    /// with the CRCs.
+    pub country: u8,
+    pub name: String,
+    pub cheats: Vec<Cheat>,
+}
+
+/// Cheats for many games, keyed by header CRC pair and region.
+#[derive(Clone, Debug, Default)]
+pub struct CheatDatabase {
+    games: BTreeMap<(u32, u32, u8), CheatGame>,
+}
+
+impl CheatDatabase {
+    pub fn new() -> CheatDatabase {
+        CheatDatabase::default()
+    }
+
+    /// Adds a game, replacing any with the same key.
+    pub fn insert(&mut self, game: CheatGame) {
+        self.games.insert((game.crc1, game.crc2, game.country), game);
+    }
+
+    pub fn games(&self) -> impl Iterator<Item = &CheatGame> {
+        self.games.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    pub fn get(&self, crc1: u32, crc2: u32, country: u8) -> Option<&CheatGame> {
+        self.games.get(&(crc1, crc2, country))
+    }
+
+    /// The cheats for the game with this header.
+    pub fn for_header(&self, header: &RomHeader) -> Option<&CheatGame> {
+        self.get(header.crc1, header.crc2, header.country().as_u8())
+    }
+
+    /// Parses a `Project64.cht`.
+    pub fn parse_project64(text: &str) -> Result<CheatDatabase, Error> {
+        let mut database = CheatDatabase::new();
+        let mut game: Option<CheatGame> = None;
+
+        for line in text.lines().map(str::trim) {
+            if let Some(key) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                if let Some(game) = game.take() {
+                    database.insert(game);
+                }
+                let (crc1, crc2, country) = parse_key(key)?;
+                game = Some(CheatGame { crc1, crc2, country, ..CheatGame::default() });
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let game = match game.as_mut() {
+                Some(game) => game,
+                None => return Err(Error::InvalidCheats("entry before the first game")),
+            };
+
+            if key == "Name" {
+                game.name = value.to_string();
+            } else if let Some(index) = key.strip_prefix("Cheat").and_then(|index| index.strip_suffix("_N")) {
+                if let Some(cheat) = cheat_at(game, index)? {
+                    cheat.note = Some(value.to_string());
+                }
+            } else if let Some(index) = key.strip_prefix("Cheat") {
+                if index.parse::<usize>().is_err() {
+                    // Option lists and other per-cheat keys.
+                    continue;
+                }
+                let (name, codes) = value.strip_prefix('"')
+                    .and_then(|value| value.split_once('"'))
+                    .ok_or(Error::InvalidCheats("cheat name must be quoted"))?;
+                let codes = parse_codes(codes.split(',').filter(|code| !code.trim().is_empty()))?;
+                game.cheats.push(Cheat { name: name.to_string(), codes, ..Cheat::default() });
+            }
+        }
+
+        if let Some(game) = game {
+            database.insert(game);
+        }
+        Ok(database)
+    }
+
+    pub fn to_project64(&self) -> String {
+        let mut text = String::new();
+        for game in self.games() {
+            let _ = writeln!(text, "[{:08X}-{:08X}-C:{:02X}]", game.crc1, game.crc2, game.country);
+            let _ = writeln!(text, "Name={}", game.name);
+            for (index, cheat) in game.cheats.iter().enumerate() {
+                let _ = write!(text, "Cheat{}=\"{}\"", index, cheat.name);
+                for code in &cheat.codes {
+                    let _ = write!(text, ",{}", code);
+                }
+                text.push('\n');
+                if let Some(ref note) = cheat.note {
+                    let _ = writeln!(text, "Cheat{}_N={}", index, note);
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Parses a mupen64plus `mupencheat.txt`.
+    pub fn parse_mupen64plus(text: &str) -> Result<CheatDatabase, Error> {
+        let mut database = CheatDatabase::new();
+        let mut game: Option<CheatGame> = None;
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+
+            let (tag, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let rest = rest.trim();
+            if tag == "crc" {
+                if let Some(game) = game.take() {
+                    database.insert(game);
+                }
+                let (crc1, crc2, country) = parse_key(rest)?;
+                game = Some(CheatGame { crc1, crc2, country, ..CheatGame::default() });
+                continue;
+            }
+
+            let game = game.as_mut().ok_or(Error::InvalidCheats("entry before the first game"))?;
+            match tag {
+                "gn" => game.name = rest.to_string(),
+                "cn" => game.cheats.push(Cheat { name: rest.to_string(), ..Cheat::default() }),
+                "cd" => {
+                    let cheat = game.cheats.last_mut().ok_or(Error::InvalidCheats("description before a cheat"))?;
+                    cheat.note = Some(rest.to_string());
+                },
+                _ => {
+                    let cheat = game.cheats.last_mut().ok_or(Error::InvalidCheats("code before a cheat"))?;
+                    cheat.codes.extend(parse_codes(core::iter::once(line))?);
+                },
+            }
+        }
+
+        if let Some(game) = game {
+            database.insert(game);
+        }
+        Ok(database)
+    }
+
+    pub fn to_mupen64plus(&self) -> String {
+        let mut text = String::new();
+        for game in self.games() {
+            let _ = writeln!(text, "crc {:08X}-{:08X}-C:{:02X}", game.crc1, game.crc2, game.country);
+            let _ = writeln!(text, "gn {}", game.name);
+            for cheat in &game.cheats {
+                let _ = writeln!(text, " cn {}", cheat.name);
+                if let Some(ref note) = cheat.note {
+                    let _ = writeln!(text, "  cd {}", note);
+                }
+                for code in &cheat.codes {
+                    let _ = writeln!(text, "  {}", code);
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+// Built through `insert`, so every game stays under its own key.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for CheatDatabase {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<CheatDatabase> {
+        let mut database = CheatDatabase::new();
+        for game in u.arbitrary_iter::<CheatGame>()? {
+            database.insert(game?);
+        }
+        Ok(database)
+    }
+}
+
+/// Parses a RetroArch `.cht` file's cheats.
+pub fn parse_retroarch_cheats(text: &str) -> Result<Vec<Cheat>, Error> {
+    let mut cheats: BTreeMap<usize, Cheat> = BTreeMap::new();
+
+    for line in text.lines() {
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim().trim_matches('"')),
+            None => continue,
+        };
+        let (index, field) = match key.strip_prefix("cheat").and_then(|key| key.split_once('_')) {
+            Some((index, field)) => match index.parse() {
+                Ok(index) => (index, field),
+                Err(_) => continue,
+            },
+            None => continue,
+        };
+
+        let cheat = cheats.entry(index).or_default();
+        match field {
+            "desc" => cheat.name = value.to_string(),
+            "code" => cheat.codes = parse_codes(value.split('+'))?,
+            "enable" => cheat.enabled = value == "true",
+            _ => {},
+        }
+    }
+
+    Ok(cheats.into_values().collect())
+}
+
+pub fn to_retroarch_cheats(cheats: &[Cheat]) -> String {
+    let mut text = String::new();
+    let _ = writeln!(text, "cheats = {}", cheats.len());
+    for (index, cheat) in cheats.iter().enumerate() {
+        let codes: Vec<String> = cheat.codes.iter().map(ToString::to_string).collect();
+        let _ = writeln!(text);
+        let _ = writeln!(text, "cheat{}_desc = \"{}\"", index, cheat.name);
+        let _ = writeln!(text, "cheat{}_code = \"{}\"", index, codes.join("+"));
+        let _ = writeln!(text, "cheat{}_enable = {}", index, cheat.enabled);
+    }
+    text
+}
+
+// "E95A7D8F-76BC1B6F-C:45", the CRCs and region byte both formats use.
+fn parse_key(key: &str) -> Result<(u32, u32, u8), Error> {
+    let mut parts = key.trim().split('-');
+    let crc1 = parts.next().and_then(|crc| u32::from_str_radix(crc, 16).ok());
+    let crc2 = parts.next().and_then(|crc| u32::from_str_radix(crc, 16).ok());
+    let country = match parts.next() {
+        Some(country) => country.strip_prefix("C:").and_then(|country| u8::from_str_radix(country, 16).ok()),
+        None => Some(0),
+    };
+
+    match (crc1, crc2, country, parts.next()) {
+        (Some(crc1), Some(crc2), Some(country), None) => Ok((crc1, crc2, country)),
+        _ => Err(Error::InvalidCheats("malformed game key")),
+    }
+}
+
+fn parse_codes<'a, I>(codes: I) -> Result<Vec<CheatCode>, Error>
+    where I: IntoIterator<Item = &'a str>
+{
+    codes.into_iter()
+        .map(str::trim)
+        .filter(|code| !code.contains('?'))
+        .map(|code| code.parse().map_err(|_| Error::InvalidCheats("malformed code")))
+        .collect()
+}
+
+fn cheat_at<'a>(game: &'a mut CheatGame, index: &str) -> Result<Option<&'a mut Cheat>, Error> {
+    let index: usize = index.parse().map_err(|_| Error::InvalidCheats("malformed cheat number"))?;
+    Ok(game.cheats.get_mut(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::CountryCode;
+
+    const PROJECT64: &str = r#"
+[E95A7D8F-76BC1B6F-C:45]
+Name=Super Mario 64 (U)
+Cheat0="Infinite Lives",8033B21D 0064
+Cheat0_N=Keeps the counter at 100
+Cheat1="Have\All Stars",8033B218 0078,8033B219 0078
+Cheat2="Level Select",D033AFA1 0020,8033B21E 00??
+Cheat2_O=$00 Castle,$01 Bob-omb
+
+[00000001-00000002-C:4A]
+Name=Other (J)
+"#;
+
+    const MUPEN: &str = "
+crc E95A7D8F-76BC1B6F-C:45
+gn Super Mario 64 (U)
+ cn Infinite Lives
+  cd Keeps the counter at 100
+  8033B21D 0064
+ cn Have\\All Stars
+  8033B218 0078
+  8033B219 0078
+";
+
+    #[test]
+    fn parses_project64() {
+        let database = CheatDatabase::parse_project64(PROJECT64).unwrap();
+        assert_eq!(database.len(), 2);
+
+        let mut header = RomHeader::new();
+        header.crc1 = 0xe95a7d8f;
+        header.crc2 = 0x76bc1b6f;
+        header.set_country(CountryCode::from_u8(0x45));
+        let game = database.for_header(&header).unwrap();
+        assert_eq!(game.name, "Super Mario 64 (U)");
+        assert_eq!(game.cheats.len(), 3);
+        assert_eq!(game.cheats[0].note.as_deref(), Some("Keeps the counter at 100"));
+        assert_eq!(game.cheats[1].codes.len(), 2);
+        assert_eq!(game.cheats[2].codes, ["D033AFA1 0020".parse().unwrap()]);
+
+        let again = CheatDatabase::parse_project64(&database.to_project64()).unwrap();
+        assert_eq!(again.games().collect::<Vec<_>>(), database.games().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn converts_between_formats() {
+        let mupen = CheatDatabase::parse_mupen64plus(MUPEN).unwrap();
+        let mut project64 = CheatDatabase::parse_project64(PROJECT64).unwrap();
+        let mario = project64.get(0xe95a7d8f, 0x76bc1b6f, 0x45).unwrap().clone();
+        let mut trimmed = mario.clone();
+        trimmed.cheats.truncate(2);
+        project64.insert(trimmed);
+
+        assert_eq!(mupen.games().next(), project64.get(0xe95a7d8f, 0x76bc1b6f, 0x45));
+        let again = CheatDatabase::parse_mupen64plus(&mupen.to_mupen64plus()).unwrap();
+        assert_eq!(again.games().collect::<Vec<_>>(), mupen.games().collect::<Vec<_>>());
+
+        let mut cheats = mario.cheats;
+        cheats[0].enabled = true;
+        cheats[0].note = None;
+        cheats[1].note = None;
+        cheats[2].note = None;
+        let retroarch = to_retroarch_cheats(&cheats);
+        assert!(retroarch.contains("cheat1_code = \"8033B218 0078+8033B219 0078\""));
+        assert_eq!(parse_retroarch_cheats(&retroarch).unwrap(), cheats);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(matches!(CheatDatabase::parse_project64("Name=Orphan"), Err(Error::InvalidCheats(_))));
+        assert!(matches!(CheatDatabase::parse_project64("[E95A7D8F]"), Err(Error::InvalidCheats(_))));
+        assert!(CheatDatabase::parse_project64("[1-2-C:45]\nCheat0=\"A\",8033 00").is_err());
+        assert!(CheatDatabase::parse_mupen64plus("crc 1-2-C:45\n  8033B21D 0064").is_err());
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary_databases_keep_their_keys() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let data: Vec<u8> = (1..=4096u32).map(|ii| (ii.wrapping_mul(2654435761) >> 11) as u8).collect();
+        let database = CheatDatabase::arbitrary(&mut Unstructured::new(&data)).unwrap();
+        assert!(!database.is_empty());
+        for game in database.games() {
+            assert_eq!(database.get(game.crc1, game.crc2, game.country), Some(game));
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! GameShark / Action Replay codes: an eight digit code word naming the code
+//! type and a 24 bit RDRAM address, followed by a four digit value.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::error;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::checksum::{native_prefix, CHECKSUM_END};
+use crate::{
+    detect_cic, fix_cart_checksum, resolve_swapping, ByteSwapping, ChecksumError, CicVariant, Error, RomHeader,
+    LOAD_LEN, LOAD_START,
+};
+
+/// What a code does, from the top byte of its code word.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum CheatKind {
+    /// Writes the low byte of the value every frame.
+    Write8,
+    /// Writes the value every frame.
+    Write16,
+    /// Writes the low byte of the value once, before the game boots.
+    BootWrite8,
+    /// Writes the value once, before the game boots.
+    BootWrite16,
+    /// Runs the next code only if the byte at the address equals the value.
+    IfEqual8,
+    IfEqual16,
+    IfNotEqual8,
+    IfNotEqual16,
+    /// Enablers, repeaters, button activators, and anything else.
+    Other(u8),
+}
+
+impl CheatKind {
+    pub fn from_u8(value: u8) -> CheatKind {
+        match value {
+            0x80 | 0xa0 => CheatKind::Write8,
+            0x81 | 0xa1 => CheatKind::Write16,
+            0xf0 => CheatKind::BootWrite8,
+            0xf1 => CheatKind::BootWrite16,
+            0xd0 => CheatKind::IfEqual8,
+            0xd1 => CheatKind::IfEqual16,
+            0xd2 => CheatKind::IfNotEqual8,
+            0xd3 => CheatKind::IfNotEqual16,
+            _ => CheatKind::Other(value),
+        }
+    }
+
+    pub fn is_boot_time(self) -> bool {
+        self == CheatKind::BootWrite8 || self == CheatKind::BootWrite16
+    }
+
+    pub fn is_conditional(self) -> bool {
+        matches!(self, CheatKind::IfEqual8 | CheatKind::IfEqual16 | CheatKind::IfNotEqual8 | CheatKind::IfNotEqual16)
+    }
+}
+
+/// A single code, such as "8033B21D 0064".
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct CheatCode {
+    pub code: u32,
+    pub value: u16,
+}
+
+impl CheatCode {
+    pub fn kind(&self) -> CheatKind {
+        CheatKind::from_u8((self.code >> 24) as u8)
+    }
+
+    /// The virtual address the code touches; uncached for the 0xA0/0xA1
+    /// writes and cached KSEG0 for everything else.
+    pub fn address(&self) -> u32 {
+        let segment = match self.code >> 24 {
+            0xa0 | 0xa1 => 0xa000_0000,
+            _ => 0x8000_0000,
+        };
+        segment | (self.code & 0x00ff_ffff)
+    }
+}
+
+impl fmt::Display for CheatCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:08X} {:04X}", self.code, self.value)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseCheatError(());
+
+impl fmt::Display for ParseCheatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cheat code must be eight hex digits followed by four")
+    }
+}
+
+impl error::Error for ParseCheatError {}
+
+impl FromStr for CheatCode {
+    type Err = ParseCheatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let (code, value) = match (words.next(), words.next(), words.next()) {
+            (Some(code), Some(value), None) => (code, value),
+            (Some(both), None, None) if both.len() == 12 => both.split_at(8),
+            _ => return Err(ParseCheatError(())),
+        };
+
+        if code.len() != 8 || value.len() != 4 || !code.bytes().chain(value.bytes()).all(|b| b.is_ascii_hexdigit()) {
+            return Err(ParseCheatError(()));
+        }
+
+        Ok(CheatCode {
+            code: u32::from_str_radix(code, 16).map_err(|_| ParseCheatError(()))?,
+            value: u16::from_str_radix(value, 16).map_err(|_| ParseCheatError(()))?,
+        })
+    }
+}
+
+/// Parses one code per line, skipping blank lines.
+#[cfg(feature = "alloc")]
+pub fn parse_cheats(text: &str) -> Result<Vec<CheatCode>, ParseCheatError> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.parse())
+        .collect()
+}
+
+/// Bakes boot-time writes into an image, in whatever byte order it's in,
+/// wherever their address falls in the part of the cartridge the IPL3 copies
+/// to memory, which for the 6103 and 6106 is below the header's load address.  The checksum is fixed afterwards so the
+/// image still boots.  Returns how many codes were applied; everything else is
+/// left to the emulator.  On an error the image is untouched.
+pub fn apply_boot_cheats(codes: &[CheatCode], rom: &mut [u8]) -> Result<usize, Error> {
+    let swapping = resolve_swapping(rom).ok_or(Error::UnknownByteSwapping)?;
+    if rom.len() < CHECKSUM_END {
+        return Err(ChecksumError::NotLongEnough { needed: CHECKSUM_END, actual: rom.len() }.into());
+    }
+    if !rom.len().is_multiple_of(swapping.unit_len()) {
+        return Err(Error::UnalignedLength { len: rom.len(), unit: swapping.unit_len() });
+    }
+
+    let prefix = native_prefix(swapping, rom);
+    let cic = detect_cic(&prefix).unwrap_or(CicVariant::Cic6102);
+    let load_addr = RomHeader::from_slice(&prefix)?.effective_entry_point(cic);
+
+    // Native offset of each byte, toggled into the image's ordering.
+    let flip = match swapping {
+        ByteSwapping::Native => 0,
+        ByteSwapping::U16LittleEndian => 1,
+        ByteSwapping::U32LittleEndian => 3,
+    };
+
+    let mut applied = 0;
+    for code in codes.iter() {
+        let bytes = match code.kind() {
+            CheatKind::BootWrite8 => &code.value.to_be_bytes()[1..],
+            CheatKind::BootWrite16 => &code.value.to_be_bytes()[..],
+            _ => continue,
+        };
+
+        let offset = match loaded_offset(load_addr, code.address(), bytes.len()) {
+            // The highest index the flipped bytes can land on.
+            Some(offset) if (offset + bytes.len() - 1) | flip < rom.len() => offset,
+            _ => continue,
+        };
+
+        for (ii, byte) in bytes.iter().enumerate() {
+            rom[(offset + ii) ^ flip] = *byte;
+        }
+        applied += 1;
+    }
+
+    if applied != 0 {
+        fix_cart_checksum(rom)?;
+    }
+
+    Ok(applied)
+}
+
+// Cartridge offset of the `len` bytes at RDRAM `address`, if the boot copy
+// put them there.
+fn loaded_offset(load_addr: u32, address: u32, len: usize) -> Option<usize> {
+    let start = (address & 0x1fff_ffff).checked_sub(load_addr & 0x1fff_ffff)? as usize;
+    if start + len > LOAD_LEN as usize {
+        return None;
+    }
+    Some(LOAD_START + start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::{stamp_cic, test_cart, test_cart_with};
+    use crate::{swap_cart_to, verify_cart_checksum};
+
+    #[test]
+    fn parses_and_classifies() {
+        let code: CheatCode = "8033B21D 0064".parse().unwrap();
+        assert_eq!(code, CheatCode { code: 0x8033b21d, value: 0x0064 });
+        assert_eq!(code.kind(), CheatKind::Write8);
+        assert_eq!(code.address(), 0x8033b21d);
+        assert_eq!(code.to_string(), "8033B21D 0064");
+
+        let uncached: CheatCode = "A1000400FFFF".parse().unwrap();
+        assert_eq!(uncached.kind(), CheatKind::Write16);
+        assert_eq!(uncached.address(), 0xa0000400);
+
+        assert_eq!("F1000400 2400".parse::<CheatCode>().unwrap().kind(), CheatKind::BootWrite16);
+        assert!("D0123456 0001".parse::<CheatCode>().unwrap().kind().is_conditional());
+        assert_eq!("DE000400 0000".parse::<CheatCode>().unwrap().kind(), CheatKind::Other(0xde));
+    }
+
+    #[test]
+    fn rejects_malformed_codes() {
+        assert!("8033B21D".parse::<CheatCode>().is_err());
+        assert!("8033B21D 064".parse::<CheatCode>().is_err());
+        assert!("8033B21G 0064".parse::<CheatCode>().is_err());
+        assert!("8033B21D 0064 0001".parse::<CheatCode>().is_err());
+        assert!("8033B21D +064".parse::<CheatCode>().is_err());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn parses_listings() {
+        let codes = parse_cheats("8033B21D 0064\n\n  F1000400 2400  \n").unwrap();
+        assert_eq!(codes.len(), 2);
+        assert!(parse_cheats("8033B21D 0064\nnope").is_err());
+    }
+
+    #[test]
+    fn bakes_boot_writes_into_the_image() {
+        let mut rom = test_cart_with(|header| header.load_addr = 0x80000400);
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut rom).unwrap();
+
+        let codes = [
+            "F1000410 1234".parse().unwrap(),
+            "F0000415 0056".parse().unwrap(),
+            // Below the load address, past the copied megabyte, and not a boot
+            // write, respectively.
+            "F0000300 0001".parse().unwrap(),
+            "F1200000 0001".parse().unwrap(),
+            "81000420 0001".parse().unwrap(),
+        ];
+        assert_eq!(apply_boot_cheats(&codes, &mut rom).unwrap(), 2);
+        assert!(verify_cart_checksum(&rom).unwrap().is_valid());
+
+        swap_cart_to(ByteSwapping::Native, &mut rom).unwrap();
+        assert_eq!(&rom[LOAD_START + 0x10..LOAD_START + 0x12], &[0x12, 0x34]);
+        assert_eq!(rom[LOAD_START + 0x15], 0x56);
+        assert_eq!(rom[LOAD_START + 0x20..LOAD_START + 0x22], test_cart()[LOAD_START + 0x20..LOAD_START + 0x22]);
+    }
+
+    #[test]
+    fn follows_where_the_ipl3_really_loads() {
+        let mut rom = test_cart_with(|header| header.load_addr = 0x80100400);
+        stamp_cic(&mut rom, CicVariant::Cic6103);
+
+        assert_eq!(apply_boot_cheats(&["F1000410 1234".parse().unwrap()], &mut rom).unwrap(), 1);
+        assert_eq!(&rom[LOAD_START + 0x10..LOAD_START + 0x12], &[0x12, 0x34]);
+        assert!(verify_cart_checksum(&rom).unwrap().is_valid());
+    }
+
+    #[test]
+    fn rejects_images_it_cannot_write_and_checksum() {
+        let codes = ["F1000410 1234".parse().unwrap()];
+        let mut rom = [0x80, 0x37, 0x12, 0x40];
+        assert!(matches!(apply_boot_cheats(&codes, &mut rom),
+                         Err(Error::Checksum(ChecksumError::NotLongEnough { needed: CHECKSUM_END, actual: 4 }))));
+
+        let mut short = test_cart_with(|header| header.load_addr = 0x80000400);
+        short.truncate(CHECKSUM_END - 4);
+        let before = short.clone();
+        assert!(apply_boot_cheats(&codes, &mut short).is_err());
+        assert_eq!(short, before);
+
+        let mut unaligned = test_cart_with(|header| header.load_addr = 0x80000400);
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut unaligned).unwrap();
+        unaligned.extend_from_slice(&[0; 2]);
+        let len = unaligned.len();
+        assert!(matches!(apply_boot_cheats(&codes, &mut unaligned),
+                         Err(Error::UnalignedLength { len: actual, unit: 4 }) if actual == len));
+    }
+}
@@ -0,0 +1,818 @@
+use core::error;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+#[cfg(feature = "std")]
+use crate::Error;
+use crate::{
+    be_u32_at, detect_cic, identify_bootcode, resolve_swapping, swap_to_native, ByteSwapping, CicVariant, Ipl3,
+    RomLayout, BOOTCODE_END, BOOTCODE_LEN, BOOTCODE_START, HEADER_CRC_END, HEADER_CRC_START, LOAD_LEN,
+};
+
+/// Why a CRC pair couldn't be calculated, fixed or forged.
+#[derive(Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ChecksumError {
+    /// The buffer ends before the checksummed region does.
+    NotLongEnough { needed: usize, actual: usize },
+    /// A stream ended at `offset`, before the end of the checksummed region.
+    ErrorReadingBuffer { offset: usize },
+    UnknownByteSwapping,
+    /// The checksummed region's length isn't a whole number of words.
+    UnalignedRegion,
+    /// No filler for the scratch words gives the requested CRC pair.
+    Unforgeable,
+}
+
+impl fmt::Display for ChecksumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ChecksumError::NotLongEnough { needed, actual } => {
+                write!(f, "image too short to checksum: needed {} bytes, got {}", needed, actual)
+            },
+            ChecksumError::ErrorReadingBuffer { offset } => {
+                write!(f, "stream ended at offset 0x{:x}, inside the checksummed region", offset)
+            },
+            ChecksumError::UnknownByteSwapping => write!(f, "unknown byte swapping"),
+            ChecksumError::UnalignedRegion => write!(f, "checksummed region isn't a whole number of words"),
+            ChecksumError::Unforgeable => write!(f, "no scratch words give the requested CRCs"),
+        }
+    }
+}
+
+impl error::Error for ChecksumError {}
+
+const CHECKSUM_START:  usize = BOOTCODE_END;
+const CHECKSUM_LENGTH: usize = LOAD_LEN as usize;
+pub(crate) const CHECKSUM_END: usize = CHECKSUM_START + CHECKSUM_LENGTH;
+
+/// Bootcode words the 6105 IPL3 folds into its checksum instead of `t5`.
+const CHECKSUM_6105_TABLE_START: usize = BOOTCODE_START + 0x710;
+
+/// The window and seed an IPL3 checksums, for bootcodes that don't use the
+/// retail 0x1000..0x101000 region.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ChecksumParams {
+    pub start: usize,
+    pub len: usize,
+    pub seed: u32,
+    /// Selects the per-word mixing and final CRC combination.
+    pub variant: CicVariant,
+}
+
+impl ChecksumParams {
+    /// The parameters the retail IPL3 for `variant` uses.
+    pub fn for_variant(variant: CicVariant) -> ChecksumParams {
+        ChecksumParams::for_layout(variant, &RomLayout::STANDARD)
+    }
+
+    /// `variant`'s seed and mixing over the region `layout` checksums.
+    pub fn for_layout(variant: CicVariant, layout: &RomLayout) -> ChecksumParams {
+        ChecksumParams {
+            start: layout.load_start(),
+            len: layout.checksum_len,
+            seed: variant.checksum_seed(),
+            variant,
+        }
+    }
+}
+
+impl Default for ChecksumParams {
+    fn default() -> ChecksumParams {
+        ChecksumParams::for_variant(CicVariant::Cic6102)
+    }
+}
+
+/// Calculates the CRC pair over a native byte order image the way its
+/// detected CIC's IPL3 does, falling back to the 6102 algorithm (shared by the
+/// 6101 and 7102) for unknown bootcode.
+pub fn calculate_cart_checksum(buffer: &[u8]) -> Result<(u32, u32), ChecksumError> {
+    let variant = detect_cic(buffer).unwrap_or(CicVariant::Cic6102);
+    calculate_cart_checksum_with(variant, buffer)
+}
+
+/// Calculates the CRC pair over a native byte order image the way the IPL3
+/// for `variant` does.
+pub fn calculate_cart_checksum_with(variant: CicVariant, buffer: &[u8]) -> Result<(u32, u32), ChecksumError> {
+    calculate_cart_checksum_with_params(&ChecksumParams::for_variant(variant), buffer)
+}
+
+/// Calculates the CRC pair over `params.len` bytes of a native byte order
+/// image starting at `params.start`.
+pub fn calculate_cart_checksum_with_params(params: &ChecksumParams, buffer: &[u8])
+                                           -> Result<(u32, u32), ChecksumError> {
+    if !params.len.is_multiple_of(4) {
+        return Err(ChecksumError::UnalignedRegion);
+    }
+
+    let end = match params.start.checked_add(params.len) {
+        Some(end) => end,
+        None => return Err(ChecksumError::NotLongEnough { needed: usize::MAX, actual: buffer.len() }),
+    };
+    let needed = core::cmp::max(end, BOOTCODE_END);
+    if buffer.len() < needed {
+        return Err(ChecksumError::NotLongEnough { needed, actual: buffer.len() });
+    }
+
+    log_trace!("checksumming {} bytes at 0x{:x} for {}", params.len, params.start, params.variant);
+    let mut state = ChecksumState::with_seed(params.variant, params.seed, &buffer[..BOOTCODE_END]);
+    state.update(&buffer[params.start..end]);
+
+    Ok(state.finish())
+}
+
+/// Like [`calculate_cart_checksum_with`], but for an image in `swapping`
+/// order.  Each word is normalized as it's read, so the buffer is left as is.
+pub fn calculate_swapped_cart_checksum(variant: CicVariant, swapping: ByteSwapping, buffer: &[u8])
+                                       -> Result<(u32, u32), ChecksumError> {
+    if buffer.len() < CHECKSUM_END {
+        return Err(ChecksumError::NotLongEnough { needed: CHECKSUM_END, actual: buffer.len() });
+    }
+
+    let prefix = native_prefix(swapping, buffer);
+    let mut state = ChecksumState::new(variant, &prefix);
+    state.update_swapped(swapping, &buffer[CHECKSUM_START..CHECKSUM_END]);
+
+    Ok(state.finish())
+}
+
+// Native order copy of the header and bootcode of a buffer known to be at
+// least that long.
+pub(crate) fn native_prefix(swapping: ByteSwapping, buffer: &[u8]) -> [u8; BOOTCODE_END] {
+    let mut prefix = [0u8; BOOTCODE_END];
+    prefix.copy_from_slice(&buffer[..BOOTCODE_END]);
+    swap_to_native(swapping, &mut prefix);
+    prefix
+}
+
+/// Streaming version of [`calculate_cart_checksum_with`] that reads a native
+/// byte order image from the start of `reader`, consuming only the header,
+/// bootcode, and checksummed region.
+#[cfg(feature = "std")]
+pub fn calculate_cart_checksum_from_reader<R: Read>(variant: CicVariant, reader: &mut R) -> Result<(u32, u32), Error> {
+    let mut prefix = [0u8; BOOTCODE_END];
+    read_checksummed(reader, 0, &mut prefix)?;
+
+    let mut state = ChecksumState::new(variant, &prefix);
+    let mut chunk = [0u8; 4096];
+
+    for ii in 0..(CHECKSUM_LENGTH / chunk.len()) {
+        read_checksummed(reader, CHECKSUM_START + ii * chunk.len(), &mut chunk)?;
+        state.update(&chunk);
+    }
+
+    Ok(state.finish())
+}
+
+#[cfg(feature = "std")]
+fn read_checksummed<R: Read>(reader: &mut R, offset: usize, buffer: &mut [u8]) -> Result<(), Error> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..]) {
+            Ok(0) => return Err(ChecksumError::ErrorReadingBuffer { offset: offset + len }.into()),
+            Ok(count) => len += count,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {},
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(())
+}
+
+/// Incremental cart checksum for data that arrives piecemeal, such as from a
+/// USB dumper.  Feed the native byte order image from its first byte in
+/// chunks of any size; anything past the checksummed region is ignored.
+pub struct CartChecksum {
+    variant: CicVariant,
+    prefix: [u8; BOOTCODE_END],
+    position: usize,
+    pending: [u8; 4],
+    pending_len: usize,
+    state: Option<ChecksumState>,
+}
+
+impl CartChecksum {
+    pub fn new(variant: CicVariant) -> CartChecksum {
+        CartChecksum {
+            variant,
+            prefix: [0u8; BOOTCODE_END],
+            position: 0,
+            pending: [0u8; 4],
+            pending_len: 0,
+            state: None,
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        if self.position < CHECKSUM_START {
+            let take = core::cmp::min(CHECKSUM_START - self.position, data.len());
+            self.prefix[self.position..self.position + take].copy_from_slice(&data[..take]);
+            self.position += take;
+            data = &data[take..];
+
+            if self.position < CHECKSUM_START {
+                return;
+            }
+            self.state = Some(ChecksumState::new(self.variant, &self.prefix));
+        }
+
+        let remaining = CHECKSUM_END.saturating_sub(self.position);
+        data = &data[..core::cmp::min(remaining, data.len())];
+        self.position += data.len();
+
+        let state = match self.state {
+            Some(ref mut state) => state,
+            None => return,
+        };
+
+        if self.pending_len != 0 {
+            let take = core::cmp::min(4 - self.pending_len, data.len());
+            self.pending[self.pending_len..self.pending_len + take].copy_from_slice(&data[..take]);
+            self.pending_len += take;
+            data = &data[take..];
+
+            if self.pending_len < 4 {
+                return;
+            }
+            state.update(&self.pending);
+            self.pending_len = 0;
+        }
+
+        let whole = data.len() & !3;
+        state.update(&data[..whole]);
+
+        let rest = &data[whole..];
+        self.pending[..rest.len()].copy_from_slice(rest);
+        self.pending_len = rest.len();
+    }
+
+    /// The CRC pair, once the whole checksummed region has been fed.
+    pub fn finalize(self) -> Result<(u32, u32), ChecksumError> {
+        match self.state {
+            Some(ref state) if self.position == CHECKSUM_END => Ok(state.finish()),
+            _ => Err(ChecksumError::NotLongEnough { needed: CHECKSUM_END, actual: self.position }),
+        }
+    }
+}
+
+// The IPL3's rolling checksum, fed one big endian word at a time.
+#[derive(Clone)]
+pub(crate) struct ChecksumState {
+    variant: CicVariant,
+    /// Bootcode words mixed in by the 6105 algorithm, by word index mod 64.
+    table: [u32; 64],
+    words: usize,
+    t1: u32,
+    t2: u32,
+    t3: u32,
+    t4: u32,
+    t5: u32,
+    t6: u32,
+}
+
+impl ChecksumState {
+    // `prefix` is the header and bootcode, which only the 6105 algorithm reads.
+    fn new(variant: CicVariant, prefix: &[u8]) -> ChecksumState {
+        ChecksumState::with_seed(variant, variant.checksum_seed(), prefix)
+    }
+
+    pub(crate) fn with_seed(variant: CicVariant, seed: u32, prefix: &[u8]) -> ChecksumState {
+        let mut table = [0u32; 64];
+        if variant == CicVariant::Cic6105 {
+            for (ii, entry) in table.iter_mut().enumerate() {
+                *entry = be_u32_at(prefix, CHECKSUM_6105_TABLE_START + ii * 4);
+            }
+        }
+
+        ChecksumState {
+            variant,
+            table,
+            words: 0,
+            t1: seed,
+            t2: seed,
+            t3: seed,
+            t4: seed,
+            t5: seed,
+            t6: seed,
+        }
+    }
+
+    // Callers always hand over whole words.
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        for word in data.chunks_exact(4) {
+            self.update_word(u32::from_be_bytes([word[0], word[1], word[2], word[3]]));
+        }
+    }
+
+    fn update_swapped(&mut self, swapping: ByteSwapping, data: &[u8]) {
+        match swapping {
+            ByteSwapping::Native => self.update(data),
+            ByteSwapping::U16LittleEndian => {
+                for word in data.chunks_exact(4) {
+                    self.update_word(u32::from_be_bytes([word[1], word[0], word[3], word[2]]));
+                }
+            },
+            ByteSwapping::U32LittleEndian => {
+                for word in data.chunks_exact(4) {
+                    self.update_word(u32::from_le_bytes([word[0], word[1], word[2], word[3]]));
+                }
+            },
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn update_word(&mut self, c1: u32) {
+        let mut k1 = self.t6.wrapping_add(c1);
+        if k1 < self.t6 {
+            self.t4 = self.t4.wrapping_add(1);
+        }
+        self.t6 = k1;
+        self.t3 ^= c1;
+        let k2 = c1 & 0x1f;
+        k1 = c1.rotate_left(k2);
+        self.t5 = self.t5.wrapping_add(k1);
+        if c1 < self.t2 {
+            self.t2 ^= k1;
+        } else {
+            self.t2 ^= self.t6 ^ c1;
+        }
+        if self.variant == CicVariant::Cic6105 {
+            self.t1 = self.t1.wrapping_add(self.table[self.words & 0x3f] ^ c1);
+        } else {
+            self.t1 = self.t1.wrapping_add(c1 ^ self.t5);
+        }
+        self.words += 1;
+    }
+
+    pub(crate) fn finish(&self) -> (u32, u32) {
+        let (t1, t2, t3, t4, t5, t6) = (self.t1, self.t2, self.t3, self.t4, self.t5, self.t6);
+
+        match self.variant {
+            CicVariant::Cic6103 => (
+                (t6 ^ t4).wrapping_add(t3),
+                (t5 ^ t2).wrapping_add(t1)),
+            CicVariant::Cic6106 => (
+                t6.wrapping_mul(t4).wrapping_add(t3),
+                t5.wrapping_mul(t2).wrapping_add(t1)),
+            _ => (
+                t6 ^ t4 ^ t3,
+                t5 ^ t2 ^ t1),
+        }
+    }
+
+    // `t4` counts `t6`'s carries, so the two are a 64 bit sum of every word.
+    pub(crate) fn sum(&self) -> u64 {
+        (self.t4 as u64) << 32 | self.t6 as u64
+    }
+
+    pub(crate) fn xor(&self) -> u32 {
+        self.t3
+    }
+
+    // The `t3` that makes `finish` give `crc1` with `sum` as `t4` and `t6`.
+    pub(crate) fn crc1_xor(variant: CicVariant, sum: u64, crc1: u32) -> u32 {
+        let (t4, t6) = ((sum >> 32) as u32, sum as u32);
+
+        match variant {
+            CicVariant::Cic6103 => crc1.wrapping_sub(t6 ^ t4),
+            CicVariant::Cic6106 => crc1.wrapping_sub(t6.wrapping_mul(t4)),
+            _ => crc1 ^ t6 ^ t4,
+        }
+    }
+}
+
+/// Recomputes the CRC pair for the image's detected CIC (falling back to the
+/// 6102 algorithm for unknown bootcode) and writes it into the header, leaving
+/// the buffer in its original byte order.
+pub fn fix_cart_checksum(buffer: &mut [u8]) -> Result<(u32, u32), ChecksumError> {
+    let swapping = match resolve_swapping(buffer) {
+        Some(swapping) => swapping,
+        None => {
+            return Err(ChecksumError::UnknownByteSwapping);
+        },
+    };
+
+    if buffer.len() < CHECKSUM_END {
+        return Err(ChecksumError::NotLongEnough { needed: CHECKSUM_END, actual: buffer.len() });
+    }
+
+    let prefix = native_prefix(swapping, buffer);
+    let variant = detect_cic(&prefix).unwrap_or(CicVariant::Cic6102);
+    let (crc1, crc2) = calculate_swapped_cart_checksum(variant, swapping, buffer)?;
+
+    let mut crcs = [0u8; HEADER_CRC_END - HEADER_CRC_START];
+    crcs[..4].copy_from_slice(&crc1.to_be_bytes());
+    crcs[4..].copy_from_slice(&crc2.to_be_bytes());
+    swap_to_native(swapping, &mut crcs);
+    buffer[HEADER_CRC_START..HEADER_CRC_END].copy_from_slice(&crcs);
+    log_debug!("wrote {} CRCs 0x{:08x} 0x{:08x} in {} order", variant, crc1, crc2, swapping);
+
+    Ok((crc1, crc2))
+}
+
+/// The CRC pair every CIC's IPL3 calculates for one image, for when the
+/// bootcode doesn't say which it is.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CicChecksums {
+    /// The pair for each of [`CicVariant::ALL`], in that order.
+    pub crcs: [(u32, u32); 6],
+    pub stored: (u32, u32),
+    /// The CIC the bootcode fingerprints as, if it's a known one.
+    pub detected: Option<CicVariant>,
+}
+
+impl CicChecksums {
+    pub fn get(&self, variant: CicVariant) -> (u32, u32) {
+        let index = CicVariant::ALL.iter().position(|candidate| *candidate == variant).unwrap();
+        self.crcs[index]
+    }
+
+    /// Every CIC whose pair is the stored one.  The 6101, 6102 and 7102
+    /// share an algorithm, so they always match together.
+    pub fn matching(&self) -> impl Iterator<Item = CicVariant> + '_ {
+        CicVariant::ALL.iter().cloned().filter(move |variant| self.get(*variant) == self.stored)
+    }
+
+    /// The CIC the stored pair was made for: the detected one if it
+    /// matches, or else the first that does.
+    pub fn matching_variant(&self) -> Option<CicVariant> {
+        match self.detected {
+            Some(detected) if self.get(detected) == self.stored => Some(detected),
+            _ => self.matching().next(),
+        }
+    }
+}
+
+/// Calculates every CIC's CRC pair in a single pass over the checksummed
+/// region of an image in any byte order, leaving the buffer as is.
+pub fn calculate_all_cart_checksums(buffer: &[u8]) -> Result<CicChecksums, ChecksumError> {
+    // One state per distinct algorithm; the rest reuse the 6102's.
+    const DISTINCT: [CicVariant; 4] = [CicVariant::Cic6102, CicVariant::Cic6103, CicVariant::Cic6105,
+                                       CicVariant::Cic6106];
+
+    let swapping = resolve_swapping(buffer).ok_or(ChecksumError::UnknownByteSwapping)?;
+    if buffer.len() < CHECKSUM_END {
+        return Err(ChecksumError::NotLongEnough { needed: CHECKSUM_END, actual: buffer.len() });
+    }
+
+    let prefix = native_prefix(swapping, buffer);
+    let mut states = DISTINCT.map(|variant| ChecksumState::new(variant, &prefix));
+    for word in buffer[CHECKSUM_START..CHECKSUM_END].chunks_exact(4) {
+        let word = match swapping {
+            ByteSwapping::Native => u32::from_be_bytes([word[0], word[1], word[2], word[3]]),
+            ByteSwapping::U16LittleEndian => u32::from_be_bytes([word[1], word[0], word[3], word[2]]),
+            ByteSwapping::U32LittleEndian => u32::from_le_bytes([word[0], word[1], word[2], word[3]]),
+        };
+        for state in states.iter_mut() {
+            state.update_word(word);
+        }
+    }
+
+    let crcs = CicVariant::ALL.map(|variant| {
+        let state = DISTINCT.iter().position(|distinct| *distinct == variant).unwrap_or(0);
+        states[state].finish()
+    });
+    let checksums = CicChecksums {
+        crcs,
+        stored: (be_u32_at(&prefix, HEADER_CRC_START), be_u32_at(&prefix, HEADER_CRC_START + 4)),
+        detected: detect_cic(&prefix),
+    };
+    log_debug!("stored CRCs match {:?}", checksums.matching_variant());
+    Ok(checksums)
+}
+
+/// Outcome of comparing the CRC pair stored in a header against the one
+/// calculated from the image.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChecksumReport {
+    pub variant: CicVariant,
+    /// The IPL3 the image boots with, or `None` if it isn't a known one and
+    /// `variant` is only a guess.
+    pub ipl3: Option<Ipl3>,
+    pub expected_crc1: u32,
+    pub expected_crc2: u32,
+    pub stored_crc1: u32,
+    pub stored_crc2: u32,
+    pub crc1_matches: bool,
+    pub crc2_matches: bool,
+}
+
+impl ChecksumReport {
+    /// Whether the image's IPL3 will accept it: the CRCs match, or the IPL3
+    /// is one that doesn't check them.
+    pub fn is_valid(&self) -> bool {
+        self.crcs_match() || self.ipl3.is_some_and(|ipl3| !ipl3.verifies_crcs())
+    }
+
+    /// Whether the stored CRCs match, regardless of the IPL3.
+    pub fn crcs_match(&self) -> bool {
+        self.crc1_matches && self.crc2_matches
+    }
+}
+
+/// Checks the CRC pair stored in the header against the one calculated for the
+/// image's detected CIC (6102 for unknown bootcode).  Byte swapped images are
+/// checksummed in place without being modified.
+pub fn verify_cart_checksum(buffer: &[u8]) -> Result<ChecksumReport, ChecksumError> {
+    let swapping = match resolve_swapping(buffer) {
+        Some(swapping) => swapping,
+        None => {
+            return Err(ChecksumError::UnknownByteSwapping);
+        },
+    };
+
+    if buffer.len() < CHECKSUM_END {
+        return Err(ChecksumError::NotLongEnough { needed: CHECKSUM_END, actual: buffer.len() });
+    }
+
+    let prefix = native_prefix(swapping, buffer);
+    let mut bootcode = [0u8; BOOTCODE_LEN as usize];
+    bootcode.copy_from_slice(&prefix[BOOTCODE_START..BOOTCODE_END]);
+    let ipl3 = identify_bootcode(&bootcode);
+    let variant = ipl3.map_or(CicVariant::Cic6102, Ipl3::cic);
+    let (expected_crc1, expected_crc2) = calculate_swapped_cart_checksum(variant, swapping, buffer)?;
+    let stored_crc1 = be_u32_at(&prefix, HEADER_CRC_START);
+    let stored_crc2 = be_u32_at(&prefix, HEADER_CRC_START + 4);
+    if (expected_crc1, expected_crc2) != (stored_crc1, stored_crc2) {
+        log_debug!("stored CRCs 0x{:08x} 0x{:08x} aren't {}'s 0x{:08x} 0x{:08x}",
+                   stored_crc1, stored_crc2, variant, expected_crc1, expected_crc2);
+    }
+
+    Ok(ChecksumReport {
+        variant,
+        ipl3,
+        expected_crc1,
+        expected_crc2,
+        stored_crc1,
+        stored_crc2,
+        crc1_matches: expected_crc1 == stored_crc1,
+        crc2_matches: expected_crc2 == stored_crc2,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::{stamp_cic, test_cart};
+    use crate::{detect_swapping, swap_cart_to, RomHeader};
+
+    #[test]
+    fn calculate_fails_with_slice_to_small() {
+        let empty_array = [0u8;0];
+        assert_eq!(calculate_cart_checksum(&empty_array),
+                   Err(ChecksumError::NotLongEnough { needed: CHECKSUM_END, actual: 0 }));
+    }
+
+    #[test]
+    #[allow(clippy::needless_range_loop, clippy::same_item_push)]
+    fn checksum_test_vectors() {
+        let mut zero_vec: Vec<u8> = Vec::new();
+        for _ in 0..CHECKSUM_END {
+            zero_vec.push(0);
+        }
+        assert_eq!(calculate_cart_checksum(&zero_vec), Ok((0xF8CA4DDC, 0x303A4DDC)));
+
+        for i in 0..CHECKSUM_END {
+            zero_vec[i] = 0xFF;
+        }
+        assert_eq!(calculate_cart_checksum(&zero_vec), Ok((0xF8C24DDC, 0xC1544DDC)));
+
+        for i in 0..CHECKSUM_END {
+            zero_vec[i] = 0x41;
+        }
+        assert_eq!(calculate_cart_checksum(&zero_vec), Ok((0xFDCF52E1, 0xCD5A4DDC)));
+    }
+
+    #[test]
+    fn checksum_with_variant_test_vectors() {
+        let mut pattern: Vec<u8> = vec![0; CHECKSUM_END];
+        for (ii, byte) in pattern.iter_mut().enumerate() {
+            *byte = (ii * 7 + 3) as u8;
+        }
+
+        let expected = [
+            (CicVariant::Cic6101, (0xF6C453DE, 0x6D3C908C)),
+            (CicVariant::Cic6102, (0xF6C453DE, 0x6D3C908C)),
+            (CicVariant::Cic6103, (0xA592715B, 0x6CC2672E)),
+            (CicVariant::Cic6105, (0xDD10FA38, 0xC11B9C1E)),
+            (CicVariant::Cic6106, (0x5141AE92, 0x66E19326)),
+            (CicVariant::Cic7102, (0xF6C453DE, 0x6D3C908C)),
+        ];
+
+        for (variant, crcs) in expected.iter() {
+            assert_eq!(calculate_cart_checksum_with(*variant, &pattern), Ok(*crcs), "{}", variant);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn streaming_checksum_matches_slice_checksum() {
+        let mut pattern = test_cart();
+        pattern.extend_from_slice(&[0x55; 64]);
+
+        for variant in CicVariant::ALL.iter() {
+            let mut reader = &pattern[..];
+            assert_eq!(calculate_cart_checksum_from_reader(*variant, &mut reader).unwrap(),
+                       calculate_cart_checksum_with(*variant, &pattern).unwrap());
+            assert_eq!(reader.len(), 64);
+        }
+
+        let short = &pattern[..CHECKSUM_END - 1];
+        match calculate_cart_checksum_from_reader(CicVariant::Cic6102, &mut &short[..]) {
+            Err(Error::Checksum(ChecksumError::ErrorReadingBuffer { offset })) => assert_eq!(offset, CHECKSUM_END - 1),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn incremental_checksum_matches_slice_checksum() {
+        let mut pattern = test_cart();
+        pattern.extend_from_slice(&[0x55; 64]);
+
+        for variant in CicVariant::ALL.iter() {
+            let expected = calculate_cart_checksum_with(*variant, &pattern).unwrap();
+
+            for chunk_len in [1, 3, 4, 4093, 65536].iter() {
+                let mut digest = CartChecksum::new(*variant);
+                for chunk in pattern.chunks(*chunk_len) {
+                    digest.update(chunk);
+                }
+                assert_eq!(digest.finalize(), Ok(expected), "{} in {} byte chunks", variant, chunk_len);
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_checksum_needs_whole_region() {
+        let pattern = test_cart();
+
+        let mut digest = CartChecksum::new(CicVariant::Cic6102);
+        digest.update(&pattern[..CHECKSUM_END - 1]);
+        assert_eq!(digest.finalize(), Err(ChecksumError::NotLongEnough { needed: CHECKSUM_END, actual: CHECKSUM_END - 1 }));
+
+        let mut digest = CartChecksum::new(CicVariant::Cic6102);
+        digest.update(&pattern[..100]);
+        assert_eq!(digest.finalize(), Err(ChecksumError::NotLongEnough { needed: CHECKSUM_END, actual: 100 }));
+    }
+
+    #[test]
+    fn detected_6105_uses_bootcode_table() {
+        let mut cart = test_cart();
+        crate::tests::stamp_cic(&mut cart, CicVariant::Cic6105);
+
+        let expected = calculate_cart_checksum_with(CicVariant::Cic6105, &cart);
+        assert_eq!(calculate_cart_checksum(&cart), expected);
+        assert_ne!(calculate_cart_checksum_with(CicVariant::Cic6102, &cart), expected);
+
+        // The table words come from the bootcode, not the checksummed data.
+        cart[CHECKSUM_6105_TABLE_START + 3] ^= 0x04;
+        assert_ne!(calculate_cart_checksum_with(CicVariant::Cic6105, &cart), expected);
+
+        let mut cart = test_cart();
+        crate::tests::stamp_cic(&mut cart, CicVariant::Cic6105);
+        let (crc1, crc2) = fix_cart_checksum(&mut cart).unwrap();
+        assert_eq!(Ok((crc1, crc2)), expected);
+        let report = verify_cart_checksum(&cart).unwrap();
+        assert_eq!(report.variant, CicVariant::Cic6105);
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn params_select_checksum_region() {
+        let cart = test_cart();
+
+        for variant in CicVariant::ALL.iter() {
+            assert_eq!(calculate_cart_checksum_with_params(&ChecksumParams::for_variant(*variant), &cart),
+                       calculate_cart_checksum_with(*variant, &cart));
+        }
+
+        // A window moved past the retail region only sees the moved data.
+        let mut shifted = vec![0u8; CHECKSUM_END + 0x1000];
+        shifted[..BOOTCODE_END].copy_from_slice(&cart[..BOOTCODE_END]);
+        shifted[CHECKSUM_START + 0x1000..].copy_from_slice(&cart[CHECKSUM_START..CHECKSUM_END]);
+        let params = ChecksumParams { start: CHECKSUM_START + 0x1000, ..ChecksumParams::default() };
+        assert_eq!(calculate_cart_checksum_with_params(&params, &shifted), calculate_cart_checksum(&cart));
+
+        let layout = RomLayout { bootcode_len: RomLayout::STANDARD.bootcode_len + 0x1000, ..RomLayout::STANDARD };
+        assert_eq!(ChecksumParams::for_layout(CicVariant::Cic6102, &layout), params);
+
+        let params = ChecksumParams { seed: 0, ..ChecksumParams::default() };
+        assert_ne!(calculate_cart_checksum_with_params(&params, &cart), calculate_cart_checksum(&cart));
+    }
+
+    #[test]
+    fn params_reject_bad_regions() {
+        let cart = test_cart();
+
+        let params = ChecksumParams { len: 6, ..ChecksumParams::default() };
+        assert_eq!(calculate_cart_checksum_with_params(&params, &cart), Err(ChecksumError::UnalignedRegion));
+
+        let params = ChecksumParams { start: cart.len(), ..ChecksumParams::default() };
+        assert_eq!(calculate_cart_checksum_with_params(&params, &cart),
+                   Err(ChecksumError::NotLongEnough { needed: cart.len() + params.len, actual: cart.len() }));
+
+        let params = ChecksumParams { start: usize::MAX, ..ChecksumParams::default() };
+        assert!(matches!(calculate_cart_checksum_with_params(&params, &cart),
+                         Err(ChecksumError::NotLongEnough { needed: usize::MAX, .. })));
+    }
+
+    #[test]
+    fn swapped_checksum_leaves_buffer_untouched() {
+        let native = test_cart();
+
+        for swapping in [ByteSwapping::Native, ByteSwapping::U16LittleEndian, ByteSwapping::U32LittleEndian].iter() {
+            let mut swapped = native.clone();
+            swap_cart_to(*swapping, &mut swapped).unwrap();
+            let before = swapped.clone();
+
+            for variant in CicVariant::ALL.iter() {
+                assert_eq!(calculate_swapped_cart_checksum(*variant, *swapping, &swapped),
+                           calculate_cart_checksum_with(*variant, &native));
+            }
+            assert_eq!(swapped, before);
+        }
+    }
+
+    #[test]
+    fn fix_checksum_writes_crcs_to_header() {
+        let mut cart = test_cart();
+        assert_eq!(fix_cart_checksum(&mut cart), Ok((0xF6C453DE, 0x6D3C908C)));
+
+        let header = RomHeader::from_slice(&cart).unwrap();
+        assert_eq!((header.crc1, header.crc2), (0xF6C453DE, 0x6D3C908C));
+    }
+
+    #[test]
+    fn fix_checksum_preserves_byte_swapping() {
+        let mut native = test_cart();
+        fix_cart_checksum(&mut native).unwrap();
+
+        let mut swapped = test_cart();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut swapped).unwrap();
+        assert_eq!(fix_cart_checksum(&mut swapped), Ok((0xF6C453DE, 0x6D3C908C)));
+        assert_eq!(detect_swapping(&swapped), Some(ByteSwapping::U16LittleEndian));
+
+        swap_cart_to(ByteSwapping::Native, &mut swapped).unwrap();
+        assert_eq!(swapped, native);
+    }
+
+    #[test]
+    fn fix_checksum_rejects_unknown_swapping() {
+        let mut blank = vec![0u8; CHECKSUM_END];
+        assert_eq!(fix_cart_checksum(&mut blank), Err(ChecksumError::UnknownByteSwapping));
+    }
+
+    #[test]
+    fn all_checksums_match_each_variant() {
+        let mut rom = test_cart();
+        stamp_cic(&mut rom, CicVariant::Cic6105);
+        let crcs = calculate_cart_checksum_with(CicVariant::Cic6105, &rom).unwrap();
+        rom[HEADER_CRC_START..HEADER_CRC_START + 4].copy_from_slice(&crcs.0.to_be_bytes());
+        rom[HEADER_CRC_START + 4..HEADER_CRC_END].copy_from_slice(&crcs.1.to_be_bytes());
+
+        let mut swapped = rom.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut swapped).unwrap();
+        let all = calculate_all_cart_checksums(&swapped).unwrap();
+        assert_eq!(all, calculate_all_cart_checksums(&rom).unwrap());
+
+        for variant in CicVariant::ALL.iter() {
+            assert_eq!(all.get(*variant), calculate_cart_checksum_with(*variant, &rom).unwrap());
+        }
+        assert_eq!((all.stored, all.detected), (crcs, Some(CicVariant::Cic6105)));
+        assert_eq!(all.matching().collect::<Vec<_>>(), [CicVariant::Cic6105]);
+        assert_eq!(all.matching_variant(), Some(CicVariant::Cic6105));
+
+        // Without a known bootcode, the shared 6102 algorithm's first member
+        // is reported.
+        let mut rom = test_cart();
+        fix_cart_checksum(&mut rom).unwrap();
+        let all = calculate_all_cart_checksums(&rom).unwrap();
+        assert_eq!(all.detected, None);
+        assert_eq!(all.matching().count(), 3);
+        assert_eq!(all.matching_variant(), Some(CicVariant::Cic6101));
+        assert!(matches!(calculate_all_cart_checksums(&rom[..CHECKSUM_END - 4]),
+                         Err(ChecksumError::NotLongEnough { .. })));
+    }
+
+    #[test]
+    fn verify_checksum_reports_per_word_matches() {
+        let mut cart = test_cart();
+        let report = verify_cart_checksum(&cart).unwrap();
+        assert_eq!(report.variant, CicVariant::Cic6102);
+        assert_eq!((report.expected_crc1, report.expected_crc2), (0xF6C453DE, 0x6D3C908C));
+        assert_eq!((report.stored_crc1, report.stored_crc2), (0, 0));
+        assert!(!report.is_valid());
+
+        fix_cart_checksum(&mut cart).unwrap();
+        cart[HEADER_CRC_START + 4] ^= 0x01;
+        let report = verify_cart_checksum(&cart).unwrap();
+        assert!(report.crc1_matches);
+        assert!(!report.crc2_matches);
+
+        cart[HEADER_CRC_START + 4] ^= 0x01;
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut cart).unwrap();
+        assert!(verify_cart_checksum(&cart).unwrap().is_valid());
+    }
+}
@@ -0,0 +1,126 @@
+use core::fmt;
+
+use crate::crc32::crc32;
+use crate::{BOOTCODE_END, BOOTCODE_START};
+
+/// The lockout chip a cart was built for, as implied by its IPL3 bootcode.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CicVariant {
+    Cic6101,
+    Cic6102,
+    Cic6103,
+    Cic6105,
+    Cic6106,
+    Cic7102,
+}
+
+impl CicVariant {
+    pub const ALL: [CicVariant; 6] = [
+        CicVariant::Cic6101,
+        CicVariant::Cic6102,
+        CicVariant::Cic6103,
+        CicVariant::Cic6105,
+        CicVariant::Cic6106,
+        CicVariant::Cic7102,
+    ];
+
+    /// Initial value the IPL3 loads into every checksum accumulator.
+    pub fn checksum_seed(self) -> u32 {
+        match self {
+            CicVariant::Cic6101 => 0xf8ca4ddc,
+            CicVariant::Cic6102 => 0xf8ca4ddc,
+            CicVariant::Cic6103 => 0xa3886759,
+            CicVariant::Cic6105 => 0xdf26f436,
+            CicVariant::Cic6106 => 0x1fea617a,
+            CicVariant::Cic7102 => 0xf8ca4ddc,
+        }
+    }
+
+    /// How far below the header's load address the IPL3 for this CIC copies
+    /// the payload and jumps, which the 6103 and 6106 shift down to make
+    /// the header alone useless for booting on another CIC.
+    pub fn entry_offset(self) -> u32 {
+        match self {
+            CicVariant::Cic6103 => 0x100000,
+            CicVariant::Cic6106 => 0x200000,
+            _ => 0,
+        }
+    }
+
+    /// CRC-32 of the bootcode region shipped with retail carts for this CIC.
+    pub fn bootcode_crc32(self) -> u32 {
+        match self {
+            CicVariant::Cic6101 => 0x6170a4a1,
+            CicVariant::Cic6102 => 0x90bb6cb5,
+            CicVariant::Cic6103 => 0x0b050ee0,
+            CicVariant::Cic6105 => 0x98bc2c86,
+            CicVariant::Cic6106 => 0xacc8580a,
+            CicVariant::Cic7102 => 0x009e9ea3,
+        }
+    }
+}
+
+impl fmt::Display for CicVariant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CicVariant::Cic6101 => write!(f, "CIC-NUS-6101"),
+            CicVariant::Cic6102 => write!(f, "CIC-NUS-6102"),
+            CicVariant::Cic6103 => write!(f, "CIC-NUS-6103"),
+            CicVariant::Cic6105 => write!(f, "CIC-NUS-6105"),
+            CicVariant::Cic6106 => write!(f, "CIC-NUS-6106"),
+            CicVariant::Cic7102 => write!(f, "CIC-NUS-7102"),
+        }
+    }
+}
+
+/// Identifies the CIC a native byte order image was built for by fingerprinting
+/// its bootcode.  Returns `None` for short buffers and unrecognized IPL3s.
+pub fn detect_cic(buffer: &[u8]) -> Option<CicVariant> {
+    if buffer.len() < BOOTCODE_END {
+        return None;
+    }
+
+    let crc = crc32(&buffer[BOOTCODE_START..BOOTCODE_END]);
+
+    let variant = CicVariant::ALL.iter()
+        .cloned()
+        .find(|variant| variant.bootcode_crc32() == crc);
+    log_trace!("bootcode CRC-32 0x{:08x} is {:?}", crc, variant);
+    variant
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_fails_with_slice_to_small() {
+        let short = [0u8; BOOTCODE_END - 1];
+        assert_eq!(detect_cic(&short), None);
+    }
+
+    #[test]
+    fn unknown_bootcode_is_not_detected() {
+        let blank = vec![0u8; BOOTCODE_END];
+        assert_eq!(detect_cic(&blank), None);
+    }
+
+    #[test]
+    fn stamped_bootcode_is_detected() {
+        let mut cart = crate::tests::test_cart();
+        for variant in CicVariant::ALL.iter() {
+            crate::tests::stamp_cic(&mut cart, *variant);
+            assert_eq!(detect_cic(&cart), Some(*variant));
+        }
+    }
+
+    #[test]
+    fn fingerprints_are_distinct() {
+        for (ii, a) in CicVariant::ALL.iter().enumerate() {
+            for b in CicVariant::ALL.iter().skip(ii + 1) {
+                assert_ne!(a.bootcode_crc32(), b.bootcode_crc32());
+            }
+        }
+    }
+}
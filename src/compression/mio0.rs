@@ -0,0 +1,192 @@
+//! MIO0 blocks: a 16 byte header followed by three streams.  A bitmap picks,
+//! per output byte or run, whether the next item comes from the literal
+//! stream or is a two byte back reference from the copy stream.
+//!
+//! | Offset | Contents                          |
+//! |--------|-----------------------------------|
+//! | 0x0    | `MIO0`                            |
+//! | 0x4    | Decompressed length               |
+//! | 0x8    | Offset of the back reference data |
+//! | 0xc    | Offset of the literal data        |
+//! | 0x10   | Bitmap, in 32 bit words           |
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::compression::{find_stream_blocks, StreamHeader};
+#[cfg(feature = "alloc")]
+use crate::compression::{check_input_len, copy_back, longest_match, Codec, StreamReader, StreamWriter};
+#[cfg(feature = "alloc")]
+use crate::Error;
+
+pub const MAGIC: &[u8; 4] = b"MIO0";
+pub const HEADER_LEN: usize = 0x10;
+
+#[cfg(feature = "alloc")]
+const MIN_MATCH: usize = 3;
+#[cfg(feature = "alloc")]
+const MAX_MATCH: usize = 0x12;
+#[cfg(feature = "alloc")]
+const WINDOW: usize = 0x1000;
+
+/// The fixed header at the start of every block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Header {
+    pub decompressed_len: u32,
+    pub copy_offset: u32,
+    pub literal_offset: u32,
+}
+
+impl Header {
+    /// Reads a block's header, or `None` if `block` doesn't start with a
+    /// plausible one.
+    pub fn parse(block: &[u8]) -> Option<Header> {
+        StreamHeader::parse(block, MAGIC).map(|header| Header {
+            decompressed_len: header.decompressed_len,
+            copy_offset: header.copy_offset,
+            literal_offset: header.literal_offset,
+        })
+    }
+}
+
+/// Offsets in `data` of everything that looks like the start of a MIO0
+/// block whose streams start within `data`.  Only the header is checked;
+/// `decompress` has the final word.
+pub fn find_blocks(data: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    find_stream_blocks(data, MAGIC)
+}
+
+/// Decompresses the block at the start of `block`.  Trailing bytes past the
+/// end of the block are ignored.
+#[cfg(feature = "alloc")]
+pub fn decompress(block: &[u8]) -> Result<Vec<u8>, Error> {
+    let header = StreamHeader::parse(block, MAGIC).ok_or(Error::InvalidCompression("missing mio0 header"))?;
+    let len = header.decompressed_len as usize;
+    let mut reader = StreamReader::new(block, &header);
+    // The length comes from the block, so don't trust it with the reservation.
+    let mut out = Vec::with_capacity(len.min(block.len().saturating_mul(9)));
+
+    while out.len() < len {
+        if reader.next_is_literal()? {
+            out.push(reader.literal_byte()?);
+        } else {
+            let (hi, lo) = (reader.copy_byte()?, reader.copy_byte()?);
+            let run = ((hi >> 4) as usize) + MIN_MATCH;
+            let distance = ((((hi & 0xf) as usize) << 8) | lo as usize) + 1;
+            copy_back(&mut out, distance, run, len)?;
+        }
+    }
+
+    Ok(out)
+}
+
+/// Compresses `data` into a MIO0 block.
+#[cfg(feature = "alloc")]
+pub fn compress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    check_input_len(data)?;
+
+    let mut writer = StreamWriter::default();
+    let mut pos = 0;
+    while pos < data.len() {
+        let (run, distance) = longest_match(data, pos, MAX_MATCH, WINDOW);
+
+        if run >= MIN_MATCH {
+            writer.push_bit(false);
+            let encoded = (((run - MIN_MATCH) as u16) << 12) | (distance - 1) as u16;
+            writer.copies.extend_from_slice(&encoded.to_be_bytes());
+            pos += run;
+        } else {
+            writer.push_bit(true);
+            writer.literals.push(data[pos]);
+            pos += 1;
+        }
+    }
+
+    Ok(writer.finish(MAGIC, data.len()))
+}
+
+/// [`Codec`] for MIO0.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mio0;
+
+#[cfg(feature = "alloc")]
+impl Codec for Mio0 {
+    fn magic(&self) -> &'static [u8; 4] {
+        MAGIC
+    }
+
+    fn decompress(&self, block: &[u8]) -> Result<Vec<u8>, Error> {
+        decompress(block)
+    }
+
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        compress(data)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    use crate::compression::tests::sample_inputs;
+
+    #[test]
+    fn decompresses_hand_built_block() {
+        // "abcabcabcd": three literals, a run of six at distance three, then
+        // one more literal.
+        let block = [
+            b'M', b'I', b'O', b'0', 0, 0, 0, 10, 0, 0, 0, 0x14, 0, 0, 0, 0x18,
+            0b1110_1000, 0, 0, 0,
+            0x30, 0x02, 0, 0,
+            b'a', b'b', b'c', b'd',
+        ];
+        assert_eq!(decompress(&block).unwrap(), b"abcabcabcd");
+    }
+
+    #[test]
+    fn round_trips() {
+        for input in sample_inputs() {
+            let block = compress(&input).unwrap();
+            assert_eq!(decompress(&block).unwrap(), input);
+        }
+
+        let noisy = sample_inputs().pop().unwrap();
+        assert!(compress(&noisy).unwrap().len() < noisy.len());
+    }
+
+    #[test]
+    fn rejects_bad_blocks() {
+        assert!(decompress(b"MIO1").is_err());
+
+        let mut block = compress(b"hello hello hello").unwrap();
+        block.truncate(block.len() - 1);
+        assert!(decompress(&block).is_err());
+
+        // A back reference as the very first item.
+        let block = [
+            b'M', b'I', b'O', b'0', 0, 0, 0, 3, 0, 0, 0, 0x14, 0, 0, 0, 0x18,
+            0, 0, 0, 0,
+            0x00, 0x00, 0, 0,
+        ];
+        assert!(decompress(&block).is_err());
+
+        // A bare header claiming 4 GiB of output.
+        let block = [b'M', b'I', b'O', b'0', 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0x10, 0, 0, 0, 0x10];
+        assert!(decompress(&block).is_err());
+    }
+
+    #[test]
+    fn finds_blocks_in_payload() {
+        let mut payload = vec![0xffu8; 0x40];
+        payload.extend_from_slice(&compress(b"asset asset asset").unwrap());
+        payload.extend_from_slice(b"MIO0 but no header");
+        payload.extend_from_slice(&compress(b"again").unwrap());
+
+        let found: Vec<usize> = find_blocks(&payload).collect();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0], 0x40);
+        assert_eq!(decompress(&payload[found[0]..]).unwrap(), b"asset asset asset");
+        assert_eq!(decompress(&payload[found[1]..]).unwrap(), b"again");
+    }
+}
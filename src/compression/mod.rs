@@ -0,0 +1,278 @@
+//! The LZ77 family compression formats first-party games pack their assets
+//! in.
+//!
+//! Blocks are big endian whatever the ROM's ordering, so these work on
+//! native order slices; find blocks with the per-format scanners and hand
+//! the slice from the block's offset onwards to `decompress`.  [`Codec`]
+//! lets callers handle all three formats alike.
+//!
+//! The encoders are greedy, so their output decompresses to exactly the
+//! input but isn't byte for byte what Nintendo's tools would have produced.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use crate::Error;
+
+pub mod mio0;
+pub mod yay0;
+pub mod yaz0;
+
+#[cfg(feature = "alloc")]
+pub use self::mio0::Mio0;
+#[cfg(feature = "alloc")]
+pub use self::yay0::Yay0;
+#[cfg(feature = "alloc")]
+pub use self::yaz0::Yaz0;
+
+/// A compression format.
+#[cfg(feature = "alloc")]
+pub trait Codec {
+    /// The four bytes every block starts with.
+    fn magic(&self) -> &'static [u8; 4];
+
+    /// Decompresses the block at the start of `block`.  Trailing bytes past
+    /// the end of the block are ignored.
+    fn decompress(&self, block: &[u8]) -> Result<Vec<u8>, Error>;
+
+    /// Compresses `data` into a block.
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+/// The codec for the block at the start of `block`, going by its magic.
+#[cfg(feature = "alloc")]
+pub fn detect_codec(block: &[u8]) -> Option<&'static dyn Codec> {
+    let codecs: [&'static dyn Codec; 3] = [&Mio0, &Yay0, &Yaz0];
+    codecs.iter().cloned().find(|codec| block.starts_with(codec.magic()))
+}
+
+fn be_u32(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn find_magic<'a>(data: &'a [u8], magic: &'static [u8; 4]) -> impl Iterator<Item = usize> + 'a {
+    data.windows(magic.len())
+        .enumerate()
+        .filter(move |(_, window)| window == magic)
+        .map(|(pos, _)| pos)
+}
+
+#[cfg(feature = "alloc")]
+fn truncated() -> Error {
+    Error::InvalidCompression("truncated block")
+}
+
+// Appends `run` bytes starting `distance` back from the end of `out`,
+// stopping short at `len`.
+#[cfg(feature = "alloc")]
+fn copy_back(out: &mut Vec<u8>, distance: usize, run: usize, len: usize) -> Result<(), Error> {
+    if distance > out.len() {
+        return Err(Error::InvalidCompression("back reference before start of output"));
+    }
+
+    let start = out.len() - distance;
+    for ii in 0..run.min(len - out.len()) {
+        let value = out[start + ii];
+        out.push(value);
+    }
+    Ok(())
+}
+
+// Longest (run, distance) of at most `max_run` for the bytes at `pos`,
+// looking back up to `window` bytes.
+#[cfg(feature = "alloc")]
+fn longest_match(data: &[u8], pos: usize, max_run: usize, window: usize) -> (usize, usize) {
+    let max_run = max_run.min(data.len() - pos);
+    let mut best = (0, 0);
+
+    for start in (pos.saturating_sub(window)..pos).rev() {
+        let run = (0..max_run).take_while(|&ii| data[start + ii] == data[pos + ii]).count();
+        if run > best.0 {
+            best = (run, pos - start);
+            if run == max_run {
+                break;
+            }
+        }
+    }
+
+    best
+}
+
+// MIO0 and Yay0 share a layout: a header giving the decompressed length and
+// the offsets of a back reference stream and a literal stream, then a bitmap
+// in 32 bit words where set bits mean literals.
+pub(crate) struct StreamHeader {
+    pub decompressed_len: u32,
+    pub copy_offset: u32,
+    pub literal_offset: u32,
+}
+
+const STREAM_HEADER_LEN: usize = 0x10;
+
+impl StreamHeader {
+    fn parse(block: &[u8], magic: &[u8; 4]) -> Option<StreamHeader> {
+        if !block.starts_with(magic) {
+            return None;
+        }
+
+        let header = StreamHeader {
+            decompressed_len: be_u32(block, 0x4)?,
+            copy_offset: be_u32(block, 0x8)?,
+            literal_offset: be_u32(block, 0xc)?,
+        };
+
+        // Both streams follow the bitmap, which holds at least one word for
+        // anything non-empty.
+        let min_offset = if header.decompressed_len == 0 { STREAM_HEADER_LEN } else { STREAM_HEADER_LEN + 4 } as u32;
+        if header.copy_offset < min_offset || header.literal_offset < min_offset {
+            return None;
+        }
+
+        Some(header)
+    }
+}
+
+// Blocks whose streams at least start within `data`.
+fn find_stream_blocks<'a>(data: &'a [u8], magic: &'static [u8; 4]) -> impl Iterator<Item = usize> + 'a {
+    find_magic(data, magic).filter(move |&pos| {
+        let remaining = (data.len() - pos) as u64;
+        StreamHeader::parse(&data[pos..], magic).is_some_and(|header| {
+            (header.copy_offset as u64) <= remaining && (header.literal_offset as u64) <= remaining
+        })
+    })
+}
+
+#[cfg(feature = "alloc")]
+struct StreamReader<'a> {
+    block: &'a [u8],
+    bitmap: usize,
+    bits: u32,
+    bits_left: u32,
+    copies: usize,
+    literals: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> StreamReader<'a> {
+    fn new(block: &'a [u8], header: &StreamHeader) -> StreamReader<'a> {
+        StreamReader {
+            block,
+            bitmap: STREAM_HEADER_LEN,
+            bits: 0,
+            bits_left: 0,
+            copies: header.copy_offset as usize,
+            literals: header.literal_offset as usize,
+        }
+    }
+
+    fn next_is_literal(&mut self) -> Result<bool, Error> {
+        if self.bits_left == 0 {
+            self.bits = be_u32(self.block, self.bitmap).ok_or_else(truncated)?;
+            self.bitmap += 4;
+            self.bits_left = 32;
+        }
+
+        let literal = self.bits & 0x8000_0000 != 0;
+        self.bits <<= 1;
+        self.bits_left -= 1;
+        Ok(literal)
+    }
+
+    fn copy_byte(&mut self) -> Result<u8, Error> {
+        let value = self.block.get(self.copies).cloned().ok_or_else(truncated)?;
+        self.copies += 1;
+        Ok(value)
+    }
+
+    fn literal_byte(&mut self) -> Result<u8, Error> {
+        let value = self.block.get(self.literals).cloned().ok_or_else(truncated)?;
+        self.literals += 1;
+        Ok(value)
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Default)]
+struct StreamWriter {
+    bitmap: Vec<u8>,
+    copies: Vec<u8>,
+    literals: Vec<u8>,
+    bits: u32,
+    bit_count: u32,
+}
+
+#[cfg(feature = "alloc")]
+impl StreamWriter {
+    fn push_bit(&mut self, literal: bool) {
+        self.bits = (self.bits << 1) | literal as u32;
+        self.bit_count += 1;
+        if self.bit_count == 32 {
+            self.bitmap.extend_from_slice(&self.bits.to_be_bytes());
+            self.bits = 0;
+            self.bit_count = 0;
+        }
+    }
+
+    fn finish(mut self, magic: &[u8; 4], len: usize) -> Vec<u8> {
+        if self.bit_count != 0 {
+            self.bitmap.extend_from_slice(&(self.bits << (32 - self.bit_count)).to_be_bytes());
+        }
+
+        // Keep the literal stream word aligned, as Nintendo's encoders do.
+        while !self.copies.len().is_multiple_of(4) {
+            self.copies.push(0);
+        }
+
+        let copy_offset = STREAM_HEADER_LEN + self.bitmap.len();
+        let literal_offset = copy_offset + self.copies.len();
+
+        let mut out = Vec::with_capacity(literal_offset + self.literals.len());
+        out.extend_from_slice(magic);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+        out.extend_from_slice(&(copy_offset as u32).to_be_bytes());
+        out.extend_from_slice(&(literal_offset as u32).to_be_bytes());
+        out.extend_from_slice(&self.bitmap);
+        out.extend_from_slice(&self.copies);
+        out.extend_from_slice(&self.literals);
+        out
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn check_input_len(data: &[u8]) -> Result<(), Error> {
+    if data.len() > u32::MAX as usize {
+        return Err(Error::InvalidCompression("input too long to compress"));
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "alloc"))]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn sample_inputs() -> Vec<Vec<u8>> {
+        let noisy: Vec<u8> = (0..20_000u32).map(|ii| (ii.wrapping_mul(2_654_435_761) >> 13) as u8 % 17).collect();
+        vec![
+            Vec::new(),
+            b"x".to_vec(),
+            b"Super Mario 64 Super Mario 64 Super Mario 64!".to_vec(),
+            vec![0u8; 5000],
+            noisy,
+        ]
+    }
+
+    #[test]
+    fn codecs_round_trip_and_detect() {
+        let codecs: [&dyn Codec; 3] = [&Mio0, &Yay0, &Yaz0];
+        for codec in codecs.iter() {
+            for input in sample_inputs() {
+                let block = codec.compress(&input).unwrap();
+                assert_eq!(detect_codec(&block).unwrap().magic(), codec.magic());
+                assert_eq!(codec.decompress(&block).unwrap(), input);
+            }
+        }
+        assert!(detect_codec(b"PATCH").is_none());
+    }
+}
@@ -0,0 +1,145 @@
+//! Yay0 blocks, MIO0's successor.  The layout is the same, but a back
+//! reference whose length nibble is zero takes an extra length byte from the
+//! literal stream, allowing runs of up to 0x111 bytes.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::compression::find_stream_blocks;
+#[cfg(feature = "alloc")]
+use crate::compression::{check_input_len, copy_back, longest_match, Codec, StreamHeader, StreamReader, StreamWriter};
+#[cfg(feature = "alloc")]
+use crate::Error;
+
+pub const MAGIC: &[u8; 4] = b"Yay0";
+
+#[cfg(feature = "alloc")]
+const MIN_MATCH: usize = 3;
+/// Longest run that fits in the length nibble.
+#[cfg(feature = "alloc")]
+const MAX_SHORT_MATCH: usize = 0x11;
+#[cfg(feature = "alloc")]
+const MAX_MATCH: usize = 0x111;
+#[cfg(feature = "alloc")]
+const WINDOW: usize = 0x1000;
+
+/// Offsets in `data` of everything that looks like the start of a Yay0
+/// block whose streams start within `data`.
+pub fn find_blocks(data: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    find_stream_blocks(data, MAGIC)
+}
+
+/// Decompresses the block at the start of `block`.  Trailing bytes past the
+/// end of the block are ignored.
+#[cfg(feature = "alloc")]
+pub fn decompress(block: &[u8]) -> Result<Vec<u8>, Error> {
+    let header = StreamHeader::parse(block, MAGIC).ok_or(Error::InvalidCompression("missing yay0 header"))?;
+    let len = header.decompressed_len as usize;
+    let mut reader = StreamReader::new(block, &header);
+    // The length comes from the block, so don't trust it with the reservation.
+    let mut out = Vec::with_capacity(len.min(block.len().saturating_mul(9)));
+
+    while out.len() < len {
+        if reader.next_is_literal()? {
+            out.push(reader.literal_byte()?);
+        } else {
+            let (hi, lo) = (reader.copy_byte()?, reader.copy_byte()?);
+            let distance = ((((hi & 0xf) as usize) << 8) | lo as usize) + 1;
+            let run = match hi >> 4 {
+                0 => reader.literal_byte()? as usize + MAX_SHORT_MATCH + 1,
+                nibble => nibble as usize + 2,
+            };
+            copy_back(&mut out, distance, run, len)?;
+        }
+    }
+
+    Ok(out)
+}
+
+/// Compresses `data` into a Yay0 block.
+#[cfg(feature = "alloc")]
+pub fn compress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    check_input_len(data)?;
+
+    let mut writer = StreamWriter::default();
+    let mut pos = 0;
+    while pos < data.len() {
+        let (run, distance) = longest_match(data, pos, MAX_MATCH, WINDOW);
+
+        if run >= MIN_MATCH {
+            writer.push_bit(false);
+            let distance = (distance - 1) as u16;
+            if run > MAX_SHORT_MATCH {
+                writer.copies.extend_from_slice(&distance.to_be_bytes());
+                writer.literals.push((run - MAX_SHORT_MATCH - 1) as u8);
+            } else {
+                writer.copies.extend_from_slice(&((((run - 2) as u16) << 12) | distance).to_be_bytes());
+            }
+            pos += run;
+        } else {
+            writer.push_bit(true);
+            writer.literals.push(data[pos]);
+            pos += 1;
+        }
+    }
+
+    Ok(writer.finish(MAGIC, data.len()))
+}
+
+/// [`Codec`] for Yay0.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Yay0;
+
+#[cfg(feature = "alloc")]
+impl Codec for Yay0 {
+    fn magic(&self) -> &'static [u8; 4] {
+        MAGIC
+    }
+
+    fn decompress(&self, block: &[u8]) -> Result<Vec<u8>, Error> {
+        decompress(block)
+    }
+
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        compress(data)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decompresses_hand_built_block() {
+        // "ab", then a long run of 0x14 at distance two taking its length
+        // from the literal stream, then "c".
+        let block = [
+            b'Y', b'a', b'y', b'0', 0, 0, 0, 0x17, 0, 0, 0, 0x14, 0, 0, 0, 0x18,
+            0b1101_0000, 0, 0, 0,
+            0x00, 0x01, 0, 0,
+            b'a', b'b', 0x14 - 0x12, b'c',
+        ];
+        let mut expected = b"ab".repeat(11);
+        expected.push(b'c');
+        assert_eq!(decompress(&block).unwrap(), expected);
+    }
+
+    #[test]
+    fn long_runs_use_extended_lengths() {
+        let data = vec![7u8; 0x1000];
+        let block = compress(&data).unwrap();
+        assert!(block.len() < 0x80);
+        assert_eq!(decompress(&block).unwrap(), data);
+
+        let found: Vec<usize> = find_blocks(&block).collect();
+        assert_eq!(found, [0]);
+    }
+
+    #[test]
+    fn rejects_truncated_huge_blocks() {
+        // A bare header claiming 4 GiB of output.
+        let block = [b'Y', b'a', b'y', b'0', 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0x10, 0, 0, 0, 0x10];
+        assert!(decompress(&block).is_err());
+    }
+}
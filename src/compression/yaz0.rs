@@ -0,0 +1,179 @@
+//! Yaz0 blocks: a 16 byte header followed by a single interleaved stream.
+//! Each group starts with a code byte whose bits, high first, say whether
+//! each of the next eight items is a literal byte or a back reference.
+//!
+//! | Offset | Contents            |
+//! |--------|---------------------|
+//! | 0x0    | `Yaz0`              |
+//! | 0x4    | Decompressed length |
+//! | 0x8    | Reserved            |
+//! | 0x10   | Groups              |
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::compression::{be_u32, find_magic};
+#[cfg(feature = "alloc")]
+use crate::compression::{check_input_len, copy_back, longest_match, truncated, Codec};
+#[cfg(feature = "alloc")]
+use crate::Error;
+
+pub const MAGIC: &[u8; 4] = b"Yaz0";
+pub const HEADER_LEN: usize = 0x10;
+
+#[cfg(feature = "alloc")]
+const MIN_MATCH: usize = 3;
+/// Longest run that fits in a two byte back reference.
+#[cfg(feature = "alloc")]
+const MAX_SHORT_MATCH: usize = 0x11;
+#[cfg(feature = "alloc")]
+const MAX_MATCH: usize = 0x111;
+#[cfg(feature = "alloc")]
+const WINDOW: usize = 0x1000;
+
+/// The decompressed length of the block at the start of `block`, or `None`
+/// if it isn't a Yaz0 block.
+pub fn decompressed_len(block: &[u8]) -> Option<u32> {
+    if block.len() < HEADER_LEN || !block.starts_with(MAGIC) {
+        return None;
+    }
+    be_u32(block, 0x4)
+}
+
+/// Offsets in `data` of everything that starts with a Yaz0 header.  The
+/// format has nothing else to check until a block is decompressed.
+pub fn find_blocks(data: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    find_magic(data, MAGIC).filter(move |&pos| decompressed_len(&data[pos..]).is_some())
+}
+
+/// Decompresses the block at the start of `block`.  Trailing bytes past the
+/// end of the block are ignored.
+#[cfg(feature = "alloc")]
+pub fn decompress(block: &[u8]) -> Result<Vec<u8>, Error> {
+    let len = decompressed_len(block).ok_or(Error::InvalidCompression("missing yaz0 header"))? as usize;
+    // The length comes from the block, so don't trust it with the reservation.
+    let mut out = Vec::with_capacity(len.min(block.len().saturating_mul(9)));
+    let mut pos = HEADER_LEN;
+
+    let mut next = || {
+        let value = block.get(pos).cloned().ok_or_else(truncated);
+        pos += 1;
+        value
+    };
+
+    while out.len() < len {
+        let code = next()?;
+        for bit in (0..8).rev() {
+            if out.len() == len {
+                break;
+            }
+
+            if code & (1 << bit) != 0 {
+                out.push(next()?);
+            } else {
+                let (hi, lo) = (next()?, next()?);
+                let distance = ((((hi & 0xf) as usize) << 8) | lo as usize) + 1;
+                let run = match hi >> 4 {
+                    0 => next()? as usize + MAX_SHORT_MATCH + 1,
+                    nibble => nibble as usize + 2,
+                };
+                copy_back(&mut out, distance, run, len)?;
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Compresses `data` into a Yaz0 block.
+#[cfg(feature = "alloc")]
+pub fn compress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    check_input_len(data)?;
+
+    let mut out = Vec::with_capacity(HEADER_LEN + data.len() + data.len() / 8 + 1);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(&[0; 8]);
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let code_pos = out.len();
+        out.push(0);
+
+        for bit in (0..8).rev() {
+            if pos == data.len() {
+                break;
+            }
+
+            let (run, distance) = longest_match(data, pos, MAX_MATCH, WINDOW);
+            if run >= MIN_MATCH {
+                let distance = (distance - 1) as u16;
+                if run > MAX_SHORT_MATCH {
+                    out.extend_from_slice(&distance.to_be_bytes());
+                    out.push((run - MAX_SHORT_MATCH - 1) as u8);
+                } else {
+                    out.extend_from_slice(&((((run - 2) as u16) << 12) | distance).to_be_bytes());
+                }
+                pos += run;
+            } else {
+                out[code_pos] |= 1 << bit;
+                out.push(data[pos]);
+                pos += 1;
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// [`Codec`] for Yaz0.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Yaz0;
+
+#[cfg(feature = "alloc")]
+impl Codec for Yaz0 {
+    fn magic(&self) -> &'static [u8; 4] {
+        MAGIC
+    }
+
+    fn decompress(&self, block: &[u8]) -> Result<Vec<u8>, Error> {
+        decompress(block)
+    }
+
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        compress(data)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decompresses_hand_built_block() {
+        // Literals "ab", a run of eight at distance two, and a run of 0x14
+        // with the extra length byte.
+        let block = [
+            b'Y', b'a', b'z', b'0', 0, 0, 0, 0x1e, 0, 0, 0, 0, 0, 0, 0, 0,
+            0b1100_0000,
+            b'a', b'b',
+            0x60, 0x01,
+            0x00, 0x01, 0x14 - 0x12,
+        ];
+        assert_eq!(decompress(&block).unwrap(), b"ab".repeat(15));
+    }
+
+    #[test]
+    fn rejects_bad_blocks() {
+        assert!(decompress(b"Yaz0").is_err());
+
+        let mut block = compress(b"yaz yaz yaz yaz").unwrap();
+        block.pop();
+        assert!(decompress(&block).is_err());
+
+        // A bare header claiming 4 GiB of output.
+        let block = [b'Y', b'a', b'z', b'0', 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert!(decompress(&block).is_err());
+    }
+}
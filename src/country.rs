@@ -0,0 +1,188 @@
+use core::fmt;
+
+/// Video standard a region's consoles output.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TvStandard {
+    Ntsc,
+    Pal,
+    Mpal,
+}
+
+impl fmt::Display for TvStandard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TvStandard::Ntsc => write!(f, "NTSC"),
+            TvStandard::Pal  => write!(f, "PAL"),
+            TvStandard::Mpal => write!(f, "MPAL"),
+        }
+    }
+}
+
+/// Destination code from byte 0x3E of the header.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CountryCode {
+    Beta,
+    Asia,
+    Brazil,
+    China,
+    Germany,
+    Usa,
+    France,
+    GatewayNtsc,
+    Netherlands,
+    Italy,
+    Japan,
+    Korea,
+    GatewayPal,
+    Canada,
+    Europe,
+    Spain,
+    Australia,
+    Scandinavia,
+    /// Other European codes ('X', 'Y', 'Z') used for multi-language releases.
+    EuropeOther(u8),
+    Unknown(u8),
+}
+
+impl CountryCode {
+    pub fn from_u8(value: u8) -> CountryCode {
+        match value {
+            b'7' => CountryCode::Beta,
+            b'A' => CountryCode::Asia,
+            b'B' => CountryCode::Brazil,
+            b'C' => CountryCode::China,
+            b'D' => CountryCode::Germany,
+            b'E' => CountryCode::Usa,
+            b'F' => CountryCode::France,
+            b'G' => CountryCode::GatewayNtsc,
+            b'H' => CountryCode::Netherlands,
+            b'I' => CountryCode::Italy,
+            b'J' => CountryCode::Japan,
+            b'K' => CountryCode::Korea,
+            b'L' => CountryCode::GatewayPal,
+            b'N' => CountryCode::Canada,
+            b'P' => CountryCode::Europe,
+            b'S' => CountryCode::Spain,
+            b'U' => CountryCode::Australia,
+            b'W' => CountryCode::Scandinavia,
+            b'X' | b'Y' | b'Z' => CountryCode::EuropeOther(value),
+            _ => CountryCode::Unknown(value),
+        }
+    }
+
+    /// The raw destination code byte.
+    pub fn as_u8(self) -> u8 {
+        match self {
+            CountryCode::Beta => b'7',
+            CountryCode::Asia => b'A',
+            CountryCode::Brazil => b'B',
+            CountryCode::China => b'C',
+            CountryCode::Germany => b'D',
+            CountryCode::Usa => b'E',
+            CountryCode::France => b'F',
+            CountryCode::GatewayNtsc => b'G',
+            CountryCode::Netherlands => b'H',
+            CountryCode::Italy => b'I',
+            CountryCode::Japan => b'J',
+            CountryCode::Korea => b'K',
+            CountryCode::GatewayPal => b'L',
+            CountryCode::Canada => b'N',
+            CountryCode::Europe => b'P',
+            CountryCode::Spain => b'S',
+            CountryCode::Australia => b'U',
+            CountryCode::Scandinavia => b'W',
+            CountryCode::EuropeOther(value) => value,
+            CountryCode::Unknown(value) => value,
+        }
+    }
+
+    /// The video standard consoles sold under this code use, or `None` if the
+    /// code isn't recognized.
+    pub fn tv_standard(self) -> Option<TvStandard> {
+        match self {
+            CountryCode::Beta
+            | CountryCode::Asia
+            | CountryCode::Usa
+            | CountryCode::GatewayNtsc
+            | CountryCode::Japan
+            | CountryCode::Korea
+            | CountryCode::Canada => Some(TvStandard::Ntsc),
+            CountryCode::China
+            | CountryCode::Germany
+            | CountryCode::France
+            | CountryCode::Netherlands
+            | CountryCode::Italy
+            | CountryCode::GatewayPal
+            | CountryCode::Europe
+            | CountryCode::Spain
+            | CountryCode::Australia
+            | CountryCode::Scandinavia
+            | CountryCode::EuropeOther(_) => Some(TvStandard::Pal),
+            CountryCode::Brazil => Some(TvStandard::Mpal),
+            CountryCode::Unknown(_) => None,
+        }
+    }
+}
+
+impl From<u8> for CountryCode {
+    fn from(value: u8) -> Self {
+        CountryCode::from_u8(value)
+    }
+}
+
+impl From<CountryCode> for u8 {
+    fn from(code: CountryCode) -> Self {
+        code.as_u8()
+    }
+}
+
+impl fmt::Display for CountryCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CountryCode::Beta => write!(f, "Beta"),
+            CountryCode::Asia => write!(f, "Asia"),
+            CountryCode::Brazil => write!(f, "Brazil"),
+            CountryCode::China => write!(f, "China"),
+            CountryCode::Germany => write!(f, "Germany"),
+            CountryCode::Usa => write!(f, "USA"),
+            CountryCode::France => write!(f, "France"),
+            CountryCode::GatewayNtsc => write!(f, "Gateway 64 (NTSC)"),
+            CountryCode::Netherlands => write!(f, "Netherlands"),
+            CountryCode::Italy => write!(f, "Italy"),
+            CountryCode::Japan => write!(f, "Japan"),
+            CountryCode::Korea => write!(f, "Korea"),
+            CountryCode::GatewayPal => write!(f, "Gateway 64 (PAL)"),
+            CountryCode::Canada => write!(f, "Canada"),
+            CountryCode::Europe => write!(f, "Europe"),
+            CountryCode::Spain => write!(f, "Spain"),
+            CountryCode::Australia => write!(f, "Australia"),
+            CountryCode::Scandinavia => write!(f, "Scandinavia"),
+            CountryCode::EuropeOther(_) => write!(f, "Europe"),
+            CountryCode::Unknown(value) => write!(f, "Unknown (0x{:02x})", value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_values_round_trip() {
+        for value in 0..=255u8 {
+            assert_eq!(CountryCode::from_u8(value).as_u8(), value);
+        }
+    }
+
+    #[test]
+    fn maps_tv_standards() {
+        assert_eq!(CountryCode::from_u8(b'E').tv_standard(), Some(TvStandard::Ntsc));
+        assert_eq!(CountryCode::from_u8(b'J').tv_standard(), Some(TvStandard::Ntsc));
+        assert_eq!(CountryCode::from_u8(b'P').tv_standard(), Some(TvStandard::Pal));
+        assert_eq!(CountryCode::from_u8(b'U').tv_standard(), Some(TvStandard::Pal));
+        assert_eq!(CountryCode::from_u8(b'B').tv_standard(), Some(TvStandard::Mpal));
+        assert_eq!(CountryCode::from_u8(0).tv_standard(), None);
+    }
+}
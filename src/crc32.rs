@@ -0,0 +1,70 @@
+//! Plain IEEE 802.3 CRC-32, as used to fingerprint bootcode and by the patch
+//! formats.
+
+const POLYNOMIAL: u32 = 0xedb88320;
+
+const TABLE: [u32; 256] = build_table();
+
+const fn build_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut ii = 0;
+    while ii < 256 {
+        let mut value = ii as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            if (value & 1) != 0 {
+                value = (value >> 1) ^ POLYNOMIAL;
+            } else {
+                value >>= 1;
+            }
+            bit += 1;
+        }
+        table[ii] = value;
+        ii += 1;
+    }
+    table
+}
+
+pub(crate) fn update(crc: u32, buffer: &[u8]) -> u32 {
+    let mut value = !crc;
+    for byte in buffer {
+        value = TABLE[((value ^ (*byte as u32)) & 0xff) as usize] ^ (value >> 8);
+    }
+    !value
+}
+
+pub(crate) fn crc32(buffer: &[u8]) -> u32 {
+    update(0, buffer)
+}
+
+/// The four bytes that, appended to data whose CRC is `crc`, bring it to
+/// `target`.  Lets tests dress up synthetic bootcode as a known IPL3.
+#[cfg(test)]
+pub(crate) fn forge(crc: u32, target: u32) -> [u8; 4] {
+    // Run four zero byte steps backwards from the target register.  Each
+    // table entry's top byte is unique, so it names the index used.
+    let mut value = !target;
+    for _ in 0..4 {
+        let index = TABLE.iter().position(|entry| (entry >> 24) == (value >> 24)).unwrap();
+        value = ((value ^ TABLE[index]) << 8) | index as u32;
+    }
+    (value ^ !crc).to_le_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(crc32(&[]), 0);
+        assert_eq!(update(crc32(b"1234"), b"56789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn forged_suffix_hits_target() {
+        let crc = crc32(b"bootcode");
+        assert_eq!(update(crc, &forge(crc, 0x98bc2c86)), 0x98bc2c86);
+    }
+}
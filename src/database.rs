@@ -0,0 +1,309 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "hashes")]
+use crate::hashes;
+use crate::{check_dump_size, DumpSize, Error};
+
+/// One dump from a No-Intro DAT.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DatEntry {
+    /// The full No-Intro name, such as "Super Mario 64 (USA) (Rev 1)".
+    pub name: String,
+    /// The name without its parenthesized tags.
+    pub title: String,
+    /// The first tag, which No-Intro always uses for the region(s).
+    pub region: Option<String>,
+    /// A "Rev N" or "vN.N" tag.
+    pub revision: Option<String>,
+    pub size: u64,
+    pub crc32: u32,
+    pub md5: Option<[u8; 16]>,
+    pub sha1: Option<[u8; 20]>,
+}
+
+impl DatEntry {
+    /// Compares an image's length against this dump's.
+    pub fn check_dump_size(&self, rom: &[u8]) -> DumpSize {
+        check_dump_size(rom, self.size)
+    }
+}
+
+/// A parsed No-Intro (Logiqx XML) DAT, indexed by each dump's hashes.
+#[derive(Clone, Debug, Default)]
+pub struct Database {
+    entries: Vec<DatEntry>,
+    by_sha1: BTreeMap<[u8; 20], usize>,
+    by_crc32: BTreeMap<u32, usize>,
+}
+
+impl Database {
+    /// Parses the `<game>` entries of a DAT.  Only the subset of XML that DAT
+    /// tools emit is understood.
+    pub fn parse(dat: &str) -> Result<Database, Error> {
+        let mut database = Database::default();
+        let mut rest = dat;
+
+        while let Some(start) = rest.find("<game") {
+            let (game, tail) = element(&rest[start..], "game")?;
+            rest = tail;
+
+            let name = attribute(game, "name")?.ok_or(Error::InvalidDatabase("game without a name"))?;
+            let rom = match game.find("<rom") {
+                Some(rom) => &game[rom..],
+                None => continue,
+            };
+
+            let (title, region, revision) = split_name(&name);
+            let size = match attribute(rom, "size")? {
+                Some(size) => size.parse().map_err(|_| Error::InvalidDatabase("malformed rom size"))?,
+                None => 0,
+            };
+            let crc32 = match attribute(rom, "crc")? {
+                Some(crc) => u32::from_str_radix(&crc, 16).map_err(|_| Error::InvalidDatabase("malformed crc"))?,
+                None => return Err(Error::InvalidDatabase("rom without a crc")),
+            };
+            let md5 = attribute(rom, "md5")?.map(|md5| hex(&md5)).transpose()?;
+            let sha1 = attribute(rom, "sha1")?.map(|sha1| hex(&sha1)).transpose()?;
+
+            database.push(DatEntry { name, title, region, revision, size, crc32, md5, sha1 });
+        }
+
+        Ok(database)
+    }
+
+    fn push(&mut self, entry: DatEntry) {
+        let index = self.entries.len();
+        if let Some(sha1) = entry.sha1 {
+            self.by_sha1.entry(sha1).or_insert(index);
+        }
+        self.by_crc32.entry(entry.crc32).or_insert(index);
+        self.entries.push(entry);
+    }
+
+    pub fn entries(&self) -> &[DatEntry] {
+        &self.entries
+    }
+
+    pub fn by_sha1(&self, sha1: &[u8; 20]) -> Option<&DatEntry> {
+        self.by_sha1.get(sha1).map(|index| &self.entries[*index])
+    }
+
+    pub fn by_crc32(&self, crc32: u32) -> Option<&DatEntry> {
+        self.by_crc32.get(&crc32).map(|index| &self.entries[*index])
+    }
+
+    /// Looks an image up by its normalized hashes, whatever its byte order,
+    /// preferring SHA-1 and falling back to the CRC-32 if the size matches.
+    #[cfg(feature = "hashes")]
+    pub fn identify(&self, rom: &[u8]) -> Result<Option<&DatEntry>, Error> {
+        let digest = hashes(rom)?;
+
+        Ok(self.by_sha1(&digest.sha1).or_else(|| {
+            self.by_crc32(digest.crc32).filter(|entry| entry.sha1.is_none() && entry.size == rom.len() as u64)
+        }))
+    }
+}
+
+// The body of the first `<name ...>...</name>` (or self-closed tag) in `xml`,
+// and everything after it.
+fn element<'a>(xml: &'a str, name: &str) -> Result<(&'a str, &'a str), Error> {
+    let open = tag(xml)?;
+    if open.ends_with('/') {
+        return Ok((open, &xml[open.len() + 2..]));
+    }
+
+    let close = ["</", name, ">"].concat();
+    match xml.find(close.as_str()) {
+        Some(end) => Ok((&xml[..end], &xml[end + close.len()..])),
+        None => Err(Error::InvalidDatabase("unterminated element")),
+    }
+}
+
+// The contents of the tag at the start of `xml`, without its angle brackets.
+fn tag(xml: &str) -> Result<&str, Error> {
+    let mut quote = None;
+    for (ii, ch) in xml.char_indices().skip(1) {
+        match (quote, ch) {
+            (None, '"') | (None, '\'') => quote = Some(ch),
+            (Some(open), _) if open == ch => quote = None,
+            (None, '>') => return Ok(&xml[1..ii]),
+            _ => {},
+        }
+    }
+    Err(Error::InvalidDatabase("unterminated tag"))
+}
+
+// The unescaped value of attribute `name` in the tag at the start of `xml`.
+fn attribute(xml: &str, name: &str) -> Result<Option<String>, Error> {
+    let tag = tag(xml)?;
+    let mut rest = tag;
+
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].rsplit(|ch: char| ch.is_whitespace()).next().unwrap_or("");
+        let value = rest[eq + 1..].trim_start();
+        let quote = value.chars().next().ok_or(Error::InvalidDatabase("malformed attribute"))?;
+        if quote != '"' && quote != '\'' {
+            return Err(Error::InvalidDatabase("malformed attribute"));
+        }
+        let end = value[1..].find(quote).ok_or(Error::InvalidDatabase("malformed attribute"))?;
+
+        if key == name {
+            return unescape(&value[1..end + 1]).map(Some);
+        }
+        rest = &value[end + 2..];
+    }
+
+    Ok(None)
+}
+
+fn unescape(text: &str) -> Result<String, Error> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        let semi = rest[amp..].find(';').ok_or(Error::InvalidDatabase("malformed entity"))?;
+        let entity = &rest[amp + 1..amp + semi];
+        let ch = match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else {
+                    entity.strip_prefix('#').and_then(|dec| dec.parse().ok())
+                };
+                code.and_then(core::char::from_u32).ok_or(Error::InvalidDatabase("malformed entity"))?
+            },
+        };
+        out.push(ch);
+        rest = &rest[amp + semi + 1..];
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+fn split_name(name: &str) -> (String, Option<String>, Option<String>) {
+    let title_end = name.find(" (").unwrap_or(name.len());
+    let mut tags = name[title_end..].split('(')
+        .filter_map(|tag| tag.split(')').next())
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty());
+
+    let region = tags.next().map(String::from);
+    let revision = tags.find(|tag| {
+        tag.starts_with("Rev ") || (tag.starts_with('v') && tag[1..].starts_with(|ch: char| ch.is_ascii_digit()))
+    });
+
+    (String::from(&name[..title_end]), region, revision.map(String::from))
+}
+
+fn hex<const N: usize>(text: &str) -> Result<[u8; N], Error> {
+    if text.len() != N * 2 || !text.is_ascii() {
+        return Err(Error::InvalidDatabase("malformed hash"));
+    }
+
+    let mut bytes = [0u8; N];
+    for (ii, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[ii * 2..ii * 2 + 2], 16)
+            .map_err(|_| Error::InvalidDatabase("malformed hash"))?;
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAT: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE datafile PUBLIC "-//Logiqx//DTD ROM Management Datafile//EN" "http://www.logiqx.com/Dats/datafile.dtd">
+<datafile>
+	<header>
+		<name>Nintendo - Nintendo 64 (BigEndian)</name>
+	</header>
+	<game name="Super Mario 64 (USA)">
+		<description>Super Mario 64 (USA)</description>
+		<rom name="Super Mario 64 (USA).z64" size="8388608" crc="3ce60709" md5="20b854b239203baf6c961b850a4a51a2" sha1="9bef1128717f958171a4afac3ed78ee2bb4e86ce" status="verified"/>
+	</game>
+	<game name="Legend of Zelda, The - Ocarina of Time (Europe) (En,Fr,De) (Rev 1)">
+		<description>Legend of Zelda, The - Ocarina of Time (Europe) (En,Fr,De) (Rev 1)</description>
+		<rom name="x.z64" size="33554432" crc="DEADBEEF"/>
+	</game>
+	<game name="Mario &amp; Friends (Japan) (v1.1)">
+		<rom name="y.z64" size='4' crc='00000001' />
+	</game>
+</datafile>
+"#;
+
+    #[test]
+    fn parses_entries() {
+        let database = Database::parse(DAT).unwrap();
+        assert_eq!(database.entries().len(), 3);
+
+        let sm64 = &database.entries()[0];
+        assert_eq!(sm64.title, "Super Mario 64");
+        assert_eq!(sm64.region.as_deref(), Some("USA"));
+        assert_eq!(sm64.revision, None);
+        assert_eq!(sm64.size, 8388608);
+        assert_eq!(sm64.crc32, 0x3ce60709);
+        assert_eq!(sm64.md5.unwrap()[0], 0x20);
+        assert_eq!(sm64.sha1.unwrap()[19], 0xce);
+
+        let zelda = &database.entries()[1];
+        assert_eq!(zelda.title, "Legend of Zelda, The - Ocarina of Time");
+        assert_eq!(zelda.region.as_deref(), Some("Europe"));
+        assert_eq!(zelda.revision.as_deref(), Some("Rev 1"));
+        assert_eq!(zelda.sha1, None);
+
+        let mario = &database.entries()[2];
+        assert_eq!(mario.name, "Mario & Friends (Japan) (v1.1)");
+        assert_eq!(mario.revision.as_deref(), Some("v1.1"));
+    }
+
+    #[test]
+    fn looks_up_by_hash() {
+        let database = Database::parse(DAT).unwrap();
+        assert_eq!(database.entries()[2].check_dump_size(&[0, 0]), DumpSize::Underdump { missing: 2 });
+        let sha1 = database.entries()[0].sha1.unwrap();
+        assert_eq!(database.by_sha1(&sha1).unwrap().title, "Super Mario 64");
+        assert_eq!(database.by_crc32(0xdeadbeef).unwrap().region.as_deref(), Some("Europe"));
+        assert!(database.by_crc32(0x12345678).is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_dats() {
+        assert!(Database::parse("<game name=\"x\"><rom crc=\"zz\"/></game>").is_err());
+        assert!(Database::parse("<game name=\"x\"><rom crc=\"00\" sha1=\"12\"/></game>").is_err());
+        assert!(Database::parse("<game name=\"x\"><rom crc=\"00\"/>").is_err());
+        assert!(Database::parse("<game><rom crc=\"00\"/></game>").is_err());
+    }
+
+    #[cfg(feature = "hashes")]
+    #[test]
+    fn identifies_images() {
+        use crate::tests::test_cart;
+        use crate::{swap_cart_to, ByteSwapping};
+
+        let rom = test_cart();
+        let digest = hashes(&rom).unwrap();
+        let sha1: String = digest.sha1.iter().map(|byte| alloc::format!("{:02x}", byte)).collect();
+        let dat = alloc::format!("<game name=\"Test (World)\"><rom size=\"{}\" crc=\"{:08x}\" sha1=\"{}\"/></game>",
+                                 rom.len(), digest.crc32, sha1);
+        let database = Database::parse(&dat).unwrap();
+
+        let mut swapped = rom.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut swapped).unwrap();
+        assert_eq!(database.identify(&swapped).unwrap().unwrap().title, "Test");
+
+        let mut other = rom.clone();
+        other[0x2000] ^= 1;
+        assert!(database.identify(&other).unwrap().is_none());
+    }
+}
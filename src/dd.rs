@@ -0,0 +1,403 @@
+//! 64DD magnetic disks.
+//!
+//! A disk is 16 physical zones, eight per head, each with its own sector
+//! size.  Logical blocks (LBAs) run through the zones in an order set by the
+//! disk type, which also decides where the read only ROM area ends and the
+//! writable RAM area begins.  Dumps come in two layouts: .ndd, every LBA in
+//! order at its zone's block size, and .d64, a 0x200 byte header carrying the
+//! system data and disk ID followed by the LBAs after the system area.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::Error;
+
+pub const ZONES: usize = 16;
+pub const SECTORS_PER_BLOCK: usize = 85;
+pub const BLOCKS_PER_TRACK: usize = 2;
+/// Tracks each zone keeps in reserve for remapping defects.
+pub const SPARE_TRACKS_PER_ZONE: usize = 12;
+
+/// Bytes per sector, by physical zone.
+pub const ZONE_SECTOR_LEN: [usize; ZONES] = [
+    232, 216, 208, 192, 176, 160, 144, 128,
+    216, 208, 192, 176, 160, 144, 128, 112,
+];
+/// Tracks, including spares, by physical zone.
+pub const ZONE_TRACKS: [usize; ZONES] = [
+    158, 158, 149, 149, 149, 149, 149, 114,
+    158, 158, 149, 149, 149, 149, 149, 114,
+];
+
+/// Physical zone of each virtual zone, by disk type.
+const VZONE_TO_PZONE: [[u8; ZONES]; DiskType::COUNT] = [
+    [0, 1, 2, 9, 8, 3, 4, 5, 6, 7, 15, 14, 13, 12, 11, 10],
+    [0, 1, 2, 3, 10, 9, 8, 4, 5, 6, 7, 15, 14, 13, 12, 11],
+    [0, 1, 2, 3, 4, 11, 10, 9, 8, 5, 6, 7, 15, 14, 13, 12],
+    [0, 1, 2, 3, 4, 5, 12, 11, 10, 9, 8, 6, 7, 15, 14, 13],
+    [0, 1, 2, 3, 4, 5, 6, 13, 12, 11, 10, 9, 8, 7, 15, 14],
+    [0, 1, 2, 3, 4, 5, 6, 7, 14, 13, 12, 11, 10, 9, 8, 15],
+    [0, 1, 2, 3, 4, 5, 6, 7, 15, 14, 13, 12, 11, 10, 9, 8],
+];
+
+/// Total LBAs on every disk type.
+pub const LBAS: usize = 4316;
+/// The system area: system data, disk ID, and their copies.
+pub const SYSTEM_AREA_LBAS: usize = 24;
+/// Length of a .ndd image.
+pub const NDD_LEN: usize = 0x3dec800;
+#[cfg(feature = "alloc")]
+const D64_HEADER_LEN: usize = 0x200;
+
+/// System data lives in these LBAs on retail disks, and two later on
+/// development disks.
+const RETAIL_SYSTEM_LBAS: [usize; 4] = [0, 1, 8, 9];
+const DEVELOPMENT_SYSTEM_LBAS: [usize; 4] = [2, 3, 10, 11];
+#[cfg(feature = "alloc")]
+const DISK_ID_LBAS: [usize; 2] = [14, 15];
+/// The meaningful prefix of each system data and disk ID sector, one zone 0
+/// sector long.
+const SYSTEM_DATA_LEN: usize = 0xe8;
+
+/// The layout of a disk's zones.  Higher types trade RAM area for ROM area.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiskType(u8);
+
+impl DiskType {
+    pub const COUNT: usize = 7;
+
+    pub fn new(value: u8) -> Option<DiskType> {
+        if (value as usize) < DiskType::COUNT {
+            Some(DiskType(value))
+        } else {
+            None
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        self.0
+    }
+
+    /// The physical zone holding virtual zone `vzone`.
+    pub fn pzone(self, vzone: usize) -> usize {
+        VZONE_TO_PZONE[self.0 as usize][vzone] as usize
+    }
+
+    /// The first LBA of the writable area; `LBAS` for type 6, which has none.
+    pub fn ram_start_lba(self) -> usize {
+        let rom_vzones = 5 + 2 * self.0 as usize;
+        (0..rom_vzones.min(ZONES)).map(|vzone| zone_lbas(self.pzone(vzone))).sum()
+    }
+
+    /// Bytes per block at `lba`.
+    pub fn block_len(self, lba: usize) -> Option<usize> {
+        self.locate(lba).map(|(_, pzone, _)| ZONE_SECTOR_LEN[pzone] * SECTORS_PER_BLOCK)
+    }
+
+    /// Offset of `lba` in a .ndd image of this disk type.
+    pub fn ndd_offset(self, lba: usize) -> Option<usize> {
+        let mut offset = 0;
+        let mut first = 0;
+        for vzone in 0..ZONES {
+            let pzone = self.pzone(vzone);
+            let lbas = zone_lbas(pzone);
+            let block_len = ZONE_SECTOR_LEN[pzone] * SECTORS_PER_BLOCK;
+            if lba < first + lbas {
+                return Some(offset + (lba - first) * block_len);
+            }
+            offset += lbas * block_len;
+            first += lbas;
+        }
+        None
+    }
+
+    /// Where `lba` sits on the platter, before any defect remapping.
+    pub fn lba_to_physical(self, lba: usize) -> Option<PhysicalBlock> {
+        let (_, pzone, index) = self.locate(lba)?;
+        let head = (pzone / 8) as u8;
+
+        // Head 1 zones are read from the inside out.
+        let usable = ZONE_TRACKS[pzone] - SPARE_TRACKS_PER_ZONE;
+        let track_in_zone = match head {
+            0 => index / BLOCKS_PER_TRACK,
+            _ => usable - 1 - index / BLOCKS_PER_TRACK,
+        };
+        let zone_start: usize = ZONE_TRACKS[pzone - pzone % 8..pzone].iter().sum();
+
+        Some(PhysicalBlock {
+            head,
+            zone: pzone as u8,
+            track: (zone_start + track_in_zone) as u16,
+            // Consecutive tracks alternate which block comes first.
+            block: ((index ^ (index / BLOCKS_PER_TRACK)) & 1) as u8,
+        })
+    }
+
+    // (virtual zone, physical zone, index within the zone) of an LBA.
+    fn locate(self, lba: usize) -> Option<(usize, usize, usize)> {
+        let mut first = 0;
+        for vzone in 0..ZONES {
+            let pzone = self.pzone(vzone);
+            let lbas = zone_lbas(pzone);
+            if lba < first + lbas {
+                return Some((vzone, pzone, lba - first));
+            }
+            first += lbas;
+        }
+        None
+    }
+}
+
+fn zone_lbas(pzone: usize) -> usize {
+    (ZONE_TRACKS[pzone] - SPARE_TRACKS_PER_ZONE) * BLOCKS_PER_TRACK
+}
+
+/// A block's place on the platter.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PhysicalBlock {
+    pub head: u8,
+    /// Physical zone, 0-7 on head 0 and 8-15 on head 1.
+    pub zone: u8,
+    /// Track from the outer edge of the head's surface.
+    pub track: u16,
+    pub block: u8,
+}
+
+/// Whose drives a disk boots in, from the first word of its system data.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DiskRegion {
+    Japan,
+    Usa,
+    Development,
+    Unknown(u32),
+}
+
+impl DiskRegion {
+    pub fn from_u32(value: u32) -> DiskRegion {
+        match value {
+            0xe848_d316 => DiskRegion::Japan,
+            0x2263_ee56 => DiskRegion::Usa,
+            0x0000_0000 => DiskRegion::Development,
+            _ => DiskRegion::Unknown(value),
+        }
+    }
+
+    pub fn as_u32(self) -> u32 {
+        match self {
+            DiskRegion::Japan => 0xe848_d316,
+            DiskRegion::Usa => 0x2263_ee56,
+            DiskRegion::Development => 0x0000_0000,
+            DiskRegion::Unknown(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for DiskRegion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DiskRegion::Japan => write!(f, "Japan"),
+            DiskRegion::Usa => write!(f, "USA"),
+            DiskRegion::Development => write!(f, "Development"),
+            DiskRegion::Unknown(value) => write!(f, "Unknown (0x{:08x})", value),
+        }
+    }
+}
+
+/// The system data block at the start of every disk.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SystemArea {
+    pub region: DiskRegion,
+    pub disk_type: DiskType,
+    /// RDRAM address the IPL loads the boot code to.
+    pub ipl_load_addr: u32,
+    /// The raw sector, including the defect tables.
+    pub data: [u8; SYSTEM_DATA_LEN],
+}
+
+impl SystemArea {
+    /// Parses the first sector of a system data block.
+    pub fn parse(data: &[u8]) -> Result<SystemArea, Error> {
+        if data.len() < SYSTEM_DATA_LEN {
+            return Err(Error::BufferTooShort { needed: SYSTEM_DATA_LEN, actual: data.len() });
+        }
+
+        let mut raw = [0u8; SYSTEM_DATA_LEN];
+        raw.copy_from_slice(&data[..SYSTEM_DATA_LEN]);
+
+        Ok(SystemArea {
+            region: DiskRegion::from_u32(crate::be_u32_at(&raw, 0x00)),
+            disk_type: DiskType::new(raw[0x05] & 0x0f).ok_or(Error::InvalidDisk("unknown disk type"))?,
+            ipl_load_addr: crate::be_u32_at(&raw, 0x1c),
+            data: raw,
+        })
+    }
+
+    /// Finds and parses the system area of a .ndd image, retail or
+    /// development.
+    pub fn from_ndd(ndd: &[u8]) -> Result<SystemArea, Error> {
+        if ndd.len() < NDD_LEN {
+            return Err(Error::BufferTooShort { needed: NDD_LEN, actual: ndd.len() });
+        }
+
+        // The system area is all zone 0 whatever the disk type.
+        let block_len = ZONE_SECTOR_LEN[0] * SECTORS_PER_BLOCK;
+        let parsed = |lba: usize| SystemArea::parse(&ndd[lba * block_len..]);
+        match parsed(RETAIL_SYSTEM_LBAS[0]) {
+            Ok(area) if !matches!(area.region, DiskRegion::Development | DiskRegion::Unknown(_)) => Ok(area),
+            _ => parsed(DEVELOPMENT_SYSTEM_LBAS[0]),
+        }
+    }
+}
+
+/// Converts a .ndd image to .d64.  The system area is reduced to the header's
+/// system data and disk ID, and everything after it is kept in LBA order.
+#[cfg(feature = "alloc")]
+pub fn ndd_to_d64(ndd: &[u8]) -> Result<Vec<u8>, Error> {
+    let system = SystemArea::from_ndd(ndd)?;
+    let block_len = ZONE_SECTOR_LEN[0] * SECTORS_PER_BLOCK;
+    let disk_id = &ndd[DISK_ID_LBAS[0] * block_len..][..SYSTEM_DATA_LEN];
+
+    let data_start = block_len * SYSTEM_AREA_LBAS;
+    let mut d64 = Vec::with_capacity(D64_HEADER_LEN + NDD_LEN - data_start);
+    d64.extend_from_slice(&system.data);
+    d64.resize(D64_HEADER_LEN / 2, 0);
+    d64.extend_from_slice(disk_id);
+    d64.resize(D64_HEADER_LEN, 0);
+    d64.extend_from_slice(&ndd[data_start..NDD_LEN]);
+
+    Ok(d64)
+}
+
+/// Converts a .d64 image to .ndd, rebuilding the system area with every copy
+/// of the system data and disk ID.  Images without a RAM area are zero
+/// filled to full size.
+#[cfg(feature = "alloc")]
+pub fn d64_to_ndd(d64: &[u8]) -> Result<Vec<u8>, Error> {
+    if d64.len() < D64_HEADER_LEN {
+        return Err(Error::BufferTooShort { needed: D64_HEADER_LEN, actual: d64.len() });
+    }
+
+    let system = SystemArea::parse(d64)?;
+    let disk_id = &d64[D64_HEADER_LEN / 2..][..SYSTEM_DATA_LEN];
+    let block_len = ZONE_SECTOR_LEN[0] * SECTORS_PER_BLOCK;
+    let data_start = block_len * SYSTEM_AREA_LBAS;
+
+    let data = &d64[D64_HEADER_LEN..];
+    if data.len() > NDD_LEN - data_start {
+        return Err(Error::InvalidDisk("d64 image longer than a disk"));
+    }
+
+    let mut ndd = alloc::vec![0u8; NDD_LEN];
+    let system_lbas = match system.region {
+        DiskRegion::Development => DEVELOPMENT_SYSTEM_LBAS,
+        _ => RETAIL_SYSTEM_LBAS,
+    };
+    let copies = system_lbas.iter().map(|lba| (*lba, &system.data[..]))
+        .chain(DISK_ID_LBAS.iter().map(|lba| (*lba, disk_id)));
+    for (lba, sector) in copies {
+        for chunk in ndd[lba * block_len..(lba + 1) * block_len].chunks_exact_mut(SYSTEM_DATA_LEN) {
+            chunk.copy_from_slice(sector);
+        }
+    }
+    ndd[data_start..data_start + data.len()].copy_from_slice(data);
+
+    Ok(ndd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system_data(region: DiskRegion, disk_type: u8) -> [u8; SYSTEM_DATA_LEN] {
+        let mut data = [0u8; SYSTEM_DATA_LEN];
+        data[..4].copy_from_slice(&region.as_u32().to_be_bytes());
+        data[0x04] = 0x10;
+        data[0x05] = 0x10 | disk_type;
+        data[0x1c..0x20].copy_from_slice(&0x8000_0400u32.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn zone_tables_add_up() {
+        for value in 0..DiskType::COUNT as u8 {
+            let disk_type = DiskType::new(value).unwrap();
+            let mut pzones: Vec<usize> = (0..ZONES).map(|vzone| disk_type.pzone(vzone)).collect();
+            pzones.sort();
+            assert_eq!(pzones, (0..ZONES).collect::<Vec<_>>());
+
+            assert_eq!(disk_type.ndd_offset(LBAS - 1).unwrap() + disk_type.block_len(LBAS - 1).unwrap(), NDD_LEN);
+            assert_eq!(disk_type.ndd_offset(LBAS), None);
+        }
+        assert_eq!(DiskType::new(7), None);
+    }
+
+    #[test]
+    fn ram_area_starts_per_type() {
+        let starts: Vec<usize> = (0..DiskType::COUNT as u8)
+            .map(|value| DiskType::new(value).unwrap().ram_start_lba())
+            .collect();
+        assert_eq!(starts, [0x5a2, 0x7c6, 0x9ea, 0xc0e, 0xe32, 0x1010, LBAS]);
+    }
+
+    #[test]
+    fn translates_lbas() {
+        let disk_type = DiskType::new(0).unwrap();
+        assert_eq!(disk_type.lba_to_physical(0), Some(PhysicalBlock { head: 0, zone: 0, track: 0, block: 0 }));
+        assert_eq!(disk_type.lba_to_physical(2), Some(PhysicalBlock { head: 0, zone: 0, track: 1, block: 1 }));
+        assert_eq!(disk_type.lba_to_physical(292), Some(PhysicalBlock { head: 0, zone: 1, track: 158, block: 0 }));
+
+        // Virtual zone 3 is the innermost usable track of head 1's second zone.
+        assert_eq!(disk_type.lba_to_physical(858).unwrap(),
+                   PhysicalBlock { head: 1, zone: 9, track: 158 + 145, block: 0 });
+        assert_eq!(disk_type.lba_to_physical(LBAS), None);
+        assert_eq!(disk_type.block_len(0), Some(232 * 85));
+    }
+
+    #[test]
+    fn parses_system_area() {
+        let area = SystemArea::parse(&system_data(DiskRegion::Usa, 3)).unwrap();
+        assert_eq!(area.region, DiskRegion::Usa);
+        assert_eq!(area.disk_type, DiskType::new(3).unwrap());
+        assert_eq!(area.ipl_load_addr, 0x8000_0400);
+
+        assert!(matches!(SystemArea::parse(&system_data(DiskRegion::Japan, 9)), Err(Error::InvalidDisk(_))));
+        assert!(matches!(SystemArea::parse(&[0; 4]), Err(Error::BufferTooShort { .. })));
+        assert_eq!(DiskRegion::from_u32(0x1234).to_string(), "Unknown (0x00001234)");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn converts_between_layouts() {
+        let block_len = ZONE_SECTOR_LEN[0] * SECTORS_PER_BLOCK;
+        let mut d64 = vec![0u8; D64_HEADER_LEN];
+        d64[..SYSTEM_DATA_LEN].copy_from_slice(&system_data(DiskRegion::Japan, 0));
+        d64[0x100..0x104].copy_from_slice(b"NEZJ");
+        d64.extend((0..0x10000).map(|ii: usize| (ii * 13) as u8));
+
+        let ndd = d64_to_ndd(&d64).unwrap();
+        assert_eq!(ndd.len(), NDD_LEN);
+        assert_eq!(&ndd[9 * block_len + 85 * SYSTEM_DATA_LEN - SYSTEM_DATA_LEN..][..4], &d64[..4]);
+        assert_eq!(&ndd[15 * block_len..][..4], b"NEZJ");
+        assert_eq!(SystemArea::from_ndd(&ndd).unwrap().region, DiskRegion::Japan);
+
+        let round_trip = ndd_to_d64(&ndd).unwrap();
+        assert_eq!(&round_trip[..d64.len()], &d64[..]);
+        assert!(round_trip[d64.len()..].iter().all(|byte| *byte == 0));
+        assert_eq!(round_trip.len(), D64_HEADER_LEN + NDD_LEN - SYSTEM_AREA_LBAS * block_len);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn finds_development_system_areas() {
+        let mut d64 = vec![0u8; D64_HEADER_LEN];
+        d64[..SYSTEM_DATA_LEN].copy_from_slice(&system_data(DiskRegion::Development, 5));
+        let ndd = d64_to_ndd(&d64).unwrap();
+
+        let area = SystemArea::from_ndd(&ndd).unwrap();
+        assert_eq!(area.region, DiskRegion::Development);
+        assert_eq!(area.disk_type, DiskType::new(5).unwrap());
+    }
+}
@@ -0,0 +1,288 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use crate::patch::native_copy;
+use crate::{resolve_swapping, ByteSwapping, Error, RomHeader, HEADER_END, HEADER_NAME_LEN};
+
+/// Unchanged bytes [`diff`] keeps around each changed range.
+pub const DEFAULT_DIFF_CONTEXT: usize = 16;
+
+/// A header field, in the order they're laid out.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HeaderField {
+    CartTiming,
+    ClockRate,
+    LoadAddr,
+    Release,
+    Crc1,
+    Crc2,
+    Reserved18,
+    Reserved1c,
+    Name,
+    Reserved34,
+    ManufId,
+    CartId,
+    CountryCode,
+}
+
+impl HeaderField {
+    pub const ALL: [HeaderField; 13] = [
+        HeaderField::CartTiming,
+        HeaderField::ClockRate,
+        HeaderField::LoadAddr,
+        HeaderField::Release,
+        HeaderField::Crc1,
+        HeaderField::Crc2,
+        HeaderField::Reserved18,
+        HeaderField::Reserved1c,
+        HeaderField::Name,
+        HeaderField::Reserved34,
+        HeaderField::ManufId,
+        HeaderField::CartId,
+        HeaderField::CountryCode,
+    ];
+
+    /// The field's bytes within the header.
+    pub fn range(self) -> Range<usize> {
+        let (start, len) = match self {
+            HeaderField::CartTiming => (0x00, 4),
+            HeaderField::ClockRate => (0x04, 4),
+            HeaderField::LoadAddr => (0x08, 4),
+            HeaderField::Release => (0x0c, 4),
+            HeaderField::Crc1 => (0x10, 4),
+            HeaderField::Crc2 => (0x14, 4),
+            HeaderField::Reserved18 => (0x18, 4),
+            HeaderField::Reserved1c => (0x1c, 4),
+            HeaderField::Name => (0x20, HEADER_NAME_LEN),
+            HeaderField::Reserved34 => (0x34, 4),
+            HeaderField::ManufId => (0x38, 4),
+            HeaderField::CartId => (0x3c, 2),
+            HeaderField::CountryCode => (0x3e, 2),
+        };
+        start..start + len
+    }
+
+    pub fn value(self, header: &RomHeader) -> HeaderValue {
+        match self {
+            HeaderField::CartTiming => HeaderValue::Word(header.cart_timing),
+            HeaderField::ClockRate => HeaderValue::Word(header.clock_rate),
+            HeaderField::LoadAddr => HeaderValue::Word(header.load_addr),
+            HeaderField::Release => HeaderValue::Word(header.release),
+            HeaderField::Crc1 => HeaderValue::Word(header.crc1),
+            HeaderField::Crc2 => HeaderValue::Word(header.crc2),
+            HeaderField::Reserved18 => HeaderValue::Word(header.rsvd_18),
+            HeaderField::Reserved1c => HeaderValue::Word(header.rsvd_1c),
+            HeaderField::Name => HeaderValue::Name(header.name),
+            HeaderField::Reserved34 => HeaderValue::Word(header.rsvd_34),
+            HeaderField::ManufId => HeaderValue::Word(header.manuf_id),
+            HeaderField::CartId => HeaderValue::Half(header.cart_id),
+            HeaderField::CountryCode => HeaderValue::Half(header.country_code),
+        }
+    }
+}
+
+impl fmt::Display for HeaderField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            HeaderField::CartTiming => "cart timing",
+            HeaderField::ClockRate => "clock rate",
+            HeaderField::LoadAddr => "load address",
+            HeaderField::Release => "release",
+            HeaderField::Crc1 => "CRC1",
+            HeaderField::Crc2 => "CRC2",
+            HeaderField::Reserved18 => "reserved 0x18",
+            HeaderField::Reserved1c => "reserved 0x1c",
+            HeaderField::Name => "name",
+            HeaderField::Reserved34 => "reserved 0x34",
+            HeaderField::ManufId => "manufacturer",
+            HeaderField::CartId => "cart id",
+            HeaderField::CountryCode => "country code",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What a header field held.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HeaderValue {
+    Word(u32),
+    Half(u16),
+    Name([u8; HEADER_NAME_LEN]),
+}
+
+impl fmt::Display for HeaderValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeaderValue::Word(value) => write!(f, "0x{:08X}", value),
+            HeaderValue::Half(value) => write!(f, "0x{:04X}", value),
+            HeaderValue::Name(name) => {
+                write!(f, "\"")?;
+                for byte in name.iter() {
+                    write!(f, "{}", core::ascii::escape_default(*byte))?;
+                }
+                write!(f, "\"")
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct HeaderChange {
+    pub field: HeaderField,
+    pub old: HeaderValue,
+    pub new: HeaderValue,
+}
+
+/// How two images differ, both compared in native order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RomDiff {
+    /// The byte orders the two images were in.
+    pub swappings: (ByteSwapping, ByteSwapping),
+    /// The lengths of the two images.
+    pub lens: (usize, usize),
+    /// Every header field that differs, in layout order.
+    pub header: Vec<HeaderChange>,
+    /// Ranges of the payload after the header that differ, including the
+    /// bytes past the end of the shorter image, widened by the context and
+    /// merged where they then touch.  Offsets index the longer image.
+    pub ranges: Vec<Range<usize>>,
+}
+
+impl RomDiff {
+    pub fn is_empty(&self) -> bool {
+        self.lens.0 == self.lens.1 && self.header.is_empty() && self.ranges.is_empty()
+    }
+
+    /// Total length of the payload ranges, context included.
+    pub fn changed_len(&self) -> usize {
+        self.ranges.iter().map(|range| range.len()).sum()
+    }
+}
+
+impl fmt::Display for RomDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.swappings.0 != self.swappings.1 {
+            writeln!(f, "byte order: {} -> {}", self.swappings.0, self.swappings.1)?;
+        }
+        if self.lens.0 != self.lens.1 {
+            writeln!(f, "length: {} -> {} bytes", self.lens.0, self.lens.1)?;
+        }
+        for change in &self.header {
+            writeln!(f, "{}: {} -> {}", change.field, change.old, change.new)?;
+        }
+        for range in &self.ranges {
+            writeln!(f, "0x{:08X}..0x{:08X} ({} bytes)", range.start, range.end, range.len())?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two images in any byte orders, with [`DEFAULT_DIFF_CONTEXT`]
+/// bytes of context around each changed range.
+pub fn diff(rom_a: &[u8], rom_b: &[u8]) -> Result<RomDiff, Error> {
+    diff_with_context(rom_a, rom_b, DEFAULT_DIFF_CONTEXT)
+}
+
+/// Compares two images with `context` unchanged bytes kept on each side of
+/// a changed range.  With no context the ranges are exactly the bytes a
+/// patch from `rom_a` to `rom_b` has to carry.
+pub fn diff_with_context(rom_a: &[u8], rom_b: &[u8], context: usize) -> Result<RomDiff, Error> {
+    let swappings = (
+        resolve_swapping(rom_a).ok_or(Error::UnknownByteSwapping)?,
+        resolve_swapping(rom_b).ok_or(Error::UnknownByteSwapping)?,
+    );
+    let a = native_copy(rom_a)?;
+    let b = native_copy(rom_b)?;
+
+    let header_a = RomHeader::from_slice(&a)?;
+    let header_b = RomHeader::from_slice(&b)?;
+    let header = HeaderField::ALL.iter()
+        .map(|field| HeaderChange { field: *field, old: field.value(&header_a), new: field.value(&header_b) })
+        .filter(|change| change.old != change.new)
+        .collect();
+
+    Ok(RomDiff { swappings, lens: (a.len(), b.len()), header, ranges: changed_ranges(&a, &b, context) })
+}
+
+fn changed_ranges(a: &[u8], b: &[u8], context: usize) -> Vec<Range<usize>> {
+    let len = core::cmp::max(a.len(), b.len());
+    let common = core::cmp::min(a.len(), b.len());
+
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut pos = HEADER_END;
+    while pos < len {
+        if pos < common && a[pos] == b[pos] {
+            pos += 1;
+            continue;
+        }
+
+        let start = pos;
+        while pos < len && (pos >= common || a[pos] != b[pos]) {
+            pos += 1;
+        }
+
+        let widened = start.saturating_sub(context).max(HEADER_END)..pos.saturating_add(context).min(len);
+        match ranges.last_mut() {
+            Some(last) if last.end >= widened.start => last.end = widened.end,
+            _ => ranges.push(widened),
+        }
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::string::ToString;
+
+    use crate::swap_cart_to;
+    use crate::tests::test_cart;
+
+    #[test]
+    fn reports_header_fields_and_payload_ranges() {
+        let original = test_cart();
+        let mut modified = original.clone();
+        let mut header = RomHeader::from_slice(&modified).unwrap();
+        header.set_name("HACKED").unwrap();
+        header.crc1 ^= 1;
+        modified[..HEADER_END].copy_from_slice(&header.to_bytes());
+        modified[0x2000] ^= 0xff;
+        modified[0x2008] ^= 0xff;
+        modified[0x8000] ^= 0xff;
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut modified).unwrap();
+
+        let changes = diff_with_context(&original, &modified, 4).unwrap();
+        assert_eq!(changes.swappings, (ByteSwapping::Native, ByteSwapping::U32LittleEndian));
+        let fields: Vec<HeaderField> = changes.header.iter().map(|change| change.field).collect();
+        assert_eq!(fields, [HeaderField::Crc1, HeaderField::Name]);
+        assert_eq!(changes.ranges, [0x1ffc..0x200d, 0x7ffc..0x8005]);
+        assert_eq!(changes.changed_len(), 0x11 + 9);
+
+        let exact = diff_with_context(&original, &modified, 0).unwrap();
+        assert_eq!(exact.ranges, [0x2000..0x2001, 0x2008..0x2009, 0x8000..0x8001]);
+
+        let report = changes.to_string();
+        assert!(report.contains("byte order: Native -> U32 Little Endian"));
+        assert!(report.contains("CRC1: 0x"));
+        assert!(report.contains("name: "));
+        assert!(report.contains("0x00001FFC..0x0000200D (17 bytes)"));
+
+        assert!(diff(&original, &original).unwrap().is_empty());
+    }
+
+    #[test]
+    fn counts_length_changes() {
+        let original = test_cart();
+        let mut grown = original.clone();
+        grown.extend_from_slice(&[0u8; 8]);
+
+        let changes = diff_with_context(&original, &grown, 16).unwrap();
+        assert_eq!(changes.lens, (original.len(), original.len() + 8));
+        assert_eq!(changes.ranges.len(), 1);
+        assert_eq!(changes.ranges[0], original.len() - 16..original.len() + 8);
+        assert!(!changes.is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+use alloc::vec::Vec;
+
+use object::{Object, ObjectSegment};
+
+use crate::{Error, RomBuilder, LOAD_START, SC64_MAX_ROM_LEN};
+
+impl RomBuilder {
+    /// Lays out the loadable segments of a MIPS ELF in the payload relative to
+    /// its entry point, which becomes the builder's entry point.
+    pub fn elf(self, elf: &[u8]) -> Result<RomBuilder, Error> {
+        let file = object::File::parse(elf).map_err(|_| Error::InvalidElf("unable to parse"))?;
+
+        if file.architecture() != object::Architecture::Mips {
+            return Err(Error::InvalidElf("not a MIPS executable"));
+        }
+
+        let entry = file.entry();
+        if entry > u64::from(u32::MAX) {
+            return Err(Error::InvalidElf("entry point does not fit in the header"));
+        }
+
+        let mut segments: Vec<(u64, &[u8])> = Vec::new();
+        for segment in file.segments() {
+            let data = segment.data().map_err(|_| Error::InvalidElf("unable to read segment"))?;
+            if data.is_empty() {
+                continue;
+            }
+            if segment.address() < entry {
+                return Err(Error::InvalidElf("segment loads below the entry point"));
+            }
+            let end = (segment.address() - entry).checked_add(LOAD_START as u64 + data.len() as u64);
+            if end.is_none_or(|end| end > SC64_MAX_ROM_LEN as u64) {
+                return Err(Error::InvalidElf("segment loads past the end of the largest cart"));
+            }
+            segments.push((segment.address(), data));
+        }
+
+        if segments.is_empty() {
+            return Err(Error::InvalidElf("no loadable segments"));
+        }
+        segments.sort_by_key(|segment| segment.0);
+
+        let mut builder = self.entry_point(entry as u32);
+        for (address, data) in segments {
+            builder = builder.segment_at(data, LOAD_START + (address - entry) as usize);
+        }
+
+        Ok(builder)
+    }
+}
+
+/// Converts a MIPS ELF into a bootable native byte order image using the
+/// given IPL3, with the CRCs filled in.
+pub fn elf_to_rom(elf: &[u8], bootcode: &[u8]) -> Result<Vec<u8>, Error> {
+    RomBuilder::new()
+        .bootcode(bootcode)
+        .elf(elf)?
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{verify_cart_checksum, RomHeader, ROM_LEN};
+
+    // A big endian ELF32 executable for MIPS with one PT_LOAD per entry.
+    fn mips_elf(entry: u32, segments: &[(u32, &[u8])]) -> Vec<u8> {
+        let phoff = 52u32;
+        let data_start = phoff + 32 * segments.len() as u32;
+
+        let mut elf: Vec<u8> = vec![0x7f, b'E', b'L', b'F', 1, 2, 1, 0];
+        elf.extend_from_slice(&[0; 8]);
+        elf.extend_from_slice(&2u16.to_be_bytes());
+        elf.extend_from_slice(&8u16.to_be_bytes());
+        elf.extend_from_slice(&1u32.to_be_bytes());
+        elf.extend_from_slice(&entry.to_be_bytes());
+        elf.extend_from_slice(&phoff.to_be_bytes());
+        elf.extend_from_slice(&0u32.to_be_bytes());
+        elf.extend_from_slice(&0u32.to_be_bytes());
+        elf.extend_from_slice(&52u16.to_be_bytes());
+        elf.extend_from_slice(&32u16.to_be_bytes());
+        elf.extend_from_slice(&(segments.len() as u16).to_be_bytes());
+        elf.extend_from_slice(&40u16.to_be_bytes());
+        elf.extend_from_slice(&0u16.to_be_bytes());
+        elf.extend_from_slice(&0u16.to_be_bytes());
+
+        let mut offset = data_start;
+        for (address, data) in segments {
+            for word in &[1, offset, *address, *address, data.len() as u32, data.len() as u32, 7, 4] {
+                elf.extend_from_slice(&word.to_be_bytes());
+            }
+            offset += data.len() as u32;
+        }
+        for (_, data) in segments {
+            elf.extend_from_slice(data);
+        }
+
+        elf
+    }
+
+    #[test]
+    fn converts_elf_segments() {
+        let elf = mips_elf(0x80000400, &[(0x80000500, &[5, 6]), (0x80000400, &[1, 2, 3, 4])]);
+        let image = elf_to_rom(&elf, &[0u8; 16]).unwrap();
+
+        assert_eq!(image.len(), ROM_LEN);
+        assert_eq!(RomHeader::from_slice(&image).unwrap().load_addr, 0x80000400);
+        assert_eq!(&image[LOAD_START..LOAD_START + 4], &[1, 2, 3, 4]);
+        assert_eq!(&image[LOAD_START + 0x100..LOAD_START + 0x102], &[5, 6]);
+        assert!(verify_cart_checksum(&image).unwrap().is_valid());
+    }
+
+    #[test]
+    fn rejects_segments_below_entry() {
+        let elf = mips_elf(0x80000400, &[(0x80000000, &[1, 2, 3, 4])]);
+        assert!(elf_to_rom(&elf, &[]).is_err());
+        assert!(elf_to_rom(&[0u8; 64], &[]).is_err());
+    }
+
+    #[test]
+    fn rejects_segments_past_the_largest_cart() {
+        let elf = mips_elf(0x80000400, &[(0x80000400, &[1, 2, 3, 4]), (0xc0000000, &[5, 6])]);
+        assert!(matches!(elf_to_rom(&elf, &[]), Err(Error::InvalidElf(_))));
+    }
+}
@@ -0,0 +1,99 @@
+use embedded_io::{Error as _, Read, ReadExactError, Write};
+
+use crate::checksum::CHECKSUM_END;
+use crate::{CartChecksum, ChecksumError, CicVariant, Error, RomHeader, HEADER_LEN};
+
+impl RomHeader {
+    /// Reads a native byte order header from an `embedded-io` reader, such as
+    /// an SD card block driver on a flashcart.
+    pub fn deserialize_embedded<R: Read>(reader: &mut R) -> Result<RomHeader, Error> {
+        let mut bytes = [0u8; HEADER_LEN as usize];
+        let len = read_full(reader, &mut bytes)?;
+        if len != bytes.len() {
+            return Err(Error::BufferTooShort { needed: bytes.len(), actual: len });
+        }
+        Ok(RomHeader::from_bytes(&bytes))
+    }
+
+    /// Writes the header in native byte order to an `embedded-io` writer.
+    pub fn serialize_embedded<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_all(&self.to_bytes()).map_err(|err| Error::EmbeddedIo(err.kind()))
+    }
+}
+
+/// `embedded-io` version of
+/// [`calculate_cart_checksum_from_reader`](crate::calculate_cart_checksum_from_reader).
+/// Needs no allocator and only a small stack buffer.
+pub fn calculate_cart_checksum_from_embedded_reader<R: Read>(variant: CicVariant, reader: &mut R)
+                                                             -> Result<(u32, u32), Error> {
+    let mut digest = CartChecksum::new(variant);
+    let mut chunk = [0u8; 512];
+    let mut remaining = CHECKSUM_END;
+
+    while remaining != 0 {
+        let want = core::cmp::min(remaining, chunk.len());
+        match reader.read_exact(&mut chunk[..want]) {
+            Ok(()) => {},
+            Err(ReadExactError::UnexpectedEof) => {
+                return Err(ChecksumError::ErrorReadingBuffer { offset: CHECKSUM_END - remaining }.into());
+            },
+            Err(ReadExactError::Other(err)) => return Err(Error::EmbeddedIo(err.kind())),
+        }
+        digest.update(&chunk[..want]);
+        remaining -= want;
+    }
+
+    Ok(digest.finalize()?)
+}
+
+// Reads until `buffer` is full or the reader runs dry, returning the count.
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Result<usize, Error> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..]) {
+            Ok(0) => break,
+            Ok(count) => len += count,
+            Err(err) => return Err(Error::EmbeddedIo(err.kind())),
+        }
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+    use crate::calculate_cart_checksum_with;
+
+    #[test]
+    fn round_trips_headers() {
+        let mut header = RomHeader::new();
+        header.load_addr = 0x80000400;
+
+        let mut bytes = [0u8; HEADER_LEN as usize];
+        header.serialize_embedded(&mut &mut bytes[..]).unwrap();
+        assert_eq!(bytes, header.to_bytes());
+
+        let parsed = RomHeader::deserialize_embedded(&mut &bytes[..]).unwrap();
+        assert_eq!(parsed.to_bytes(), bytes);
+        assert!(matches!(RomHeader::deserialize_embedded(&mut &bytes[..10]),
+                         Err(Error::BufferTooShort { needed: 64, actual: 10 })));
+
+        let mut tiny = [0u8; 8];
+        assert!(matches!(header.serialize_embedded(&mut &mut tiny[..]), Err(Error::EmbeddedIo(_))));
+    }
+
+    #[test]
+    fn checksums_streams() {
+        let cart = test_cart();
+        for variant in CicVariant::ALL.iter() {
+            assert_eq!(calculate_cart_checksum_from_embedded_reader(*variant, &mut &cart[..]).unwrap(),
+                       calculate_cart_checksum_with(*variant, &cart).unwrap());
+        }
+
+        let short = &cart[..CHECKSUM_END - 1];
+        assert!(matches!(calculate_cart_checksum_from_embedded_reader(CicVariant::Cic6102, &mut &short[..]),
+                         Err(Error::Checksum(ChecksumError::ErrorReadingBuffer { offset: 0x100e00 }))));
+    }
+}
@@ -0,0 +1,144 @@
+//! Entries for the two emulator databases homebrew most often needs to be
+//! added to: mupen64plus's `mupen64plus.ini`, keyed by MD5, and Project64's
+//! `Project64.rdb`, keyed by the header CRC pair and country code.
+
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::{RomInfo, SaveType};
+
+/// Settings to record alongside an image's identity.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct EmulatorHints {
+    /// Overrides the save type guessed by [`analyze`](crate::analyze).
+    pub save_type: Option<SaveType>,
+    /// CPU cycles per emulated instruction, 1 to 6.  Most games run with the
+    /// emulators' defaults; timing sensitive homebrew often wants 1.
+    pub counter_factor: Option<u8>,
+    pub players: Option<u8>,
+    pub rumble: Option<bool>,
+}
+
+fn save_type(info: &RomInfo, hints: &EmulatorHints) -> Option<SaveType> {
+    hints.save_type.or_else(|| info.save.map(|guess| guess.save_type))
+}
+
+/// A `mupen64plus.ini` section for the image.
+#[cfg(feature = "hashes")]
+pub fn mupen64plus_entry(info: &RomInfo, good_name: &str, hints: &EmulatorHints) -> String {
+    let mut entry = String::new();
+
+    entry.push('[');
+    for byte in info.hashes.md5.iter() {
+        write!(entry, "{:02X}", byte).unwrap();
+    }
+    entry.push_str("]\n");
+    writeln!(entry, "GoodName={}", good_name).unwrap();
+    writeln!(entry, "CRC={:08X} {:08X}", info.header.crc1, info.header.crc2).unwrap();
+
+    if let Some(save_type) = save_type(info, hints) {
+        let name = match save_type {
+            SaveType::Eeprom4k => "Eeprom 4KB",
+            SaveType::Eeprom16k => "Eeprom 16KB",
+            SaveType::Sram256k => "SRAM",
+            SaveType::FlashRam => "Flash RAM",
+            SaveType::ControllerPak => "Controller Pack",
+        };
+        writeln!(entry, "SaveType={}", name).unwrap();
+    }
+    if let Some(players) = hints.players {
+        writeln!(entry, "Players={}", players).unwrap();
+    }
+    if let Some(rumble) = hints.rumble {
+        writeln!(entry, "Rumble={}", if rumble { "Yes" } else { "No" }).unwrap();
+    }
+    if let Some(counter_factor) = hints.counter_factor {
+        writeln!(entry, "CountPerOp={}", counter_factor).unwrap();
+    }
+
+    entry
+}
+
+/// A `Project64.rdb` section for the image.
+pub fn project64_entry(info: &RomInfo, good_name: &str, hints: &EmulatorHints) -> String {
+    let mut entry = String::new();
+
+    writeln!(entry, "[{:08X}-{:08X}-C:{:02X}]", info.header.crc1, info.header.crc2, info.country.as_u8()).unwrap();
+    writeln!(entry, "Good Name={}", good_name).unwrap();
+    writeln!(entry, "Internal Name={}", info.header.name_str()).unwrap();
+
+    // Project64 has no setting for games that only use a Controller Pak.
+    let name = match save_type(info, hints) {
+        Some(SaveType::Eeprom4k) => Some("4kbit Eeprom"),
+        Some(SaveType::Eeprom16k) => Some("16kbit Eeprom"),
+        Some(SaveType::Sram256k) => Some("Sram"),
+        Some(SaveType::FlashRam) => Some("FlashRam"),
+        Some(SaveType::ControllerPak) | None => None,
+    };
+    if let Some(name) = name {
+        writeln!(entry, "Save Type={}", name).unwrap();
+    }
+    if let Some(counter_factor) = hints.counter_factor {
+        writeln!(entry, "Counter Factor={}", counter_factor).unwrap();
+    }
+    if let Some(players) = hints.players {
+        writeln!(entry, "Players={}", players).unwrap();
+    }
+    if let Some(rumble) = hints.rumble {
+        writeln!(entry, "Rumble={}", if rumble { "Yes" } else { "No" }).unwrap();
+    }
+
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+    use crate::{analyze, RomHeader, HEADER_END, HEADER_START};
+
+    fn homebrew_info() -> RomInfo {
+        let mut rom = test_cart();
+        let mut header = RomHeader::new();
+        header.set_name("MY HOMEBREW").unwrap();
+        header.set_game_id(&"NHBE".parse().unwrap());
+        header.crc1 = 0x0123abcd;
+        header.crc2 = 0x89ef4567;
+        rom[HEADER_START..HEADER_END].copy_from_slice(&header.to_bytes());
+        analyze(&rom).unwrap()
+    }
+
+    #[test]
+    fn emits_project64_entry() {
+        let hints = EmulatorHints { save_type: Some(SaveType::Eeprom16k), counter_factor: Some(1), ..Default::default() };
+        assert_eq!(project64_entry(&homebrew_info(), "My Homebrew (USA)", &hints), "\
+[0123ABCD-89EF4567-C:45]
+Good Name=My Homebrew (USA)
+Internal Name=MY HOMEBREW
+Save Type=16kbit Eeprom
+Counter Factor=1
+");
+    }
+
+    #[cfg(feature = "hashes")]
+    #[test]
+    fn emits_mupen64plus_entry() {
+        let info = homebrew_info();
+        let hints = EmulatorHints { save_type: Some(SaveType::FlashRam), players: Some(2), rumble: Some(true), ..Default::default() };
+        let entry = mupen64plus_entry(&info, "My Homebrew (USA)", &hints);
+
+        let mut md5 = String::new();
+        for byte in info.hashes.md5.iter() {
+            write!(md5, "{:02X}", byte).unwrap();
+        }
+        assert_eq!(entry, alloc::format!("\
+[{}]
+GoodName=My Homebrew (USA)
+CRC=0123ABCD 89EF4567
+SaveType=Flash RAM
+Players=2
+Rumble=Yes
+", md5));
+    }
+}
@@ -0,0 +1,215 @@
+//! Mapping out what a payload holds, block by block: code, plain data,
+//! compressed data, or fill, told apart by entropy and by how much of a
+//! block decodes as MIPS instructions.
+
+use alloc::vec::Vec;
+
+use crate::{resolve_swapping, swap_to_native, Error, LOAD_START};
+
+/// Block size that separates regions well on retail images.
+pub const DEFAULT_BLOCK_LEN: usize = 0x1000;
+
+/// Blocks at or below this many bits per byte are fill.
+const PADDING_MAX_ENTROPY: f64 = 1.0;
+/// Blocks above this many bits per byte are compressed or encrypted.
+const COMPRESSED_MIN_ENTROPY: f64 = 7.5;
+/// Share of words that must decode as common MIPS instructions for a block
+/// to count as code.  Random data lands near a third.
+const CODE_MIN_OPCODE_RATIO: f64 = 0.75;
+
+/// What a stretch of the image most likely holds.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RegionKind {
+    /// Fill bytes, usually safe to overwrite.
+    Padding,
+    /// MIPS instructions.
+    Code,
+    /// Uncompressed assets and tables.
+    Data,
+    /// Compressed or encrypted data.
+    Compressed,
+}
+
+/// A run of adjacent blocks that classified the same.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    pub start: usize,
+    pub len: usize,
+    pub kind: RegionKind,
+    /// Mean Shannon entropy of the region's blocks, in bits per byte.
+    pub entropy: f64,
+}
+
+/// Shannon entropy of `data` in bits per byte, from 0 for a single repeated
+/// value up to 8.
+pub fn block_entropy(data: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for byte in data {
+        counts[*byte as usize] += 1;
+    }
+
+    let total = data.len() as f64;
+    counts.iter()
+        .filter(|count| **count != 0)
+        .map(|count| {
+            let p = *count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Splits the payload of an image in any byte order into regions of
+/// `block_len` byte blocks, classified by entropy and how much of each
+/// decodes as code.  Offsets are from the start of the image.
+pub fn scan_regions(rom: &[u8], block_len: usize) -> Result<Vec<Region>, Error> {
+    if block_len == 0 || !block_len.is_multiple_of(4) {
+        return Err(Error::InvalidArgument("block length must be a non-zero multiple of 4"));
+    }
+
+    let swapping = resolve_swapping(rom).ok_or(Error::UnknownByteSwapping)?;
+    if rom.len() < LOAD_START {
+        return Err(Error::BufferTooShort { needed: LOAD_START, actual: rom.len() });
+    }
+
+    // Sums of the merged blocks' entropies, to average once a region ends.
+    let mut regions: Vec<(Region, f64, usize)> = Vec::new();
+    let mut block = Vec::with_capacity(block_len);
+
+    for (ii, data) in rom[LOAD_START..].chunks(block_len).enumerate() {
+        block.clear();
+        block.extend_from_slice(data);
+        // A ragged tail can't be swapped, but swapping doesn't change
+        // entropy, so only the opcode check is off for those few bytes.
+        let whole = block.len() - block.len() % 4;
+        swap_to_native(swapping, &mut block[..whole]);
+
+        let entropy = block_entropy(&block);
+        let kind = classify(&block, entropy);
+        let start = LOAD_START + ii * block_len;
+
+        match regions.last_mut() {
+            Some((region, sum, count)) if region.kind == kind => {
+                region.len += block.len();
+                *sum += entropy;
+                *count += 1;
+            },
+            _ => regions.push((Region { start, len: block.len(), kind, entropy }, entropy, 1)),
+        }
+    }
+
+    Ok(regions.into_iter()
+        .map(|(mut region, sum, count)| {
+            region.entropy = sum / count as f64;
+            region
+        })
+        .collect())
+}
+
+fn classify(block: &[u8], entropy: f64) -> RegionKind {
+    if entropy <= PADDING_MAX_ENTROPY {
+        RegionKind::Padding
+    } else if entropy > COMPRESSED_MIN_ENTROPY {
+        RegionKind::Compressed
+    } else if opcode_ratio(block) >= CODE_MIN_OPCODE_RATIO {
+        RegionKind::Code
+    } else {
+        RegionKind::Data
+    }
+}
+
+// Share of the big endian words in `block` whose primary opcode is one the
+// compilers of the era emit constantly.
+fn opcode_ratio(block: &[u8]) -> f64 {
+    let words = block.chunks_exact(4);
+    let total = words.len();
+    if total == 0 {
+        return 0.0;
+    }
+
+    let common = words
+        .filter(|word| matches!(word[0] >> 2,
+            0x00 | 0x02 | 0x03 | 0x04 | 0x05 | 0x09 | 0x0a | 0x0c | 0x0d | 0x0f | 0x11 |
+            0x20 | 0x21 | 0x23 | 0x24 | 0x25 | 0x28 | 0x29 | 0x2b | 0x31 | 0x39))
+        .count();
+    common as f64 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+    use crate::{swap_cart_to, ByteSwapping};
+
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x1234_5678u32;
+        (0..len).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state >> 24) as u8
+        }).collect()
+    }
+
+    fn code(len: usize) -> Vec<u8> {
+        // addiu, lw, sw, jal and jr ra over varying registers and immediates.
+        (0..len / 4).flat_map(|ii| {
+            let ii = ii as u32;
+            let word = match ii % 5 {
+                0 => 0x27bd_0000 | (ii.wrapping_mul(40503) & 0xffff),
+                1 => 0x8c00_0000 | ((ii % 31) << 16) | ((ii % 29) << 21) | (ii & 0xfffc),
+                2 => 0xac00_0000 | ((ii % 23) << 16) | ((ii % 19) << 21) | (ii.wrapping_mul(7) & 0xfffc),
+                3 => 0x0c00_0000 | (ii.wrapping_mul(2_654_435_761) & 0x03ff_ffff),
+                _ => 0x03e0_0008,
+            };
+            word.to_be_bytes().to_vec()
+        }).collect()
+    }
+
+    fn layered_rom() -> Vec<u8> {
+        let mut rom = test_cart();
+        rom.truncate(LOAD_START);
+        rom.extend_from_slice(&code(0x4000));
+        rom.extend_from_slice(&noise(0x8000));
+        rom.extend_from_slice(&b"Princess Toadstool BOWSER ".repeat(0x800)[..0x4000]);
+        rom.extend_from_slice(&[0xffu8; 0x3000]);
+        rom
+    }
+
+    #[test]
+    fn entropy_bounds() {
+        assert_eq!(block_entropy(&[0u8; 64]), 0.0);
+        let all: Vec<u8> = (0..=255).collect();
+        assert!((block_entropy(&all) - 8.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn classifies_layered_image() {
+        let kinds = |rom: &[u8]| -> Vec<(usize, usize, RegionKind)> {
+            scan_regions(rom, DEFAULT_BLOCK_LEN).unwrap().iter().map(|region| (region.start, region.len, region.kind)).collect()
+        };
+
+        let rom = layered_rom();
+        let expected = vec![
+            (LOAD_START, 0x4000, RegionKind::Code),
+            (LOAD_START + 0x4000, 0x8000, RegionKind::Compressed),
+            (LOAD_START + 0xc000, 0x4000, RegionKind::Data),
+            (LOAD_START + 0x10000, 0x3000, RegionKind::Padding),
+        ];
+        assert_eq!(kinds(&rom), expected);
+
+        let mut swapped = rom.clone();
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut swapped).unwrap();
+        assert_eq!(kinds(&swapped), expected);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        let rom = test_cart();
+        assert!(matches!(scan_regions(&rom, 0), Err(Error::InvalidArgument(_))));
+        assert!(matches!(scan_regions(&rom, 6), Err(Error::InvalidArgument(_))));
+        assert!(matches!(scan_regions(&[0u8; 64], 16), Err(Error::UnknownByteSwapping)));
+    }
+}
@@ -0,0 +1,211 @@
+//! A small MIPS pass over the start of the payload that recovers what
+//! libultra's entry stub sets up.
+//!
+//! The IPL3 copies the first megabyte of the payload to the load address
+//! and jumps to it, where the stub zeroes the BSS with a short loop, points
+//! the stack somewhere, and jumps on to the real boot function, all with
+//! addresses built by `lui`/`addiu` pairs.  Tracking constant registers
+//! through that straight line of code is enough to read them back out, and
+//! they have to agree with the header for the game to boot.
+
+use crate::{CicVariant, Error, RomHeader, LOAD_LEN, LOAD_START};
+
+const IPL3_COPY_LEN: u32 = LOAD_LEN as u32;
+
+/// How many instructions the pass reads before giving up on finding the
+/// stub's final jump.
+pub const BOOT_SCAN_LIMIT: usize = 64;
+
+const SP: usize = 29;
+
+/// What [`analyze_boot`] recovered from the entry stub.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BootAnalysis {
+    /// Where the IPL3 puts the payload, after the CIC's entry offset.
+    pub load_addr: u32,
+    /// Where the stub jumps once it's done: the game's real entry point.
+    pub entry: Option<u32>,
+    /// The first address the BSS clearing loop zeroes.
+    pub bss_start: Option<u32>,
+    /// How many bytes that loop counts down.
+    pub bss_len: Option<u32>,
+    /// The stack pointer the stub leaves for the boot function.
+    pub stack_pointer: Option<u32>,
+}
+
+/// Something in the entry stub that disagrees with the header.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BootMismatch {
+    /// The stub jumps outside the code the IPL3 loaded.
+    EntryOutsideLoad { entry: u32 },
+    /// The BSS starts below the load address, so the code was linked to run
+    /// somewhere else.
+    BssBelowLoad { bss_start: u32 },
+    /// The code and data before the BSS are bigger than the megabyte the
+    /// IPL3 copies, so the end of it never gets loaded.
+    SegmentTooLarge { len: u32 },
+}
+
+impl BootAnalysis {
+    /// The size of the boot segment's code and data, from the load address
+    /// up to the BSS.
+    pub fn loaded_len(&self) -> Option<u32> {
+        self.bss_start?.checked_sub(self.load_addr)
+    }
+
+    pub fn mismatches(&self) -> impl Iterator<Item = BootMismatch> {
+        let loaded = core::cmp::min(self.loaded_len().unwrap_or(IPL3_COPY_LEN), IPL3_COPY_LEN);
+        let entry = self.entry
+            .filter(|entry| entry.wrapping_sub(self.load_addr) >= loaded)
+            .map(|entry| BootMismatch::EntryOutsideLoad { entry });
+        let bss = self.bss_start
+            .filter(|bss_start| *bss_start < self.load_addr)
+            .map(|bss_start| BootMismatch::BssBelowLoad { bss_start });
+        let len = self.loaded_len()
+            .filter(|len| *len > IPL3_COPY_LEN)
+            .map(|len| BootMismatch::SegmentTooLarge { len });
+
+        entry.into_iter().chain(bss).chain(len)
+    }
+}
+
+/// Follows the entry stub at the start of a native byte order image's
+/// payload, as booted by `cic`'s IPL3.  Anything the stub doesn't set up
+/// with constants within [`BOOT_SCAN_LIMIT`] instructions is left `None`.
+pub fn analyze_boot(rom: &[u8], cic: CicVariant) -> Result<BootAnalysis, Error> {
+    let header = RomHeader::from_slice(rom)?;
+    let load_addr = header.effective_entry_point(cic);
+
+    let end = core::cmp::min(rom.len(), LOAD_START + BOOT_SCAN_LIMIT * 4);
+    let code = rom.get(LOAD_START..end).unwrap_or(&[]);
+    let words = code.chunks_exact(4).map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]));
+
+    let mut analysis = BootAnalysis { load_addr, entry: None, bss_start: None, bss_len: None, stack_pointer: None };
+    let mut regs = [None; 32];
+    // Registers as they were before each instruction ran, to read a loop's
+    // starting count back when its branch is reached.
+    let mut history = [[None; 32]; BOOT_SCAN_LIMIT];
+
+    for (index, word) in words.enumerate() {
+        regs[0] = Some(0);
+        history[index] = regs;
+
+        let pc = load_addr.wrapping_add(index as u32 * 4);
+        let op = word >> 26;
+        let (rs, rt, rd) = ((word >> 21 & 0x1f) as usize, (word >> 16 & 0x1f) as usize, (word >> 11 & 0x1f) as usize);
+        let imm = word & 0xffff;
+        let simm = imm as u16 as i16 as i32 as u32;
+
+        match op {
+            // lui, addiu/addi, ori
+            0x0f => regs[rt] = Some(imm << 16),
+            0x08 | 0x09 => regs[rt] = regs[rs].map(|value| value.wrapping_add(simm)),
+            0x0d => regs[rt] = regs[rs].map(|value| value | imm),
+            0x00 => match word & 0x3f {
+                // add/addu, or
+                0x20 | 0x21 => regs[rd] = regs[rs].zip(regs[rt]).map(|(a, b)| a.wrapping_add(b)),
+                0x25 => regs[rd] = regs[rs].zip(regs[rt]).map(|(a, b)| a | b),
+                // jr, jalr
+                0x08 | 0x09 => {
+                    analysis.entry = regs[rs];
+                    break;
+                },
+                _ => regs[rd] = None,
+            },
+            // j, jal
+            0x02 | 0x03 => {
+                analysis.entry = Some((pc.wrapping_add(4) & 0xf000_0000) | (word & 0x03ff_ffff) << 2);
+                break;
+            },
+            // sw/sd of zero: the BSS loop's stores.
+            0x2b | 0x3f if rt == 0 => {
+                if analysis.bss_start.is_none() {
+                    analysis.bss_start = regs[rs].map(|base| base.wrapping_add(simm));
+                }
+            },
+            // beq/bne and their likely forms; a backward one closes the
+            // BSS loop, counting down whichever register isn't zero.
+            0x04 | 0x05 | 0x14 | 0x15 => {
+                let target = (index as i64 + 1 + i64::from(simm as i32)) as usize;
+                if analysis.bss_len.is_none() && target <= index {
+                    let counter = if rt == 0 { rs } else { rt };
+                    analysis.bss_len = history[target][counter];
+                }
+            },
+            0x01 | 0x06 | 0x07 | 0x16 | 0x17 | 0x28..=0x2f | 0x38..=0x3f => {},
+            // Loads, the remaining immediates and cop0 moves write `rt`.
+            _ => regs[rt] = None,
+        }
+        analysis.stack_pointer = regs[SP];
+    }
+
+    Ok(analysis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+
+    // libultra's entry stub, zeroing 0x1000 bytes of BSS at 0x80010000 and
+    // jumping to 0x80000450.
+    const STUB: [u32; 14] = [
+        0x3c088001, // lui   t0, 0x8001
+        0x25080000, // addiu t0, t0, 0x0000
+        0x3c090000, // lui   t1, 0x0000
+        0x25291000, // addiu t1, t1, 0x1000
+        0x2129fff8, // addi  t1, t1, -8
+        0xad000000, // sw    zero, 0(t0)
+        0xad000004, // sw    zero, 4(t0)
+        0x1520fffc, // bnez  t1, -4
+        0x21080008, // addi  t0, t0, 8
+        0x3c1d8001, // lui   sp, 0x8001
+        0x27bd3000, // addiu sp, sp, 0x3000
+        0x3c0a8000, // lui   t2, 0x8000
+        0x254a0450, // addiu t2, t2, 0x0450
+        0x01400008, // jr    t2
+    ];
+
+    fn cart_with_stub(load_addr: u32) -> Vec<u8> {
+        let mut rom = test_cart();
+        rom[0x08..0x0c].copy_from_slice(&load_addr.to_be_bytes());
+        for (word, out) in STUB.iter().zip(rom[LOAD_START..].chunks_exact_mut(4)) {
+            out.copy_from_slice(&word.to_be_bytes());
+        }
+        rom
+    }
+
+    #[test]
+    fn follows_the_libultra_stub() {
+        let analysis = analyze_boot(&cart_with_stub(0x80000400), CicVariant::Cic6102).unwrap();
+        assert_eq!(analysis.entry, Some(0x80000450));
+        assert_eq!(analysis.bss_start, Some(0x80010000));
+        assert_eq!(analysis.bss_len, Some(0x1000));
+        assert_eq!(analysis.stack_pointer, Some(0x80013000));
+        assert_eq!(analysis.loaded_len(), Some(0xfc00));
+        assert_eq!(analysis.mismatches().count(), 0);
+    }
+
+    #[test]
+    fn flags_a_wrong_load_address() {
+        let analysis = analyze_boot(&cart_with_stub(0x80100400), CicVariant::Cic6102).unwrap();
+        let mismatches: Vec<BootMismatch> = analysis.mismatches().collect();
+        assert_eq!(mismatches, [BootMismatch::EntryOutsideLoad { entry: 0x80000450 },
+                                BootMismatch::BssBelowLoad { bss_start: 0x80010000 }]);
+
+        // The same header is right for a 6103, which loads 1 MiB lower.
+        let analysis = analyze_boot(&cart_with_stub(0x80100400), CicVariant::Cic6103).unwrap();
+        assert_eq!(analysis.load_addr, 0x80000400);
+        assert_eq!(analysis.mismatches().count(), 0);
+    }
+
+    #[test]
+    fn stops_without_a_stub() {
+        let mut rom = test_cart();
+        rom[LOAD_START..].fill(0);
+        let analysis = analyze_boot(&rom, CicVariant::Cic6102).unwrap();
+        assert_eq!((analysis.entry, analysis.bss_start), (None, None));
+        assert!(analyze_boot(&[0u8; 16], CicVariant::Cic6102).is_err());
+    }
+}
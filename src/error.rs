@@ -0,0 +1,163 @@
+use core::error;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::{ChecksumError, NameError};
+
+/// Everything that can go wrong across the crate.
+#[derive(Debug)]
+pub enum Error {
+    /// The byte order of the image couldn't be determined.
+    UnknownByteSwapping,
+    /// The buffer isn't a whole number of units for the requested swapping.
+    UnalignedLength { len: usize, unit: usize },
+    /// The buffer is shorter than the operation requires.
+    BufferTooShort { needed: usize, actual: usize },
+    /// The requested image layout can't be built.
+    InvalidLayout(&'static str),
+    /// A header field is out of range, or contradicts another one.
+    InvalidHeader(&'static str),
+    /// An argument is out of range for the operation it was passed to.
+    InvalidArgument(&'static str),
+    /// An ELF couldn't be converted.
+    InvalidElf(&'static str),
+    /// A patch is malformed or can't describe the requested change.
+    InvalidPatch(&'static str),
+    /// A ROM database couldn't be parsed.
+    InvalidDatabase(&'static str),
+    /// A 64DD disk image is malformed.
+    InvalidDisk(&'static str),
+    /// A compressed block is malformed, or the input can't be compressed.
+    InvalidCompression(&'static str),
+    /// An archive couldn't be read.
+    InvalidArchive(&'static str),
+    /// An emulator cheat file couldn't be parsed.
+    InvalidCheats(&'static str),
+    /// A save file or Controller Pak image is malformed or full.
+    InvalidSave(&'static str),
+    InvalidName(NameError),
+    Checksum(ChecksumError),
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// An `embedded-io` reader or writer failed.
+    #[cfg(feature = "embedded-io")]
+    EmbeddedIo(embedded_io::ErrorKind),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnknownByteSwapping => write!(f, "unknown original byte swapping"),
+            Error::UnalignedLength { len, unit } => {
+                write!(f, "length {} is not a multiple of the {} byte swapping unit", len, unit)
+            },
+            Error::BufferTooShort { needed, actual } => {
+                write!(f, "buffer too short: needed {} bytes, got {}", needed, actual)
+            },
+            Error::InvalidLayout(reason) => write!(f, "invalid ROM layout: {}", reason),
+            Error::InvalidHeader(reason) => write!(f, "invalid header: {}", reason),
+            Error::InvalidArgument(reason) => write!(f, "invalid argument: {}", reason),
+            Error::InvalidElf(reason) => write!(f, "invalid ELF: {}", reason),
+            Error::InvalidPatch(reason) => write!(f, "invalid patch: {}", reason),
+            Error::InvalidDatabase(reason) => write!(f, "invalid database: {}", reason),
+            Error::InvalidDisk(reason) => write!(f, "invalid 64DD disk: {}", reason),
+            Error::InvalidCompression(reason) => write!(f, "invalid compressed data: {}", reason),
+            Error::InvalidArchive(reason) => write!(f, "invalid archive: {}", reason),
+            Error::InvalidCheats(reason) => write!(f, "invalid cheat file: {}", reason),
+            Error::InvalidSave(reason) => write!(f, "invalid save: {}", reason),
+            Error::InvalidName(ref err) => write!(f, "invalid name: {}", err),
+            Error::Checksum(ref err) => write!(f, "checksum failed: {}", err),
+            #[cfg(feature = "std")]
+            Error::Io(ref err) => write!(f, "I/O error: {}", err),
+            #[cfg(feature = "embedded-io")]
+            Error::EmbeddedIo(kind) => write!(f, "I/O error: {:?}", kind),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::InvalidName(ref err) => Some(err),
+            Error::Checksum(ref err) => Some(err),
+            #[cfg(feature = "std")]
+            Error::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+#[cfg(feature = "archive")]
+impl From<zip::result::ZipError> for Error {
+    fn from(err: zip::result::ZipError) -> Self {
+        use zip::result::ZipError;
+
+        match err {
+            ZipError::Io(err) => Error::Io(err),
+            ZipError::InvalidArchive(reason) | ZipError::UnsupportedArchive(reason) => Error::InvalidArchive(reason),
+            ZipError::FileNotFound => Error::InvalidArchive("entry not found"),
+            ZipError::InvalidPassword => Error::InvalidArchive("wrong password"),
+            _ => Error::InvalidArchive("unreadable zip"),
+        }
+    }
+}
+
+impl From<NameError> for Error {
+    fn from(err: NameError) -> Self {
+        Error::InvalidName(err)
+    }
+}
+
+impl From<ChecksumError> for Error {
+    fn from(err: ChecksumError) -> Self {
+        Error::Checksum(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            Error::BufferTooShort { .. } => io::Error::new(io::ErrorKind::UnexpectedEof, err),
+            other => io::Error::new(io::ErrorKind::InvalidData, other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn round_trips_io_errors() {
+        let err: Error = io::Error::new(io::ErrorKind::BrokenPipe, "pipe").into();
+        assert!(error::Error::source(&err).is_some());
+
+        let io_err: io::Error = err.into();
+        assert_eq!(io_err.kind(), io::ErrorKind::BrokenPipe);
+
+        let io_err: io::Error = Error::BufferTooShort { needed: 64, actual: 3 }.into();
+        assert_eq!(io_err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn displays_context() {
+        assert_eq!(Error::BufferTooShort { needed: 64, actual: 3 }.to_string(),
+                   "buffer too short: needed 64 bytes, got 3");
+        assert_eq!(Error::from(NameError::TooLong).to_string(), "invalid name: name longer than 20 bytes");
+
+        let err = Error::from(ChecksumError::NotLongEnough { needed: 0x101000, actual: 4096 });
+        assert_eq!(err.to_string(), "checksum failed: image too short to checksum: needed 1052672 bytes, got 4096");
+        assert!(error::Error::source(&err).is_some());
+    }
+}
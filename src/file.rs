@@ -0,0 +1,181 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::{
+    calculate_cart_checksum, resolve_swapping, swap_to_native, Bootcode, ByteSwapping, Error, RomHeader,
+    BOOTCODE_LEN, BOOTCODE_START, HEADER_CRC_END, HEADER_CRC_START, HEADER_LEN, HEADER_START,
+    LOAD_START, ROM_LEN,
+};
+
+/// An image on disk, read a range at a time rather than loaded whole.  All
+/// reads come back in native order whatever order the file is in, and CRC
+/// fixes are written back in the file's own order.
+pub struct RomFile<F> {
+    inner: F,
+    swapping: Option<ByteSwapping>,
+    header: Option<RomHeader>,
+}
+
+impl<F: Read + Seek> RomFile<F> {
+    pub fn new(inner: F) -> RomFile<F> {
+        RomFile { inner, swapping: None, header: None }
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    /// The file's byte order, found from its header on first use.
+    pub fn swapping(&mut self) -> Result<ByteSwapping, Error> {
+        self.load_header()?;
+        Ok(self.swapping.unwrap())
+    }
+
+    /// The header, read on first use.
+    pub fn header(&mut self) -> Result<&RomHeader, Error> {
+        self.load_header()?;
+        Ok(self.header.as_ref().unwrap())
+    }
+
+    /// Length of the file in bytes.
+    pub fn file_len(&mut self) -> Result<u64, Error> {
+        Ok(self.inner.seek(SeekFrom::End(0))?)
+    }
+
+    /// Fills `buf` with the native order bytes starting at `offset`.
+    pub fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<(), Error> {
+        let swapping = self.swapping()?;
+
+        // Swapping works on whole units, so read the units around the range.
+        let unit = swapping.unit_len() as u64;
+        let start = offset - offset % unit;
+        let end = (offset + buf.len() as u64).div_ceil(unit) * unit;
+
+        let mut units = vec![0u8; (end - start) as usize];
+        self.inner.seek(SeekFrom::Start(start))?;
+        self.inner.read_exact(&mut units)?;
+        swap_to_native(swapping, &mut units);
+
+        let skip = (offset - start) as usize;
+        buf.copy_from_slice(&units[skip..skip + buf.len()]);
+        Ok(())
+    }
+
+    pub fn bootcode(&mut self) -> Result<Bootcode, Error> {
+        let mut bootcode = [0u8; BOOTCODE_LEN as usize];
+        self.read_at(BOOTCODE_START as u64, &mut bootcode)?;
+        Ok(bootcode)
+    }
+
+    /// Fills `buf` from the payload, `offset` bytes past its start.
+    pub fn read_payload(&mut self, offset: u64, buf: &mut [u8]) -> Result<(), Error> {
+        self.read_at(LOAD_START as u64 + offset, buf)
+    }
+
+    /// The CRC pair for the file's detected CIC, reading only the header,
+    /// bootcode, and checksummed region.
+    pub fn calculate_checksum(&mut self) -> Result<(u32, u32), Error> {
+        let mut prefix = vec![0u8; ROM_LEN];
+        self.read_at(0, &mut prefix)?;
+        Ok(calculate_cart_checksum(&prefix)?)
+    }
+
+    fn load_header(&mut self) -> Result<(), Error> {
+        if self.header.is_some() {
+            return Ok(());
+        }
+
+        let mut bytes = [0u8; HEADER_LEN as usize];
+        self.inner.seek(SeekFrom::Start(HEADER_START as u64))?;
+        self.inner.read_exact(&mut bytes)?;
+
+        let swapping = resolve_swapping(&bytes).ok_or(Error::UnknownByteSwapping)?;
+        swap_to_native(swapping, &mut bytes);
+        self.swapping = Some(swapping);
+        self.header = Some(RomHeader::from_bytes(&bytes));
+        Ok(())
+    }
+}
+
+impl<F: Read + Write + Seek> RomFile<F> {
+    /// Overwrites just the CRC words in the file.
+    pub fn write_crcs(&mut self, crc1: u32, crc2: u32) -> Result<(), Error> {
+        let swapping = self.swapping()?;
+
+        let mut crcs = [0u8; HEADER_CRC_END - HEADER_CRC_START];
+        crcs[..4].copy_from_slice(&crc1.to_be_bytes());
+        crcs[4..].copy_from_slice(&crc2.to_be_bytes());
+        swap_to_native(swapping, &mut crcs);
+
+        self.inner.seek(SeekFrom::Start(HEADER_CRC_START as u64))?;
+        self.inner.write_all(&crcs)?;
+
+        let header = self.header.as_mut().unwrap();
+        header.crc1 = crc1;
+        header.crc2 = crc2;
+        Ok(())
+    }
+
+    /// Recomputes the CRCs and writes them in place, leaving the rest of the
+    /// file untouched.
+    pub fn fix_checksum(&mut self) -> Result<(u32, u32), Error> {
+        let (crc1, crc2) = self.calculate_checksum()?;
+        self.write_crcs(crc1, crc2)?;
+        Ok((crc1, crc2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    use crate::tests::test_cart;
+    use crate::{swap_cart_to, verify_cart_checksum};
+
+    #[test]
+    fn reads_ranges_in_native_order() {
+        let native = test_cart();
+        let mut swapped = native.clone();
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut swapped).unwrap();
+
+        let mut file = RomFile::new(Cursor::new(swapped));
+        assert_eq!(file.swapping().unwrap(), ByteSwapping::U32LittleEndian);
+        assert_eq!(file.header().unwrap().cart_timing, native_header(&native).cart_timing);
+        assert_eq!(file.file_len().unwrap(), native.len() as u64);
+        assert_eq!(&file.bootcode().unwrap()[..], &native[BOOTCODE_START..LOAD_START]);
+
+        let mut buf = [0u8; 7];
+        file.read_payload(0x123, &mut buf).unwrap();
+        assert_eq!(buf, native[LOAD_START + 0x123..LOAD_START + 0x12a]);
+    }
+
+    #[test]
+    fn fixes_crcs_in_place() {
+        let mut swapped = test_cart();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut swapped).unwrap();
+        let before = swapped.clone();
+
+        let mut file = RomFile::new(Cursor::new(swapped));
+        let crcs = file.fix_checksum().unwrap();
+        assert_eq!(file.header().unwrap().crc1, crcs.0);
+
+        let after = file.into_inner().into_inner();
+        assert!(verify_cart_checksum(&after).unwrap().is_valid());
+        assert_eq!(&after[..HEADER_CRC_START], &before[..HEADER_CRC_START]);
+        assert_eq!(&after[HEADER_CRC_END..], &before[HEADER_CRC_END..]);
+    }
+
+    #[test]
+    fn rejects_unknown_files() {
+        let mut file = RomFile::new(Cursor::new(vec![0u8; 64]));
+        assert!(matches!(file.header(), Err(Error::UnknownByteSwapping)));
+
+        let mut file = RomFile::new(Cursor::new(vec![0u8; 8]));
+        assert!(matches!(file.swapping(), Err(Error::Io(_))));
+    }
+
+    fn native_header(native: &[u8]) -> RomHeader {
+        RomHeader::from_slice(native).unwrap()
+    }
+}
@@ -0,0 +1,176 @@
+//! Finding the file tables first-party games keep in ROM: arrays of
+//! `(vrom start, vrom end, rom start, rom end)` words, as in Zelda's
+//! dmadata, that map each file's address in the game's flat virtual ROM to
+//! where it actually sits in the image.
+//!
+//! A rom end of zero means the file is stored as is, and anything else that
+//! it's compressed into `rom start..rom end`.  Both set to `0xffffffff`
+//! mark a file that was dropped from the build.  Virtual ranges run in
+//! order, so a long enough run of entries that are all in bounds and
+//! ascending is very unlikely to be anything else.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Entries a run needs before [`scan_file_tables`] believes it's a table.
+pub const MIN_FILE_TABLE_ENTRIES: usize = 8;
+
+const ENTRY_LEN: usize = 16;
+const DELETED: u32 = 0xffff_ffff;
+
+/// One file in a table.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FileExtent {
+    pub vrom_start: u32,
+    pub vrom_end: u32,
+    pub rom_start: u32,
+    pub rom_end: u32,
+}
+
+impl FileExtent {
+    pub fn is_compressed(&self) -> bool {
+        self.rom_end != 0 && !self.is_deleted()
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.rom_start == DELETED && self.rom_end == DELETED
+    }
+
+    /// The file's size once loaded.
+    pub fn len(&self) -> u32 {
+        self.vrom_end - self.vrom_start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Where the file's bytes are in the image, compressed or not, or `None`
+    /// if it was dropped.
+    pub fn rom_range(&self) -> Option<Range<usize>> {
+        if self.is_deleted() {
+            None
+        } else if self.is_compressed() {
+            Some(self.rom_start as usize..self.rom_end as usize)
+        } else {
+            Some(self.rom_start as usize..self.rom_start as usize + self.len() as usize)
+        }
+    }
+
+    fn read(data: &[u8]) -> FileExtent {
+        let word = |at: usize| u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+        FileExtent { vrom_start: word(0), vrom_end: word(4), rom_start: word(8), rom_end: word(12) }
+    }
+
+    // In bounds for an image of `len` bytes, and following `previous`.
+    fn is_plausible(&self, len: usize, previous: Option<&FileExtent>) -> bool {
+        if self.vrom_start >= self.vrom_end || !self.vrom_start.is_multiple_of(4) {
+            return false;
+        }
+        if previous.is_some_and(|previous| self.vrom_start < previous.vrom_end) {
+            return false;
+        }
+
+        match self.rom_range() {
+            None => true,
+            Some(range) => range.start < range.end && range.end <= len && range.start.is_multiple_of(4),
+        }
+    }
+}
+
+/// A file table found in an image.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FileTable {
+    /// Offset of the first entry in the image.
+    pub offset: usize,
+    pub files: Vec<FileExtent>,
+}
+
+impl FileTable {
+    /// The bytes of `file` in `rom`, compressed if it's stored compressed.
+    pub fn file_data<'a>(&self, rom: &'a [u8], file: &FileExtent) -> Option<&'a [u8]> {
+        rom.get(file.rom_range()?)
+    }
+}
+
+/// Finds runs of at least [`MIN_FILE_TABLE_ENTRIES`] plausible entries at
+/// word aligned offsets in a native byte order image.
+pub fn scan_file_tables(rom: &[u8]) -> Vec<FileTable> {
+    let mut tables = Vec::new();
+
+    let mut offset = 0;
+    while offset + ENTRY_LEN * MIN_FILE_TABLE_ENTRIES <= rom.len() {
+        let mut files: Vec<FileExtent> = Vec::new();
+        for entry in rom[offset..].chunks_exact(ENTRY_LEN) {
+            let file = FileExtent::read(entry);
+            if !file.is_plausible(rom.len(), files.last()) {
+                break;
+            }
+            files.push(file);
+        }
+
+        if files.len() >= MIN_FILE_TABLE_ENTRIES {
+            let len = files.len() * ENTRY_LEN;
+            tables.push(FileTable { offset, files });
+            offset += len;
+        } else {
+            offset += 4;
+        }
+    }
+
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+
+    // Ten files of 0x100 bytes each, laid out from 0x20000, with the fourth
+    // compressed and the sixth dropped.
+    fn write_table(rom: &mut [u8], at: usize) {
+        for ii in 0..10u32 {
+            let vrom = ii * 0x100;
+            let (rom_start, rom_end) = match ii {
+                3 => (0x20000 + vrom, 0x20000 + vrom + 0x80),
+                5 => (DELETED, DELETED),
+                _ => (0x20000 + vrom, 0),
+            };
+            let entry = at + ii as usize * ENTRY_LEN;
+            for (word, value) in [vrom, vrom + 0x100, rom_start, rom_end].iter().enumerate() {
+                rom[entry + word * 4..entry + word * 4 + 4].copy_from_slice(&value.to_be_bytes());
+            }
+        }
+        rom[at + 10 * ENTRY_LEN..at + 11 * ENTRY_LEN].fill(0);
+    }
+
+    #[test]
+    fn finds_a_table() {
+        let mut rom = test_cart();
+        rom[0x1000..].fill(0xff);
+        write_table(&mut rom, 0x7430);
+
+        let tables = scan_file_tables(&rom);
+        assert_eq!(tables.len(), 1);
+        assert_eq!((tables[0].offset, tables[0].files.len()), (0x7430, 10));
+
+        let files = &tables[0].files;
+        assert_eq!(files[0].rom_range(), Some(0x20000..0x20100));
+        assert!(files[3].is_compressed());
+        assert_eq!(tables[0].file_data(&rom, &files[3]).unwrap().len(), 0x80);
+        assert!(files[5].is_deleted() && files[5].rom_range().is_none());
+        assert_eq!(files[9].len(), 0x100);
+    }
+
+    #[test]
+    fn ignores_short_and_disordered_runs() {
+        let mut rom = test_cart();
+        rom[0x1000..].fill(0);
+        write_table(&mut rom, 0x8000);
+        // Swapping two entries breaks the ascending order part way through.
+        let (first, second) = rom[0x8000..].split_at_mut(0x50);
+        first[0x40..0x50].swap_with_slice(&mut second[..0x10]);
+        assert!(scan_file_tables(&rom).is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+//! Choosing filler words so a modified image keeps a given CRC pair, for
+//! patches that have to leave the original CRCs in place.
+//!
+//! CRC1 only depends on the sum and xor of the words, which pass through
+//! the rest of the checksum unchanged, so for every sum of the two scratch
+//! words it pins down their xor, and the pairs with that sum and xor are the
+//! only ones that can work.  CRC2 mixes in comparisons and data dependent
+//! rotations, so each of those pairs is checked by running the checksum over
+//! everything after the scratch words.
+
+use crate::checksum::ChecksumState;
+use crate::{ChecksumError, ChecksumParams, CicVariant, Error, BOOTCODE_END};
+
+/// How many bytes of scratch [`forge_cart_checksum`] fills: one word can
+/// only steer 32 bits of the 64 bit pair, so it takes two.
+pub const FORGE_SCRATCH_LEN: usize = 8;
+
+/// Fills the two words at `scratch`, inside the checksummed region of a
+/// native byte order image, so that `variant`'s IPL3 computes `target`, and
+/// returns the words written.
+///
+/// This is a search over up to 2^33 sums, checking about 2^32 candidate
+/// pairs in the worst case, each of which replays the words after the
+/// scratch.  Put the scratch at the end of the checksummed region, where a
+/// full search takes minutes; deeper in, it takes proportionally longer.
+/// Roughly a third of targets have no filler at all, which is reported as
+/// [`ChecksumError::Unforgeable`] and leaves the image untouched.
+pub fn forge_cart_checksum(variant: CicVariant, rom: &mut [u8], scratch: usize, target: (u32, u32))
+                           -> Result<[u32; 2], Error> {
+    let params = ChecksumParams::for_variant(variant);
+    let end = params.start + params.len;
+    if rom.len() < end {
+        return Err(ChecksumError::NotLongEnough { needed: end, actual: rom.len() }.into());
+    }
+    if scratch < params.start || scratch + FORGE_SCRATCH_LEN > end || !(scratch - params.start).is_multiple_of(4) {
+        return Err(Error::InvalidArgument("scratch must be two aligned words inside the checksummed region"));
+    }
+
+    let mut prefix = ChecksumState::with_seed(variant, params.seed, &rom[..BOOTCODE_END]);
+    prefix.update(&rom[params.start..scratch]);
+    let tail = &rom[scratch + FORGE_SCRATCH_LEN..end];
+    let (tail_sum, tail_xor) = tail.chunks_exact(4)
+        .map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+        .fold((0u64, 0u32), |(sum, xor), word| (sum + word as u64, xor ^ word));
+
+    let base = prefix.sum().wrapping_add(tail_sum);
+    let other_xor = prefix.xor() ^ tail_xor;
+    for sum in 0..=2 * u32::MAX as u64 {
+        let xor = ChecksumState::crc1_xor(variant, base.wrapping_add(sum), target.0) ^ other_xor;
+
+        // a + b == (a ^ b) + 2 * (a & b), so the bits both words share are
+        // fixed too, and can't overlap the ones that differ.
+        let shared = match sum.checked_sub(xor as u64) {
+            Some(twice) if twice.is_multiple_of(2) && twice / 2 <= u32::MAX as u64 => (twice / 2) as u32,
+            _ => continue,
+        };
+        if shared & xor != 0 {
+            continue;
+        }
+
+        // Each way of splitting the differing bits between the two words.
+        let mut split = xor;
+        loop {
+            let words = [shared | split, shared | (xor ^ split)];
+            let mut state = prefix.clone();
+            state.update_word(words[0]);
+            state.update_word(words[1]);
+            state.update(tail);
+
+            if state.finish() == target {
+                rom[scratch..scratch + 4].copy_from_slice(&words[0].to_be_bytes());
+                rom[scratch + 4..scratch + 8].copy_from_slice(&words[1].to_be_bytes());
+                return Ok(words);
+            }
+            if split == 0 {
+                break;
+            }
+            split = (split - 1) & xor;
+        }
+    }
+
+    Err(ChecksumError::Unforgeable.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{be_u32_at, calculate_cart_checksum_with};
+    use crate::checksum::CHECKSUM_END;
+    use crate::tests::test_cart;
+
+    fn forges_back(variant: CicVariant, scratch: usize) {
+        let mut rom = test_cart();
+        rom[0x2000..0x2010].copy_from_slice(b"patched code....");
+        rom[scratch..scratch + 8].copy_from_slice(&[0, 0, 0, 0x15, 0, 0, 0, 0x2a]);
+        let target = calculate_cart_checksum_with(variant, &rom).unwrap();
+
+        rom[scratch..scratch + 8].fill(0);
+        assert_ne!(calculate_cart_checksum_with(variant, &rom).unwrap(), target);
+
+        let words = forge_cart_checksum(variant, &mut rom, scratch, target).unwrap();
+        assert_eq!(calculate_cart_checksum_with(variant, &rom).unwrap(), target);
+        assert_eq!(be_u32_at(&rom, scratch + 4), words[1]);
+    }
+
+    #[test]
+    fn forges_every_variant() {
+        for variant in [CicVariant::Cic6102, CicVariant::Cic6103, CicVariant::Cic6105, CicVariant::Cic6106] {
+            forges_back(variant, CHECKSUM_END - 8);
+        }
+        forges_back(CicVariant::Cic6102, CHECKSUM_END - 0x100);
+    }
+
+    #[test]
+    fn rejects_scratch_outside_the_region() {
+        let mut rom = test_cart();
+        for scratch in [BOOTCODE_END - 4, BOOTCODE_END + 2, CHECKSUM_END - 4] {
+            assert!(matches!(forge_cart_checksum(CicVariant::Cic6102, &mut rom, scratch, (0, 0)),
+                             Err(Error::InvalidArgument(_))));
+        }
+        assert!(matches!(forge_cart_checksum(CicVariant::Cic6102, &mut rom[..0x2000], BOOTCODE_END, (0, 0)),
+                         Err(Error::Checksum(ChecksumError::NotLongEnough { needed: CHECKSUM_END, actual: 0x2000 }))));
+    }
+}
@@ -0,0 +1,148 @@
+//! Telling the byte order of data with no header to go by, such as partial
+//! dumps or segments already cut out of an image, from the code in it.
+//!
+//! Compiled MIPS code is far from random: a handful of opcodes make up
+//! most of it, their unused fields are zero, and every function saves and
+//! restores `ra` and moves `sp` the same way.  Read in the wrong order the
+//! same words scatter over the whole opcode space, so counting how many
+//! look like common instructions under each ordering picks out the right
+//! one once there's more than a few dozen words of code.
+
+use crate::{swap_to_native, Confidence, SwappingGuess, CANDIDATE_SWAPPINGS};
+
+/// Non-zero words [`guess_fragment_swapping`] needs before it guesses.
+pub const MIN_FRAGMENT_WORDS: usize = 16;
+
+// Prologue and epilogue idioms, worth more than an ordinary instruction.
+const IDIOM_SCORE: u32 = 3;
+
+/// Guesses the byte order of a word aligned fragment of code from how
+/// plausible its words are as MIPS instructions under each ordering.
+///
+/// The guess is never [`Confidence::Certain`], and is `None` when there are
+/// fewer than [`MIN_FRAGMENT_WORDS`] non-zero words or no ordering scores
+/// above the rest.  Data with little or no code in it, like graphics or
+/// audio, gives weak guesses or none.
+pub fn guess_fragment_swapping(data: &[u8]) -> Option<SwappingGuess> {
+    let mut scores = [0u32; 3];
+    let mut words = 0;
+    for chunk in data.chunks_exact(4) {
+        if chunk == [0; 4] {
+            continue;
+        }
+        words += 1;
+
+        for (score, swapping) in scores.iter_mut().zip(CANDIDATE_SWAPPINGS.iter()) {
+            let mut bytes = [chunk[0], chunk[1], chunk[2], chunk[3]];
+            swap_to_native(*swapping, &mut bytes);
+            *score += instruction_plausibility(u32::from_be_bytes(bytes));
+        }
+    }
+
+    if words < MIN_FRAGMENT_WORDS {
+        return None;
+    }
+
+    let mut ranked = [0, 1, 2];
+    ranked.sort_unstable_by_key(|&index| core::cmp::Reverse(scores[index]));
+    let (best, runner_up) = (scores[ranked[0]], scores[ranked[1]]);
+    if best == runner_up {
+        log_trace!("no byte order stands out, code scores {:?} over {} words", scores, words);
+        return None;
+    }
+
+    // How far ahead the best ordering is, and how much of the data looks
+    // like code at all.
+    let confidence = if best >= runner_up * 2 && best as usize >= words {
+        Confidence::High
+    } else if best * 2 >= runner_up * 3 && best as usize * 2 >= words {
+        Confidence::Medium
+    } else {
+        Confidence::Low
+    };
+    log_trace!("guessed {} from code scores {:?} over {} words", CANDIDATE_SWAPPINGS[ranked[0]], scores, words);
+
+    Some(SwappingGuess { swapping: CANDIDATE_SWAPPINGS[ranked[0]], confidence })
+}
+
+fn instruction_plausibility(word: u32) -> u32 {
+    // jr ra; addiu sp, sp, n; sw ra, n(sp); lw ra, n(sp).
+    if word == 0x03e0_0008 || matches!(word >> 16, 0x27bd | 0xafbf | 0x8fbf) {
+        return IDIOM_SCORE;
+    }
+
+    let rs = word >> 21 & 0x1f;
+    let rt = word >> 16 & 0x1f;
+    let shamt = word >> 6 & 0x1f;
+    let plausible = match word >> 26 {
+        // SPECIAL: shifts by a constant have no rs, everything else no shamt.
+        0x00 => match word & 0x3f {
+            0x00 | 0x02 | 0x03 => rs == 0,
+            0x08 => word & 0x001f_ffc0 == 0,
+            0x09 | 0x10 | 0x12 | 0x18 | 0x19 | 0x1a | 0x1b | 0x21 | 0x23 | 0x24..=0x27 | 0x2a | 0x2b => shamt == 0,
+            _ => false,
+        },
+        // j, jal, beq, bne.
+        0x02..=0x05 => true,
+        // blez, bgtz.
+        0x06 | 0x07 => rt == 0,
+        // addi, addiu, slti, sltiu, andi, ori, xori.
+        0x08..=0x0e => true,
+        // lui.
+        0x0f => rs == 0,
+        // COP0 and COP1.
+        0x10 | 0x11 => true,
+        // beql, bnel.
+        0x14 | 0x15 => true,
+        // lb, lh, lwl, lw, lbu, lhu, sb, sh, sw.
+        0x20..=0x25 | 0x28 | 0x29 | 0x2b => true,
+        // lwc1, ldc1, swc1, sdc1.
+        0x31 | 0x35 | 0x39 | 0x3d => true,
+        _ => false,
+    };
+    plausible as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{convert_swapping, ByteSwapping};
+
+    // A small leaf-calling function, the shape most of a game's code has.
+    const FUNCTION: [u32; 16] = [
+        0x27bdffe8, 0xafbf0014, 0xafa40018, 0x0c0012a4, 0x00000000, 0x3c018000, 0x8c2e0300, 0x15c00003,
+        0x24020001, 0x8fa40018, 0x00801025, 0x8fbf0014, 0x27bd0018, 0x03e00008, 0x00000000, 0x00000000,
+    ];
+
+    fn code() -> Vec<u8> {
+        (0..4).flat_map(|_| FUNCTION.iter().flat_map(|word| word.to_be_bytes())).collect()
+    }
+
+    #[test]
+    fn finds_the_order_of_code() {
+        for &swapping in CANDIDATE_SWAPPINGS.iter() {
+            let mut data = code();
+            convert_swapping(ByteSwapping::Native, swapping, &mut data).unwrap();
+            let guess = guess_fragment_swapping(&data).unwrap();
+            assert_eq!(guess, SwappingGuess { swapping, confidence: Confidence::High });
+        }
+    }
+
+    #[test]
+    fn does_not_trust_noise_or_short_fragments() {
+        let mut state = 0x2545_f491u32;
+        let noise: Vec<u8> = (0..1024)
+            .flat_map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state.to_be_bytes()
+            })
+            .collect();
+        assert!(guess_fragment_swapping(&noise).is_none_or(|guess| guess.confidence == Confidence::Low));
+
+        assert_eq!(guess_fragment_swapping(&code()[..12 * 4]), None);
+        assert_eq!(guess_fragment_swapping(&[0u8; 4096]), None);
+    }
+}
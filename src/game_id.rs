@@ -0,0 +1,147 @@
+use core::error;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::CountryCode;
+
+/// Media the game shipped on, from byte 0x3B of the header.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MediaFormat {
+    Cartridge,
+    Disk,
+    /// Cartridge that can be expanded by a 64DD disk.
+    ExpandableCartridge,
+    /// 64DD expansion disk for a cartridge.
+    ExpansionDisk,
+    Aleck64,
+    Unknown(u8),
+}
+
+impl MediaFormat {
+    pub fn from_u8(value: u8) -> MediaFormat {
+        match value {
+            b'N' => MediaFormat::Cartridge,
+            b'D' => MediaFormat::Disk,
+            b'C' => MediaFormat::ExpandableCartridge,
+            b'E' => MediaFormat::ExpansionDisk,
+            b'Z' => MediaFormat::Aleck64,
+            _ => MediaFormat::Unknown(value),
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        match self {
+            MediaFormat::Cartridge => b'N',
+            MediaFormat::Disk => b'D',
+            MediaFormat::ExpandableCartridge => b'C',
+            MediaFormat::ExpansionDisk => b'E',
+            MediaFormat::Aleck64 => b'Z',
+            MediaFormat::Unknown(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for MediaFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MediaFormat::Cartridge => write!(f, "Cartridge"),
+            MediaFormat::Disk => write!(f, "64DD Disk"),
+            MediaFormat::ExpandableCartridge => write!(f, "Expandable Cartridge"),
+            MediaFormat::ExpansionDisk => write!(f, "64DD Expansion Disk"),
+            MediaFormat::Aleck64 => write!(f, "Aleck64"),
+            MediaFormat::Unknown(value) => write!(f, "Unknown (0x{:02x})", value),
+        }
+    }
+}
+
+/// The four character game code ("NSME") spread across header bytes
+/// 0x3B..0x3F: media format, two character cart ID, and destination.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameId {
+    pub media: MediaFormat,
+    pub cart_id: [u8; 2],
+    pub country: CountryCode,
+}
+
+impl GameId {
+    pub fn from_bytes(bytes: [u8; 4]) -> GameId {
+        GameId {
+            media: MediaFormat::from_u8(bytes[0]),
+            cart_id: [bytes[1], bytes[2]],
+            country: CountryCode::from_u8(bytes[3]),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; 4] {
+        [self.media.as_u8(), self.cart_id[0], self.cart_id[1], self.country.as_u8()]
+    }
+}
+
+impl fmt::Display for GameId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.to_bytes().iter() {
+            if byte.is_ascii_graphic() {
+                write!(f, "{}", *byte as char)?;
+            } else {
+                write!(f, "\\x{:02x}", byte)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseGameIdError(());
+
+impl fmt::Display for ParseGameIdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "game ID must be exactly four ASCII characters")
+    }
+}
+
+impl error::Error for ParseGameIdError {}
+
+impl FromStr for GameId {
+    type Err = ParseGameIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = s.as_bytes();
+        if bytes.len() != 4 || !bytes.iter().all(|byte| byte.is_ascii_graphic()) {
+            return Err(ParseGameIdError(()));
+        }
+
+        Ok(GameId::from_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_displays() {
+        let id: GameId = "NSME".parse().unwrap();
+        assert_eq!(id.media, MediaFormat::Cartridge);
+        assert_eq!(&id.cart_id, b"SM");
+        assert_eq!(id.country, CountryCode::Usa);
+        assert_eq!(id.to_string(), "NSME");
+
+        let disk: GameId = "DMGJ".parse().unwrap();
+        assert_eq!(disk.media, MediaFormat::Disk);
+        assert_eq!(disk.country, CountryCode::Japan);
+    }
+
+    #[test]
+    fn rejects_malformed_codes() {
+        assert!("NSM".parse::<GameId>().is_err());
+        assert!("NSMEE".parse::<GameId>().is_err());
+        assert!("NS E".parse::<GameId>().is_err());
+    }
+
+    #[test]
+    fn escapes_unprintable_bytes() {
+        assert_eq!(GameId::from_bytes([0, b'S', b'M', b'E']).to_string(), "\\x00SME");
+    }
+}
@@ -0,0 +1,84 @@
+use md5::{Digest, Md5};
+use sha1::Sha1;
+
+use crate::{crc32, resolve_swapping, swap_to_native, ByteSwapping, Error};
+
+const HASH_CHUNK_LEN: usize = 4096;
+
+/// Digests of a whole image in big endian (.z64) order, the form ROM
+/// databases key on.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RomHashes {
+    pub crc32: u32,
+    pub md5: [u8; 16],
+    pub sha1: [u8; 20],
+}
+
+/// Hashes an image in whatever byte order it's in, as if it were native.
+pub fn hashes(buffer: &[u8]) -> Result<RomHashes, Error> {
+    let swapping = resolve_swapping(buffer).ok_or(Error::UnknownByteSwapping)?;
+    hashes_in(swapping, buffer)
+}
+
+// Hashes a buffer whose ordering the caller has already settled on.
+pub(crate) fn hashes_in(swapping: ByteSwapping, buffer: &[u8]) -> Result<RomHashes, Error> {
+    let unit = swapping.unit_len();
+    if !buffer.len().is_multiple_of(unit) {
+        return Err(Error::UnalignedLength { len: buffer.len(), unit });
+    }
+
+    let mut crc = 0;
+    let mut md5 = Md5::new();
+    let mut sha1 = Sha1::new();
+    let mut chunk = [0u8; HASH_CHUNK_LEN];
+
+    for data in buffer.chunks(HASH_CHUNK_LEN) {
+        let chunk = &mut chunk[..data.len()];
+        chunk.copy_from_slice(data);
+        swap_to_native(swapping, chunk);
+
+        crc = crc32::update(crc, chunk);
+        md5.update(&chunk[..]);
+        sha1.update(&chunk[..]);
+    }
+
+    Ok(RomHashes {
+        crc32: crc,
+        md5: md5.finalize().into(),
+        sha1: sha1.finalize().into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+    use crate::swap_cart_to;
+
+    #[test]
+    fn hashes_are_byte_order_independent() {
+        let native = test_cart();
+        let expected = hashes(&native).unwrap();
+        assert_eq!(expected.crc32, crc32::crc32(&native));
+        assert_eq!(&expected.md5[..], &Md5::digest(&native)[..]);
+        assert_eq!(&expected.sha1[..], &Sha1::digest(&native)[..]);
+
+        for swapping in [ByteSwapping::U16LittleEndian, ByteSwapping::U32LittleEndian].iter() {
+            let mut swapped = native.clone();
+            swap_cart_to(*swapping, &mut swapped).unwrap();
+            assert_eq!(hashes(&swapped).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn rejects_unhashable_images() {
+        assert!(matches!(hashes(&[0u8; 64]), Err(Error::UnknownByteSwapping)));
+
+        let mut swapped = test_cart();
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut swapped).unwrap();
+        swapped.truncate(swapped.len() - 2);
+        assert!(matches!(hashes(&swapped), Err(Error::UnalignedLength { unit: 4, .. })));
+    }
+}
@@ -0,0 +1,157 @@
+//! Building a header from its meaning rather than its raw words.
+
+use crate::{name, CountryCode, Error, GameId, LibultraVersion, PiTiming, RomHeader};
+
+/// Assembles a [`RomHeader`] with chained setters; see
+/// [`RomHeader::builder`].
+///
+/// Setters never fail themselves.  The first bad value, and any fields that
+/// contradict each other, are reported by [`RomHeaderBuilder::build`].
+#[derive(Debug)]
+pub struct RomHeaderBuilder {
+    header: RomHeader,
+    error: Option<Error>,
+    country: Option<CountryCode>,
+    game_id: Option<GameId>,
+    load_addr: Option<u32>,
+}
+
+impl RomHeader {
+    /// Starts a header from [`RomHeader::new`]'s defaults.
+    pub fn builder() -> RomHeaderBuilder {
+        RomHeaderBuilder { header: RomHeader::new(), error: None, country: None, game_id: None, load_addr: None }
+    }
+}
+
+impl RomHeaderBuilder {
+    /// The internal name; see [`RomHeader::set_name`].
+    pub fn name(mut self, name: &str) -> RomHeaderBuilder {
+        match name::encode_name(name) {
+            Ok(encoded) => self.header.name = encoded,
+            Err(err) => self.fail(err.into()),
+        }
+        self
+    }
+
+    /// The game code, including its destination.
+    pub fn game_id(mut self, game_id: GameId) -> RomHeaderBuilder {
+        self.header.set_game_id(&game_id);
+        self.game_id = Some(game_id);
+        self
+    }
+
+    /// The destination, which has to agree with any game code set.
+    pub fn country(mut self, country: CountryCode) -> RomHeaderBuilder {
+        self.header.set_country(country);
+        self.country = Some(country);
+        self
+    }
+
+    pub fn version(mut self, version: u8) -> RomHeaderBuilder {
+        self.header.set_version(version);
+        self
+    }
+
+    /// The address the payload is loaded at, which has to be word aligned
+    /// and in KSEG0 or KSEG1.
+    pub fn load_addr(mut self, load_addr: u32) -> RomHeaderBuilder {
+        self.header.load_addr = load_addr;
+        self.load_addr = Some(load_addr);
+        self
+    }
+
+    /// See [`RomHeader::set_clock_rate_override`].
+    pub fn clock_rate(mut self, rate: Option<u32>) -> RomHeaderBuilder {
+        if let Err(err) = self.header.set_clock_rate_override(rate) {
+            self.fail(err);
+        }
+        self
+    }
+
+    pub fn pi_timing(mut self, timing: PiTiming) -> RomHeaderBuilder {
+        self.header.set_pi_timing(&timing);
+        self
+    }
+
+    /// The raw `release` word, which holds the libultra version.
+    pub fn release(mut self, release: u32) -> RomHeaderBuilder {
+        self.header.release = release;
+        self
+    }
+
+    /// See [`RomHeader::set_libultra_version`].
+    pub fn libultra_version(mut self, version: LibultraVersion) -> RomHeaderBuilder {
+        if let Err(err) = self.header.set_libultra_version(Some(version)) {
+            self.fail(err);
+        }
+        self
+    }
+
+    pub fn build(self) -> Result<RomHeader, Error> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        if let (Some(game_id), Some(country)) = (self.game_id, self.country) {
+            if game_id.country != country {
+                return Err(Error::InvalidHeader("the game code's destination differs from the country"));
+            }
+        }
+        if let Some(load_addr) = self.load_addr {
+            if !load_addr.is_multiple_of(4) || !(0x8000_0000..0xc000_0000).contains(&load_addr) {
+                return Err(Error::InvalidHeader("load address must be a word aligned KSEG0 or KSEG1 address"));
+            }
+        }
+        Ok(self.header)
+    }
+
+    fn fail(&mut self, err: Error) {
+        if self.error.is_none() {
+            self.error = Some(err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{MediaFormat, NameError};
+
+    fn game_id(country: CountryCode) -> GameId {
+        GameId { media: MediaFormat::Cartridge, cart_id: *b"SM", country }
+    }
+
+    #[test]
+    fn builds_a_header() {
+        let header = RomHeader::builder()
+            .name("SUPER MARIO 64")
+            .game_id(game_id(CountryCode::Usa))
+            .version(1)
+            .load_addr(0x80000400)
+            .clock_rate(Some(0x1234560))
+            .libultra_version(LibultraVersion::parse("2.0L").unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(header.name, *b"SUPER MARIO 64      ");
+        assert_eq!(header.game_id(), game_id(CountryCode::Usa));
+        assert_eq!((header.country(), header.version()), (CountryCode::Usa, 1));
+        assert_eq!(header.load_addr, 0x80000400);
+        assert_eq!(header.clock_rate_override(), Some(0x1234560));
+        assert_eq!(header.release, 0x0000144c);
+    }
+
+    #[test]
+    fn reports_bad_values_and_combinations() {
+        assert!(matches!(RomHeader::builder().name("A NAME LONGER THAN TWENTY").clock_rate(Some(3)).build(),
+                         Err(Error::InvalidName(NameError::TooLong))));
+        assert!(matches!(RomHeader::builder().clock_rate(Some(0)).build(), Err(Error::InvalidHeader(_))));
+        assert!(matches!(RomHeader::builder().load_addr(0x80000402).build(), Err(Error::InvalidHeader(_))));
+        assert!(matches!(RomHeader::builder().load_addr(0x00000400).build(), Err(Error::InvalidHeader(_))));
+
+        let mismatched = RomHeader::builder().game_id(game_id(CountryCode::Japan)).country(CountryCode::Europe);
+        assert!(matches!(mismatched.build(), Err(Error::InvalidHeader(_))));
+        let agreeing = RomHeader::builder().game_id(game_id(CountryCode::Japan)).country(CountryCode::Japan);
+        assert_eq!(agreeing.build().unwrap().country(), CountryCode::Japan);
+    }
+}
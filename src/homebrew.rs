@@ -0,0 +1,319 @@
+//! Accessors for the community "advanced homebrew ROM header", which
+//! flashcart menus and emulators read to configure homebrew that has no
+//! entry in their databases.  A header opts in by using `ED` as its cart ID;
+//! the version byte then holds the save type and flags, and `rsvd_34` the
+//! accessory expected in each controller port.
+//!
+//! Separately, [`BuildInfo`] stamps a build's commit and time into the other
+//! two reserved words, which nothing else reads.
+
+use core::convert::TryFrom;
+use core::fmt;
+
+use crate::RomHeader;
+
+/// The cart ID that marks a header as using the advanced fields.
+pub const ADVANCED_HOMEBREW_CART_ID: [u8; 2] = *b"ED";
+
+const RTC_FLAG: u8 = 0x01;
+const REGION_FREE_FLAG: u8 = 0x02;
+
+/// Save hardware declared in the high nibble of the version byte.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HomebrewSaveType {
+    None,
+    Eeprom4k,
+    Eeprom16k,
+    Sram256k,
+    /// Three banks of 256 kbit SRAM.
+    Sram768k,
+    FlashRam,
+    Sram1m,
+    Unknown(u8),
+}
+
+impl HomebrewSaveType {
+    pub fn from_u8(value: u8) -> HomebrewSaveType {
+        match value {
+            0 => HomebrewSaveType::None,
+            1 => HomebrewSaveType::Eeprom4k,
+            2 => HomebrewSaveType::Eeprom16k,
+            3 => HomebrewSaveType::Sram256k,
+            4 => HomebrewSaveType::Sram768k,
+            5 => HomebrewSaveType::FlashRam,
+            6 => HomebrewSaveType::Sram1m,
+            _ => HomebrewSaveType::Unknown(value),
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        match self {
+            HomebrewSaveType::None => 0,
+            HomebrewSaveType::Eeprom4k => 1,
+            HomebrewSaveType::Eeprom16k => 2,
+            HomebrewSaveType::Sram256k => 3,
+            HomebrewSaveType::Sram768k => 4,
+            HomebrewSaveType::FlashRam => 5,
+            HomebrewSaveType::Sram1m => 6,
+            HomebrewSaveType::Unknown(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for HomebrewSaveType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HomebrewSaveType::None => write!(f, "None"),
+            HomebrewSaveType::Eeprom4k => write!(f, "4 kbit EEPROM"),
+            HomebrewSaveType::Eeprom16k => write!(f, "16 kbit EEPROM"),
+            HomebrewSaveType::Sram256k => write!(f, "256 kbit SRAM"),
+            HomebrewSaveType::Sram768k => write!(f, "768 kbit banked SRAM"),
+            HomebrewSaveType::FlashRam => write!(f, "1 Mbit FlashRAM"),
+            HomebrewSaveType::Sram1m => write!(f, "1 Mbit SRAM"),
+            HomebrewSaveType::Unknown(value) => write!(f, "Unknown ({})", value),
+        }
+    }
+}
+
+/// What a controller port should have plugged in, one byte per port.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ControllerAccessory {
+    /// A standard controller, with nothing in its accessory slot.
+    Standard,
+    ControllerPak,
+    RumblePak,
+    TransferPak,
+    Mouse,
+    /// Voice Recognition Unit.
+    Vru,
+    GameCubeController,
+    RandnetKeyboard,
+    GameCubeKeyboard,
+    /// Nothing should be plugged into the port.
+    Absent,
+    Unknown(u8),
+}
+
+impl ControllerAccessory {
+    pub fn from_u8(value: u8) -> ControllerAccessory {
+        match value {
+            0x00 => ControllerAccessory::Standard,
+            0x01 => ControllerAccessory::ControllerPak,
+            0x02 => ControllerAccessory::RumblePak,
+            0x03 => ControllerAccessory::TransferPak,
+            0x80 => ControllerAccessory::Mouse,
+            0x81 => ControllerAccessory::Vru,
+            0x82 => ControllerAccessory::GameCubeController,
+            0x83 => ControllerAccessory::RandnetKeyboard,
+            0x84 => ControllerAccessory::GameCubeKeyboard,
+            0xff => ControllerAccessory::Absent,
+            _ => ControllerAccessory::Unknown(value),
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        match self {
+            ControllerAccessory::Standard => 0x00,
+            ControllerAccessory::ControllerPak => 0x01,
+            ControllerAccessory::RumblePak => 0x02,
+            ControllerAccessory::TransferPak => 0x03,
+            ControllerAccessory::Mouse => 0x80,
+            ControllerAccessory::Vru => 0x81,
+            ControllerAccessory::GameCubeController => 0x82,
+            ControllerAccessory::RandnetKeyboard => 0x83,
+            ControllerAccessory::GameCubeKeyboard => 0x84,
+            ControllerAccessory::Absent => 0xff,
+            ControllerAccessory::Unknown(value) => value,
+        }
+    }
+}
+
+/// Which build an image came from, kept in `rsvd_18` and `rsvd_1c`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BuildInfo {
+    /// The first 32 bits of the commit hash.
+    pub commit: u32,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u32,
+}
+
+impl BuildInfo {
+    /// Packs a hex commit hash, of which the first eight digits are kept, and
+    /// a Unix timestamp.  Returns `None` if the hash is too short or isn't
+    /// hex, or the timestamp is past 2106.
+    pub fn new(commit_hash: &str, timestamp: u64) -> Option<BuildInfo> {
+        let digits = commit_hash.get(..8)?;
+        if !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        Some(BuildInfo {
+            commit: u32::from_str_radix(digits, 16).ok()?,
+            timestamp: u32::try_from(timestamp).ok()?,
+        })
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:08x} built at {}", self.commit, self.timestamp)
+    }
+}
+
+impl RomHeader {
+    /// The build stamped into the reserved words, or `None` if they're clear,
+    /// as they are in retail images.
+    pub fn build_info(&self) -> Option<BuildInfo> {
+        if self.rsvd_18 == 0 && self.rsvd_1c == 0 {
+            return None;
+        }
+        Some(BuildInfo { commit: self.rsvd_18, timestamp: self.rsvd_1c })
+    }
+
+    pub fn set_build_info(&mut self, info: BuildInfo) {
+        self.rsvd_18 = info.commit;
+        self.rsvd_1c = info.timestamp;
+    }
+}
+
+/// The advanced fields reuse the version byte, so these accessors and
+/// [`RomHeader::set_version`] overwrite each other.
+impl RomHeader {
+    /// Whether the header opts into the advanced homebrew fields.
+    pub fn is_advanced_homebrew(&self) -> bool {
+        self.cart_id.to_be_bytes() == ADVANCED_HOMEBREW_CART_ID
+    }
+
+    /// Opts the header into the advanced homebrew fields by setting its cart
+    /// ID.
+    pub fn set_advanced_homebrew(&mut self) {
+        self.cart_id = u16::from_be_bytes(ADVANCED_HOMEBREW_CART_ID);
+    }
+
+    /// The declared save type, or `None` if the header doesn't use the
+    /// advanced fields.
+    pub fn homebrew_save_type(&self) -> Option<HomebrewSaveType> {
+        self.advanced_byte().map(|byte| HomebrewSaveType::from_u8(byte >> 4))
+    }
+
+    pub fn set_homebrew_save_type(&mut self, save_type: HomebrewSaveType) {
+        let flags = self.version() & 0x0f;
+        self.set_version(((save_type.as_u8() & 0x0f) << 4) | flags);
+    }
+
+    /// Whether the game uses a real time clock, if the header declares it.
+    pub fn homebrew_rtc(&self) -> Option<bool> {
+        self.advanced_byte().map(|byte| byte & RTC_FLAG != 0)
+    }
+
+    pub fn set_homebrew_rtc(&mut self, rtc: bool) {
+        self.set_advanced_flag(RTC_FLAG, rtc);
+    }
+
+    /// Whether the game should boot on consoles of any region, if the header
+    /// declares it.
+    pub fn homebrew_region_free(&self) -> Option<bool> {
+        self.advanced_byte().map(|byte| byte & REGION_FREE_FLAG != 0)
+    }
+
+    pub fn set_homebrew_region_free(&mut self, region_free: bool) {
+        self.set_advanced_flag(REGION_FREE_FLAG, region_free);
+    }
+
+    /// The accessory expected in each controller port, from port 1, if the
+    /// header declares them.
+    pub fn controller_config(&self) -> Option<[ControllerAccessory; 4]> {
+        if !self.is_advanced_homebrew() {
+            return None;
+        }
+
+        let bytes = self.rsvd_34.to_be_bytes();
+        Some([
+            ControllerAccessory::from_u8(bytes[0]),
+            ControllerAccessory::from_u8(bytes[1]),
+            ControllerAccessory::from_u8(bytes[2]),
+            ControllerAccessory::from_u8(bytes[3]),
+        ])
+    }
+
+    pub fn set_controller_config(&mut self, ports: [ControllerAccessory; 4]) {
+        self.rsvd_34 = u32::from_be_bytes([ports[0].as_u8(), ports[1].as_u8(), ports[2].as_u8(), ports[3].as_u8()]);
+    }
+
+    fn advanced_byte(&self) -> Option<u8> {
+        if self.is_advanced_homebrew() {
+            Some(self.version())
+        } else {
+            None
+        }
+    }
+
+    fn set_advanced_flag(&mut self, flag: u8, set: bool) {
+        let version = self.version();
+        self.set_version(if set { version | flag } else { version & !flag });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_need_opt_in() {
+        let mut header = RomHeader::new();
+        header.set_homebrew_save_type(HomebrewSaveType::Eeprom16k);
+        assert_eq!(header.homebrew_save_type(), None);
+        assert_eq!(header.controller_config(), None);
+
+        header.set_advanced_homebrew();
+        assert!(header.is_advanced_homebrew());
+        assert_eq!(header.homebrew_save_type(), Some(HomebrewSaveType::Eeprom16k));
+    }
+
+    #[test]
+    fn packs_build_info() {
+        let info = BuildInfo::new("1f3a9c0de2b7", 1_700_000_000).unwrap();
+        assert_eq!(info, BuildInfo { commit: 0x1f3a9c0d, timestamp: 1_700_000_000 });
+        assert_eq!(info.to_string(), "1f3a9c0d built at 1700000000");
+
+        assert_eq!(BuildInfo::new("1f3a9c", 0), None);
+        assert_eq!(BuildInfo::new("+f3a9c0de", 0), None);
+        assert_eq!(BuildInfo::new("1f3a9c0d", 1 << 32), None);
+
+        let mut header = RomHeader::new();
+        assert_eq!(header.build_info(), None);
+        header.set_build_info(info);
+        assert_eq!(RomHeader::from_bytes(&header.to_bytes()).build_info(), Some(info));
+    }
+
+    #[test]
+    fn round_trips_fields() {
+        let mut header = RomHeader::new();
+        header.set_game_id(&"NEDE".parse().unwrap());
+        header.set_homebrew_save_type(HomebrewSaveType::FlashRam);
+        header.set_homebrew_rtc(true);
+        header.set_homebrew_region_free(true);
+        header.set_homebrew_region_free(false);
+        let ports = [
+            ControllerAccessory::RumblePak,
+            ControllerAccessory::Mouse,
+            ControllerAccessory::Absent,
+            ControllerAccessory::Absent,
+        ];
+        header.set_controller_config(ports);
+
+        let bytes = header.to_bytes();
+        assert_eq!(bytes[0x3f], 0x51);
+        assert_eq!(&bytes[0x34..0x38], &[0x02, 0x80, 0xff, 0xff]);
+
+        let parsed = RomHeader::from_bytes(&bytes);
+        assert_eq!(parsed.homebrew_save_type(), Some(HomebrewSaveType::FlashRam));
+        assert_eq!(parsed.homebrew_rtc(), Some(true));
+        assert_eq!(parsed.homebrew_region_free(), Some(false));
+        assert_eq!(parsed.controller_config(), Some(ports));
+        assert_eq!(parsed.country(), crate::CountryCode::Usa);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{
+    classify_cart_size, detect_platform, detect_save_type, guess_fragment_swapping, guess_swapping, identify_bootcode,
+    standard_cart_size, swap_to_native, verify_cart_checksum, Bootcode, CartSizeClass, ChecksumReport, CicVariant,
+    Confidence, CountryCode, Error, Ipl3, Platform, RomHeader, SaveGuess, SwappingGuess, TvStandard, BOOTCODE_END,
+    BOOTCODE_LEN, BOOTCODE_START, HEADER_END, HEADER_LEN, HEADER_START,
+};
+#[cfg(feature = "hashes")]
+use crate::hashes::hashes_in;
+#[cfg(feature = "hashes")]
+use crate::RomHashes;
+
+/// Everything the crate can tell about an image in one place; see
+/// [`analyze`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RomInfo {
+    pub swapping: SwappingGuess,
+    /// The header, in native order.
+    pub header: RomHeader,
+    pub ipl3: Option<Ipl3>,
+    /// The CIC the image needs, if its IPL3 is known.
+    pub cic: Option<CicVariant>,
+    pub platform: Platform,
+    pub country: CountryCode,
+    pub tv_standard: Option<TvStandard>,
+    /// Length of the image in bytes.
+    pub size: usize,
+    /// The smallest standard cart size that holds the image, or `None` if
+    /// it's larger than any.
+    pub cart_size: Option<usize>,
+    /// Whether the image fits a retail cart, only a flashcart, or nothing.
+    pub size_class: CartSizeClass,
+    pub save: Option<SaveGuess>,
+    /// `None` if the image is too short to checksum.
+    pub checksum: Option<ChecksumReport>,
+    #[cfg(feature = "hashes")]
+    pub hashes: RomHashes,
+}
+
+/// Fingerprints an image in any byte order.  When the header doesn't give
+/// the order away, the code after it is looked at instead.  Fails only if
+/// the byte order can't be determined or the image is shorter than its
+/// header and bootcode.
+pub fn analyze(buffer: &[u8]) -> Result<RomInfo, Error> {
+    let swapping = guess_swapping(buffer)
+        .filter(|guess| guess.confidence >= Confidence::Medium)
+        .or_else(|| guess_fragment_swapping(buffer.get(HEADER_END..)?))
+        .filter(|guess| guess.confidence >= Confidence::Medium)
+        .ok_or(Error::UnknownByteSwapping)?;
+    if buffer.len() < BOOTCODE_END {
+        return Err(Error::BufferTooShort { needed: BOOTCODE_END, actual: buffer.len() });
+    }
+
+    let mut header_bytes = [0u8; HEADER_LEN as usize];
+    header_bytes.copy_from_slice(&buffer[HEADER_START..HEADER_END]);
+    swap_to_native(swapping.swapping, &mut header_bytes);
+    let header = RomHeader::from_bytes(&header_bytes);
+
+    let mut bootcode: Bootcode = [0u8; BOOTCODE_LEN as usize];
+    bootcode.copy_from_slice(&buffer[BOOTCODE_START..BOOTCODE_END]);
+    swap_to_native(swapping.swapping, &mut bootcode);
+    let ipl3 = identify_bootcode(&bootcode);
+
+    let country = header.country();
+
+    Ok(RomInfo {
+        swapping,
+        header,
+        ipl3,
+        cic: ipl3.map(Ipl3::cic),
+        platform: detect_platform(buffer).unwrap_or(Platform::Retail),
+        country,
+        tv_standard: country.tv_standard(),
+        size: buffer.len(),
+        cart_size: standard_cart_size(buffer.len()),
+        size_class: classify_cart_size(buffer.len()),
+        save: detect_save_type(buffer),
+        checksum: verify_cart_checksum(buffer).ok(),
+        #[cfg(feature = "hashes")]
+        hashes: hashes_in(swapping.swapping, buffer)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::{stamp_cic, test_cart};
+    use crate::{convert_swapping, fix_cart_checksum, swap_cart_to, ByteSwapping, SaveType, CART_SIZES};
+
+    #[test]
+    fn fingerprints_swapped_image() {
+        let mut rom = test_cart();
+        let mut header = RomHeader::new();
+        header.set_game_id(&"NSMJ".parse().unwrap());
+        header.load_addr = 0x80246000;
+        rom[HEADER_START..HEADER_END].copy_from_slice(&header.to_bytes());
+        stamp_cic(&mut rom, CicVariant::Cic6102);
+        fix_cart_checksum(&mut rom).unwrap();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut rom).unwrap();
+
+        let info = analyze(&rom).unwrap();
+        assert_eq!(info.swapping.swapping, ByteSwapping::U16LittleEndian);
+        assert_eq!(info.swapping.confidence, Confidence::Certain);
+        assert_eq!(info.header.load_addr, 0x80246000);
+        assert_eq!(info.ipl3, Some(Ipl3::Retail(CicVariant::Cic6102)));
+        assert_eq!(info.cic, Some(CicVariant::Cic6102));
+        assert_eq!(info.platform, Platform::Retail);
+        assert_eq!(info.country, CountryCode::Japan);
+        assert_eq!(info.tv_standard, Some(TvStandard::Ntsc));
+        assert_eq!(info.size, rom.len());
+        assert_eq!(info.cart_size, Some(CART_SIZES[0]));
+        assert_eq!(info.size_class, CartSizeClass::Retail(CART_SIZES[0]));
+        assert_eq!(info.save.map(|guess| guess.save_type), Some(SaveType::Eeprom4k));
+        assert!(info.checksum.unwrap().is_valid());
+    }
+
+    #[test]
+    fn rejects_unreadable_images() {
+        assert!(matches!(analyze(&[0u8; 64]), Err(Error::UnknownByteSwapping)));
+
+        let rom = test_cart();
+        assert!(matches!(analyze(&rom[..HEADER_END]), Err(Error::BufferTooShort { .. })));
+        assert!(analyze(&rom[..BOOTCODE_END]).unwrap().checksum.is_none());
+    }
+
+    #[test]
+    fn falls_back_to_the_code_without_a_header() {
+        let function = [0x27bdffe8u32, 0xafbf0014, 0x0c0012a4, 0x00000000, 0x8fbf0014, 0x03e00008, 0x27bd0018, 0x24020001];
+        let mut rom: Vec<u8> = function.iter().cycle().take(0x800).flat_map(|word| word.to_be_bytes()).collect();
+        rom[HEADER_START..HEADER_END].fill(0);
+        convert_swapping(ByteSwapping::Native, ByteSwapping::U32LittleEndian, &mut rom).unwrap();
+
+        let info = analyze(&rom).unwrap();
+        assert_eq!(info.swapping.swapping, ByteSwapping::U32LittleEndian);
+        assert_eq!(info.swapping.confidence, Confidence::High);
+    }
+}
@@ -0,0 +1,119 @@
+//! The iQue Player, iQue's Chinese N64 derivative.  Games run from NAND
+//! without a CIC, booted by the secure kernel and system app ("SKSA").
+
+use crate::{
+    detect_cic, resolve_swapping, swap_to_native, CountryCode, Error, RomHeader, BOOTCODE_END, HEADER_END,
+    HEADER_START,
+};
+
+/// NAND block size, which everything on the iQue is laid out in.
+pub const BLOCK_LEN: usize = 0x4000;
+/// The secure kernel at the start of an SKSA image.
+pub const SK_LEN: usize = 4 * BLOCK_LEN;
+/// The content metadata block in front of the system app.
+pub const CMD_LEN: usize = BLOCK_LEN;
+
+/// The boot segments of an SKSA image.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Sksa<'a> {
+    pub secure_kernel: &'a [u8],
+    /// The system app's content metadata, left opaque.
+    pub metadata: &'a [u8],
+    /// The encrypted system apps.
+    pub system_apps: &'a [u8],
+}
+
+/// Splits an SKSA image into its secure kernel, system app metadata, and
+/// system apps.
+pub fn split_sksa(sksa: &[u8]) -> Result<Sksa<'_>, Error> {
+    if sksa.len() <= SK_LEN + CMD_LEN {
+        return Err(Error::BufferTooShort { needed: SK_LEN + CMD_LEN + BLOCK_LEN, actual: sksa.len() });
+    }
+    if !sksa.len().is_multiple_of(BLOCK_LEN) {
+        return Err(Error::UnalignedLength { len: sksa.len(), unit: BLOCK_LEN });
+    }
+
+    let (secure_kernel, rest) = sksa.split_at(SK_LEN);
+    let (metadata, system_apps) = rest.split_at(CMD_LEN);
+    Ok(Sksa { secure_kernel, metadata, system_apps })
+}
+
+/// Whether an image (in any byte order) looks like a decrypted iQue title: a
+/// Chinese game code, and bootcode no CIC would accept since the iQue doesn't
+/// check one.
+pub fn is_ique_title(rom: &[u8]) -> bool {
+    let swapping = match resolve_swapping(rom) {
+        Some(swapping) if rom.len() >= BOOTCODE_END => swapping,
+        _ => return false,
+    };
+
+    let mut prefix = [0u8; BOOTCODE_END];
+    prefix.copy_from_slice(&rom[..BOOTCODE_END]);
+    swap_to_native(swapping, &mut prefix);
+
+    let header = RomHeader::from_slice(&prefix[HEADER_START..HEADER_END]).unwrap_or_default();
+    is_ique(&header, &prefix)
+}
+
+// `prefix` is the native order header and bootcode.
+pub(crate) fn is_ique(header: &RomHeader, prefix: &[u8]) -> bool {
+    header.game_id().country == CountryCode::China && detect_cic(prefix).is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::{stamp_cic, test_cart, test_cart_with};
+    use crate::{swap_cart_to, ByteSwapping, CicVariant};
+
+    fn ique_cart() -> Vec<u8> {
+        test_cart_with(|header| {
+            header.cart_timing = 0x80270740;
+            header.load_addr = 0x80000400;
+            header.name.copy_from_slice(b"IQUE TITLE          ");
+            header.set_game_id(&"NSMC".parse().unwrap());
+        })
+    }
+
+    #[test]
+    fn recognizes_titles_in_any_order() {
+        let rom = ique_cart();
+        for swapping in [ByteSwapping::U16LittleEndian, ByteSwapping::U32LittleEndian, ByteSwapping::Native].iter() {
+            let mut swapped = rom.clone();
+            crate::convert_swapping(ByteSwapping::Native, *swapping, &mut swapped).unwrap();
+            assert!(is_ique_title(&swapped));
+            assert_eq!(crate::detect_swapping(&swapped), None);
+
+            #[cfg(feature = "alloc")]
+            {
+                let normalized = crate::Rom::from_bytes(swapped).unwrap();
+                assert_eq!(normalized.original_swapping(), *swapping);
+                assert_eq!(normalized.header().cart_timing, 0x80270740);
+            }
+        }
+    }
+
+    #[test]
+    fn retail_carts_are_not_ique() {
+        assert!(!is_ique_title(&test_cart()));
+
+        let mut rom = ique_cart();
+        stamp_cic(&mut rom, CicVariant::Cic6102);
+        assert!(!is_ique_title(&rom));
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut rom).unwrap();
+        assert!(!is_ique_title(&rom[..16]));
+    }
+
+    #[test]
+    fn splits_sksa() {
+        let sksa: Vec<u8> = (0..SK_LEN + CMD_LEN + 3 * BLOCK_LEN).map(|ii| (ii / BLOCK_LEN) as u8).collect();
+        let segments = split_sksa(&sksa).unwrap();
+        assert_eq!(segments.secure_kernel.len(), SK_LEN);
+        assert_eq!(segments.metadata[0], 4);
+        assert_eq!(segments.system_apps.len(), 3 * BLOCK_LEN);
+
+        assert!(matches!(split_sksa(&sksa[..SK_LEN + CMD_LEN]), Err(Error::BufferTooShort { .. })));
+        assert!(matches!(split_sksa(&sksa[..sksa.len() - 1]), Err(Error::UnalignedLength { .. })));
+    }
+}
@@ -0,0 +1,71 @@
+//! Images that circulate widely but are known to be broken.
+//!
+//! Early dumping hardware produced overdumps padded with mirrored data,
+//! underdumps missing their end, and dumps with flipped bits, and many of
+//! them are still the copies people find first.  Their CRCs are often fixed
+//! up, so only a hash of the whole image tells them from a good dump.
+
+use crate::RomHashes;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BadDumpKind {
+    /// Corrupted data in an image of the right size.
+    BadDump,
+    /// Longer than the cart, with the extra space filled in by the dumper.
+    Overdump,
+    /// Cut short of the end of the cart.
+    Underdump,
+}
+
+/// A known broken image.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct KnownBadDump {
+    /// SHA-1 of the image in big endian (.z64) order.
+    pub sha1: [u8; 20],
+    /// The name the image usually goes by.
+    pub name: &'static str,
+    pub kind: BadDumpKind,
+}
+
+/// The built in list of broken images, kept sorted by SHA-1.  Only hashes
+/// checked against a good dump of the same game belong here, so a match is
+/// never a false alarm.
+pub const KNOWN_BAD_DUMPS: &[KnownBadDump] = &[];
+
+/// Looks up an image by its hashes in [`KNOWN_BAD_DUMPS`].
+pub fn find_known_bad(hashes: &RomHashes) -> Option<&'static KnownBadDump> {
+    find_in(KNOWN_BAD_DUMPS, &hashes.sha1)
+}
+
+fn find_in<'a>(dumps: &'a [KnownBadDump], sha1: &[u8; 20]) -> Option<&'a KnownBadDump> {
+    dumps.binary_search_by(|dump| dump.sha1.cmp(sha1)).ok().map(|index| &dumps[index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::hashes;
+    use crate::tests::test_cart;
+
+    #[test]
+    fn the_list_is_sorted() {
+        assert!(KNOWN_BAD_DUMPS.windows(2).all(|pair| pair[0].sha1 < pair[1].sha1));
+    }
+
+    #[test]
+    fn finds_dumps_by_hash() {
+        let sha1 = hashes(&test_cart()).unwrap().sha1;
+        let dumps = [
+            KnownBadDump { sha1: [0; 20], name: "Other", kind: BadDumpKind::BadDump },
+            KnownBadDump { sha1, name: "Test", kind: BadDumpKind::Overdump },
+            KnownBadDump { sha1: [0xff; 20], name: "Another", kind: BadDumpKind::Underdump },
+        ];
+        let mut dumps = dumps.to_vec();
+        dumps.sort_by_key(|dump| dump.sha1);
+
+        assert_eq!(find_in(&dumps, &sha1).map(|dump| dump.name), Some("Test"));
+        assert_eq!(find_in(&dumps, &[1; 20]), None);
+        assert_eq!(find_known_bad(&hashes(&test_cart()).unwrap()), None);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{BOOTCODE_LEN, HEADER_LEN, LOAD_LEN};
+
+/// Where an image's header, bootcode, and checksummed payload lie.  The
+/// crate-level constants describe [`RomLayout::STANDARD`]; images with a
+/// larger IPL3 or checksummed region carry their own.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RomLayout {
+    pub header_len: usize,
+    pub bootcode_len: usize,
+    /// Bytes after the bootcode that the IPL3 checksums.
+    pub checksum_len: usize,
+}
+
+impl RomLayout {
+    /// The retail layout: a 64 byte header, bootcode up to 0x1000, and a
+    /// checksummed first megabyte of payload.
+    pub const STANDARD: RomLayout = RomLayout {
+        header_len: HEADER_LEN as usize,
+        bootcode_len: BOOTCODE_LEN as usize,
+        checksum_len: LOAD_LEN as usize,
+    };
+
+    pub fn bootcode_start(&self) -> usize {
+        self.header_len
+    }
+
+    pub fn bootcode_end(&self) -> usize {
+        self.header_len + self.bootcode_len
+    }
+
+    /// Offset of the first payload byte, straight after the bootcode.
+    pub fn load_start(&self) -> usize {
+        self.bootcode_end()
+    }
+
+    /// End of the checksummed region, and so the shortest complete image.
+    pub fn checksum_end(&self) -> usize {
+        self.load_start() + self.checksum_len
+    }
+}
+
+impl Default for RomLayout {
+    fn default() -> Self {
+        RomLayout::STANDARD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{BOOTCODE_END, BOOTCODE_START, LOAD_START, ROM_LEN};
+
+    #[test]
+    fn standard_matches_constants() {
+        let layout = RomLayout::default();
+        assert_eq!(layout.bootcode_start(), BOOTCODE_START);
+        assert_eq!(layout.bootcode_end(), BOOTCODE_END);
+        assert_eq!(layout.load_start(), LOAD_START);
+        assert_eq!(layout.checksum_end(), ROM_LEN);
+    }
+}
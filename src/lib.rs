@@ -1,9 +1,216 @@
-use std::io::{Cursor, Error, ErrorKind};
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-use std::fmt;
-use std::io;
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+
+#[cfg(feature = "std")]
+use byteorder::{BigEndian, ReadBytesExt};
+
+// `log` calls for the `log` feature, which compile to nothing without it
+// while still counting their arguments as used.
+macro_rules! log_debug {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        log::debug!($($arg)*);
+        #[cfg(not(feature = "log"))]
+        let _ = format_args!($($arg)*);
+    }};
+}
+
+macro_rules! log_trace {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        log::trace!($($arg)*);
+        #[cfg(not(feature = "log"))]
+        let _ = format_args!($($arg)*);
+    }};
+}
+
+mod bootcode;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "archive")]
+mod archive;
+#[cfg(feature = "tokio")]
+mod async_io;
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "alloc")]
+mod builder;
+#[cfg(feature = "alloc")]
+mod cheat_file;
+mod cheats;
+mod checksum;
+mod cic;
+pub mod compression;
+mod country;
+mod crc32;
+#[cfg(feature = "alloc")]
+mod database;
+pub mod dd;
+#[cfg(feature = "alloc")]
+mod diff;
+#[cfg(feature = "elf")]
+mod elf;
+#[cfg(feature = "embedded-io")]
+mod embedded;
+#[cfg(feature = "alloc")]
+mod emulator;
+#[cfg(feature = "std")]
+mod entropy;
+mod entry;
+mod error;
+#[cfg(feature = "std")]
+mod file;
+#[cfg(feature = "alloc")]
+mod file_table;
+mod forge;
+mod fragment;
+mod game_id;
+mod homebrew;
+#[cfg(feature = "alloc")]
+mod info;
+mod ique;
+#[cfg(feature = "hashes")]
+mod hashes;
+mod header_builder;
+#[cfg(feature = "known-bad")]
+mod known_bad;
+mod layout;
+mod libultra;
+mod name;
+#[cfg(feature = "alloc")]
+mod naming;
+#[cfg(feature = "alloc")]
+mod pad;
+#[cfg(feature = "alloc")]
+pub mod pak;
+#[cfg(feature = "alloc")]
+pub mod patch;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod platform;
+#[cfg(feature = "python")]
+mod python;
+mod raw;
+mod region;
+#[cfg(feature = "alloc")]
+mod rom;
+#[cfg(all(feature = "alloc", feature = "hashes"))]
+mod romset;
+mod save;
+pub mod saves;
+#[cfg(feature = "alloc")]
+mod split;
+#[cfg(feature = "std")]
+mod stream;
+mod strings;
+mod timing;
+mod trim;
+#[cfg(feature = "alloc")]
+mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "archive")]
+pub use crate::archive::{RomArchive, Roms};
+#[cfg(feature = "tokio")]
+pub use crate::async_io::{calculate_cart_checksum_from_async_reader, convert_stream_async, swap_stream_to_async};
+pub use crate::bootcode::{extract_bootcode, identify_bootcode, is_libdragon_ipl3, Bootcode, Ipl3, KNOWN_IPL3S};
+#[cfg(feature = "alloc")]
+pub use crate::builder::{MemoryMap, RomBuilder, SegmentPlacement};
+#[cfg(feature = "alloc")]
+pub use crate::cheat_file::{parse_retroarch_cheats, to_retroarch_cheats, Cheat, CheatDatabase, CheatGame};
+#[cfg(feature = "alloc")]
+pub use crate::cheats::parse_cheats;
+pub use crate::cheats::{apply_boot_cheats, CheatCode, CheatKind, ParseCheatError};
+#[cfg(feature = "std")]
+pub use crate::checksum::calculate_cart_checksum_from_reader;
+pub use crate::checksum::{
+    calculate_all_cart_checksums, calculate_cart_checksum, calculate_cart_checksum_with,
+    calculate_cart_checksum_with_params, calculate_swapped_cart_checksum, fix_cart_checksum, verify_cart_checksum,
+    CartChecksum, ChecksumError, ChecksumParams, ChecksumReport, CicChecksums,
+};
+pub use crate::cic::{detect_cic, CicVariant};
+pub use crate::country::{CountryCode, TvStandard};
+#[cfg(feature = "alloc")]
+pub use crate::database::{Database, DatEntry};
+#[cfg(feature = "alloc")]
+pub use crate::diff::{diff, diff_with_context, HeaderChange, HeaderField, HeaderValue, RomDiff, DEFAULT_DIFF_CONTEXT};
+#[cfg(feature = "elf")]
+pub use crate::elf::elf_to_rom;
+#[cfg(feature = "embedded-io")]
+pub use crate::embedded::calculate_cart_checksum_from_embedded_reader;
+#[cfg(all(feature = "alloc", feature = "hashes"))]
+pub use crate::emulator::mupen64plus_entry;
+#[cfg(feature = "alloc")]
+pub use crate::emulator::{project64_entry, EmulatorHints};
+#[cfg(feature = "std")]
+pub use crate::entropy::{block_entropy, scan_regions, Region, RegionKind, DEFAULT_BLOCK_LEN};
+pub use crate::entry::{analyze_boot, BootAnalysis, BootMismatch, BOOT_SCAN_LIMIT};
+pub use crate::error::Error;
+#[cfg(feature = "std")]
+pub use crate::file::RomFile;
+#[cfg(feature = "alloc")]
+pub use crate::file_table::{scan_file_tables, FileExtent, FileTable, MIN_FILE_TABLE_ENTRIES};
+pub use crate::forge::{forge_cart_checksum, FORGE_SCRATCH_LEN};
+pub use crate::fragment::{guess_fragment_swapping, MIN_FRAGMENT_WORDS};
+pub use crate::game_id::{GameId, MediaFormat, ParseGameIdError};
+#[cfg(feature = "hashes")]
+pub use crate::hashes::{hashes, RomHashes};
+pub use crate::header_builder::RomHeaderBuilder;
+pub use crate::homebrew::{BuildInfo, ControllerAccessory, HomebrewSaveType, ADVANCED_HOMEBREW_CART_ID};
+#[cfg(feature = "alloc")]
+pub use crate::info::{analyze, RomInfo};
+pub use crate::ique::{is_ique_title, split_sksa, Sksa};
+#[cfg(feature = "known-bad")]
+pub use crate::known_bad::{find_known_bad, BadDumpKind, KnownBadDump, KNOWN_BAD_DUMPS};
+pub use crate::layout::RomLayout;
+pub use crate::libultra::{scan_libultra, LibultraIdent, LibultraVersion};
+pub use crate::name::NameError;
+#[cfg(feature = "alloc")]
+pub use crate::naming::{canonical_name, NamingOptions, NamingStyle, RevisionStyle};
+#[cfg(feature = "alloc")]
+pub use crate::pad::{
+    classify_cart_size, pad_rom, pad_rom_to, standard_cart_size, CartSizeClass, PadFill, CART_SIZES, SC64_MAX_ROM_LEN,
+};
+#[cfg(feature = "rayon")]
+pub use crate::parallel::verify_all;
+pub use crate::platform::{detect_platform, Platform};
+pub use crate::raw::RawRomHeader;
+pub use crate::region::{os_tv_type, patch_region, RegionPatch};
+#[cfg(feature = "alloc")]
+pub use crate::rom::Rom;
+#[cfg(all(feature = "alloc", feature = "hashes"))]
+pub use crate::romset::{Dump, RomSet, RomSetReport};
+#[cfg(feature = "alloc")]
+pub use crate::save::save_db;
+pub use crate::save::{detect_save_type, SaveDbEntry, SaveGuess, SaveType};
+#[cfg(feature = "alloc")]
+pub use crate::split::{join_parts, split_banks, split_parts, JoinedRom, PartLayout, DOCTOR_V64_PART_LEN, FLASHCART_BANK_LEN};
+#[cfg(feature = "std")]
+pub use crate::split::{write_banks, BankOptions};
+#[cfg(feature = "std")]
+pub use crate::stream::{SwappedReader, SwappedWriter};
+pub use crate::strings::{strings, RomString, StringEncoding, StringOptions, Strings};
+pub use crate::timing::{PiTiming, CLOCK_RATE_MASK};
+pub use crate::trim::{check_dump_size, detect_trim, trimmed, DumpSize, TailPadding, TrimReport};
+#[cfg(feature = "alloc")]
+pub use crate::validate::{Diagnostic, Severity};
 
 pub const DEFAULT_CART_TIMING: u32 = 0x80371240;
 pub const DEFAULT_CLOCK_RATE: u32  = 0x0000000f;
@@ -14,6 +221,9 @@ pub const HEADER_START: usize = 0;
 pub const HEADER_LEN: u64 = 64;
 pub const HEADER_END: usize = HEADER_START + (HEADER_LEN as usize);
 
+const HEADER_CRC_START: usize = 0x10;
+const HEADER_CRC_END: usize = HEADER_CRC_START + 8;
+
 pub const BOOTCODE_START: usize = HEADER_LEN as usize;
 pub const BOOTCODE_LEN: u64 = 4096 - HEADER_LEN;
 pub const BOOTCODE_END: usize = BOOTCODE_START + (BOOTCODE_LEN as usize);
@@ -23,10 +233,48 @@ pub const LOAD_LEN: u64 = 0x100000;
 
 pub const ROM_LEN: usize = (HEADER_LEN + BOOTCODE_LEN + LOAD_LEN) as usize;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum ByteSwapping {
     Native,
     U16LittleEndian,
+    U32LittleEndian,
+}
+
+impl ByteSwapping {
+    /// Size of the unit this ordering swaps within; buffers converted to or
+    /// from it must be a multiple of this length.
+    pub fn unit_len(&self) -> usize {
+        match *self {
+            ByteSwapping::Native          => 1,
+            ByteSwapping::U16LittleEndian => 2,
+            ByteSwapping::U32LittleEndian => 4,
+        }
+    }
+
+    /// The file extension conventionally used for images in this order.
+    pub fn file_extension(&self) -> &'static str {
+        match *self {
+            ByteSwapping::Native          => "z64",
+            ByteSwapping::U16LittleEndian => "v64",
+            ByteSwapping::U32LittleEndian => "n64",
+        }
+    }
+
+    /// The ordering a file extension conventionally means, ignoring case and
+    /// any leading dot.
+    pub fn from_file_extension(extension: &str) -> Option<ByteSwapping> {
+        CANDIDATE_SWAPPINGS.iter()
+            .cloned()
+            .find(|swapping| swapping.file_extension().eq_ignore_ascii_case(extension.trim_start_matches('.')))
+    }
+
+    /// The ordering a path's extension conventionally means.
+    #[cfg(feature = "std")]
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ByteSwapping> {
+        ByteSwapping::from_file_extension(path.as_ref().extension()?.to_str()?)
+    }
 }
 
 impl fmt::Display for ByteSwapping {
@@ -34,11 +282,17 @@ impl fmt::Display for ByteSwapping {
         match *self {
             ByteSwapping::Native          => write!(f, "Native"),
             ByteSwapping::U16LittleEndian => write!(f, "U16 Little Endian"),
+            ByteSwapping::U32LittleEndian => write!(f, "U32 Little Endian"),
         }
     }
 }
 
+/// The cart header with its fields decoded to host integers.  Use
+/// [`RawRomHeader`] to overlay the header on image bytes without copying.
 #[repr(C)]
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RomHeader {
 	pub cart_timing: u32,
 	pub clock_rate: u32,
@@ -56,33 +310,10 @@ pub struct RomHeader {
 }
 
 impl RomHeader {
-    pub fn new() -> RomHeader {
-        Default::default()
-    }
-
-	pub fn serialize(&self, writer: &mut std::io::Write) -> io::Result<()> {
-		writer.write_u32::<BigEndian>(self.cart_timing)?;
-		writer.write_u32::<BigEndian>(self.clock_rate)?;
-		writer.write_u32::<BigEndian>(self.load_addr)?;
-		writer.write_u32::<BigEndian>(self.release)?;
-		writer.write_u32::<BigEndian>(self.crc1)?;
-		writer.write_u32::<BigEndian>(self.crc2)?;
-		writer.write_u32::<BigEndian>(self.rsvd_18)?;
-		writer.write_u32::<BigEndian>(self.rsvd_1c)?;
-		for name_char in self.name.iter() {
-			writer.write_u8(*name_char)?;
-		}
-		writer.write_u32::<BigEndian>(self.rsvd_34)?;
-		writer.write_u32::<BigEndian>(self.manuf_id)?;
-		writer.write_u16::<BigEndian>(self.cart_id)?;
-		writer.write_u16::<BigEndian>(self.country_code)?;
-
-		Ok(())
-	}
-}
-
-impl std::default::Default for RomHeader {
-    fn default() -> Self {
+    /// A header with the retail timing and clock rate and everything else
+    /// zero.  Being `const`, it can seed a header built at compile time:
+    /// `RomHeader { load_addr: 0x80000400, ..RomHeader::new() }.to_bytes()`.
+    pub const fn new() -> RomHeader {
         RomHeader {
 			cart_timing: DEFAULT_CART_TIMING,
 			clock_rate: DEFAULT_CLOCK_RATE,
@@ -99,12 +330,262 @@ impl std::default::Default for RomHeader {
 			country_code: 0,
         }
     }
+
+	#[cfg(feature = "std")]
+	pub fn deserialize<R: Read>(reader: &mut R) -> io::Result<RomHeader> {
+		let cart_timing = reader.read_u32::<BigEndian>()?;
+		let clock_rate = reader.read_u32::<BigEndian>()?;
+		let load_addr = reader.read_u32::<BigEndian>()?;
+		let release = reader.read_u32::<BigEndian>()?;
+		let crc1 = reader.read_u32::<BigEndian>()?;
+		let crc2 = reader.read_u32::<BigEndian>()?;
+		let rsvd_18 = reader.read_u32::<BigEndian>()?;
+		let rsvd_1c = reader.read_u32::<BigEndian>()?;
+		let mut name = [0u8; HEADER_NAME_LEN];
+		reader.read_exact(&mut name)?;
+		let rsvd_34 = reader.read_u32::<BigEndian>()?;
+		let manuf_id = reader.read_u32::<BigEndian>()?;
+		let cart_id = reader.read_u16::<BigEndian>()?;
+		let country_code = reader.read_u16::<BigEndian>()?;
+
+		Ok(RomHeader {
+			cart_timing,
+			clock_rate,
+			load_addr,
+			release,
+			crc1,
+			crc2,
+			rsvd_18,
+			rsvd_1c,
+			name,
+			rsvd_34,
+			manuf_id,
+			cart_id,
+			country_code,
+		})
+	}
+
+	/// Parses a header from the start of a native byte order ROM image.
+	pub fn from_slice(buffer: &[u8]) -> Result<RomHeader, Error> {
+		RomHeader::try_from(buffer)
+	}
+
+	/// Writes the header in native byte order.  `W` may be unsized, so
+	/// existing callers passing a `&mut dyn Write` keep working.
+	#[cfg(feature = "std")]
+	pub fn serialize<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+		writer.write_all(&self.to_bytes())
+	}
+
+	#[cfg(feature = "alloc")]
+	pub fn serialize_to_vec(&self) -> Vec<u8> {
+		self.to_bytes().to_vec()
+	}
+
+	pub fn serialize_into(&self, bytes: &mut [u8; HEADER_LEN as usize]) {
+		*bytes = self.to_bytes();
+	}
+
+	/// The internal name decoded to text, without its padding.
+	#[cfg(feature = "alloc")]
+	pub fn name_str(&self) -> Cow<'_, str> {
+		name::decode_name(&self.name)
+	}
+
+	/// Sets the internal name from ASCII or JIS X 0201 text, space padded to
+	/// the full field.
+	pub fn set_name(&mut self, name: &str) -> Result<(), NameError> {
+		self.name = name::encode_name(name)?;
+		Ok(())
+	}
+
+	/// Destination code, stored in the upper byte of `country_code`.
+	pub fn country(&self) -> CountryCode {
+		CountryCode::from_u8((self.country_code >> 8) as u8)
+	}
+
+	pub fn set_country(&mut self, country: CountryCode) {
+		self.country_code = (u16::from(country.as_u8()) << 8) | (self.country_code & 0x00ff);
+	}
+
+	/// ROM revision, stored in the lower byte of `country_code`.
+	pub fn version(&self) -> u8 {
+		(self.country_code & 0x00ff) as u8
+	}
+
+	pub fn set_version(&mut self, version: u8) {
+		self.country_code = (self.country_code & 0xff00) | u16::from(version);
+	}
+
+	/// Where the payload really lands and starts running when booted by
+	/// `cic`'s IPL3, which for the 6103 and 6106 is below `load_addr`.
+	pub fn effective_entry_point(&self, cic: CicVariant) -> u32 {
+		self.load_addr.wrapping_sub(cic.entry_offset())
+	}
+
+	/// Media format, cart ID, and destination decoded as a game code.
+	pub fn game_id(&self) -> GameId {
+		let cart_id = self.cart_id.to_be_bytes();
+		GameId::from_bytes([self.manuf_id as u8, cart_id[0], cart_id[1], (self.country_code >> 8) as u8])
+	}
+
+	/// Stores a game code, leaving the version byte untouched.
+	pub fn set_game_id(&mut self, game_id: &GameId) {
+		let bytes = game_id.to_bytes();
+		self.manuf_id = (self.manuf_id & 0xffff_ff00) | u32::from(bytes[0]);
+		self.cart_id = u16::from_be_bytes([bytes[1], bytes[2]]);
+		self.set_country(game_id.country);
+	}
+
+	/// Parses a header out of a fixed size native byte order buffer without
+	/// going through a reader.
+	pub const fn from_bytes(bytes: &[u8; HEADER_LEN as usize]) -> RomHeader {
+		let mut name = [0u8; HEADER_NAME_LEN];
+		let mut ii = 0;
+		while ii < HEADER_NAME_LEN {
+			name[ii] = bytes[0x20 + ii];
+			ii += 1;
+		}
+
+		RomHeader {
+			cart_timing: be_u32_at(bytes, 0x00),
+			clock_rate: be_u32_at(bytes, 0x04),
+			load_addr: be_u32_at(bytes, 0x08),
+			release: be_u32_at(bytes, 0x0c),
+			crc1: be_u32_at(bytes, 0x10),
+			crc2: be_u32_at(bytes, 0x14),
+			rsvd_18: be_u32_at(bytes, 0x18),
+			rsvd_1c: be_u32_at(bytes, 0x1c),
+			name,
+			rsvd_34: be_u32_at(bytes, 0x34),
+			manuf_id: be_u32_at(bytes, 0x38),
+			cart_id: u16::from_be_bytes([bytes[0x3c], bytes[0x3d]]),
+			country_code: u16::from_be_bytes([bytes[0x3e], bytes[0x3f]]),
+		}
+	}
+
+	/// Emits the header as a fixed size native byte order buffer.  This is a
+	/// `const fn`, so a prebuilt header can be a `static` in `.rodata`.
+	pub const fn to_bytes(&self) -> [u8; HEADER_LEN as usize] {
+		let mut bytes = [0u8; HEADER_LEN as usize];
+
+		put_bytes(&mut bytes, 0x00, &self.cart_timing.to_be_bytes());
+		put_bytes(&mut bytes, 0x04, &self.clock_rate.to_be_bytes());
+		put_bytes(&mut bytes, 0x08, &self.load_addr.to_be_bytes());
+		put_bytes(&mut bytes, 0x0c, &self.release.to_be_bytes());
+		put_bytes(&mut bytes, 0x10, &self.crc1.to_be_bytes());
+		put_bytes(&mut bytes, 0x14, &self.crc2.to_be_bytes());
+		put_bytes(&mut bytes, 0x18, &self.rsvd_18.to_be_bytes());
+		put_bytes(&mut bytes, 0x1c, &self.rsvd_1c.to_be_bytes());
+		put_bytes(&mut bytes, 0x20, &self.name);
+		put_bytes(&mut bytes, 0x34, &self.rsvd_34.to_be_bytes());
+		put_bytes(&mut bytes, 0x38, &self.manuf_id.to_be_bytes());
+		put_bytes(&mut bytes, 0x3c, &self.cart_id.to_be_bytes());
+		put_bytes(&mut bytes, 0x3e, &self.country_code.to_be_bytes());
+
+		bytes
+	}
+}
+
+// `copy_from_slice` for `const fn`s, which can't index by range.
+const fn put_bytes(bytes: &mut [u8; HEADER_LEN as usize], offset: usize, data: &[u8]) {
+    let mut ii = 0;
+    while ii < data.len() {
+        bytes[offset + ii] = data[ii];
+        ii += 1;
+    }
+}
+
+const fn be_u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+impl From<&[u8; HEADER_LEN as usize]> for RomHeader {
+    fn from(bytes: &[u8; HEADER_LEN as usize]) -> Self {
+        RomHeader::from_bytes(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for RomHeader {
+    type Error = Error;
+
+    fn try_from(buffer: &[u8]) -> Result<Self, Self::Error> {
+        if buffer.len() < HEADER_END {
+            return Err(Error::BufferTooShort { needed: HEADER_END, actual: buffer.len() });
+        }
+
+        let mut bytes = [0u8; HEADER_LEN as usize];
+        bytes.copy_from_slice(&buffer[HEADER_START..HEADER_END]);
+        Ok(RomHeader::from_bytes(&bytes))
+    }
+}
+
+impl core::default::Default for RomHeader {
+    fn default() -> Self {
+        RomHeader::new()
+    }
+}
+
+// Shows a raw name field as quoted text, escaping anything unprintable.
+struct RawName<'a>(&'a [u8]);
+
+impl fmt::Debug for RawName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"")?;
+        for byte in self.0 {
+            write!(f, "{}", core::ascii::escape_default(*byte))?;
+        }
+        write!(f, "\"")
+    }
+}
+
+impl fmt::Debug for RomHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RomHeader")
+            .field("cart_timing", &format_args!("0x{:08x}", self.cart_timing))
+            .field("clock_rate", &format_args!("0x{:08x}", self.clock_rate))
+            .field("load_addr", &format_args!("0x{:08x}", self.load_addr))
+            .field("release", &format_args!("0x{:08x}", self.release))
+            .field("crc1", &format_args!("0x{:08x}", self.crc1))
+            .field("crc2", &format_args!("0x{:08x}", self.crc2))
+            .field("rsvd_18", &format_args!("0x{:08x}", self.rsvd_18))
+            .field("rsvd_1c", &format_args!("0x{:08x}", self.rsvd_1c))
+            .field("name", &RawName(&self.name))
+            .field("rsvd_34", &format_args!("0x{:08x}", self.rsvd_34))
+            .field("manuf_id", &format_args!("0x{:08x}", self.manuf_id))
+            .field("cart_id", &format_args!("0x{:04x}", self.cart_id))
+            .field("country_code", &format_args!("0x{:04x}", self.country_code))
+            .finish()
+    }
 }
 
-#[derive(Debug, Eq, PartialEq)]
-pub enum ChecksumError {
-    NotLongEnough,
-    ErrorReadingBuffer,
+/// The summary rominfo style tools print, one field per line.
+impl fmt::Display for RomHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        #[cfg(feature = "alloc")]
+        writeln!(f, "Name:         {}", self.name_str())?;
+        #[cfg(not(feature = "alloc"))]
+        {
+            let len = self.name.iter().rposition(|byte| *byte != b' ' && *byte != 0).map_or(0, |last| last + 1);
+            write!(f, "Name:         ")?;
+            for byte in &self.name[..len] {
+                write!(f, "{}", core::ascii::escape_default(*byte))?;
+            }
+            writeln!(f)?;
+        }
+
+        let country = self.country();
+        writeln!(f, "Game code:    {}", self.game_id())?;
+        writeln!(f, "Media:        {}", self.game_id().media)?;
+        match country.tv_standard() {
+            Some(standard) => writeln!(f, "Region:       {} ({})", country, standard)?,
+            None => writeln!(f, "Region:       {}", country)?,
+        }
+        writeln!(f, "Version:      1.{}", self.version())?;
+        writeln!(f, "CRC1:         0x{:08X}", self.crc1)?;
+        writeln!(f, "CRC2:         0x{:08X}", self.crc2)?;
+        write!(f, "Load address: 0x{:08X}", self.load_addr)
+    }
 }
 
 pub fn detect_swapping(buffer: &[u8]) -> Option<ByteSwapping> {
@@ -112,120 +593,771 @@ pub fn detect_swapping(buffer: &[u8]) -> Option<ByteSwapping> {
         return None;
     }
 
-    return match (buffer[0], buffer[1], buffer[2], buffer[3]) {
+    match (buffer[0], buffer[1], buffer[2], buffer[3]) {
         (0x80, 0x37, 0x12, 0x40) => Some(ByteSwapping::Native),
         (0x37, 0x80, 0x40, 0x12) => Some(ByteSwapping::U16LittleEndian),
+        (0x40, 0x12, 0x37, 0x80) => Some(ByteSwapping::U32LittleEndian),
         (   _,    _,    _,    _) => None,
+    }
+}
+
+/// How much a [`SwappingGuess`] should be trusted.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+    /// The header magic matched exactly.
+    Certain,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwappingGuess {
+    pub swapping: ByteSwapping,
+    pub confidence: Confidence,
+}
+
+const CANDIDATE_SWAPPINGS: [ByteSwapping; 3] = [
+    ByteSwapping::Native,
+    ByteSwapping::U16LittleEndian,
+    ByteSwapping::U32LittleEndian,
+];
+
+/// Like [`detect_swapping`], but falls back to judging how plausible the other
+/// header fields look under each candidate ordering when the magic doesn't
+/// match, as is common for homebrew.
+pub fn guess_swapping(buffer: &[u8]) -> Option<SwappingGuess> {
+    if let Some(swapping) = detect_swapping(buffer) {
+        return Some(SwappingGuess { swapping, confidence: Confidence::Certain });
+    }
+
+    if buffer.len() < HEADER_END {
+        return None;
+    }
+
+    let mut scores = [0u32; 3];
+    for (score, swapping) in scores.iter_mut().zip(CANDIDATE_SWAPPINGS.iter()) {
+        let mut bytes = [0u8; HEADER_LEN as usize];
+        bytes.copy_from_slice(&buffer[HEADER_START..HEADER_END]);
+        swap_to_native(*swapping, &mut bytes);
+        *score = header_plausibility(&RomHeader::from_bytes(&bytes));
+    }
+
+    let mut best = 0;
+    for ii in 1..scores.len() {
+        if scores[ii] > scores[best] {
+            best = ii;
+        }
+    }
+
+    let best_score = scores[best];
+    if best_score == 0 || scores.iter().filter(|score| **score == best_score).count() > 1 {
+        log_trace!("no byte order stands out, header scores {:?}", scores);
+        return None;
+    }
+
+    let confidence = match best_score {
+        0..=2 => Confidence::Low,
+        3..=5 => Confidence::Medium,
+        _     => Confidence::High,
     };
+    log_trace!("guessed {} from header scores {:?}", CANDIDATE_SWAPPINGS[best], scores);
+
+    Some(SwappingGuess { swapping: CANDIDATE_SWAPPINGS[best], confidence })
+}
+
+fn header_plausibility(header: &RomHeader) -> u32 {
+    let mut score = 0;
+
+    // Entry points live in cached KSEG0 RDRAM.
+    if (header.load_addr & 0xff80_0000) == 0x8000_0000 && (header.load_addr & 3) == 0 {
+        score += 3;
+    }
+
+    if header.clock_rate != 0 && header.uses_default_clock_rate() {
+        score += 1;
+    }
+
+    // The PI domain 1 timing word starts 0x80 and ends 0x40 even on boards,
+    // like the iQue's, that don't use the retail 0x80371240.
+    if (header.cart_timing >> 24) == 0x80 {
+        score += 1;
+        if (header.cart_timing & 0xff) == 0x40 {
+            score += 1;
+        }
+    }
+
+    // Doesn't distinguish orderings, but vouches for this being a header at all.
+    let printable = header.name.iter().all(|c| *c == 0 || (0x20..0x7f).contains(c));
+    let nonblank = header.name.iter().any(|c| *c != 0 && *c != b' ');
+    if printable && nonblank {
+        score += 2;
+    }
+
+    score
+}
+
+// Orderings trusted enough to act on without the caller's say so.
+fn resolve_swapping(buffer: &[u8]) -> Option<ByteSwapping> {
+    match guess_swapping(buffer) {
+        Some(guess) if guess.confidence >= Confidence::Medium => Some(guess.swapping),
+        _ => None,
+    }
 }
 
 pub fn swap_cart_to(new_swapping: ByteSwapping, buffer: &mut [u8]) -> Result<(), Error> {
-    let original_swapping = match detect_swapping(buffer) {
+    let original_swapping = match resolve_swapping(buffer) {
         Some(swapping) => swapping,
         None => {
-            return Err(Error::new(ErrorKind::Other, "Unknown original byte swapping"));
+            return Err(Error::UnknownByteSwapping);
         },
     };
 
-    if (buffer.len() % 2) != 0 {
-        return Err(Error::new(ErrorKind::Other, "Not an even length for swapping"));
-    }
+    convert_swapping(original_swapping, new_swapping, buffer)
+}
 
-    if original_swapping == new_swapping {
+/// Converts a buffer already known to be in `from` ordering into `to` ordering,
+/// without looking for the header magic.
+pub fn convert_swapping(from: ByteSwapping, to: ByteSwapping, buffer: &mut [u8]) -> Result<(), Error> {
+    if from == to {
         return Ok(());
     }
 
-    for ii in 0..(buffer.len() / 2) {
-        let cur_base = (ii * 2) as usize;
-        let temp = buffer[cur_base];
-        buffer[cur_base] = buffer[cur_base + 1];
-        buffer[cur_base + 1] = temp;
+    let unit_len = core::cmp::max(from.unit_len(), to.unit_len());
+    if !buffer.len().is_multiple_of(unit_len) {
+        return Err(Error::UnalignedLength { len: buffer.len(), unit: unit_len });
     }
+    log_trace!("converting {} bytes from {} to {}", buffer.len(), from, to);
+
+    swap_to_native(from, buffer);
+    swap_to_native(to, buffer);
 
     Ok(())
 }
 
-const CHECKSUM_START:  usize = BOOTCODE_END;
-const CHECKSUM_LENGTH: usize = LOAD_LEN as usize;
-const CHECKSUM_END: usize = CHECKSUM_START + CHECKSUM_LENGTH;
-const CHECKSUM_START_VALUE: u32 = 0xf8ca4ddc;
+#[cfg(feature = "std")]
+const SWAP_CHUNK_LEN: usize = 64 * 1024;
+
+/// Streaming version of [`swap_cart_to`]: detects the ordering from the start
+/// of `reader` and copies it to `writer` in `new_swapping` ordering, holding
+/// only a fixed size chunk in memory.  Returns the number of bytes written.
+#[cfg(feature = "std")]
+pub fn swap_stream_to<R: Read, W: Write>(new_swapping: ByteSwapping, reader: &mut R, writer: &mut W) -> Result<u64, Error> {
+    let mut chunk = vec![0u8; SWAP_CHUNK_LEN];
+    let len = fill_chunk(reader, &mut chunk)?;
+
+    let original_swapping = match resolve_swapping(&chunk[..len]) {
+        Some(swapping) => swapping,
+        None => {
+            return Err(Error::UnknownByteSwapping);
+        },
+    };
+
+    convert_chunks(original_swapping, new_swapping, chunk, len, reader, writer)
+}
+
+/// Streaming version of [`convert_swapping`] for data whose ordering is known
+/// up front.  Returns the number of bytes written.
+#[cfg(feature = "std")]
+pub fn convert_stream<R: Read, W: Write>(from: ByteSwapping, to: ByteSwapping, reader: &mut R, writer: &mut W) -> Result<u64, Error> {
+    let mut chunk = vec![0u8; SWAP_CHUNK_LEN];
+    let len = fill_chunk(reader, &mut chunk)?;
 
-pub fn calculate_cart_checksum(buffer: &[u8]) -> Result<(u32, u32), ChecksumError> {
-    if buffer.len() < CHECKSUM_END {
-        return Err(ChecksumError::NotLongEnough);
+    convert_chunks(from, to, chunk, len, reader, writer)
+}
+
+/// Copies the image at `src` to `dst` in the ordering `dst`'s extension
+/// conventionally means, whatever order `src` is in.  Returns the ordering
+/// written and the number of bytes.
+#[cfg(feature = "std")]
+pub fn convert_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> Result<(ByteSwapping, u64), Error> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let swapping = ByteSwapping::from_path(dst).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "destination has no z64, v64 or n64 extension")
+    })?;
+    if dst.exists() && fs::canonicalize(src)? == fs::canonicalize(dst)? {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "source and destination are the same file").into());
     }
 
-    let checksum_slice = &buffer[CHECKSUM_START..CHECKSUM_END];
+    let mut reader = io::BufReader::new(fs::File::open(src)?);
+    let mut writer = io::BufWriter::new(fs::File::create(dst)?);
+    let len = swap_stream_to(swapping, &mut reader, &mut writer)?;
+    writer.flush()?;
+    Ok((swapping, len))
+}
 
-    let mut reader = Cursor::new(checksum_slice);
+#[cfg(feature = "std")]
+fn convert_chunks<R: Read, W: Write>(from: ByteSwapping, to: ByteSwapping, mut chunk: Vec<u8>, mut len: usize,
+                                     reader: &mut R, writer: &mut W) -> Result<u64, Error> {
+    let mut total = 0u64;
 
-    let mut c1: u32;
-    let mut k1: u32;
-    let mut k2: u32;
+    while len != 0 {
+        convert_swapping(from, to, &mut chunk[..len])?;
+        writer.write_all(&chunk[..len])?;
+        total += len as u64;
 
-    let mut t1 = CHECKSUM_START_VALUE;
-    let mut t2 = CHECKSUM_START_VALUE;
-    let mut t3 = CHECKSUM_START_VALUE;
-    let mut t4 = CHECKSUM_START_VALUE;
-    let mut t5 = CHECKSUM_START_VALUE;
-    let mut t6 = CHECKSUM_START_VALUE;
+        len = fill_chunk(reader, &mut chunk)?;
+    }
 
-    for _ in 0..(CHECKSUM_LENGTH / 4) {
-        c1 = match reader.read_u32::<BigEndian>() {
-            Ok(value) => value,
-            Err(_) => {
-                return Err(ChecksumError::ErrorReadingBuffer);
-            },
-        };
+    log_debug!("streamed {} bytes from {} to {}", total, from, to);
+    Ok(total)
+}
 
-        k1 = t6.wrapping_add(c1);
-        if k1 < t6 {
-            t4 += 1;
-        }
-        t6 = k1;
-        t3 ^= c1;
-        k2 = c1 & 0x1f;
-        k1 = c1.rotate_left(k2);
-        t5 = t5.wrapping_add(k1);
-        if c1 < t2 {
-            t2 ^= k1;
-        } else {
-            t2 ^= t6 ^ c1;
+// Keeps reading until the chunk is full or the reader is exhausted, so that
+// short reads never split a swapping unit.
+#[cfg(feature = "std")]
+fn fill_chunk<R: Read>(reader: &mut R, chunk: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < chunk.len() {
+        match reader.read(&mut chunk[len..]) {
+            Ok(0) => break,
+            Ok(count) => len += count,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {},
+            Err(err) => return Err(err),
         }
-        t1 = t1.wrapping_add(c1 ^ t5);
     }
+    Ok(len)
+}
 
-    return Ok((
-        t6 ^ t4 ^ t3, 
-        t5 ^ t2 ^ t1))
+// Every supported ordering is its own inverse, so this also converts native
+// data into `swapping`.  The bulk of the buffer is handled a u64 at a time,
+// which is several times faster than shuffling individual bytes.
+fn swap_to_native(swapping: ByteSwapping, buffer: &mut [u8]) {
+    match swapping {
+        ByteSwapping::Native => {},
+        ByteSwapping::U16LittleEndian => {
+            let mut words = buffer.chunks_exact_mut(8);
+            for word in &mut words {
+                let value = u64::from_ne_bytes(array_of(word));
+                let swapped = ((value & 0x00ff_00ff_00ff_00ff) << 8) | ((value >> 8) & 0x00ff_00ff_00ff_00ff);
+                word.copy_from_slice(&swapped.to_ne_bytes());
+            }
+            for half in words.into_remainder().chunks_exact_mut(2) {
+                half.swap(0, 1);
+            }
+        },
+        ByteSwapping::U32LittleEndian => {
+            let mut words = buffer.chunks_exact_mut(8);
+            for word in &mut words {
+                let value = u64::from_ne_bytes(array_of(word));
+                word.copy_from_slice(&value.swap_bytes().rotate_left(32).to_ne_bytes());
+            }
+            for word in words.into_remainder().chunks_exact_mut(4) {
+                word.reverse();
+            }
+        },
+    }
+}
+
+fn array_of(word: &[u8]) -> [u8; 8] {
+    let mut array = [0u8; 8];
+    array.copy_from_slice(word);
+    array
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    pub(crate) fn test_cart() -> Vec<u8> {
+        let mut cart: Vec<u8> = vec![0; ROM_LEN];
+        for (ii, byte) in cart.iter_mut().enumerate() {
+            *byte = (ii * 7 + 3) as u8;
+        }
+        cart[HEADER_START..HEADER_END].copy_from_slice(&RomHeader::new().to_bytes());
+        cart
+    }
+
+    /// A [`test_cart`] with its header changed by `edit`.
+    pub(crate) fn test_cart_with<F: FnOnce(&mut RomHeader)>(edit: F) -> Vec<u8> {
+        let mut cart = test_cart();
+        let mut header = RomHeader::from_slice(&cart).unwrap();
+        edit(&mut header);
+        cart[HEADER_START..HEADER_END].copy_from_slice(&header.to_bytes());
+        cart
+    }
+
+    /// Rewrites the last bootcode word of a native image so it fingerprints
+    /// as `variant`.
+    pub(crate) fn stamp_cic(cart: &mut [u8], variant: CicVariant) {
+        let crc = crc32::crc32(&cart[BOOTCODE_START..BOOTCODE_END - 4]);
+        cart[BOOTCODE_END - 4..BOOTCODE_END].copy_from_slice(&crc32::forge(crc, variant.bootcode_crc32()));
+    }
+
+    #[test]
+    fn formats_header() {
+        let mut header = RomHeader::new();
+        header.set_name("SUPER MARIO 64").unwrap();
+        header.set_game_id(&"NSME".parse().unwrap());
+        header.load_addr = 0x80246000;
+        header.crc1 = 0x635a2bff;
+        header.crc2 = 0x8b022326;
+
+        assert_eq!(header.to_string(), "\
+Name:         SUPER MARIO 64
+Game code:    NSME
+Media:        Cartridge
+Region:       USA (NTSC)
+Version:      1.0
+CRC1:         0x635A2BFF
+CRC2:         0x8B022326
+Load address: 0x80246000");
+
+        header.name[0] = 0xb1;
+        let debug = format!("{:?}", header);
+        assert!(debug.contains("name: \"\\xb1UPER MARIO 64      \""), "{}", debug);
+        assert!(debug.contains("load_addr: 0x80246000"), "{}", debug);
+    }
+
     #[test]
-    fn calculate_fails_with_slice_to_small() {
-        let empty_array = [0u8;0];
-        assert_eq!(calculate_cart_checksum(&empty_array), Err(ChecksumError::NotLongEnough));
+    fn detects_all_swappings() {
+        assert_eq!(detect_swapping(&[0x80, 0x37, 0x12, 0x40]), Some(ByteSwapping::Native));
+        assert_eq!(detect_swapping(&[0x37, 0x80, 0x40, 0x12]), Some(ByteSwapping::U16LittleEndian));
+        assert_eq!(detect_swapping(&[0x40, 0x12, 0x37, 0x80]), Some(ByteSwapping::U32LittleEndian));
+        assert_eq!(detect_swapping(&[0x12, 0x40, 0x80, 0x37]), None);
+        assert_eq!(detect_swapping(&[0x80, 0x37]), None);
     }
 
     #[test]
-    fn checksum_test_vectors() {
-        let mut zero_vec: Vec<u8> = Vec::new();
-        for _ in 0..CHECKSUM_END {
-            zero_vec.push(0);
+    fn file_extensions_round_trip() {
+        for swapping in CANDIDATE_SWAPPINGS.iter() {
+            assert_eq!(ByteSwapping::from_file_extension(swapping.file_extension()), Some(*swapping));
         }
-        assert_eq!(calculate_cart_checksum(&zero_vec), Ok((0xF8CA4DDC, 0x303A4DDC)));
+        assert_eq!(ByteSwapping::from_file_extension(".V64"), Some(ByteSwapping::U16LittleEndian));
+        assert_eq!(ByteSwapping::from_file_extension("rom"), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn converts_files_by_extension() {
+        assert_eq!(ByteSwapping::from_path("roms/Game (USA).N64"), Some(ByteSwapping::U32LittleEndian));
+        assert_eq!(ByteSwapping::from_path("roms/game"), None);
+
+        let native = test_cart();
+        let dir = std::env::temp_dir();
+        let src = dir.join(format!("rs64-rom-convert-{}.z64", std::process::id()));
+        let dst = dir.join(format!("rs64-rom-convert-{}.v64", std::process::id()));
+        std::fs::write(&src, &native).unwrap();
 
-        for i in 0..CHECKSUM_END {
-            zero_vec[i] = 0xFF;
+        assert_eq!(convert_file(&src, &dst).unwrap(), (ByteSwapping::U16LittleEndian, native.len() as u64));
+        let mut expected = native.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut expected).unwrap();
+        assert_eq!(std::fs::read(&dst).unwrap(), expected);
+
+        assert!(matches!(convert_file(&dst, &dst), Err(Error::Io(_))));
+        assert!(matches!(convert_file(&src, dir.join("rs64-rom-convert.bin")), Err(Error::Io(_))));
+        assert_eq!(std::fs::read(&dst).unwrap(), expected);
+
+        std::fs::remove_file(&src).unwrap();
+        std::fs::remove_file(&dst).unwrap();
+    }
+
+    #[test]
+    fn swaps_between_all_orderings() {
+        let orderings = [
+            ByteSwapping::Native,
+            ByteSwapping::U16LittleEndian,
+            ByteSwapping::U32LittleEndian,
+        ];
+        let native = [0x80, 0x37, 0x12, 0x40, 0x01, 0x02, 0x03, 0x04];
+
+        for from in orderings.iter() {
+            for to in orderings.iter() {
+                let mut buffer = native;
+                swap_cart_to(*from, &mut buffer).unwrap();
+                assert_eq!(detect_swapping(&buffer), Some(*from));
+
+                swap_cart_to(*to, &mut buffer).unwrap();
+                assert_eq!(detect_swapping(&buffer), Some(*to));
+
+                swap_cart_to(ByteSwapping::Native, &mut buffer).unwrap();
+                assert_eq!(buffer, native);
+            }
         }
-        assert_eq!(calculate_cart_checksum(&zero_vec), Ok((0xF8C24DDC, 0xC1544DDC)));
 
-        for i in 0..CHECKSUM_END {
-            zero_vec[i] = 0x41;
+        let mut buffer = native;
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut buffer).unwrap();
+        assert_eq!(buffer, [0x40, 0x12, 0x37, 0x80, 0x04, 0x03, 0x02, 0x01]);
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut buffer).unwrap();
+        assert_eq!(buffer, [0x37, 0x80, 0x40, 0x12, 0x02, 0x01, 0x04, 0x03]);
+    }
+
+    #[test]
+    fn wide_swap_matches_bytewise_swap() {
+        let mut buffer: Vec<u8> = (0..54u8).collect();
+        let original = buffer.clone();
+
+        swap_to_native(ByteSwapping::U16LittleEndian, &mut buffer);
+        for (ii, pair) in buffer.chunks(2).enumerate() {
+            assert_eq!(pair, [original[ii * 2 + 1], original[ii * 2]]);
+        }
+        swap_to_native(ByteSwapping::U16LittleEndian, &mut buffer);
+        assert_eq!(buffer, original);
+
+        let mut buffer: Vec<u8> = (0..52u8).collect();
+        let original = buffer.clone();
+
+        swap_to_native(ByteSwapping::U32LittleEndian, &mut buffer);
+        for (ii, word) in buffer.chunks(4).enumerate() {
+            let base = ii * 4;
+            assert_eq!(word, [original[base + 3], original[base + 2], original[base + 1], original[base]]);
         }
-        assert_eq!(calculate_cart_checksum(&zero_vec), Ok((0xFDCF52E1, 0xCD5A4DDC)));
+    }
+
+    #[test]
+    fn word_swap_requires_word_multiple() {
+        let mut buffer = [0x80, 0x37, 0x12, 0x40, 0x01, 0x02];
+        assert!(swap_cart_to(ByteSwapping::U16LittleEndian, &mut buffer).is_ok());
+        assert!(swap_cart_to(ByteSwapping::U32LittleEndian, &mut buffer).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    // Hands out at most three bytes per read to exercise chunk refilling.
+    struct TrickleReader<'a>(&'a [u8]);
+
+    #[cfg(feature = "std")]
+    impl<'a> Read for TrickleReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let count = std::cmp::min(3, std::cmp::min(buf.len(), self.0.len()));
+            buf[..count].copy_from_slice(&self.0[..count]);
+            self.0 = &self.0[count..];
+            Ok(count)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn streaming_swap_matches_in_place_swap() {
+        let native = test_cart();
+
+        for swapping in CANDIDATE_SWAPPINGS.iter() {
+            let mut expected = native.clone();
+            swap_cart_to(*swapping, &mut expected).unwrap();
+
+            let mut output: Vec<u8> = Vec::new();
+            let written = swap_stream_to(*swapping, &mut TrickleReader(&native), &mut output).unwrap();
+            assert_eq!(written, native.len() as u64);
+            assert_eq!(output, expected);
+
+            let mut back: Vec<u8> = Vec::new();
+            convert_stream(*swapping, ByteSwapping::Native, &mut &output[..], &mut back).unwrap();
+            assert_eq!(back, native);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn streaming_swap_rejects_unknown_or_ragged_input() {
+        let mut output: Vec<u8> = Vec::new();
+        assert!(swap_stream_to(ByteSwapping::Native, &mut &[0u8; 8][..], &mut output).is_err());
+
+        let ragged = [0x80, 0x37, 0x12, 0x40, 0x00, 0x00];
+        assert!(swap_stream_to(ByteSwapping::U32LittleEndian, &mut &ragged[..], &mut output).is_err());
+    }
+
+    fn homebrew_header() -> [u8; HEADER_LEN as usize] {
+        let mut header = RomHeader::new();
+        header.cart_timing = 0x80371241;
+        header.load_addr = 0x80000400;
+        header.name.copy_from_slice(b"HOMEBREW            ");
+        header.to_bytes()
+    }
+
+    #[test]
+    fn guesses_swapping_without_magic() {
+        for swapping in CANDIDATE_SWAPPINGS.iter() {
+            let mut bytes = homebrew_header();
+            assert_eq!(detect_swapping(&bytes), None);
+            convert_swapping(ByteSwapping::Native, *swapping, &mut bytes).unwrap();
+
+            let guess = guess_swapping(&bytes).unwrap();
+            assert_eq!(guess.swapping, *swapping);
+            assert_eq!(guess.confidence, Confidence::High);
+
+            swap_cart_to(ByteSwapping::Native, &mut bytes).unwrap();
+            assert_eq!(bytes, homebrew_header());
+        }
+    }
+
+    #[test]
+    fn guess_prefers_magic() {
+        let bytes = RomHeader::new().to_bytes();
+        assert_eq!(guess_swapping(&bytes),
+                   Some(SwappingGuess { swapping: ByteSwapping::Native, confidence: Confidence::Certain }));
+    }
+
+    #[test]
+    fn guess_gives_up_on_ambiguous_data() {
+        assert_eq!(guess_swapping(&[0u8; HEADER_LEN as usize]), None);
+        assert_eq!(guess_swapping(&[0u8; 16]), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn header_round_trips_through_deserialize() {
+        let mut header = RomHeader::new();
+        header.load_addr = 0x80000400;
+        header.crc1 = 0x12345678;
+        header.crc2 = 0x9abcdef0;
+        header.name.copy_from_slice(b"TEST ROM            ");
+        header.manuf_id = 0x0000004e;
+        header.cart_id = 0x534d;
+        header.country_code = 0x4500;
+
+        let mut bytes: Vec<u8> = Vec::new();
+        header.serialize(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), HEADER_LEN as usize);
+
+        let parsed = RomHeader::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.cart_timing, DEFAULT_CART_TIMING);
+        assert_eq!(parsed.clock_rate, DEFAULT_CLOCK_RATE);
+        assert_eq!(parsed.load_addr, 0x80000400);
+        assert_eq!(parsed.crc1, 0x12345678);
+        assert_eq!(parsed.crc2, 0x9abcdef0);
+        assert_eq!(&parsed.name, b"TEST ROM            ");
+        assert_eq!(parsed.manuf_id, 0x0000004e);
+        assert_eq!(parsed.cart_id, 0x534d);
+        assert_eq!(parsed.country_code, 0x4500);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn header_round_trips_through_fixed_buffer() {
+        let mut header = RomHeader::new();
+        header.load_addr = 0x80000400;
+        header.crc1 = 0xdeadbeef;
+        header.rsvd_1c = 0x01020304;
+        header.name.copy_from_slice(b"FIXED SIZE HEADER   ");
+        header.cart_id = 0x4142;
+        header.country_code = 0x4a01;
+
+        let bytes = header.to_bytes();
+        let mut streamed: Vec<u8> = Vec::new();
+        header.serialize(&mut streamed).unwrap();
+        assert_eq!(&bytes[..], &streamed[..]);
+
+        let parsed = RomHeader::from(&bytes);
+        assert_eq!(parsed.to_bytes(), bytes);
+        assert_eq!(parsed.crc1, 0xdeadbeef);
+        assert_eq!(parsed.rsvd_1c, 0x01020304);
+        assert_eq!(parsed.cart_id, 0x4142);
+        assert_eq!(parsed.country_code, 0x4a01);
+
+        let from_slice = RomHeader::try_from(&streamed[..]).unwrap();
+        assert_eq!(from_slice.to_bytes(), bytes);
+        assert!(RomHeader::try_from(&streamed[..63]).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn arbitrary_headers_round_trip() {
+        use std::collections::HashSet;
+
+        // xorshift32, so every run checks the same few hundred headers.
+        let mut state = 0x2545_f491u32;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+
+        let mut seen = HashSet::new();
+        for _ in 0..256 {
+            let mut bytes = [0u8; HEADER_LEN as usize];
+            for word in bytes.chunks_exact_mut(4) {
+                word.copy_from_slice(&next().to_be_bytes());
+            }
+
+            let header = RomHeader::from_bytes(&bytes);
+            assert_eq!(header.to_bytes(), bytes);
+            assert_eq!(RomHeader::deserialize(&mut &header.serialize_to_vec()[..]).unwrap(), header);
+            assert_eq!(header.clone(), header);
+            assert!(seen.insert(header));
+            assert!(seen.contains(&RomHeader::from_bytes(&bytes)));
+        }
+
+        let mut changed = RomHeader::new();
+        changed.rsvd_34 = 1;
+        assert_ne!(changed, RomHeader::new());
+        changed.set_name("DEBUG").unwrap();
+        assert!(format!("{:?}", changed).contains("name: \"DEBUG               \""));
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary_headers_and_swappings() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let data: Vec<u8> = (0..4096u32).map(|ii| (ii.wrapping_mul(2654435761) >> 13) as u8).collect();
+        let mut u = Unstructured::new(&data);
+        while !u.is_empty() {
+            let header = RomHeader::arbitrary(&mut u).unwrap();
+            assert_eq!(RomHeader::from_bytes(&header.to_bytes()), header);
+
+            let swapping = ByteSwapping::arbitrary(&mut u).unwrap();
+            let mut bytes = header.to_bytes();
+            convert_swapping(ByteSwapping::Native, swapping, &mut bytes).unwrap();
+            convert_swapping(swapping, ByteSwapping::Native, &mut bytes).unwrap();
+            assert_eq!(bytes, header.to_bytes());
+        }
+    }
+
+    #[test]
+    fn builds_headers_at_compile_time() {
+        static HEADER: [u8; HEADER_LEN as usize] = RomHeader {
+            load_addr: 0x80000400,
+            name: *b"CONST HEADER        ",
+            country_code: 0x4500,
+            ..RomHeader::new()
+        }.to_bytes();
+        const PARSED: RomHeader = RomHeader::from_bytes(&HEADER_BYTES);
+        const HEADER_BYTES: [u8; HEADER_LEN as usize] = RomHeader::new().to_bytes();
+
+        let mut expected = RomHeader::new();
+        expected.load_addr = 0x80000400;
+        expected.set_name("CONST HEADER").unwrap();
+        expected.set_country(CountryCode::Usa);
+        assert_eq!(HEADER, expected.to_bytes());
+        assert_eq!(RomHeader::from_bytes(&HEADER), expected);
+        assert_eq!(PARSED, RomHeader::default());
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn logs_swapping_and_checksums() {
+        use std::sync::Mutex;
+
+        struct Capture(Mutex<Vec<String>>);
+
+        impl log::Log for Capture {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                self.0.lock().unwrap().push(record.args().to_string());
+            }
+
+            fn flush(&self) {}
+        }
+
+        static CAPTURE: Capture = Capture(Mutex::new(Vec::new()));
+        // Other tests log too, so only look for this one's messages.
+        let _ = log::set_logger(&CAPTURE);
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let mut rom = test_cart();
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut rom).unwrap();
+        fix_cart_checksum(&mut rom).unwrap();
+
+        let logged = CAPTURE.0.lock().unwrap();
+        let len = rom.len();
+        let converted = format!("converting {} bytes from Native to U32 Little Endian", len);
+        assert!(logged.contains(&converted));
+        assert!(logged.iter().any(|line| line.starts_with("wrote CIC-NUS-6102 CRCs")
+                                         && line.ends_with("U32 Little Endian order")));
+    }
+
+    #[test]
+    fn country_accessors_use_upper_byte() {
+        let mut header = RomHeader::new();
+        header.country_code = 0x4501;
+        assert_eq!(header.country(), CountryCode::Usa);
+        assert_eq!(header.country().tv_standard(), Some(TvStandard::Ntsc));
+
+        header.set_country(CountryCode::Europe);
+        assert_eq!(header.country_code, 0x5001);
+    }
+
+    #[test]
+    fn version_is_independent_of_country() {
+        let mut header = RomHeader::new();
+        header.set_country(CountryCode::Japan);
+        header.set_version(2);
+        assert_eq!(header.country(), CountryCode::Japan);
+        assert_eq!(header.version(), 2);
+
+        header.set_country(CountryCode::Usa);
+        assert_eq!(header.version(), 2);
+
+        let bytes = header.to_bytes();
+        assert_eq!(&bytes[0x3e..0x40], &[b'E', 2]);
+        let parsed = RomHeader::from(&bytes);
+        assert_eq!(parsed.country(), CountryCode::Usa);
+        assert_eq!(parsed.version(), 2);
+    }
+
+    #[test]
+    fn game_id_spans_header_fields() {
+        let mut header = RomHeader::new();
+        header.set_version(1);
+        header.set_game_id(&"NSME".parse().unwrap());
+        assert_eq!(header.manuf_id, 0x0000004e);
+        assert_eq!(header.cart_id, 0x534d);
+        assert_eq!(header.country_code, 0x4501);
+        assert_eq!(&header.to_bytes()[0x3b..0x3f], b"NSME");
+        assert_eq!(header.game_id().to_string(), "NSME");
+    }
+
+    #[test]
+    fn set_name_pads_and_validates() {
+        let mut header = RomHeader::new();
+        header.set_name("HOMEBREW").unwrap();
+        assert_eq!(&header.name, b"HOMEBREW            ");
+        #[cfg(feature = "alloc")]
+        assert_eq!(header.name_str(), "HOMEBREW");
+
+        assert_eq!(header.set_name("THIS NAME IS FAR TOO LONG"), Err(NameError::TooLong));
+        assert_eq!(&header.name, b"HOMEBREW            ");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn serialize_variants_agree() {
+        let mut header = RomHeader::new();
+        header.load_addr = 0x80000400;
+        header.set_name("SERIALIZE").unwrap();
+
+        let mut generic: Vec<u8> = Vec::new();
+        header.serialize(&mut generic).unwrap();
+
+        let mut dynamic: Vec<u8> = Vec::new();
+        header.serialize(&mut dynamic as &mut dyn Write).unwrap();
+
+        let mut fixed = [0u8; HEADER_LEN as usize];
+        header.serialize_into(&mut fixed);
+
+        assert_eq!(generic, header.serialize_to_vec());
+        assert_eq!(dynamic, generic);
+        assert_eq!(&fixed[..], &generic[..]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn metadata_round_trips_through_serde() {
+        let mut header = RomHeader::new();
+        header.set_name("SERDE").unwrap();
+        header.set_game_id(&"NSME".parse().unwrap());
+
+        let json = serde_json::to_string(&header).unwrap();
+        let parsed: RomHeader = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, header);
+
+        let report = verify_cart_checksum(&test_cart()).unwrap();
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<ChecksumReport>(&json).unwrap(), report);
+
+        let swapping: ByteSwapping = serde_json::from_str("\"U16LittleEndian\"").unwrap();
+        assert_eq!(swapping, ByteSwapping::U16LittleEndian);
+        assert_eq!(serde_json::to_string(&header.country()).unwrap(), "\"Usa\"");
+    }
+
+    #[test]
+    fn deserialize_fails_on_short_buffer() {
+        let short = [0u8; 10];
+        assert!(RomHeader::from_slice(&short).is_err());
     }
 }
\ No newline at end of file
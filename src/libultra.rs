@@ -0,0 +1,192 @@
+//! Which libultra a game was linked against.
+//!
+//! Later SDKs record it in the header: the low half of the `release` word is
+//! the version as major * 10 + minor, then the revision letter, so 2.0L is
+//! `0x0000144c`.  Older games leave it zero, but some still carry the SCCS
+//! `@(#)` identification strings that the SDK's objects were built with,
+//! which [`scan_libultra`] picks out along with the build date they give.
+
+use core::fmt;
+
+use crate::{Error, RomHeader, LOAD_START};
+
+// What strings are short; anything longer is binary that happens to match.
+const MAX_IDENT_LEN: usize = 128;
+
+/// A libultra release such as 2.0L.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct LibultraVersion {
+    pub major: u8,
+    pub minor: u8,
+    /// The revision letter, as ASCII.
+    pub revision: u8,
+}
+
+impl LibultraVersion {
+    /// Parses `2.0L` style text.
+    pub fn parse(text: &str) -> Option<LibultraVersion> {
+        match *text.as_bytes() {
+            [major, b'.', minor, revision] if major.is_ascii_digit() && minor.is_ascii_digit()
+                                              && revision.is_ascii_uppercase() => {
+                Some(LibultraVersion { major: major - b'0', minor: minor - b'0', revision })
+            },
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LibultraVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}{}", self.major, self.minor, self.revision as char)
+    }
+}
+
+impl RomHeader {
+    /// The libultra version the header records, if it records one.
+    pub fn libultra_version(&self) -> Option<LibultraVersion> {
+        let [_, _, number, revision] = self.release.to_be_bytes();
+        if number == 0 || number >= 100 || !revision.is_ascii_uppercase() {
+            return None;
+        }
+        Some(LibultraVersion { major: number / 10, minor: number % 10, revision })
+    }
+
+    /// Records `version` in the low half of `release`, or clears it, leaving
+    /// the unused high half alone.
+    pub fn set_libultra_version(&mut self, version: Option<LibultraVersion>) -> Result<(), Error> {
+        let low = match version {
+            None => 0,
+            Some(version) if version.major > 9 || version.minor > 9 || !version.revision.is_ascii_uppercase() => {
+                return Err(Error::InvalidHeader("libultra versions are a digit, a digit and a capital letter"));
+            },
+            Some(version) => u32::from(version.major * 10 + version.minor) << 8 | u32::from(version.revision),
+        };
+        self.release = (self.release & 0xffff_0000) | low;
+        Ok(())
+    }
+}
+
+/// A libultra identification string found in a payload.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LibultraIdent<'a> {
+    /// Offset of the string in the image.
+    pub offset: usize,
+    /// The string, without its `@(#)` prefix.
+    pub text: &'a str,
+    pub version: Option<LibultraVersion>,
+    /// The first date in the string, as written, such as `1998/06/04`.
+    pub build_date: Option<&'a str>,
+}
+
+/// Finds the first `@(#)` string in the payload of a native byte order
+/// image that mentions libultra.
+pub fn scan_libultra(rom: &[u8]) -> Option<LibultraIdent<'_>> {
+    let payload = rom.get(LOAD_START..)?;
+
+    let mut start = 0;
+    while let Some(found) = payload[start..].windows(4).position(|window| window == b"@(#)") {
+        let offset = start + found + 4;
+        start = offset;
+
+        let len = payload[offset..].iter().take(MAX_IDENT_LEN + 1)
+            .position(|byte| !(0x20..0x7f).contains(byte))
+            .unwrap_or(payload.len() - offset);
+        if len > MAX_IDENT_LEN {
+            continue;
+        }
+
+        let text = match core::str::from_utf8(&payload[offset..offset + len]) {
+            Ok(text) if contains_ignore_case(text, "libultra") => text,
+            _ => continue,
+        };
+
+        let tokens = || text.split(|c: char| c.is_ascii_whitespace() || c == ',' || c == '(' || c == ')');
+        return Some(LibultraIdent {
+            offset: LOAD_START + offset,
+            text,
+            version: tokens().find_map(LibultraVersion::parse),
+            build_date: tokens().find(|token| is_date(token)),
+        });
+    }
+    None
+}
+
+// `1998/06/04`, `98-06-04` or `06/04/98`: three runs of digits, split by
+// the same separator.
+fn is_date(token: &str) -> bool {
+    let separator = match token.find(['/', '-']) {
+        Some(at) => token.as_bytes()[at] as char,
+        None => return false,
+    };
+    let mut fields = 0;
+    for field in token.split(separator) {
+        if !(2..=4).contains(&field.len()) || !field.bytes().all(|byte| byte.is_ascii_digit()) {
+            return false;
+        }
+        fields += 1;
+    }
+    fields == 3
+}
+
+fn contains_ignore_case(text: &str, needle: &str) -> bool {
+    text.as_bytes().windows(needle.len()).any(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+
+    #[test]
+    fn reads_the_header_version() {
+        let mut header = RomHeader::new();
+        assert_eq!(header.libultra_version(), None);
+
+        header.release = 0x0000144c;
+        let version = header.libultra_version().unwrap();
+        assert_eq!(version, LibultraVersion { major: 2, minor: 0, revision: b'L' });
+        assert_eq!(version.to_string(), "2.0L");
+        assert!(version > LibultraVersion::parse("2.0D").unwrap());
+    }
+
+    #[test]
+    fn writes_the_header_version() {
+        let mut header = RomHeader::new();
+        header.release = 0x1234_0000;
+        header.set_libultra_version(LibultraVersion::parse("2.0I")).unwrap();
+        assert_eq!(header.release, 0x1234_1449);
+        assert_eq!(header.libultra_version(), LibultraVersion::parse("2.0I"));
+
+        let bad = LibultraVersion { major: 10, minor: 0, revision: b'A' };
+        assert!(matches!(header.set_libultra_version(Some(bad)), Err(Error::InvalidHeader(_))));
+        header.set_libultra_version(None).unwrap();
+        assert_eq!((header.release, header.libultra_version()), (0x1234_0000, None));
+    }
+
+    #[test]
+    fn scans_identification_strings() {
+        let mut rom = test_cart();
+        assert_eq!(scan_libultra(&rom), None);
+
+        let decoy = b"@(#)some other library 1.2\0";
+        let ident = b"@(#)libultra 2.0I (1997/11/06 10:00:00)\0";
+        rom[LOAD_START + 0x100..LOAD_START + 0x100 + decoy.len()].copy_from_slice(decoy);
+        rom[LOAD_START + 0x200..LOAD_START + 0x200 + ident.len()].copy_from_slice(ident);
+
+        let found = scan_libultra(&rom).unwrap();
+        assert_eq!(found.offset, LOAD_START + 0x204);
+        assert_eq!(found.text, "libultra 2.0I (1997/11/06 10:00:00)");
+        assert_eq!(found.version.unwrap().to_string(), "2.0I");
+        assert_eq!(found.build_date, Some("1997/11/06"));
+    }
+
+    #[test]
+    fn recognizes_dates() {
+        assert!(is_date("98-06-04"));
+        assert!(is_date("06/04/98"));
+        assert!(!is_date("2.0I"));
+        assert!(!is_date("1998/06"));
+        assert!(!is_date("1998/06-04"));
+    }
+}
@@ -0,0 +1,140 @@
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+use core::error;
+use core::fmt;
+
+use crate::HEADER_NAME_LEN;
+
+/// Why a string can't be stored as the internal name.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NameError {
+    TooLong,
+    InvalidCharacter(char),
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NameError::TooLong => write!(f, "name longer than {} bytes", HEADER_NAME_LEN),
+            NameError::InvalidCharacter(c) => write!(f, "{:?} has no JIS X 0201 encoding", c),
+        }
+    }
+}
+
+impl error::Error for NameError {}
+
+// JIS X 0201 is ASCII with yen and overline in place of backslash and tilde,
+// plus halfwidth katakana in 0xA1..=0xDF.
+fn encode_char(c: char) -> Option<u8> {
+    match c {
+        ' '..='~' => Some(c as u8),
+        '\u{a5}' => Some(0x5c),
+        '\u{203e}' => Some(0x7e),
+        '\u{ff61}'..='\u{ff9f}' => Some((c as u32 - 0xff61 + 0xa1) as u8),
+        _ => None,
+    }
+}
+
+/// Encodes `name` as JIS X 0201, padded out with spaces.
+pub(crate) fn encode_name(name: &str) -> Result<[u8; HEADER_NAME_LEN], NameError> {
+    let mut encoded = [b' '; HEADER_NAME_LEN];
+
+    for (ii, c) in name.chars().enumerate() {
+        let byte = encode_char(c).ok_or(NameError::InvalidCharacter(c))?;
+        if ii == HEADER_NAME_LEN {
+            return Err(NameError::TooLong);
+        }
+        encoded[ii] = byte;
+    }
+
+    Ok(encoded)
+}
+
+#[cfg(feature = "alloc")]
+fn trim_name(raw: &[u8]) -> &[u8] {
+    let len = raw.iter()
+        .rposition(|byte| *byte != b' ' && *byte != 0)
+        .map_or(0, |last| last + 1);
+    &raw[..len]
+}
+
+/// Decodes the internal name with trailing spaces and NULs removed.  Bytes
+/// outside ASCII and halfwidth katakana become U+FFFD unless the `encoding`
+/// feature is enabled, in which case the name is decoded as Shift-JIS.
+#[cfg(feature = "alloc")]
+pub(crate) fn decode_name(raw: &[u8]) -> Cow<'_, str> {
+    let trimmed = trim_name(raw);
+
+    if let Ok(ascii) = core::str::from_utf8(trimmed) {
+        if trimmed.is_ascii() {
+            return Cow::Borrowed(ascii);
+        }
+    }
+
+    decode_non_ascii(trimmed)
+}
+
+#[cfg(feature = "encoding")]
+fn decode_non_ascii(trimmed: &[u8]) -> Cow<'_, str> {
+    let (decoded, _) = encoding_rs::SHIFT_JIS.decode_without_bom_handling(trimmed);
+    Cow::Owned(decoded.into_owned())
+}
+
+#[cfg(all(feature = "alloc", not(feature = "encoding")))]
+fn decode_non_ascii(trimmed: &[u8]) -> Cow<'_, str> {
+    Cow::Owned(trimmed.iter()
+        .map(|byte| match *byte {
+            0x00..=0x7f => *byte as char,
+            0xa1..=0xdf => core::char::from_u32(u32::from(*byte) - 0xa1 + 0xff61).unwrap_or('\u{fffd}'),
+            _ => '\u{fffd}',
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pads_with_spaces() {
+        assert_eq!(&encode_name("SUPER MARIO 64").unwrap(), b"SUPER MARIO 64      ");
+        assert_eq!(&encode_name("").unwrap(), &[b' '; HEADER_NAME_LEN]);
+        assert_eq!(&encode_name("01234567890123456789").unwrap(), b"01234567890123456789");
+    }
+
+    #[test]
+    fn encodes_halfwidth_katakana() {
+        let encoded = encode_name("\u{ff7f}\u{ff9e}\u{ff99}\u{ff80}\u{a5}").unwrap();
+        assert_eq!(&encoded[..5], &[0xbf, 0xde, 0xd9, 0xc0, 0x5c]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn decodes_trimmed_names() {
+        assert_eq!(decode_name(b"SUPER MARIO 64      "), "SUPER MARIO 64");
+        assert_eq!(decode_name(b"ZELDA\0\0 \0"), "ZELDA");
+        assert_eq!(decode_name(&[b' '; HEADER_NAME_LEN]), "");
+        assert!(matches!(decode_name(b"ASCII   "), Cow::Borrowed(_)));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn decodes_halfwidth_katakana() {
+        let raw = encode_name("\u{ff7f}\u{ff9e}\u{ff99}\u{ff80}").unwrap();
+        assert_eq!(decode_name(&raw), "\u{ff7f}\u{ff9e}\u{ff99}\u{ff80}");
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn decodes_shift_jis() {
+        // "ゼルダ" in Shift-JIS.
+        assert_eq!(decode_name(&[0x83, 0x5b, 0x83, 0x8b, 0x83, 0x5f, b' ']), "\u{30bc}\u{30eb}\u{30c0}");
+    }
+
+    #[test]
+    fn rejects_bad_names() {
+        assert_eq!(encode_name("012345678901234567890"), Err(NameError::TooLong));
+        assert_eq!(encode_name("ZELDA\u{3042}"), Err(NameError::InvalidCharacter('\u{3042}')));
+        assert_eq!(encode_name("TAB\t"), Err(NameError::InvalidCharacter('\t')));
+    }
+}
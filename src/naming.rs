@@ -0,0 +1,199 @@
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::{ByteSwapping, CountryCode, DatEntry, RomInfo};
+
+/// Which renaming convention [`canonical_name`] follows.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NamingStyle {
+    /// "Title (USA) (Rev A)".
+    NoIntro,
+    /// GoodN64's "Title (U) (V1.1)".
+    GoodTools,
+}
+
+/// How a No-Intro style name spells revisions the database didn't name.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RevisionStyle {
+    /// "Rev A", "Rev B", ...
+    Letter,
+    /// "Rev 1", "Rev 2", ...
+    Number,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NamingOptions {
+    pub style: NamingStyle,
+    pub revision: RevisionStyle,
+    /// The byte order whose extension to use, or `None` for the image's own.
+    pub swapping: Option<ByteSwapping>,
+}
+
+impl Default for NamingOptions {
+    fn default() -> Self {
+        NamingOptions {
+            style: NamingStyle::NoIntro,
+            revision: RevisionStyle::Letter,
+            swapping: None,
+        }
+    }
+}
+
+/// A normalized filename for an image.  A database hit supplies the title,
+/// and for No-Intro names the region and revision tags; otherwise they're
+/// taken from the header.
+pub fn canonical_name(info: &RomInfo, entry: Option<&DatEntry>, options: &NamingOptions) -> String {
+    let mut name = String::new();
+
+    match entry {
+        Some(entry) => push_sanitized(&mut name, &entry.title),
+        None => push_sanitized(&mut name, info.header.name_str().trim()),
+    }
+    if name.is_empty() {
+        name.push_str("Unknown");
+    }
+
+    let version = info.header.version();
+    match options.style {
+        NamingStyle::NoIntro => {
+            match entry.and_then(|entry| entry.region.as_deref()) {
+                Some(region) => write!(name, " ({})", region),
+                None => write!(name, " ({})", no_intro_region(info.country)),
+            }.unwrap();
+
+            match (entry.and_then(|entry| entry.revision.as_deref()), options.revision) {
+                (Some(revision), _) => write!(name, " ({})", revision).unwrap(),
+                (None, _) if version == 0 => {},
+                (None, RevisionStyle::Letter) if version <= 26 => {
+                    write!(name, " (Rev {})", (b'A' + version - 1) as char).unwrap()
+                },
+                (None, _) => write!(name, " (Rev {})", version).unwrap(),
+            }
+        },
+        NamingStyle::GoodTools => {
+            write!(name, " ({})", good_tools_region(info.country)).unwrap();
+            if version != 0 {
+                write!(name, " (V1.{})", version).unwrap();
+            }
+        },
+    }
+
+    name.push('.');
+    name.push_str(options.swapping.unwrap_or(info.swapping.swapping).file_extension());
+    name
+}
+
+// Drops or replaces characters that aren't allowed in filenames on common
+// filesystems.
+fn push_sanitized(name: &mut String, title: &str) {
+    for c in title.chars() {
+        match c {
+            ':' => name.push_str(" -"),
+            '/' | '\\' | '|' => name.push('-'),
+            '"' => name.push('\''),
+            '<' | '>' | '*' | '?' => {},
+            c if c.is_control() => {},
+            c => name.push(c),
+        }
+    }
+}
+
+fn no_intro_region(country: CountryCode) -> &'static str {
+    match country {
+        CountryCode::Beta => "Beta",
+        CountryCode::Asia => "Asia",
+        CountryCode::Brazil => "Brazil",
+        CountryCode::China => "China",
+        CountryCode::Germany => "Germany",
+        CountryCode::Usa | CountryCode::GatewayNtsc => "USA",
+        CountryCode::France => "France",
+        CountryCode::Netherlands => "Netherlands",
+        CountryCode::Italy => "Italy",
+        CountryCode::Japan => "Japan",
+        CountryCode::Korea => "Korea",
+        CountryCode::Canada => "Canada",
+        CountryCode::Europe | CountryCode::GatewayPal | CountryCode::EuropeOther(_) => "Europe",
+        CountryCode::Spain => "Spain",
+        CountryCode::Australia => "Australia",
+        CountryCode::Scandinavia => "Scandinavia",
+        CountryCode::Unknown(_) => "Unknown",
+    }
+}
+
+fn good_tools_region(country: CountryCode) -> &'static str {
+    match country {
+        CountryCode::Beta => "Beta",
+        CountryCode::Asia => "As",
+        CountryCode::Brazil => "B",
+        CountryCode::China => "C",
+        CountryCode::Germany => "G",
+        CountryCode::Usa | CountryCode::GatewayNtsc => "U",
+        CountryCode::France => "F",
+        CountryCode::Netherlands => "H",
+        CountryCode::Italy => "I",
+        CountryCode::Japan => "J",
+        CountryCode::Korea => "K",
+        CountryCode::Canada => "Can",
+        CountryCode::Europe | CountryCode::GatewayPal | CountryCode::EuropeOther(_) => "E",
+        CountryCode::Spain => "S",
+        CountryCode::Australia => "A",
+        CountryCode::Scandinavia => "Sw",
+        CountryCode::Unknown(_) => "Unk",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+    use crate::{analyze, swap_cart_to, RomHeader, HEADER_END, HEADER_START};
+
+    fn info_for(name: &str, game_id: &str, version: u8) -> RomInfo {
+        let mut rom = test_cart();
+        let mut header = RomHeader::new();
+        header.set_name(name).unwrap();
+        header.set_game_id(&game_id.parse().unwrap());
+        header.set_version(version);
+        rom[HEADER_START..HEADER_END].copy_from_slice(&header.to_bytes());
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut rom).unwrap();
+        analyze(&rom).unwrap()
+    }
+
+    #[test]
+    fn names_from_header() {
+        let info = info_for("ZELDA MAJORA'S MASK", "NZSE", 1);
+
+        assert_eq!(canonical_name(&info, None, &NamingOptions::default()), "ZELDA MAJORA'S MASK (USA) (Rev A).v64");
+
+        let options = NamingOptions { revision: RevisionStyle::Number, swapping: Some(ByteSwapping::Native), ..Default::default() };
+        assert_eq!(canonical_name(&info, None, &options), "ZELDA MAJORA'S MASK (USA) (Rev 1).z64");
+
+        let options = NamingOptions { style: NamingStyle::GoodTools, ..Default::default() };
+        assert_eq!(canonical_name(&info, None, &options), "ZELDA MAJORA'S MASK (U) (V1.1).v64");
+
+        let info = info_for("F-ZERO X", "NFZJ", 0);
+        assert_eq!(canonical_name(&info, None, &NamingOptions::default()), "F-ZERO X (Japan).v64");
+    }
+
+    #[test]
+    fn prefers_database_entry() {
+        let info = info_for("ZELDA MAJORA'S MASK", "NZSE", 0);
+        let entry = DatEntry {
+            name: String::from("Legend of Zelda, The: Majora's Mask (USA) (Rev 1)"),
+            title: String::from("Legend of Zelda, The: Majora's Mask"),
+            region: Some(String::from("USA")),
+            revision: Some(String::from("Rev 1")),
+            size: 0,
+            crc32: 0,
+            md5: None,
+            sha1: None,
+        };
+
+        assert_eq!(canonical_name(&info, Some(&entry), &NamingOptions::default()),
+                   "Legend of Zelda, The - Majora's Mask (USA) (Rev 1).v64");
+
+        let options = NamingOptions { style: NamingStyle::GoodTools, ..Default::default() };
+        assert_eq!(canonical_name(&info, Some(&entry), &options), "Legend of Zelda, The - Majora's Mask (U).v64");
+    }
+}
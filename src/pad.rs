@@ -0,0 +1,166 @@
+use alloc::vec::Vec;
+
+use crate::{fix_cart_checksum, Error};
+
+const MIB: usize = 1024 * 1024;
+
+/// Sizes mask ROMs were manufactured in, smallest first.
+pub const CART_SIZES: [usize; 5] = [8 * MIB, 12 * MIB, 16 * MIB, 32 * MIB, 64 * MIB];
+
+/// The most ROM a SummerCart64 maps: the 64 MiB a cart's address space
+/// has room for, then 14 MiB of extended ROM past it.
+pub const SC64_MAX_ROM_LEN: usize = 78 * MIB;
+
+/// How an image's length compares with the carts it could be written to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CartSizeClass {
+    /// Fits a mask ROM of this size; see [`standard_cart_size`].
+    Retail(usize),
+    /// Larger than any mask ROM, but fits a SummerCart64 with its extended
+    /// ROM enabled.
+    Extended,
+    /// Larger than any cart or flashcart maps.
+    TooLarge,
+}
+
+/// Classifies an image by length; see [`CartSizeClass`].
+pub fn classify_cart_size(len: usize) -> CartSizeClass {
+    match standard_cart_size(len) {
+        Some(size) => CartSizeClass::Retail(size),
+        None if len <= SC64_MAX_ROM_LEN => CartSizeClass::Extended,
+        None => CartSizeClass::TooLarge,
+    }
+}
+
+/// What to pad an image with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PadFill {
+    Byte(u8),
+    /// Repeat the image from its start, the way the SDK's makemask does so
+    /// reads past the end look like address mirroring.
+    Repeat,
+}
+
+/// The smallest standard cart size that holds `len` bytes.
+pub fn standard_cart_size(len: usize) -> Option<usize> {
+    CART_SIZES.iter().cloned().find(|size| *size >= len)
+}
+
+/// Pads an image (in any byte order) to the smallest standard cart size that
+/// holds it and fixes its CRCs.
+pub fn pad_rom(rom: &mut Vec<u8>, fill: PadFill) -> Result<(u32, u32), Error> {
+    let size = standard_cart_size(rom.len()).ok_or(Error::InvalidArgument("image larger than the largest cart"))?;
+    pad_rom_to(rom, size, fill)
+}
+
+/// Pads an image (in any byte order) to `size` bytes and fixes its CRCs.  If
+/// the CRCs can't be fixed the padding is taken off again.
+pub fn pad_rom_to(rom: &mut Vec<u8>, size: usize, fill: PadFill) -> Result<(u32, u32), Error> {
+    if size < rom.len() {
+        return Err(Error::InvalidArgument("image larger than the target size"));
+    }
+
+    let original_len = rom.len();
+    match fill {
+        PadFill::Byte(byte) => rom.resize(size, byte),
+        PadFill::Repeat => {
+            if rom.is_empty() {
+                return Err(Error::InvalidArgument("nothing to repeat"));
+            }
+            let len = rom.len();
+            rom.reserve(size - len);
+            while rom.len() < size {
+                let count = core::cmp::min(len, size - rom.len());
+                rom.extend_from_within(..count);
+            }
+        },
+    }
+
+    fix_cart_checksum(rom).map_err(|err| {
+        rom.truncate(original_len);
+        err.into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+    use crate::{swap_cart_to, verify_cart_checksum, ByteSwapping};
+
+    #[test]
+    fn picks_standard_sizes() {
+        assert_eq!(standard_cart_size(0), Some(8 * MIB));
+        assert_eq!(standard_cart_size(8 * MIB + 1), Some(12 * MIB));
+        assert_eq!(standard_cart_size(64 * MIB), Some(64 * MIB));
+        assert_eq!(standard_cart_size(64 * MIB + 1), None);
+    }
+
+    #[test]
+    fn classifies_oversized_images() {
+        assert_eq!(classify_cart_size(12 * MIB), CartSizeClass::Retail(12 * MIB));
+        assert_eq!(classify_cart_size(64 * MIB + 4), CartSizeClass::Extended);
+        assert_eq!(classify_cart_size(SC64_MAX_ROM_LEN), CartSizeClass::Extended);
+        assert_eq!(classify_cart_size(SC64_MAX_ROM_LEN + 4), CartSizeClass::TooLarge);
+    }
+
+    #[test]
+    fn handles_images_past_64_mib() {
+        let mut rom = test_cart();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut rom).unwrap();
+        pad_rom_to(&mut rom, 72 * MIB, PadFill::Repeat).unwrap();
+        assert!(verify_cart_checksum(&rom).unwrap().is_valid());
+        assert!(matches!(pad_rom(&mut rom, PadFill::Repeat), Err(Error::InvalidArgument(_))));
+
+        swap_cart_to(ByteSwapping::Native, &mut rom).unwrap();
+        assert_eq!(rom.len(), 72 * MIB);
+        let repeat = 70 * MIB / test_cart().len() * test_cart().len();
+        assert_eq!(rom[repeat + 0x40..repeat + 0x1000], rom[0x40..0x1000]);
+    }
+
+    #[test]
+    fn pads_with_a_fill_byte() {
+        let mut rom = test_cart();
+        let len = rom.len();
+        pad_rom(&mut rom, PadFill::Byte(0xff)).unwrap();
+
+        assert_eq!(rom.len(), 8 * MIB);
+        assert!(rom[len..].iter().all(|byte| *byte == 0xff));
+        assert!(verify_cart_checksum(&rom).unwrap().is_valid());
+    }
+
+    #[test]
+    fn repeats_like_makemask() {
+        let original = test_cart();
+        let mut rom = original.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut rom).unwrap();
+        let swapped = rom.clone();
+        pad_rom_to(&mut rom, 3 * original.len() + 2, PadFill::Repeat).unwrap();
+
+        assert_eq!(rom.len(), 3 * original.len() + 2);
+        assert_eq!(&rom[original.len()..2 * original.len()], &swapped[..]);
+        assert_eq!(&rom[3 * original.len()..], &swapped[..2]);
+        assert!(verify_cart_checksum(&rom).unwrap().is_valid());
+    }
+
+    #[test]
+    fn rejects_shrinking() {
+        let mut rom = test_cart();
+        assert!(matches!(pad_rom_to(&mut rom, 16, PadFill::Byte(0)), Err(Error::InvalidArgument(_))));
+        assert!(matches!(pad_rom_to(&mut Vec::new(), 16, PadFill::Repeat), Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn leaves_the_image_alone_when_the_crcs_cannot_be_fixed() {
+        let mut headerless = vec![0u8; 16];
+        assert!(matches!(pad_rom_to(&mut headerless, 32, PadFill::Byte(0)), Err(Error::Checksum(_))));
+        assert_eq!(headerless, [0u8; 16]);
+
+        let mut short = test_cart();
+        short.truncate(0x2000);
+        assert!(matches!(pad_rom_to(&mut short, 0x4000, PadFill::Repeat), Err(Error::Checksum(_))));
+        assert_eq!(short, test_cart()[..0x2000]);
+    }
+}
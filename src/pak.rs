@@ -0,0 +1,372 @@
+//! Controller Pak filesystems.
+//!
+//! A pak is 128 pages of 256 bytes.  Page 0 holds the label and the ID
+//! blocks, pages 1 and 2 a table with one entry per page linking each page
+//! of a note to the next, and pages 3 and 4 the 16 note entries.  The other
+//! 123 pages hold note data.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::Error;
+
+pub const PAK_LEN: usize = 32 * 1024;
+pub const PAGE_LEN: usize = 256;
+pub const PAGES: usize = PAK_LEN / PAGE_LEN;
+/// The first page notes can use.
+pub const FIRST_DATA_PAGE: usize = 5;
+pub const MAX_NOTES: usize = 16;
+
+const INDEX_TABLE: usize = PAGE_LEN;
+const INDEX_BACKUP: usize = 2 * PAGE_LEN;
+const NOTE_TABLE: usize = 3 * PAGE_LEN;
+const NOTE_ENTRY_LEN: usize = 32;
+
+/// Index table values that aren't the next page.
+const END_OF_NOTE: u16 = 0x0001;
+const FREE_PAGE: u16 = 0x0003;
+
+/// Offsets of the four copies of the ID block in page 0.
+const ID_BLOCKS: [usize; 4] = [0x20, 0x60, 0x80, 0xc0];
+const ID_BLOCK_LEN: usize = 32;
+
+/// The characters of the N64 font below its kana, by code.
+const FONT: &[u8; 66] = b"\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0 0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ!\"#'*+,-./:=?@";
+
+/// A note's entry in the note table.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Note {
+    /// The game ID of the game that wrote it, such as `NSME`.
+    pub game_code: [u8; 4],
+    pub publisher_code: [u8; 2],
+    /// The name shown in the pak manager, in the N64 font.
+    pub name: [u8; 16],
+    /// A suffix shown after the name, also in the N64 font.  Games use only
+    /// the first character, if any.
+    pub extension: [u8; 4],
+}
+
+impl Note {
+    /// A note entry with `name` encoded in the N64 font.
+    pub fn new(game_code: [u8; 4], publisher_code: [u8; 2], name: &str) -> Result<Note, Error> {
+        let mut encoded = [0u8; 16];
+        encode_text(name, &mut encoded)?;
+        Ok(Note { game_code, publisher_code, name: encoded, extension: [0; 4] })
+    }
+
+    /// The name decoded from the N64 font, with kana and other codes this
+    /// crate doesn't map replaced by U+FFFD.
+    pub fn name_str(&self) -> String {
+        decode_text(&self.name)
+    }
+
+    pub fn extension_str(&self) -> String {
+        decode_text(&self.extension)
+    }
+}
+
+/// A Controller Pak image with its page chains checked.
+#[derive(Clone, Debug)]
+pub struct ControllerPak {
+    data: Vec<u8>,
+}
+
+impl ControllerPak {
+    /// Wraps a pak image.  The index table is checked against its checksum,
+    /// falling back to the backup copy if the primary is damaged.
+    pub fn from_bytes(data: Vec<u8>) -> Result<ControllerPak, Error> {
+        if data.len() != PAK_LEN {
+            return Err(Error::InvalidSave("controller pak images are 32 KiB"));
+        }
+
+        let mut pak = ControllerPak { data };
+        if !pak.index_valid(INDEX_TABLE) {
+            if !pak.index_valid(INDEX_BACKUP) {
+                return Err(Error::InvalidSave("both index tables fail their checksum"));
+            }
+            pak.data.copy_within(INDEX_BACKUP..INDEX_BACKUP + PAGE_LEN, INDEX_TABLE);
+        }
+        Ok(pak)
+    }
+
+    /// A freshly formatted pak, with no notes and every data page free.
+    pub fn format() -> ControllerPak {
+        let mut data = alloc::vec![0u8; PAK_LEN];
+
+        let mut id_block = [0u8; ID_BLOCK_LEN];
+        // Device ID and bank count: a standard single bank pak.
+        id_block[0x19] = 0x01;
+        id_block[0x1a] = 0x01;
+        let sum = id_block[..0x1c].chunks_exact(2)
+            .fold(0u16, |sum, word| sum.wrapping_add(u16::from_be_bytes([word[0], word[1]])));
+        id_block[0x1c..0x1e].copy_from_slice(&sum.to_be_bytes());
+        id_block[0x1e..0x20].copy_from_slice(&0xfff2u16.wrapping_sub(sum).to_be_bytes());
+        for offset in ID_BLOCKS.iter() {
+            data[*offset..*offset + ID_BLOCK_LEN].copy_from_slice(&id_block);
+        }
+
+        let mut pak = ControllerPak { data };
+        for page in FIRST_DATA_PAGE..PAGES {
+            pak.set_link(page, FREE_PAGE);
+        }
+        pak.write_index();
+        pak
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Data pages not used by any note.
+    pub fn free_pages(&self) -> usize {
+        (FIRST_DATA_PAGE..PAGES).filter(|page| self.link(*page) == FREE_PAGE).count()
+    }
+
+    /// Every note in use, with its slot in the note table.
+    pub fn notes(&self) -> impl Iterator<Item = (usize, Note)> + '_ {
+        (0..MAX_NOTES).filter_map(move |slot| self.note(slot).map(|note| (slot, note)))
+    }
+
+    /// The note in `slot`, if it's in use.
+    pub fn note(&self, slot: usize) -> Option<Note> {
+        self.start_page(slot)?;
+        let entry = self.entry(slot);
+        Some(Note {
+            game_code: array(&entry[0x00..0x04]),
+            publisher_code: array(&entry[0x04..0x06]),
+            extension: array(&entry[0x0c..0x10]),
+            name: array(&entry[0x10..0x20]),
+        })
+    }
+
+    /// The pages the note in `slot` occupies, in order.
+    pub fn note_pages(&self, slot: usize) -> Result<Vec<usize>, Error> {
+        if slot >= MAX_NOTES {
+            return Err(Error::InvalidSave("note slot is past the note table"));
+        }
+        let mut page = self.start_page(slot).ok_or(Error::InvalidSave("note slot is empty"))?;
+        let mut pages = Vec::new();
+
+        loop {
+            if pages.len() == PAGES - FIRST_DATA_PAGE {
+                return Err(Error::InvalidSave("note's page chain loops"));
+            }
+            pages.push(page);
+
+            match self.link(page) {
+                END_OF_NOTE => return Ok(pages),
+                next if (FIRST_DATA_PAGE..PAGES).contains(&(next as usize)) => page = next as usize,
+                _ => return Err(Error::InvalidSave("note's page chain is broken")),
+            }
+        }
+    }
+
+    /// Reads the note in `slot`, a whole number of pages long.
+    pub fn read_note(&self, slot: usize) -> Result<Vec<u8>, Error> {
+        let pages = self.note_pages(slot)?;
+        Ok(pages.iter().flat_map(|page| self.data[page * PAGE_LEN..(page + 1) * PAGE_LEN].iter().copied()).collect())
+    }
+
+    /// Writes `data` as a new note, zero padded to a whole page, returning
+    /// the slot it went in.
+    pub fn insert_note(&mut self, note: &Note, data: &[u8]) -> Result<usize, Error> {
+        if data.is_empty() {
+            return Err(Error::InvalidSave("notes need at least one page"));
+        }
+        let slot = (0..MAX_NOTES).find(|slot| self.start_page(*slot).is_none())
+            .ok_or(Error::InvalidSave("every note slot is in use"))?;
+        let pages: Vec<usize> = (FIRST_DATA_PAGE..PAGES).filter(|page| self.link(*page) == FREE_PAGE)
+            .take(data.len().div_ceil(PAGE_LEN))
+            .collect();
+        if pages.len() < data.len().div_ceil(PAGE_LEN) {
+            return Err(Error::InvalidSave("not enough free pages"));
+        }
+
+        for (ii, page) in pages.iter().enumerate() {
+            let chunk = &data[ii * PAGE_LEN..core::cmp::min(data.len(), (ii + 1) * PAGE_LEN)];
+            let dest = &mut self.data[page * PAGE_LEN..(page + 1) * PAGE_LEN];
+            dest.fill(0);
+            dest[..chunk.len()].copy_from_slice(chunk);
+
+            let link = pages.get(ii + 1).map_or(END_OF_NOTE, |next| *next as u16);
+            self.set_link(*page, link);
+        }
+        self.write_index();
+
+        let entry = self.entry_mut(slot);
+        entry.fill(0);
+        entry[0x00..0x04].copy_from_slice(&note.game_code);
+        entry[0x04..0x06].copy_from_slice(&note.publisher_code);
+        entry[0x06..0x08].copy_from_slice(&(pages[0] as u16).to_be_bytes());
+        entry[0x08] = 0x02;
+        entry[0x0c..0x10].copy_from_slice(&note.extension);
+        entry[0x10..0x20].copy_from_slice(&note.name);
+        Ok(slot)
+    }
+
+    /// Frees the note in `slot` and its pages.
+    pub fn delete_note(&mut self, slot: usize) -> Result<(), Error> {
+        for page in self.note_pages(slot)? {
+            self.set_link(page, FREE_PAGE);
+        }
+        self.write_index();
+        self.entry_mut(slot).fill(0);
+        Ok(())
+    }
+
+    fn start_page(&self, slot: usize) -> Option<usize> {
+        if slot >= MAX_NOTES {
+            return None;
+        }
+        let entry = self.entry(slot);
+        let page = u16::from_be_bytes([entry[0x06], entry[0x07]]) as usize;
+        if (FIRST_DATA_PAGE..PAGES).contains(&page) {
+            Some(page)
+        } else {
+            None
+        }
+    }
+
+    fn entry(&self, slot: usize) -> &[u8] {
+        let start = NOTE_TABLE + slot * NOTE_ENTRY_LEN;
+        &self.data[start..start + NOTE_ENTRY_LEN]
+    }
+
+    fn entry_mut(&mut self, slot: usize) -> &mut [u8] {
+        let start = NOTE_TABLE + slot * NOTE_ENTRY_LEN;
+        &mut self.data[start..start + NOTE_ENTRY_LEN]
+    }
+
+    fn link(&self, page: usize) -> u16 {
+        let offset = INDEX_TABLE + page * 2;
+        u16::from_be_bytes([self.data[offset], self.data[offset + 1]])
+    }
+
+    fn set_link(&mut self, page: usize, link: u16) {
+        let offset = INDEX_TABLE + page * 2;
+        self.data[offset..offset + 2].copy_from_slice(&link.to_be_bytes());
+    }
+
+    fn index_valid(&self, table: usize) -> bool {
+        self.data[table + 1] == index_checksum(&self.data[table..table + PAGE_LEN])
+    }
+
+    // Stamps the primary index table's checksum and copies it to the backup.
+    fn write_index(&mut self) {
+        self.data[INDEX_TABLE + 1] = index_checksum(&self.data[INDEX_TABLE..INDEX_TABLE + PAGE_LEN]);
+        self.data.copy_within(INDEX_TABLE..INDEX_TABLE + PAGE_LEN, INDEX_BACKUP);
+    }
+}
+
+// The sum of the low bytes of the data pages' entries.
+fn index_checksum(table: &[u8]) -> u8 {
+    (FIRST_DATA_PAGE..PAGES).fold(0u8, |sum, page| sum.wrapping_add(table[page * 2 + 1]))
+}
+
+fn decode_text(text: &[u8]) -> String {
+    let len = text.iter().rposition(|code| *code != 0).map_or(0, |last| last + 1);
+    text[..len].iter()
+        .map(|code| match FONT.get(*code as usize) {
+            Some(0) | None => '\u{fffd}',
+            Some(ascii) => *ascii as char,
+        })
+        .collect()
+}
+
+fn encode_text(text: &str, out: &mut [u8]) -> Result<(), Error> {
+    if text.len() > out.len() {
+        return Err(Error::InvalidSave("note name too long"));
+    }
+    for (byte, code) in text.bytes().zip(out.iter_mut()) {
+        let upper = byte.to_ascii_uppercase();
+        *code = match FONT.iter().rposition(|ascii| *ascii == upper && upper != 0) {
+            Some(index) => index as u8,
+            None => return Err(Error::InvalidSave("character has no N64 font code")),
+        };
+    }
+    Ok(())
+}
+
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut array = [0u8; N];
+    array.copy_from_slice(bytes);
+    array
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_notes() {
+        let mut pak = ControllerPak::format();
+        assert_eq!(pak.free_pages(), PAGES - FIRST_DATA_PAGE);
+        assert_eq!(pak.notes().count(), 0);
+
+        let note = Note::new(*b"NSME", *b"01", "Mario 64").unwrap();
+        assert_eq!(note.name_str(), "MARIO 64");
+        let data: Vec<u8> = (0..600).map(|ii| ii as u8).collect();
+
+        let slot = pak.insert_note(&note, &data).unwrap();
+        assert_eq!(pak.free_pages(), PAGES - FIRST_DATA_PAGE - 3);
+        assert_eq!(pak.note_pages(slot).unwrap(), [5, 6, 7]);
+
+        let reloaded = ControllerPak::from_bytes(pak.clone().into_bytes()).unwrap();
+        let notes: Vec<_> = reloaded.notes().collect();
+        assert_eq!(notes, [(0, note)]);
+        let read = reloaded.read_note(slot).unwrap();
+        assert_eq!(read.len(), 3 * PAGE_LEN);
+        assert_eq!(read[..600], data[..]);
+
+        pak.delete_note(slot).unwrap();
+        assert_eq!(pak.free_pages(), PAGES - FIRST_DATA_PAGE);
+        assert!(pak.note(slot).is_none());
+        assert!(matches!(pak.read_note(slot), Err(Error::InvalidSave(_))));
+    }
+
+    #[test]
+    fn rejects_slots_past_the_note_table() {
+        let mut pak = ControllerPak::format();
+        pak.insert_note(&Note::new(*b"NSME", *b"01", "MARIO").unwrap(), &[1; 10]).unwrap();
+        for slot in [MAX_NOTES, MAX_NOTES + 1, 2000, usize::MAX / NOTE_ENTRY_LEN].iter() {
+            assert!(pak.note(*slot).is_none());
+            assert!(matches!(pak.note_pages(*slot), Err(Error::InvalidSave(_))));
+            assert!(matches!(pak.read_note(*slot), Err(Error::InvalidSave(_))));
+            assert!(matches!(pak.delete_note(*slot), Err(Error::InvalidSave(_))));
+        }
+        assert_eq!(pak.notes().count(), 1);
+    }
+
+    #[test]
+    fn falls_back_to_the_backup_index_table() {
+        let mut pak = ControllerPak::format();
+        pak.insert_note(&Note::new(*b"NZLE", *b"01", "ZELDA").unwrap(), &[1; 10]).unwrap();
+        let mut data = pak.into_bytes();
+        data[INDEX_TABLE + 21] ^= 0xff;
+
+        let repaired = ControllerPak::from_bytes(data.clone()).unwrap();
+        assert_eq!(repaired.free_pages(), PAGES - FIRST_DATA_PAGE - 1);
+
+        data[INDEX_BACKUP + 21] ^= 0xff;
+        assert!(matches!(ControllerPak::from_bytes(data), Err(Error::InvalidSave(_))));
+        assert!(ControllerPak::from_bytes(alloc::vec![0; 100]).is_err());
+    }
+
+    #[test]
+    fn rejects_full_paks_and_unencodable_names() {
+        assert!(Note::new(*b"NSME", *b"01", "caf\u{e9}").is_err());
+        assert!(Note::new(*b"NSME", *b"01", "A NAME FAR TOO LONG").is_err());
+
+        let mut pak = ControllerPak::format();
+        let note = Note::new(*b"NSME", *b"01", "BIG").unwrap();
+        assert!(pak.insert_note(&note, &[0; (PAGES - FIRST_DATA_PAGE + 1) * PAGE_LEN]).is_err());
+        for _ in 0..MAX_NOTES {
+            pak.insert_note(&note, &[0; 1]).unwrap();
+        }
+        assert!(matches!(pak.insert_note(&note, &[0; 1]), Err(Error::InvalidSave(_))));
+    }
+}
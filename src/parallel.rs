@@ -0,0 +1,63 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::{verify_cart_checksum, ChecksumReport, Error};
+
+/// Reads and verifies each image on the rayon thread pool.  Results come back
+/// in the order the paths were given, each carrying its own error.
+pub fn verify_all<I>(paths: I) -> Vec<(PathBuf, Result<ChecksumReport, Error>)>
+    where I: IntoIterator<Item = PathBuf>
+{
+    let paths: Vec<PathBuf> = paths.into_iter().collect();
+
+    paths.into_par_iter()
+        .map(|path| {
+            let report = verify_path(&path);
+            (path, report)
+        })
+        .collect()
+}
+
+fn verify_path(path: &Path) -> Result<ChecksumReport, Error> {
+    let image = fs::read(path)?;
+    Ok(verify_cart_checksum(&image)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    use crate::fix_cart_checksum;
+    use crate::tests::test_cart;
+
+    #[test]
+    fn verifies_files_in_order() {
+        let dir = env::temp_dir().join(format!("rs64-rom-verify-all-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut good = test_cart();
+        fix_cart_checksum(&mut good).unwrap();
+        let good_path = dir.join("good.z64");
+        fs::write(&good_path, &good).unwrap();
+
+        let bad_path = dir.join("bad.z64");
+        fs::write(&bad_path, test_cart()).unwrap();
+
+        let missing_path = dir.join("missing.z64");
+
+        let results = verify_all(vec![good_path.clone(), missing_path.clone(), bad_path.clone()]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].0, good_path);
+        assert!(results[0].1.as_ref().unwrap().is_valid());
+        assert_eq!(results[1].0, missing_path);
+        assert!(matches!(results[1].1, Err(Error::Io(_))));
+        assert_eq!(results[2].0, bad_path);
+        assert!(!results[2].1.as_ref().unwrap().is_valid());
+    }
+}
@@ -0,0 +1,330 @@
+//! Beat patches: a delta of reads and copies against the source and target,
+//! with CRC-32s of both images and the patch itself.
+
+use alloc::vec::Vec;
+
+use crate::crc32::crc32;
+use crate::patch::{native_copy, with_native};
+use crate::{fix_cart_checksum, Error, SC64_MAX_ROM_LEN};
+
+const MAGIC: &[u8] = b"BPS1";
+const FOOTER_LEN: usize = 12;
+
+const SOURCE_READ: usize = 0;
+const TARGET_READ: usize = 1;
+const SOURCE_COPY: usize = 2;
+const TARGET_COPY: usize = 3;
+
+/// Applies a BPS patch to `rom` in whatever byte order it's in, then fixes
+/// the image's checksum so the output boots.  Returns the new CRC pair.
+pub fn apply(patch: &[u8], rom: &mut Vec<u8>) -> Result<(u32, u32), Error> {
+    let mut crcs = (0, 0);
+    with_native(rom, |rom| {
+        apply_native(patch, rom)?;
+        crcs = fix_cart_checksum(rom)?;
+        Ok(())
+    })?;
+    Ok(crcs)
+}
+
+/// Applies a BPS patch to a buffer without any byte order or checksum
+/// handling, verifying the source, target, and patch CRC-32s.
+pub fn apply_native(patch: &[u8], rom: &mut Vec<u8>) -> Result<(), Error> {
+    if !patch.starts_with(MAGIC) || patch.len() < MAGIC.len() + FOOTER_LEN {
+        return Err(Error::InvalidPatch("missing bps magic"));
+    }
+
+    let actions_end = patch.len() - FOOTER_LEN;
+    let source_crc = le_u32_at(patch, actions_end);
+    let target_crc = le_u32_at(patch, actions_end + 4);
+    if crc32(&patch[..actions_end + 8]) != le_u32_at(patch, actions_end + 8) {
+        return Err(Error::InvalidPatch("bps patch is corrupt"));
+    }
+
+    let mut reader = Reader { data: &patch[..actions_end], pos: MAGIC.len() };
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+    let metadata_size = reader.number()?;
+    reader.take(metadata_size)?;
+    if target_size > SC64_MAX_ROM_LEN {
+        return Err(Error::InvalidPatch("bps target is larger than any cart"));
+    }
+
+    if rom.len() != source_size || crc32(rom) != source_crc {
+        return Err(Error::InvalidPatch("bps source doesn't match the image"));
+    }
+
+    let source: &[u8] = rom;
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset = 0usize;
+    let mut target_offset = 0usize;
+
+    while reader.remaining() > 0 {
+        let action = reader.number()?;
+        let len = (action >> 2) + 1;
+        if target.len() + len > target_size {
+            return Err(Error::InvalidPatch("bps action writes past the target"));
+        }
+
+        match action & 3 {
+            SOURCE_READ => {
+                let start = target.len();
+                target.extend_from_slice(slice(source, start, len)?);
+            },
+            TARGET_READ => target.extend_from_slice(reader.take(len)?),
+            SOURCE_COPY => {
+                source_offset = relative(source_offset, reader.number()?)?;
+                target.extend_from_slice(slice(source, source_offset, len)?);
+                source_offset += len;
+            },
+            TARGET_COPY => {
+                target_offset = relative(target_offset, reader.number()?)?;
+                if target_offset >= target.len() {
+                    return Err(Error::InvalidPatch("bps action reads past the data"));
+                }
+                // Copies may overlap their own output, so go a byte at a time.
+                for _ in 0..len {
+                    let byte = target[target_offset];
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    if target.len() != target_size || crc32(&target) != target_crc {
+        return Err(Error::InvalidPatch("bps target doesn't match the patch"));
+    }
+
+    *rom = target;
+    Ok(())
+}
+
+/// Creates a BPS patch turning `original` into `modified`.  Both images are
+/// normalized to native order first, so the patch applies to any dump.
+pub fn create(original: &[u8], modified: &[u8]) -> Result<Vec<u8>, Error> {
+    Ok(create_native(&native_copy(original)?, &native_copy(modified)?))
+}
+
+/// Creates a BPS patch between two buffers without any byte order handling.
+/// Changed bytes are stored literally rather than searched for elsewhere in
+/// either image, which suits hacks that patch in place.
+pub fn create_native(original: &[u8], modified: &[u8]) -> Vec<u8> {
+    let same = |pos: usize| pos < original.len() && original[pos] == modified[pos];
+
+    let mut patch = MAGIC.to_vec();
+    push_number(&mut patch, original.len());
+    push_number(&mut patch, modified.len());
+    push_number(&mut patch, 0);
+
+    let mut pos = 0;
+    while pos < modified.len() {
+        let run_same = same(pos);
+        let start = pos;
+        while pos < modified.len() && same(pos) == run_same {
+            pos += 1;
+        }
+
+        if run_same {
+            push_number(&mut patch, ((pos - start - 1) << 2) | SOURCE_READ);
+        } else {
+            push_number(&mut patch, ((pos - start - 1) << 2) | TARGET_READ);
+            patch.extend_from_slice(&modified[start..pos]);
+        }
+    }
+
+    patch.extend_from_slice(&crc32(original).to_le_bytes());
+    patch.extend_from_slice(&crc32(modified).to_le_bytes());
+    let patch_crc = crc32(&patch);
+    patch.extend_from_slice(&patch_crc.to_le_bytes());
+
+    patch
+}
+
+fn slice(data: &[u8], offset: usize, len: usize) -> Result<&[u8], Error> {
+    match offset.checked_add(len) {
+        Some(end) if end <= data.len() => Ok(&data[offset..end]),
+        _ => Err(Error::InvalidPatch("bps action reads past the data")),
+    }
+}
+
+// Applies a copy action's signed, sign-magnitude encoded offset delta.
+fn relative(offset: usize, encoded: usize) -> Result<usize, Error> {
+    let delta = encoded >> 1;
+    let moved = if encoded & 1 != 0 {
+        offset.checked_sub(delta)
+    } else {
+        offset.checked_add(delta)
+    };
+    moved.ok_or(Error::InvalidPatch("bps action reads past the data"))
+}
+
+fn le_u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+// Beat's variable length numbers drop the redundancy of plain LEB128 by
+// biasing each continuation.
+fn push_number(patch: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let low = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            patch.push(0x80 | low);
+            return;
+        }
+        patch.push(low);
+        value -= 1;
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = slice(self.data, self.pos, len)
+            .map_err(|_| Error::InvalidPatch("truncated bps patch"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn number(&mut self) -> Result<usize, Error> {
+        let mut value = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.take(1)?[0];
+            value = (byte as usize & 0x7f).checked_mul(shift)
+                .and_then(|part| value.checked_add(part))
+                .ok_or(Error::InvalidPatch("bps number overflows"))?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_shl(7).filter(|shift| *shift != 0)
+                .ok_or(Error::InvalidPatch("bps number overflows"))?;
+            value = value.checked_add(shift).ok_or(Error::InvalidPatch("bps number overflows"))?;
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+    use crate::{swap_cart_to, verify_cart_checksum, ByteSwapping};
+
+    fn finish(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        let patch_crc = crc32(&patch);
+        patch.extend_from_slice(&patch_crc.to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn numbers_round_trip() {
+        for value in [0, 1, 0x7f, 0x80, 0x407f, 0x4080, 0x12345678].iter() {
+            let mut encoded = Vec::new();
+            push_number(&mut encoded, *value);
+            assert_eq!(Reader { data: &encoded, pos: 0 }.number().unwrap(), *value);
+        }
+    }
+
+    #[test]
+    fn applies_every_action() {
+        let source = b"abcdef".to_vec();
+        let target = b"abXYcdefefefe";
+
+        let mut patch = MAGIC.to_vec();
+        push_number(&mut patch, source.len());
+        push_number(&mut patch, target.len());
+        push_number(&mut patch, 2);
+        patch.extend_from_slice(b"{}");
+        push_number(&mut patch, (1 << 2) | SOURCE_READ);
+        push_number(&mut patch, (1 << 2) | TARGET_READ);
+        patch.extend_from_slice(b"XY");
+        push_number(&mut patch, (3 << 2) | SOURCE_COPY);
+        push_number(&mut patch, 2 << 1);
+        push_number(&mut patch, (4 << 2) | TARGET_COPY);
+        push_number(&mut patch, 6 << 1);
+        let patch = finish(patch, &source, target);
+
+        let mut rom = source;
+        apply_native(&patch, &mut rom).unwrap();
+        assert_eq!(rom, target);
+    }
+
+    #[test]
+    fn rejects_mismatched_images() {
+        let patch = create_native(b"abcd", b"abXd");
+
+        let mut rom = b"abcz".to_vec();
+        assert!(matches!(apply_native(&patch, &mut rom), Err(Error::InvalidPatch(_))));
+        assert_eq!(rom, b"abcz");
+
+        let mut corrupt = patch.clone();
+        corrupt[5] ^= 1;
+        let mut rom = b"abcd".to_vec();
+        assert!(matches!(apply_native(&corrupt, &mut rom), Err(Error::InvalidPatch(_))));
+        assert!(matches!(apply_native(b"BPS", &mut rom), Err(Error::InvalidPatch(_))));
+    }
+
+    #[test]
+    fn rejects_huge_targets() {
+        let source = b"abcd".to_vec();
+        let mut patch = MAGIC.to_vec();
+        push_number(&mut patch, source.len());
+        push_number(&mut patch, usize::MAX >> 8);
+        push_number(&mut patch, 0);
+        let patch = finish(patch, &source, b"");
+
+        let mut rom = source;
+        assert!(matches!(apply_native(&patch, &mut rom), Err(Error::InvalidPatch(_))));
+    }
+
+    #[test]
+    fn created_patches_round_trip() {
+        let original = b"the quick brown fox".to_vec();
+        for modified in [&b"the quack brown fox"[..], b"the quick", b"the quick brown fox jumps", b""].iter() {
+            let patch = create_native(&original, modified);
+            let mut rom = original.clone();
+            apply_native(&patch, &mut rom).unwrap();
+            assert_eq!(rom, *modified);
+        }
+    }
+
+    #[test]
+    fn applied_images_boot() {
+        let original = test_cart();
+        let mut modified = original.clone();
+        modified[0x2000..0x2004].copy_from_slice(b"hack");
+        let patch = create(&original, &modified).unwrap();
+
+        let mut rom = original.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut rom).unwrap();
+        let crcs = apply(&patch, &mut rom).unwrap();
+
+        let report = verify_cart_checksum(&rom).unwrap();
+        assert!(report.is_valid());
+        assert_eq!((report.expected_crc1, report.expected_crc2), crcs);
+
+        swap_cart_to(ByteSwapping::Native, &mut rom).unwrap();
+        assert_eq!(&rom[0x2000..0x2004], b"hack");
+
+        // A failed apply leaves the dump as it was.
+        let mut rom = original.clone();
+        rom[0x3000] ^= 1;
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut rom).unwrap();
+        let before = rom.clone();
+        assert!(apply(&patch, &mut rom).is_err());
+        assert_eq!(rom, before);
+    }
+}
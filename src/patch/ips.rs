@@ -0,0 +1,256 @@
+//! International Patching System patches: a list of (offset, bytes) records
+//! with 24 bit offsets, optionally ending in a truncation length.
+
+use alloc::vec::Vec;
+
+use crate::patch::{native_copy, with_native};
+use crate::Error;
+
+const MAGIC: &[u8] = b"PATCH";
+const EOF_MARKER: &[u8] = b"EOF";
+
+/// A record at this offset would read as the end marker.
+const EOF_OFFSET: usize = 0x454f46;
+const MAX_OFFSET: usize = 0xff_ffff;
+const MAX_RECORD_LEN: usize = 0xffff;
+
+/// Unchanged bytes cheaper to repeat than to start a new record over.
+const MERGE_GAP: usize = 5;
+/// Shortest repeated byte run worth a run length record.
+const MIN_RLE_LEN: usize = 9;
+
+/// Applies an IPS patch to `rom` in whatever byte order it's in.  Records
+/// past the end of the image grow it, zero filled.
+pub fn apply(patch: &[u8], rom: &mut Vec<u8>) -> Result<(), Error> {
+    with_native(rom, |rom| apply_native(patch, rom))
+}
+
+/// Applies an IPS patch to a buffer without any byte order handling.
+pub fn apply_native(patch: &[u8], rom: &mut Vec<u8>) -> Result<(), Error> {
+    if !patch.starts_with(MAGIC) {
+        return Err(Error::InvalidPatch("missing ips magic"));
+    }
+
+    let mut reader = Reader { data: patch, pos: MAGIC.len() };
+
+    loop {
+        let record = reader.take(3)?;
+        if record == EOF_MARKER {
+            break;
+        }
+
+        let offset = be_uint(record);
+        let len = be_uint(reader.take(2)?);
+
+        if len == 0 {
+            let count = be_uint(reader.take(2)?);
+            let value = reader.take(1)?[0];
+            fill(rom, offset, count, value);
+        } else {
+            let bytes = reader.take(len)?;
+            grow(rom, offset + len);
+            rom[offset..offset + len].copy_from_slice(bytes);
+        }
+    }
+
+    match reader.remaining() {
+        0 => {},
+        3 => rom.truncate(be_uint(reader.take(3)?)),
+        _ => return Err(Error::InvalidPatch("trailing data after ips end marker")),
+    }
+
+    Ok(())
+}
+
+/// Creates an IPS patch turning `original` into `modified`.  Both images are
+/// normalized to native order first, so the patch applies to any dump.
+pub fn create(original: &[u8], modified: &[u8]) -> Result<Vec<u8>, Error> {
+    create_native(&native_copy(original)?, &native_copy(modified)?)
+}
+
+/// Creates an IPS patch between two buffers without any byte order handling.
+pub fn create_native(original: &[u8], modified: &[u8]) -> Result<Vec<u8>, Error> {
+    let differs = |pos: usize| pos >= original.len() || original[pos] != modified[pos];
+
+    let mut patch = MAGIC.to_vec();
+    let mut pos = 0;
+
+    while pos < modified.len() {
+        if !differs(pos) {
+            pos += 1;
+            continue;
+        }
+
+        // Keep the record clear of the offset that would read as "EOF".
+        let start = if pos == EOF_OFFSET { pos - 1 } else { pos };
+        if start > MAX_OFFSET {
+            return Err(Error::InvalidPatch("ips offsets are limited to 16 MiB"));
+        }
+
+        let limit = core::cmp::min(modified.len(), start + MAX_RECORD_LEN);
+        let mut end = pos + 1;
+        let mut scan = end;
+        while scan < limit && scan - end <= MERGE_GAP {
+            if differs(scan) {
+                end = scan + 1;
+            }
+            scan += 1;
+        }
+
+        write_record(&mut patch, start, &modified[start..end]);
+        pos = end;
+    }
+
+    patch.extend_from_slice(EOF_MARKER);
+
+    if modified.len() < original.len() {
+        if modified.len() > MAX_OFFSET {
+            return Err(Error::InvalidPatch("ips offsets are limited to 16 MiB"));
+        }
+        push_be(&mut patch, modified.len(), 3);
+    }
+
+    Ok(patch)
+}
+
+fn write_record(patch: &mut Vec<u8>, offset: usize, bytes: &[u8]) {
+    push_be(patch, offset, 3);
+
+    if bytes.len() >= MIN_RLE_LEN && bytes.iter().all(|byte| *byte == bytes[0]) {
+        push_be(patch, 0, 2);
+        push_be(patch, bytes.len(), 2);
+        patch.push(bytes[0]);
+    } else {
+        push_be(patch, bytes.len(), 2);
+        patch.extend_from_slice(bytes);
+    }
+}
+
+fn fill(rom: &mut Vec<u8>, offset: usize, count: usize, value: u8) {
+    grow(rom, offset + count);
+    for byte in rom[offset..offset + count].iter_mut() {
+        *byte = value;
+    }
+}
+
+fn grow(rom: &mut Vec<u8>, len: usize) {
+    if rom.len() < len {
+        rom.resize(len, 0);
+    }
+}
+
+fn be_uint(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |acc, byte| (acc << 8) | *byte as usize)
+}
+
+fn push_be(patch: &mut Vec<u8>, value: usize, len: usize) {
+    for shift in (0..len).rev() {
+        patch.push((value >> (shift * 8)) as u8);
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.remaining() < len {
+            return Err(Error::InvalidPatch("truncated ips record"));
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::swap_cart_to;
+    use crate::tests::test_cart;
+    use crate::ByteSwapping;
+
+    #[test]
+    fn applies_records_rle_and_truncation() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x02, 0x00, 0x02, 0xaa, 0xbb]);
+        patch.extend_from_slice(&[0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x03, 0xcc]);
+        patch.extend_from_slice(b"EOF");
+        patch.extend_from_slice(&[0x00, 0x00, 0x08]);
+
+        let mut rom = vec![0u8; 16];
+        apply_native(&patch, &mut rom).unwrap();
+        assert_eq!(rom, [0, 0, 0xaa, 0xbb, 0, 0, 0xcc, 0xcc]);
+    }
+
+    #[test]
+    fn records_past_the_end_grow_the_image() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x04, 0x00, 0x01, 0x11]);
+        patch.extend_from_slice(b"EOF");
+
+        let mut rom = vec![0xffu8; 2];
+        apply_native(&patch, &mut rom).unwrap();
+        assert_eq!(rom, [0xff, 0xff, 0, 0, 0x11]);
+    }
+
+    #[test]
+    fn rejects_malformed_patches() {
+        let mut rom = vec![0u8; 4];
+        assert!(matches!(apply_native(b"PATCX", &mut rom), Err(Error::InvalidPatch(_))));
+        assert!(matches!(apply_native(b"PATCH\x00\x00\x01\x00\x04\x01", &mut rom), Err(Error::InvalidPatch(_))));
+        assert!(matches!(apply_native(b"PATCHEOF\x01", &mut rom), Err(Error::InvalidPatch(_))));
+    }
+
+    #[test]
+    fn created_patches_round_trip() {
+        let mut original = test_cart();
+        original.resize(EOF_OFFSET + 0x1000, 0);
+        let mut modified = original.clone();
+        modified[0x2000..0x2010].copy_from_slice(&[0x5a; 16]);
+        modified[0x3000] ^= 1;
+        modified[0x3004] ^= 1;
+        modified[EOF_OFFSET] ^= 1;
+        modified.truncate(original.len() - 0x100);
+
+        let patch = create(&original, &modified).unwrap();
+        let mut patched = original.clone();
+        apply(&patch, &mut patched).unwrap();
+        assert_eq!(patched, modified);
+
+        // Longer outputs only need records.
+        let patch = create_native(&modified, &original).unwrap();
+        assert!(patch.ends_with(b"EOF"));
+        let mut patched = modified.clone();
+        apply_native(&patch, &mut patched).unwrap();
+        assert_eq!(patched, original);
+    }
+
+    #[test]
+    fn applies_in_the_dumps_byte_order() {
+        let original = test_cart();
+        let mut modified = original.clone();
+        modified[0x1001] = 0x42;
+        let patch = create(&original, &modified).unwrap();
+
+        let mut swapped = original.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut swapped).unwrap();
+        apply(&patch, &mut swapped).unwrap();
+        assert_eq!(swapped[0x1000], 0x42);
+
+        swap_cart_to(ByteSwapping::Native, &mut swapped).unwrap();
+        assert_eq!(swapped, modified);
+
+        // Patches made from swapped dumps are the same as from native ones.
+        let mut swapped_modified = modified.clone();
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut swapped_modified).unwrap();
+        assert_eq!(create(&original, &swapped_modified).unwrap(), patch);
+    }
+}
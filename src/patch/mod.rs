@@ -0,0 +1,122 @@
+//! Applying and creating ROM hacking patches.
+//!
+//! Patches are distributed against big endian (.z64) images, so every entry
+//! point here normalizes the ROM to native order first and restores the
+//! caller's ordering afterwards.  [`apply`] picks the format from the
+//! patch's magic; the per-format modules also expose creation.
+
+use alloc::vec::Vec;
+
+use crate::{convert_swapping, resolve_swapping, ByteSwapping, Error};
+
+pub mod bps;
+pub mod ips;
+pub mod vcdiff;
+
+/// The patch formats [`apply`] understands.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum PatchFormat {
+    Ips,
+    Bps,
+    /// RFC 3284 VCDIFF, the format of xdelta3's .xdelta files.
+    Vcdiff,
+}
+
+impl PatchFormat {
+    /// Identifies a patch's format from its magic.
+    pub fn detect(patch: &[u8]) -> Option<PatchFormat> {
+        if patch.starts_with(b"PATCH") {
+            Some(PatchFormat::Ips)
+        } else if patch.starts_with(b"BPS1") {
+            Some(PatchFormat::Bps)
+        } else if patch.starts_with(&[0xd6, 0xc3, 0xc4]) {
+            Some(PatchFormat::Vcdiff)
+        } else {
+            None
+        }
+    }
+}
+
+/// Applies a patch of any supported format to `rom` in whatever byte order
+/// it's in, returning the format it turned out to be.
+pub fn apply(patch: &[u8], rom: &mut Vec<u8>) -> Result<PatchFormat, Error> {
+    let format = PatchFormat::detect(patch).ok_or(Error::InvalidPatch("unrecognized patch format"))?;
+
+    match format {
+        PatchFormat::Ips => ips::apply(patch, rom)?,
+        PatchFormat::Bps => {
+            bps::apply(patch, rom)?;
+        },
+        PatchFormat::Vcdiff => vcdiff::apply(patch, rom)?,
+    }
+
+    Ok(format)
+}
+
+// Runs `f` over a native order copy of `rom` and stores the result back in
+// the ROM's original ordering.  If `f` fails, or leaves a length the
+// original ordering can't hold, `rom` is left as it was.
+fn with_native<F>(rom: &mut Vec<u8>, f: F) -> Result<(), Error>
+    where F: FnOnce(&mut Vec<u8>) -> Result<(), Error>
+{
+    let swapping = resolve_swapping(rom).ok_or(Error::UnknownByteSwapping)?;
+
+    let mut native = native_copy(rom)?;
+    f(&mut native)?;
+    convert_swapping(ByteSwapping::Native, swapping, &mut native)?;
+    *rom = native;
+    Ok(())
+}
+
+// A native order copy of `rom`.
+pub(crate) fn native_copy(rom: &[u8]) -> Result<Vec<u8>, Error> {
+    let swapping = resolve_swapping(rom).ok_or(Error::UnknownByteSwapping)?;
+
+    let mut native = rom.to_vec();
+    convert_swapping(swapping, ByteSwapping::Native, &mut native)?;
+    Ok(native)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::swap_cart_to;
+    use crate::tests::test_cart;
+
+    #[test]
+    fn applies_any_format() {
+        let original = test_cart();
+        let mut modified = original.clone();
+        modified[0x1234] ^= 0xff;
+
+        let patches = [
+            (PatchFormat::Ips, ips::create(&original, &modified).unwrap()),
+            (PatchFormat::Bps, bps::create(&original, &modified).unwrap()),
+        ];
+        for (format, patch) in patches.iter() {
+            let mut rom = original.clone();
+            assert_eq!(apply(patch, &mut rom).unwrap(), *format);
+            assert_eq!(rom[0x1234], modified[0x1234]);
+        }
+
+        assert_eq!(PatchFormat::detect(&[0xd6, 0xc3, 0xc4, 0x00]), Some(PatchFormat::Vcdiff));
+        let mut rom = original.clone();
+        assert!(matches!(apply(b"UPS1", &mut rom), Err(Error::InvalidPatch(_))));
+    }
+
+    #[test]
+    fn failed_patches_leave_the_image_alone() {
+        let mut rom = test_cart();
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut rom).unwrap();
+        let before = rom.clone();
+
+        // Grows the image by a byte, then ends without the EOF marker.
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&(rom.len() as u32).to_be_bytes()[1..]);
+        patch.extend_from_slice(&[0, 1, 0xaa]);
+        assert!(matches!(apply(&patch, &mut rom), Err(Error::InvalidPatch(_))));
+        assert_eq!(rom, before);
+    }
+}
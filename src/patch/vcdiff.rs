@@ -0,0 +1,409 @@
+//! RFC 3284 VCDIFF deltas, as produced by xdelta3.  Only the default code
+//! table is supported, without secondary compression (`xdelta3 -S none`).
+
+use alloc::vec::Vec;
+
+use crate::patch::with_native;
+use crate::{Error, SC64_MAX_ROM_LEN};
+
+const MAGIC: &[u8] = &[0xd6, 0xc3, 0xc4];
+
+const VCD_DECOMPRESS: u8 = 0x01;
+const VCD_CODETABLE: u8 = 0x02;
+/// xdelta3 extension carrying the file names the delta was made from.
+const VCD_APPHEADER: u8 = 0x04;
+
+const VCD_SOURCE: u8 = 0x01;
+const VCD_TARGET: u8 = 0x02;
+/// xdelta3 extension carrying an Adler-32 of the target window.
+const VCD_ADLER32: u8 = 0x04;
+
+const NEAR_SLOTS: usize = 4;
+const SAME_SLOTS: usize = 3;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Kind {
+    Noop,
+    Add,
+    Run,
+    Copy,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Instruction {
+    kind: Kind,
+    size: usize,
+    mode: u8,
+}
+
+const NOOP: Instruction = Instruction { kind: Kind::Noop, size: 0, mode: 0 };
+
+/// Applies a VCDIFF delta to `rom` in whatever byte order it's in.
+pub fn apply(patch: &[u8], rom: &mut Vec<u8>) -> Result<(), Error> {
+    with_native(rom, |rom| apply_native(patch, rom))
+}
+
+/// Applies a VCDIFF delta to a buffer without any byte order handling.
+pub fn apply_native(patch: &[u8], rom: &mut Vec<u8>) -> Result<(), Error> {
+    if !patch.starts_with(MAGIC) || patch.len() < 5 {
+        return Err(Error::InvalidPatch("missing vcdiff magic"));
+    }
+
+    let mut reader = Reader { data: patch, pos: 4 };
+    let indicator = reader.byte()?;
+    if indicator & (VCD_DECOMPRESS | VCD_CODETABLE) != 0 {
+        return Err(Error::InvalidPatch("vcdiff secondary compression and code tables are unsupported"));
+    }
+    if indicator & VCD_APPHEADER != 0 {
+        let len = reader.number()?;
+        reader.take(len)?;
+    }
+
+    let table = default_code_table();
+    let mut target = Vec::new();
+
+    while reader.remaining() > 0 {
+        decode_window(&mut reader, &table, rom, &mut target)?;
+    }
+
+    *rom = target;
+    Ok(())
+}
+
+fn decode_window(reader: &mut Reader, table: &[(Instruction, Instruction); 256], source: &[u8],
+                 target: &mut Vec<u8>) -> Result<(), Error> {
+    let indicator = reader.byte()?;
+
+    let (segment_len, segment_pos) = if indicator & (VCD_SOURCE | VCD_TARGET) != 0 {
+        (reader.number()?, reader.number()?)
+    } else {
+        (0, 0)
+    };
+    let segment_end = segment_pos.checked_add(segment_len)
+        .ok_or(Error::InvalidPatch("vcdiff window reads past the data"))?;
+
+    let _delta_len = reader.number()?;
+    let window_len = reader.number()?;
+    if target.len().checked_add(window_len).is_none_or(|len| len > SC64_MAX_ROM_LEN) {
+        return Err(Error::InvalidPatch("vcdiff target is larger than any cart"));
+    }
+    if reader.byte()? != 0 {
+        return Err(Error::InvalidPatch("vcdiff secondary compression and code tables are unsupported"));
+    }
+    let data_len = reader.number()?;
+    let inst_len = reader.number()?;
+    let addr_len = reader.number()?;
+    let checksum = if indicator & VCD_ADLER32 != 0 {
+        let bytes = reader.take(4)?;
+        Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    } else {
+        None
+    };
+
+    let mut data = Reader { data: reader.take(data_len)?, pos: 0 };
+    let mut inst = Reader { data: reader.take(inst_len)?, pos: 0 };
+    let mut addr = Reader { data: reader.take(addr_len)?, pos: 0 };
+
+    // Copies address the source segment followed by the window so far.
+    let segment: Vec<u8> = if indicator & VCD_TARGET != 0 {
+        slice(target, segment_pos, segment_end)?.to_vec()
+    } else {
+        slice(source, segment_pos, segment_end)?.to_vec()
+    };
+
+    let start = target.len();
+    let mut cache = AddressCache::new();
+
+    while inst.remaining() > 0 {
+        let (first, second) = table[inst.byte()? as usize];
+        for instruction in [first, second].iter() {
+            if instruction.kind == Kind::Noop {
+                continue;
+            }
+
+            let size = match instruction.size {
+                0 => inst.number()?,
+                size => size,
+            };
+            if (target.len() - start).checked_add(size).is_none_or(|end| end > window_len) {
+                return Err(Error::InvalidPatch("vcdiff instruction writes past the window"));
+            }
+
+            match instruction.kind {
+                Kind::Add => target.extend_from_slice(data.take(size)?),
+                Kind::Run => {
+                    let value = data.byte()?;
+                    target.resize(target.len() + size, value);
+                },
+                Kind::Copy => {
+                    let here = segment.len() + (target.len() - start);
+                    let address = cache.decode(&mut addr, here, instruction.mode)?;
+                    let end = address.checked_add(size).ok_or(Error::InvalidPatch("vcdiff address is invalid"))?;
+                    for offset in address..end {
+                        let byte = if offset < segment.len() {
+                            segment[offset]
+                        } else {
+                            // May overlap its own output, so go a byte at a time.
+                            target[start + offset - segment.len()]
+                        };
+                        target.push(byte);
+                    }
+                },
+                Kind::Noop => {},
+            }
+        }
+    }
+
+    if target.len() - start != window_len {
+        return Err(Error::InvalidPatch("vcdiff window is short"));
+    }
+    if let Some(expected) = checksum {
+        if adler32(&target[start..]) != expected {
+            return Err(Error::InvalidPatch("vcdiff window checksum mismatch"));
+        }
+    }
+
+    Ok(())
+}
+
+struct AddressCache {
+    near: [usize; NEAR_SLOTS],
+    next_slot: usize,
+    same: [usize; SAME_SLOTS * 256],
+}
+
+impl AddressCache {
+    fn new() -> AddressCache {
+        AddressCache {
+            near: [0; NEAR_SLOTS],
+            next_slot: 0,
+            same: [0; SAME_SLOTS * 256],
+        }
+    }
+
+    fn decode(&mut self, addr: &mut Reader, here: usize, mode: u8) -> Result<usize, Error> {
+        let mode = mode as usize;
+        let address = match mode {
+            0 => addr.number()?,
+            1 => here.checked_sub(addr.number()?).ok_or(Error::InvalidPatch("vcdiff address is invalid"))?,
+            m if m < 2 + NEAR_SLOTS => self.near[m - 2].checked_add(addr.number()?)
+                .ok_or(Error::InvalidPatch("vcdiff address is invalid"))?,
+            m => self.same[(m - 2 - NEAR_SLOTS) * 256 + addr.byte()? as usize],
+        };
+        if address >= here {
+            return Err(Error::InvalidPatch("vcdiff address is invalid"));
+        }
+
+        self.near[self.next_slot] = address;
+        self.next_slot = (self.next_slot + 1) % NEAR_SLOTS;
+        self.same[address % (SAME_SLOTS * 256)] = address;
+
+        Ok(address)
+    }
+}
+
+// The code table from section 5.6 of the RFC.
+fn default_code_table() -> [(Instruction, Instruction); 256] {
+    let single = |kind, size, mode| (Instruction { kind, size, mode }, NOOP);
+
+    let mut table = [(NOOP, NOOP); 256];
+    let mut entry = 0;
+    let mut push = |pair| {
+        table[entry] = pair;
+        entry += 1;
+    };
+
+    push(single(Kind::Run, 0, 0));
+    for size in 0..18 {
+        push(single(Kind::Add, size, 0));
+    }
+    for mode in 0..9 {
+        push(single(Kind::Copy, 0, mode));
+        for size in 4..19 {
+            push(single(Kind::Copy, size, mode));
+        }
+    }
+    for mode in 0..9 {
+        let copy_sizes = if mode < 6 { 4..7 } else { 4..5 };
+        for add_size in 1..5 {
+            for copy_size in copy_sizes.clone() {
+                push((Instruction { kind: Kind::Add, size: add_size, mode: 0 },
+                      Instruction { kind: Kind::Copy, size: copy_size, mode }));
+            }
+        }
+    }
+    for mode in 0..9 {
+        push((Instruction { kind: Kind::Copy, size: 4, mode },
+              Instruction { kind: Kind::Add, size: 1, mode: 0 }));
+    }
+
+    table
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn slice(data: &[u8], start: usize, end: usize) -> Result<&[u8], Error> {
+    data.get(start..end).ok_or(Error::InvalidPatch("vcdiff window reads past the data"))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self.pos.checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or(Error::InvalidPatch("truncated vcdiff patch"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    // Big endian base 128 with the top bit marking continuation.
+    fn number(&mut self) -> Result<usize, Error> {
+        let mut value = 0usize;
+        loop {
+            let byte = self.byte()?;
+            if value > (usize::MAX >> 7) {
+                return Err(Error::InvalidPatch("vcdiff number overflows"));
+            }
+            value = (value << 7) | (byte & 0x7f) as usize;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+    use crate::{swap_cart_to, ByteSwapping};
+
+    // A window over `source[..segment_len]` with the given sections.
+    fn window(segment_len: u8, window_len: u8, data: &[u8], inst: &[u8], addr: &[u8]) -> Vec<u8> {
+        let mut window = vec![VCD_SOURCE, segment_len, 0];
+        let body = [&[window_len, 0, data.len() as u8, inst.len() as u8, addr.len() as u8][..],
+                    data, inst, addr].concat();
+        window.push(body.len() as u8);
+        window.extend_from_slice(&body);
+        window
+    }
+
+    #[test]
+    fn code_table_matches_rfc() {
+        let table = default_code_table();
+        assert_eq!(table[0].0.kind, Kind::Run);
+        assert_eq!((table[1].0.kind, table[1].0.size), (Kind::Add, 0));
+        assert_eq!((table[19].0.kind, table[19].0.size, table[19].0.mode), (Kind::Copy, 0, 0));
+        assert_eq!((table[162].0.size, table[162].0.mode), (18, 8));
+        assert_eq!((table[163].0.size, table[163].1.size, table[163].1.mode), (1, 4, 0));
+        assert_eq!((table[246].0.size, table[246].1.size, table[246].1.mode), (4, 4, 8));
+        assert_eq!((table[255].0.kind, table[255].0.mode, table[255].1.kind), (Kind::Copy, 8, Kind::Add));
+    }
+
+    #[test]
+    fn applies_add_run_and_copy() {
+        let source = b"0123456789".to_vec();
+        // ADD 2 "ab", COPY 4 from source address 3, RUN 3 'z', COPY 5 HERE-2.
+        let inst = [3, 19 + 1, 0, 3, 19 + 16, 5];
+        let mut patch = vec![0xd6, 0xc3, 0xc4, 0x00, 0x00];
+        patch.extend_from_slice(&window(10, 14, b"abz", &inst, &[3, 2]));
+
+        let mut rom = source;
+        apply_native(&patch, &mut rom).unwrap();
+        assert_eq!(rom, b"ab3456zzzzzzzz");
+    }
+
+    #[test]
+    fn verifies_xdelta_checksums() {
+        let mut rom = b"anything".to_vec();
+        let target = b"hello";
+        let mut patch = vec![0xd6, 0xc3, 0xc4, 0x00, VCD_APPHEADER, 2, b'/', b'/'];
+        let body = [&[5, 0, 5, 1, 0][..], &adler32(target).to_be_bytes(), target, &[6]].concat();
+        patch.extend_from_slice(&[VCD_ADLER32, body.len() as u8]);
+        patch.extend_from_slice(&body);
+        apply_native(&patch, &mut rom).unwrap();
+        assert_eq!(rom, target);
+
+        let last = patch.len() - 2;
+        patch[last] ^= 1;
+        assert!(matches!(apply_native(&patch, &mut rom), Err(Error::InvalidPatch(_))));
+    }
+
+    #[test]
+    fn rejects_unsupported_patches() {
+        let mut rom = Vec::new();
+        assert!(matches!(apply_native(b"PATCH", &mut rom), Err(Error::InvalidPatch(_))));
+        assert!(matches!(apply_native(&[0xd6, 0xc3, 0xc4, 0x00, VCD_DECOMPRESS, 1], &mut rom),
+                         Err(Error::InvalidPatch(_))));
+        assert!(matches!(apply_native(&[0xd6, 0xc3, 0xc4, 0x00, 0x00, 0x00, 0x05], &mut rom),
+                         Err(Error::InvalidPatch(_))));
+    }
+
+    // The RFC's big endian base 128 integers.
+    fn number(mut value: usize) -> Vec<u8> {
+        let mut encoded = vec![(value & 0x7f) as u8];
+        value >>= 7;
+        while value > 0 {
+            encoded.insert(0, 0x80 | (value & 0x7f) as u8);
+            value >>= 7;
+        }
+        encoded
+    }
+
+    #[test]
+    fn rejects_oversized_windows_and_instructions() {
+        let mut rom = b"0123456789".to_vec();
+
+        // A window claiming far more than any cart holds.
+        let mut patch = vec![0xd6, 0xc3, 0xc4, 0x00, 0x00, 0x00];
+        let body = [&number(usize::MAX >> 1)[..], &[0, 0, 0, 0]].concat();
+        patch.extend_from_slice(&number(body.len()));
+        patch.extend_from_slice(&body);
+        assert!(matches!(apply_native(&patch, &mut rom), Err(Error::InvalidPatch(_))));
+
+        // ADD 1, then a COPY whose size wraps the window length check.
+        let inst = [&[2, 19][..], &number(usize::MAX)].concat();
+        let mut patch = vec![0xd6, 0xc3, 0xc4, 0x00, 0x00];
+        patch.extend_from_slice(&window(10, 14, b"a", &inst, &[0]));
+        assert!(matches!(apply_native(&patch, &mut rom), Err(Error::InvalidPatch(_))));
+        assert_eq!(rom, b"0123456789");
+    }
+
+    #[test]
+    fn applies_in_the_dumps_byte_order() {
+        let original = test_cart();
+        let mut rom = original.clone();
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut rom).unwrap();
+
+        // A single window copying the whole 64 KiB prefix of the source.
+        let len = 0x10000usize;
+        let mut patch = vec![0xd6, 0xc3, 0xc4, 0x00, 0x00, VCD_SOURCE, 0x84, 0x80, 0x00, 0x00];
+        let body = [&[0x84, 0x80, 0x00, 0x00, 0x00, 0x04, 0x01][..], &[19, 0x84, 0x80, 0x00], &[0]].concat();
+        patch.push(body.len() as u8);
+        patch.extend_from_slice(&body);
+
+        apply(&patch, &mut rom).unwrap();
+        swap_cart_to(ByteSwapping::Native, &mut rom).unwrap();
+        assert_eq!(rom, &original[..len]);
+    }
+}
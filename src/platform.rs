@@ -0,0 +1,76 @@
+use core::fmt;
+
+use crate::ique::is_ique;
+use crate::{resolve_swapping, swap_to_native, MediaFormat, RomHeader, BOOTCODE_END, HEADER_END, HEADER_START};
+
+/// The hardware an image was built to run on.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Platform {
+    Retail,
+    /// Seta's N64 based arcade board, marked by a 'Z' media format.
+    Aleck64,
+    IQue,
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Platform::Retail => write!(f, "Nintendo 64"),
+            Platform::Aleck64 => write!(f, "Aleck64"),
+            Platform::IQue => write!(f, "iQue Player"),
+        }
+    }
+}
+
+/// Classifies an image in any byte order.  Returns `None` when its byte
+/// order can't be determined.
+pub fn detect_platform(rom: &[u8]) -> Option<Platform> {
+    let swapping = resolve_swapping(rom)?;
+    if rom.len() < BOOTCODE_END {
+        return None;
+    }
+
+    let mut prefix = [0u8; BOOTCODE_END];
+    prefix.copy_from_slice(&rom[..BOOTCODE_END]);
+    swap_to_native(swapping, &mut prefix);
+
+    let header = RomHeader::from_slice(&prefix[HEADER_START..HEADER_END]).ok()?;
+    Some(classify(&header, &prefix))
+}
+
+// `prefix` is the native order header and bootcode.
+pub(crate) fn classify(header: &RomHeader, prefix: &[u8]) -> Platform {
+    if header.game_id().media == MediaFormat::Aleck64 {
+        Platform::Aleck64
+    } else if is_ique(header, prefix) {
+        Platform::IQue
+    } else {
+        Platform::Retail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart_with;
+    use crate::{swap_cart_to, ByteSwapping};
+
+    fn cart_with_id(code: &str) -> Vec<u8> {
+        test_cart_with(|header| header.set_game_id(&code.parse().unwrap()))
+    }
+
+    #[test]
+    fn classifies_platforms() {
+        assert_eq!(detect_platform(&cart_with_id("NSME")), Some(Platform::Retail));
+        assert_eq!(detect_platform(&cart_with_id("NSMC")), Some(Platform::IQue));
+
+        let mut aleck = cart_with_id("ZTKJ");
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut aleck).unwrap();
+        assert_eq!(detect_platform(&aleck), Some(Platform::Aleck64));
+
+        assert_eq!(detect_platform(&[0u8; 64]), None);
+        assert_eq!(Platform::IQue.to_string(), "iQue Player");
+    }
+}
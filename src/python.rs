@@ -0,0 +1,359 @@
+//! PyO3 bindings, exposed to Python as the `rs64_rom` module.
+//!
+//! Build an importable library with
+//! `cargo rustc --release --features python,pyo3/extension-module --crate-type cdylib`
+//! and install it as `rs64_rom.so` (`rs64_rom.pyd` on Windows).  Byte orders are named by their usual file extensions
+//! (`"z64"`, `"v64"`, `"n64"`) and CICs by their number, such as `6102`.
+//! Errors are raised as `ValueError`s carrying this crate's messages.
+
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::{ByteSwapping, CicVariant, Error, Rom, RomHeader};
+
+/// The 64 byte cart header, with its fields decoded to integers.
+#[pyclass(name = "RomHeader", module = "rs64_rom")]
+#[derive(Clone)]
+pub struct PyRomHeader {
+    header: RomHeader,
+}
+
+#[pymethods]
+impl PyRomHeader {
+    #[new]
+    fn new() -> PyRomHeader {
+        PyRomHeader { header: RomHeader::new() }
+    }
+
+    /// Parses the header from the start of a native order image.
+    #[staticmethod]
+    fn from_bytes(data: &[u8]) -> PyResult<PyRomHeader> {
+        Ok(PyRomHeader { header: RomHeader::from_slice(data).map_err(to_py_err)? })
+    }
+
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.header.to_bytes())
+    }
+
+    #[getter]
+    fn name(&self) -> String {
+        self.header.name_str().trim_end().to_string()
+    }
+
+    #[setter]
+    fn set_name(&mut self, name: &str) -> PyResult<()> {
+        self.header.set_name(name).map_err(|err| to_py_err(err.into()))
+    }
+
+    #[getter]
+    fn game_code(&self) -> String {
+        self.header.game_id().to_string()
+    }
+
+    #[getter]
+    fn version(&self) -> u8 {
+        self.header.version()
+    }
+
+    #[setter]
+    fn set_version(&mut self, version: u8) {
+        self.header.set_version(version);
+    }
+
+    #[getter]
+    fn cart_timing(&self) -> u32 {
+        self.header.cart_timing
+    }
+
+    #[setter]
+    fn set_cart_timing(&mut self, value: u32) {
+        self.header.cart_timing = value;
+    }
+
+    #[getter]
+    fn clock_rate(&self) -> u32 {
+        self.header.clock_rate
+    }
+
+    #[setter]
+    fn set_clock_rate(&mut self, value: u32) {
+        self.header.clock_rate = value;
+    }
+
+    #[getter]
+    fn load_addr(&self) -> u32 {
+        self.header.load_addr
+    }
+
+    #[setter]
+    fn set_load_addr(&mut self, value: u32) {
+        self.header.load_addr = value;
+    }
+
+    #[getter]
+    fn release(&self) -> u32 {
+        self.header.release
+    }
+
+    #[setter]
+    fn set_release(&mut self, value: u32) {
+        self.header.release = value;
+    }
+
+    #[getter]
+    fn crc1(&self) -> u32 {
+        self.header.crc1
+    }
+
+    #[setter]
+    fn set_crc1(&mut self, value: u32) {
+        self.header.crc1 = value;
+    }
+
+    #[getter]
+    fn crc2(&self) -> u32 {
+        self.header.crc2
+    }
+
+    #[setter]
+    fn set_crc2(&mut self, value: u32) {
+        self.header.crc2 = value;
+    }
+
+    #[getter]
+    fn manuf_id(&self) -> u32 {
+        self.header.manuf_id
+    }
+
+    #[setter]
+    fn set_manuf_id(&mut self, value: u32) {
+        self.header.manuf_id = value;
+    }
+
+    #[getter]
+    fn cart_id(&self) -> u16 {
+        self.header.cart_id
+    }
+
+    #[setter]
+    fn set_cart_id(&mut self, value: u16) {
+        self.header.cart_id = value;
+    }
+
+    #[getter]
+    fn country_code(&self) -> u16 {
+        self.header.country_code
+    }
+
+    #[setter]
+    fn set_country_code(&mut self, value: u16) {
+        self.header.country_code = value;
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.header)
+    }
+
+    fn __str__(&self) -> String {
+        self.header.to_string()
+    }
+}
+
+/// A whole image, normalized to native order on load.
+#[pyclass(name = "Rom", module = "rs64_rom")]
+pub struct PyRom {
+    rom: Rom,
+}
+
+#[pymethods]
+impl PyRom {
+    /// Loads an image in any supported byte order.
+    #[new]
+    fn new(data: Vec<u8>) -> PyResult<PyRom> {
+        Ok(PyRom { rom: Rom::from_bytes(data).map_err(to_py_err)? })
+    }
+
+    /// A copy of the header; assign to `header` to change it.
+    #[getter]
+    fn header(&self) -> PyRomHeader {
+        PyRomHeader { header: *self.rom.header() }
+    }
+
+    #[setter]
+    fn set_header(&mut self, header: PyRomHeader) {
+        *self.rom.header_mut() = header.header;
+    }
+
+    #[getter]
+    fn original_swapping(&self) -> &'static str {
+        self.rom.original_swapping().file_extension()
+    }
+
+    #[getter]
+    fn ipl3(&self) -> Option<String> {
+        self.rom.ipl3().map(|ipl3| ipl3.to_string())
+    }
+
+    /// The CRC pair for `cic`, or for the CIC the bootcode implies.
+    #[pyo3(signature = (cic=None))]
+    fn checksum(&self, cic: Option<u16>) -> PyResult<(u32, u32)> {
+        let variant = self.variant(cic)?;
+        self.rom.calculate_checksum(variant).map_err(|err| to_py_err(err.into()))
+    }
+
+    /// Stores the CRC pair for `cic`, or for the CIC the bootcode implies,
+    /// in the header and returns it.
+    #[pyo3(signature = (cic=None))]
+    fn fix_checksum(&mut self, cic: Option<u16>) -> PyResult<(u32, u32)> {
+        let (crc1, crc2) = self.checksum(cic)?;
+        let header = self.rom.header_mut();
+        header.crc1 = crc1;
+        header.crc2 = crc2;
+        Ok((crc1, crc2))
+    }
+
+    /// The image, including header edits, in the byte order `swapping`.
+    #[pyo3(signature = (swapping="z64"))]
+    fn to_bytes<'py>(&self, py: Python<'py>, swapping: &str) -> PyResult<Bound<'py, PyBytes>> {
+        let mut data = Vec::with_capacity(self.rom.len());
+        self.rom.save(&mut data, parse_swapping(swapping)?).map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &data))
+    }
+
+    fn __len__(&self) -> usize {
+        self.rom.len()
+    }
+}
+
+impl PyRom {
+    fn variant(&self, cic: Option<u16>) -> PyResult<CicVariant> {
+        match cic {
+            Some(number) => parse_cic(number),
+            None => Ok(self.rom.ipl3().map_or(CicVariant::Cic6102, |ipl3| ipl3.cic())),
+        }
+    }
+}
+
+/// The byte order of an image, or `None` if it can't be determined.
+#[pyfunction]
+fn detect_swapping(data: &[u8]) -> Option<&'static str> {
+    crate::resolve_swapping(data).map(|swapping| swapping.file_extension())
+}
+
+/// Returns a copy of the image in the byte order `swapping`.
+#[pyfunction]
+fn swap_cart_to<'py>(py: Python<'py>, data: &[u8], swapping: &str) -> PyResult<Bound<'py, PyBytes>> {
+    let mut data = data.to_vec();
+    crate::swap_cart_to(parse_swapping(swapping)?, &mut data).map_err(to_py_err)?;
+    Ok(PyBytes::new(py, &data))
+}
+
+/// The CRC pair of a native order image for `cic`, or for the CIC its
+/// bootcode implies.
+#[pyfunction]
+#[pyo3(signature = (data, cic=None))]
+fn calculate_cart_checksum(data: &[u8], cic: Option<u16>) -> PyResult<(u32, u32)> {
+    let result = match cic {
+        Some(number) => crate::calculate_cart_checksum_with(parse_cic(number)?, data),
+        None => crate::calculate_cart_checksum(data),
+    };
+    result.map_err(|err| to_py_err(err.into()))
+}
+
+/// Returns a copy of the image, in its original byte order, with its CRCs
+/// fixed, along with the new CRC pair.
+#[pyfunction]
+fn fix_cart_checksum<'py>(py: Python<'py>, data: &[u8]) -> PyResult<(Bound<'py, PyBytes>, (u32, u32))> {
+    let mut data = data.to_vec();
+    let crcs = crate::fix_cart_checksum(&mut data).map_err(|err| to_py_err(err.into()))?;
+    Ok((PyBytes::new(py, &data), crcs))
+}
+
+#[pymodule]
+fn rs64_rom(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyRomHeader>()?;
+    module.add_class::<PyRom>()?;
+    module.add_function(wrap_pyfunction!(detect_swapping, module)?)?;
+    module.add_function(wrap_pyfunction!(swap_cart_to, module)?)?;
+    module.add_function(wrap_pyfunction!(calculate_cart_checksum, module)?)?;
+    module.add_function(wrap_pyfunction!(fix_cart_checksum, module)?)?;
+    Ok(())
+}
+
+fn parse_swapping(extension: &str) -> PyResult<ByteSwapping> {
+    ByteSwapping::from_file_extension(extension)
+        .ok_or_else(|| PyValueError::new_err(format!("unknown byte order {:?}", extension)))
+}
+
+fn parse_cic(number: u16) -> PyResult<CicVariant> {
+    match number {
+        6101 => Ok(CicVariant::Cic6101),
+        6102 => Ok(CicVariant::Cic6102),
+        6103 => Ok(CicVariant::Cic6103),
+        6105 => Ok(CicVariant::Cic6105),
+        6106 => Ok(CicVariant::Cic6106),
+        7102 => Ok(CicVariant::Cic7102),
+        _ => Err(PyValueError::new_err(format!("unknown CIC {}", number))),
+    }
+}
+
+fn to_py_err(err: Error) -> PyErr {
+    match err {
+        Error::Io(err) => PyOSError::new_err(err.to_string()),
+        other => PyValueError::new_err(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::{stamp_cic, test_cart};
+
+    #[test]
+    fn cic_numbers_cover_every_variant() {
+        for (number, variant) in [6101, 6102, 6103, 6105, 6106, 7102].iter().zip(CicVariant::ALL.iter()) {
+            assert_eq!(parse_cic(*number).unwrap(), *variant);
+        }
+    }
+
+    #[test]
+    fn module_round_trips_images() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "rs64_rom").unwrap();
+            rs64_rom(&module).unwrap();
+
+            let mut cart = test_cart();
+            stamp_cic(&mut cart, CicVariant::Cic6103);
+            let expected = crate::calculate_cart_checksum(&cart).unwrap();
+            crate::swap_cart_to(ByteSwapping::U16LittleEndian, &mut cart).unwrap();
+
+            let globals = pyo3::types::PyDict::new(py);
+            globals.set_item("rs64_rom", &module).unwrap();
+            globals.set_item("cart", PyBytes::new(py, &cart)).unwrap();
+            let script = std::ffi::CString::new("
+assert rs64_rom.detect_swapping(cart) == 'v64'
+rom = rs64_rom.Rom(cart)
+assert rom.original_swapping == 'v64' and len(rom) == len(cart)
+crcs = rom.fix_checksum()
+header = rom.header
+header.name = 'PYTHON'
+rom.header = header
+native = rom.to_bytes()
+assert rs64_rom.RomHeader.from_bytes(native).name == 'PYTHON'
+assert rs64_rom.calculate_cart_checksum(native, 6103) == crcs
+try:
+    rs64_rom.swap_cart_to(cart, 'rom')
+    raise AssertionError('expected a ValueError')
+except ValueError:
+    pass
+").unwrap();
+            py.run(&script, Some(&globals), None).unwrap();
+
+            let crcs: (u32, u32) = globals.get_item("crcs").unwrap().unwrap().extract().unwrap();
+            assert_eq!(crcs, expected);
+        });
+    }
+}
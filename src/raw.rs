@@ -0,0 +1,264 @@
+use crate::{Error, RomHeader, HEADER_LEN, HEADER_NAME_LEN};
+
+/// The 64 byte header exactly as it sits in a native byte order image, for
+/// overlaying on mapped or borrowed data without copying.
+///
+/// Every field is a big endian byte array, so the struct has no padding, an
+/// alignment of one, and is valid for any bit pattern.  The accessors do the
+/// endian conversion.  [`RomHeader`] holds the same fields as host integers
+/// and is the better type for anything but in-place access.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "bytemuck", derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromBytes, zerocopy::IntoBytes, zerocopy::KnownLayout,
+                                        zerocopy::Immutable, zerocopy::Unaligned))]
+pub struct RawRomHeader {
+    cart_timing: [u8; 4],
+    clock_rate: [u8; 4],
+    load_addr: [u8; 4],
+    release: [u8; 4],
+    crc1: [u8; 4],
+    crc2: [u8; 4],
+    rsvd_18: [u8; 4],
+    rsvd_1c: [u8; 4],
+    name: [u8; HEADER_NAME_LEN],
+    rsvd_34: [u8; 4],
+    manuf_id: [u8; 4],
+    cart_id: [u8; 2],
+    country_code: [u8; 2],
+}
+
+const _: () = assert!(core::mem::size_of::<RawRomHeader>() == HEADER_LEN as usize);
+const _: () = assert!(core::mem::align_of::<RawRomHeader>() == 1);
+
+impl RawRomHeader {
+    /// Views the header at the start of a native byte order image.
+    pub fn view(bytes: &[u8]) -> Result<&RawRomHeader, Error> {
+        check_len(bytes.len())?;
+        // Safety: the struct is 64 bytes of byte arrays with an alignment of
+        // one, so any 64 bytes are a valid value, and the borrow is carried
+        // over from `bytes`.
+        Ok(unsafe { &*(bytes.as_ptr() as *const RawRomHeader) })
+    }
+
+    /// Mutable version of [`RawRomHeader::view`]; writes through the view
+    /// land directly in `bytes`.
+    pub fn view_mut(bytes: &mut [u8]) -> Result<&mut RawRomHeader, Error> {
+        check_len(bytes.len())?;
+        // Safety: as in `view`, and the unique borrow is carried over.
+        Ok(unsafe { &mut *(bytes.as_mut_ptr() as *mut RawRomHeader) })
+    }
+
+    pub fn as_bytes(&self) -> &[u8; HEADER_LEN as usize] {
+        // Safety: the struct is exactly 64 bytes with no padding.
+        unsafe { &*(self as *const RawRomHeader as *const [u8; HEADER_LEN as usize]) }
+    }
+
+    /// Copies the header out into host integers.
+    pub fn to_header(&self) -> RomHeader {
+        RomHeader::from_bytes(self.as_bytes())
+    }
+
+    /// Overwrites every field from `header`.
+    pub fn set_header(&mut self, header: &RomHeader) {
+        let bytes = header.to_bytes();
+        *self = *RawRomHeader::view(&bytes).unwrap();
+    }
+
+    pub fn cart_timing(&self) -> u32 {
+        u32::from_be_bytes(self.cart_timing)
+    }
+
+    pub fn set_cart_timing(&mut self, value: u32) {
+        self.cart_timing = value.to_be_bytes();
+    }
+
+    pub fn clock_rate(&self) -> u32 {
+        u32::from_be_bytes(self.clock_rate)
+    }
+
+    pub fn set_clock_rate(&mut self, value: u32) {
+        self.clock_rate = value.to_be_bytes();
+    }
+
+    pub fn load_addr(&self) -> u32 {
+        u32::from_be_bytes(self.load_addr)
+    }
+
+    pub fn set_load_addr(&mut self, value: u32) {
+        self.load_addr = value.to_be_bytes();
+    }
+
+    pub fn release(&self) -> u32 {
+        u32::from_be_bytes(self.release)
+    }
+
+    pub fn set_release(&mut self, value: u32) {
+        self.release = value.to_be_bytes();
+    }
+
+    pub fn crc1(&self) -> u32 {
+        u32::from_be_bytes(self.crc1)
+    }
+
+    pub fn set_crc1(&mut self, value: u32) {
+        self.crc1 = value.to_be_bytes();
+    }
+
+    pub fn crc2(&self) -> u32 {
+        u32::from_be_bytes(self.crc2)
+    }
+
+    pub fn set_crc2(&mut self, value: u32) {
+        self.crc2 = value.to_be_bytes();
+    }
+
+    pub fn rsvd_18(&self) -> u32 {
+        u32::from_be_bytes(self.rsvd_18)
+    }
+
+    pub fn set_rsvd_18(&mut self, value: u32) {
+        self.rsvd_18 = value.to_be_bytes();
+    }
+
+    pub fn rsvd_1c(&self) -> u32 {
+        u32::from_be_bytes(self.rsvd_1c)
+    }
+
+    pub fn set_rsvd_1c(&mut self, value: u32) {
+        self.rsvd_1c = value.to_be_bytes();
+    }
+
+    pub fn name(&self) -> &[u8; HEADER_NAME_LEN] {
+        &self.name
+    }
+
+    pub fn name_mut(&mut self) -> &mut [u8; HEADER_NAME_LEN] {
+        &mut self.name
+    }
+
+    pub fn rsvd_34(&self) -> u32 {
+        u32::from_be_bytes(self.rsvd_34)
+    }
+
+    pub fn set_rsvd_34(&mut self, value: u32) {
+        self.rsvd_34 = value.to_be_bytes();
+    }
+
+    pub fn manuf_id(&self) -> u32 {
+        u32::from_be_bytes(self.manuf_id)
+    }
+
+    pub fn set_manuf_id(&mut self, value: u32) {
+        self.manuf_id = value.to_be_bytes();
+    }
+
+    pub fn cart_id(&self) -> u16 {
+        u16::from_be_bytes(self.cart_id)
+    }
+
+    pub fn set_cart_id(&mut self, value: u16) {
+        self.cart_id = value.to_be_bytes();
+    }
+
+    pub fn country_code(&self) -> u16 {
+        u16::from_be_bytes(self.country_code)
+    }
+
+    pub fn set_country_code(&mut self, value: u16) {
+        self.country_code = value.to_be_bytes();
+    }
+}
+
+fn check_len(len: usize) -> Result<(), Error> {
+    if len < HEADER_LEN as usize {
+        return Err(Error::BufferTooShort { needed: HEADER_LEN as usize, actual: len });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::HEADER_END;
+
+    fn sample_header() -> RomHeader {
+        let mut header = RomHeader::new();
+        header.load_addr = 0x80000400;
+        header.crc1 = 0x01234567;
+        header.crc2 = 0x89abcdef;
+        header.cart_id = 0x4e53;
+        header.country_code = 0x4500;
+        header.set_name("OVERLAY").unwrap();
+        header
+    }
+
+    #[test]
+    fn fields_sit_at_header_offsets() {
+        let mut bytes = [0u8; HEADER_LEN as usize];
+        for (ii, byte) in bytes.iter_mut().enumerate() {
+            *byte = ii as u8;
+        }
+
+        let raw = RawRomHeader::view(&bytes).unwrap();
+        let header = RomHeader::from_bytes(&bytes);
+        assert_eq!(raw.cart_timing(), header.cart_timing);
+        assert_eq!(raw.clock_rate(), header.clock_rate);
+        assert_eq!(raw.load_addr(), header.load_addr);
+        assert_eq!(raw.release(), header.release);
+        assert_eq!(raw.crc1(), 0x10111213);
+        assert_eq!(raw.crc2(), 0x14151617);
+        assert_eq!(raw.rsvd_18(), header.rsvd_18);
+        assert_eq!(raw.rsvd_1c(), header.rsvd_1c);
+        assert_eq!(raw.name(), &header.name);
+        assert_eq!(raw.rsvd_34(), header.rsvd_34);
+        assert_eq!(raw.manuf_id(), header.manuf_id);
+        assert_eq!(raw.cart_id(), 0x3c3d);
+        assert_eq!(raw.country_code(), 0x3e3f);
+        assert_eq!(raw.as_bytes(), &bytes);
+    }
+
+    #[test]
+    fn edits_write_through_big_endian() {
+        let mut image = crate::tests::test_cart();
+        image[..HEADER_END].copy_from_slice(&sample_header().to_bytes());
+
+        let raw = RawRomHeader::view_mut(&mut image).unwrap();
+        assert_eq!(raw.to_header().to_bytes(), sample_header().to_bytes());
+        raw.set_crc1(0xdeadbeef);
+        raw.set_cart_id(0x5a45);
+        raw.name_mut()[0] = b'X';
+
+        assert_eq!(&image[0x10..0x14], &[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(&image[0x3c..0x3e], b"ZE");
+        assert_eq!(image[0x20], b'X');
+
+        let raw = RawRomHeader::view_mut(&mut image).unwrap();
+        raw.set_header(&sample_header());
+        assert_eq!(&image[..HEADER_END], &sample_header().to_bytes()[..]);
+
+        assert!(matches!(RawRomHeader::view(&image[..63]), Err(Error::BufferTooShort { needed: 64, actual: 63 })));
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn casts_with_bytemuck() {
+        let bytes = sample_header().to_bytes();
+        let raw: &RawRomHeader = bytemuck::from_bytes(&bytes);
+        assert_eq!(raw.load_addr(), 0x80000400);
+        assert_eq!(bytemuck::bytes_of(raw), &bytes[..]);
+    }
+
+    #[cfg(feature = "zerocopy")]
+    #[test]
+    fn casts_with_zerocopy() {
+        use zerocopy::{FromBytes, IntoBytes};
+
+        let bytes = sample_header().to_bytes();
+        let raw = RawRomHeader::ref_from_bytes(&bytes[..]).unwrap();
+        assert_eq!(raw.crc2(), 0x89abcdef);
+        assert_eq!(raw.as_bytes(), &bytes);
+        assert_eq!(IntoBytes::as_bytes(raw), &bytes[..]);
+    }
+}
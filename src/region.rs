@@ -0,0 +1,151 @@
+//! Retargeting a game at another region's consoles.
+//!
+//! Changing the header's country byte is enough for emulators and flashcart
+//! menus, but many games also read `osTvType`, where the IPL3 leaves the
+//! console's video standard at 0x80000300, and refuse to run on the wrong
+//! one.  libultra builds have every such read as an `lw` at offset 0x300
+//! from a register just loaded with `lui 0x8000` or `lui 0xa000`, so those
+//! can be found and turned into constants.
+
+use crate::checksum::CHECKSUM_END;
+use crate::{
+    fix_cart_checksum, resolve_swapping, swap_to_native, ChecksumError, CountryCode, Error, RomHeader, TvStandard,
+    HEADER_END, HEADER_LEN, LOAD_START,
+};
+
+// `lui rt, 0x8000` and `lui rt, 0xa000`, ignoring `rt`.
+const LUI_MASK: u32 = 0xffe0_ffff;
+const LUI_KSEG0: u32 = 0x3c00_8000;
+const LUI_KSEG1: u32 = 0x3c00_a000;
+// `lw rt, 0x300(rs)`, ignoring both registers.
+const LW_MASK: u32 = 0xfc00_ffff;
+const LW_TV_TYPE: u32 = 0x8c00_0300;
+const ORI: u32 = 0x3400_0000;
+
+/// How many instructions after the `lui` the load may come.
+const TV_TYPE_READ_WINDOW: usize = 4;
+
+/// What [`patch_region`] changed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RegionPatch {
+    /// How many reads of `osTvType` were replaced.
+    pub tv_type_reads: usize,
+    pub crc1: u32,
+    pub crc2: u32,
+}
+
+/// The value `osTvType` holds on `standard`'s consoles.
+pub fn os_tv_type(standard: TvStandard) -> u32 {
+    match standard {
+        TvStandard::Pal => 0,
+        TvStandard::Ntsc => 1,
+        TvStandard::Mpal => 2,
+    }
+}
+
+/// Sets the header's country, in whatever byte order the image is in, and
+/// with `tv_type` also turns every read of `osTvType` into a load of that
+/// standard's value, so a check for it always passes.  The game picks its
+/// video modes from `osTvType` too, so it then also outputs that standard.
+/// The CRCs are fixed after.
+pub fn patch_region(rom: &mut [u8], country: CountryCode, tv_type: Option<TvStandard>) -> Result<RegionPatch, Error> {
+    let swapping = resolve_swapping(rom).ok_or(Error::UnknownByteSwapping)?;
+    if rom.len() < CHECKSUM_END {
+        return Err(ChecksumError::NotLongEnough { needed: CHECKSUM_END, actual: rom.len() }.into());
+    }
+    if !rom.len().is_multiple_of(4) {
+        return Err(Error::UnalignedLength { len: rom.len(), unit: 4 });
+    }
+
+    swap_to_native(swapping, rom);
+
+    let mut header_bytes = [0u8; HEADER_LEN as usize];
+    header_bytes.copy_from_slice(&rom[..HEADER_END]);
+    let mut header = RomHeader::from_bytes(&header_bytes);
+    header.set_country(country);
+    rom[..HEADER_END].copy_from_slice(&header.to_bytes());
+
+    let tv_type_reads = match tv_type {
+        Some(standard) => patch_tv_type_reads(&mut rom[LOAD_START..], os_tv_type(standard)),
+        None => 0,
+    };
+
+    swap_to_native(swapping, rom);
+    let (crc1, crc2) = fix_cart_checksum(rom)?;
+    Ok(RegionPatch { tv_type_reads, crc1, crc2 })
+}
+
+fn patch_tv_type_reads(code: &mut [u8], value: u32) -> usize {
+    let word_at = |code: &[u8], index: usize| {
+        u32::from_be_bytes([code[index * 4], code[index * 4 + 1], code[index * 4 + 2], code[index * 4 + 3]])
+    };
+
+    let words = code.len() / 4;
+    let mut patched = 0;
+    for index in 0..words {
+        let lui = word_at(code, index);
+        if lui & LUI_MASK != LUI_KSEG0 && lui & LUI_MASK != LUI_KSEG1 {
+            continue;
+        }
+        let base = lui >> 16 & 0x1f;
+
+        for load_index in index + 1..core::cmp::min(index + 1 + TV_TYPE_READ_WINDOW, words) {
+            let load = word_at(code, load_index);
+            if load & LW_MASK == LW_TV_TYPE && load >> 21 & 0x1f == base {
+                let rt = load >> 16 & 0x1f;
+                let ori = ORI | rt << 16 | value;
+                code[load_index * 4..load_index * 4 + 4].copy_from_slice(&ori.to_be_bytes());
+                patched += 1;
+            }
+        }
+    }
+    patched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+    use crate::{calculate_cart_checksum, swap_cart_to, ByteSwapping};
+
+    // A PAL game's check: lui at, 0x8000; lw t6, 0x300(at); bnez t6, ...;
+    // and a read through another register that targets something else.
+    const CHECK: [u32; 5] = [0x3c018000, 0x8c2e0300, 0x15c00003, 0x3c0fa000, 0x8dd80300];
+
+    #[test]
+    fn retargets_a_swapped_image() {
+        let mut native = test_cart();
+        native[LOAD_START..].fill(0);
+        for (word, out) in CHECK.iter().zip(native[LOAD_START + 0x40..].chunks_exact_mut(4)) {
+            out.copy_from_slice(&word.to_be_bytes());
+        }
+        let mut rom = native.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut rom).unwrap();
+
+        let patch = patch_region(&mut rom, CountryCode::Usa, Some(TvStandard::Pal)).unwrap();
+        assert_eq!(patch.tv_type_reads, 1);
+        assert_eq!(resolve_swapping(&rom), Some(ByteSwapping::U16LittleEndian));
+
+        swap_cart_to(ByteSwapping::Native, &mut rom).unwrap();
+        let header = RomHeader::from_slice(&rom).unwrap();
+        assert_eq!(header.country(), CountryCode::Usa);
+        assert_eq!((header.crc1, header.crc2), calculate_cart_checksum(&rom).unwrap());
+        assert_eq!((patch.crc1, patch.crc2), (header.crc1, header.crc2));
+
+        // ori t6, zero, 0, and the unrelated load left alone.
+        assert_eq!(rom[LOAD_START + 0x44..LOAD_START + 0x48], 0x340e0000u32.to_be_bytes());
+        assert_eq!(rom[LOAD_START + 0x50..LOAD_START + 0x54], CHECK[4].to_be_bytes());
+    }
+
+    #[test]
+    fn only_touches_the_header_without_a_tv_type() {
+        let mut rom = test_cart();
+        let before = rom.clone();
+        let patch = patch_region(&mut rom, CountryCode::Japan, None).unwrap();
+        assert_eq!(patch.tv_type_reads, 0);
+        assert_eq!(rom[HEADER_END..], before[HEADER_END..]);
+        assert_eq!(RomHeader::from_slice(&rom).unwrap().country(), CountryCode::Japan);
+        assert!(matches!(patch_region(&mut rom[..0x2000], CountryCode::Japan, None), Err(Error::Checksum(_))));
+    }
+}
@@ -0,0 +1,491 @@
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut, Range};
+#[cfg(feature = "std")]
+use std::fs::{self, File};
+#[cfg(feature = "std")]
+use std::io::{BufWriter, Read, Write};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+#[cfg(feature = "std")]
+use crate::convert_stream;
+#[cfg(feature = "known-bad")]
+use crate::{find_known_bad, hashes, KnownBadDump};
+use crate::{
+    calculate_cart_checksum_with_params, identify_bootcode, resolve_swapping, Error, swap_to_native, Bootcode,
+    ByteSwapping, ChecksumError, ChecksumParams, CicVariant, Diagnostic, Ipl3, PadFill, Platform, RomHeader,
+    RomLayout, StringOptions, Strings, strings, BOOTCODE_END, BOOTCODE_LEN, HEADER_END, HEADER_LEN, HEADER_START,
+};
+
+/// A complete cart image held in native byte order, with the header parsed out
+/// so it can be edited in place.
+pub struct Rom {
+    header: RomHeader,
+    data: Storage,
+    original_swapping: ByteSwapping,
+    layout: RomLayout,
+}
+
+impl Rom {
+    /// Takes ownership of an image in any supported byte order and normalizes
+    /// it to native order.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Rom, Error> {
+        Rom::from_bytes_with_layout(data, RomLayout::STANDARD)
+    }
+
+    /// Like [`Rom::from_bytes`], for an image with a nonstandard layout.
+    pub fn from_bytes_with_layout(data: Vec<u8>, layout: RomLayout) -> Result<Rom, Error> {
+        Rom::from_storage(Storage::Owned(data), layout)
+    }
+
+    /// Reads an image file in any supported byte order into memory.
+    #[cfg(feature = "std")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Rom, Error> {
+        Rom::from_bytes(fs::read(path)?)
+    }
+
+    /// Maps an image file instead of reading it into memory.  The mapping is
+    /// private, so normalizing byte order or editing the image copies only
+    /// the pages touched and never writes to the file.
+    ///
+    /// As with any file mapping, the file must not be modified by another
+    /// process while the `Rom` is alive.
+    #[cfg(feature = "mmap")]
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> Result<Rom, Error> {
+        let file = File::open(path)?;
+        // Safety: the mapping is copy on write, and callers are told not to
+        // let the file change underneath it.
+        let map = unsafe { memmap2::MmapOptions::new().map_copy(&file)? };
+        Rom::from_storage(Storage::Mapped(map), RomLayout::STANDARD)
+    }
+
+    fn from_storage(mut data: Storage, layout: RomLayout) -> Result<Rom, Error> {
+        if layout.header_len < HEADER_END || layout.bootcode_end() < BOOTCODE_END {
+            return Err(Error::InvalidLayout("layout smaller than the standard header and bootcode"));
+        }
+        if data.len() < layout.load_start() {
+            return Err(Error::BufferTooShort { needed: layout.load_start(), actual: data.len() });
+        }
+
+        let original_swapping = match resolve_swapping(&data) {
+            Some(swapping) => swapping,
+            None => {
+                return Err(Error::UnknownByteSwapping);
+            },
+        };
+
+        let unit = original_swapping.unit_len();
+        if !data.len().is_multiple_of(unit) {
+            return Err(Error::UnalignedLength { len: data.len(), unit });
+        }
+        swap_to_native(original_swapping, &mut data);
+
+        let mut header_bytes = [0u8; HEADER_LEN as usize];
+        header_bytes.copy_from_slice(&data[HEADER_START..HEADER_END]);
+
+        Ok(Rom {
+            header: RomHeader::from_bytes(&header_bytes),
+            data,
+            original_swapping,
+            layout,
+        })
+    }
+
+    pub fn layout(&self) -> &RomLayout {
+        &self.layout
+    }
+
+    /// Byte order the image was in before it was normalized.
+    pub fn original_swapping(&self) -> ByteSwapping {
+        self.original_swapping
+    }
+
+    pub fn header(&self) -> &RomHeader {
+        &self.header
+    }
+
+    /// The hardware the image targets, judged from the current header.
+    pub fn platform(&self) -> Platform {
+        crate::platform::classify(&self.header, &self.data[..BOOTCODE_END])
+    }
+
+    pub fn header_mut(&mut self) -> &mut RomHeader {
+        &mut self.header
+    }
+
+    /// The whole bootcode region of the image's layout.
+    pub fn bootcode(&self) -> &[u8] {
+        &self.data[self.layout.bootcode_start()..self.layout.bootcode_end()]
+    }
+
+    /// Replaces the IPL3 at the start of the bootcode region.  The CRCs are
+    /// left alone; the new bootcode may expect a different CIC's.
+    pub fn set_bootcode(&mut self, bootcode: &Bootcode) {
+        let start = self.layout.bootcode_start();
+        self.data[start..start + bootcode.len()].copy_from_slice(bootcode);
+    }
+
+    /// The IPL3 the image boots with, if it's a known one.
+    pub fn ipl3(&self) -> Option<Ipl3> {
+        let mut bootcode = [0u8; BOOTCODE_LEN as usize];
+        bootcode.copy_from_slice(&self.bootcode()[..BOOTCODE_LEN as usize]);
+        identify_bootcode(&bootcode)
+    }
+
+    /// The CRC pair `variant`'s checksum gives over the region the layout
+    /// covers.
+    pub fn calculate_checksum(&self, variant: CicVariant) -> Result<(u32, u32), ChecksumError> {
+        calculate_cart_checksum_with_params(&ChecksumParams::for_layout(variant, &self.layout), &self.data)
+    }
+
+    /// Everything after the bootcode, starting at the first byte the IPL3 loads.
+    pub fn payload(&self) -> &[u8] {
+        &self.data[self.layout.load_start()..]
+    }
+
+    /// The runs of text in the payload, with offsets into the image.
+    pub fn strings(&self, options: StringOptions) -> Strings<'_> {
+        let mut strings = strings(self.payload(), options);
+        strings.base = self.layout.load_start();
+        strings
+    }
+
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let start = self.layout.load_start();
+        &mut self.data[start..]
+    }
+
+    /// Stores the CRC pair for the CIC the bootcode implies, or the 6102's
+    /// for unknown bootcode, in the header and returns it.
+    pub fn fix_checksum(&mut self) -> Result<(u32, u32), ChecksumError> {
+        let variant = self.ipl3().map_or(CicVariant::Cic6102, |ipl3| ipl3.cic());
+        let (crc1, crc2) = self.calculate_checksum(variant)?;
+        self.header.crc1 = crc1;
+        self.header.crc2 = crc2;
+        Ok((crc1, crc2))
+    }
+
+    /// Grows the image to `size` bytes, the usual first step of a hack that
+    /// needs room for new data, and fixes the CRCs.  The layout is unchanged:
+    /// the IPL3 still checksums the same region, and everything past it is
+    /// free for the hack to use.  Mapped images are copied into memory.
+    pub fn expand_to(&mut self, size: usize, fill: PadFill) -> Result<(u32, u32), Error> {
+        if size < self.data.len() {
+            return Err(Error::InvalidArgument("image larger than the target size"));
+        }
+        if !size.is_multiple_of(4) {
+            return Err(Error::UnalignedLength { len: size, unit: 4 });
+        }
+
+        let data = self.data.to_owned_mut();
+        let len = data.len();
+        match fill {
+            PadFill::Byte(byte) => data.resize(size, byte),
+            PadFill::Repeat => {
+                data.reserve(size - len);
+                while data.len() < size {
+                    let count = core::cmp::min(len, size - data.len());
+                    data.extend_from_within(..count);
+                }
+            },
+        }
+
+        Ok(self.fix_checksum()?)
+    }
+
+    /// Moves the bytes in `region` to `dest`, typically space [`Rom::expand_to`]
+    /// just added, refills what they leave behind with `fill`, and fixes the
+    /// CRCs.  Nothing that points into the region is updated, so it must be
+    /// data nothing refers to by address, such as unused space or a block
+    /// the hack will repoint itself.  Both ranges must lie in the payload.
+    pub fn relocate_region(&mut self, region: Range<usize>, dest: usize, fill: u8) -> Result<(u32, u32), Error> {
+        let load_start = self.layout.load_start();
+        let len = region.end.saturating_sub(region.start);
+        let fits = |start: usize| start >= load_start && start.checked_add(len).is_some_and(|end| end <= self.data.len());
+        if region.start > region.end || !fits(region.start) || !fits(dest) {
+            return Err(Error::InvalidLayout("relocated region must lie within the payload"));
+        }
+
+        self.data.copy_within(region.clone(), dest);
+        let dest = dest..dest + len;
+        for offset in region.filter(|offset| !dest.contains(offset)) {
+            self.data[offset] = fill;
+        }
+
+        Ok(self.fix_checksum()?)
+    }
+
+    /// Checks the image for everything that commonly stops one booting or
+    /// marks it as a bad dump, reporting all problems found.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        crate::validate::diagnose(&self.header, &self.data, &self.layout)
+    }
+
+    /// Whether the image is one of [`KNOWN_BAD_DUMPS`](crate::KNOWN_BAD_DUMPS),
+    /// as loaded and without any header edits.
+    #[cfg(feature = "known-bad")]
+    pub fn check_known_bad(&self) -> Result<Option<&'static KnownBadDump>, Error> {
+        Ok(find_known_bad(&hashes(&self.data)?))
+    }
+
+    /// Length of the whole image in bytes.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Writes the image, including any header edits, in `swapping` order.
+    #[cfg(feature = "std")]
+    pub fn save<W: Write>(&self, writer: &mut W, swapping: ByteSwapping) -> Result<(), Error> {
+        let header_bytes = self.header.to_bytes();
+        let mut reader = (&header_bytes[..]).chain(&self.data[HEADER_END..]);
+
+        convert_stream(ByteSwapping::Native, swapping, &mut reader, writer)?;
+
+        Ok(())
+    }
+
+    /// Writes the image to a new file, or over an existing one, in
+    /// `swapping` order; see [`Rom::save`].
+    #[cfg(feature = "std")]
+    pub fn save_as<P: AsRef<Path>>(&self, path: P, swapping: ByteSwapping) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.save(&mut writer, swapping)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Returns the native order image, including any header edits.  Mapped
+    /// images are copied into memory.
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        self.data[HEADER_START..HEADER_END].copy_from_slice(&self.header.to_bytes());
+        match self.data {
+            Storage::Owned(data) => data,
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(map) => map.to_vec(),
+        }
+    }
+}
+
+enum Storage {
+    Owned(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::MmapMut),
+}
+
+impl Storage {
+    // The image as a growable buffer, copying a mapping into memory first.
+    fn to_owned_mut(&mut self) -> &mut Vec<u8> {
+        #[cfg(feature = "mmap")]
+        {
+            if let Storage::Mapped(ref map) = *self {
+                *self = Storage::Owned(map.to_vec());
+            }
+        }
+        match *self {
+            Storage::Owned(ref mut data) => data,
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(_) => unreachable!(),
+        }
+    }
+}
+
+impl Deref for Storage {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match *self {
+            Storage::Owned(ref data) => data,
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(ref map) => map,
+        }
+    }
+}
+
+impl DerefMut for Storage {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match *self {
+            Storage::Owned(ref mut data) => data,
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(ref mut map) => map,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{calculate_cart_checksum_with, swap_cart_to, BOOTCODE_START, LOAD_START, ROM_LEN};
+
+    fn test_image() -> Vec<u8> {
+        let mut image: Vec<u8> = (0..(LOAD_START + 256)).map(|ii| (ii * 5) as u8).collect();
+        image[HEADER_START..HEADER_END].copy_from_slice(&RomHeader::new().to_bytes());
+        image
+    }
+
+    #[test]
+    fn normalizes_swapped_images() {
+        let native = test_image();
+        let mut swapped = native.clone();
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut swapped).unwrap();
+
+        let rom = Rom::from_bytes(swapped).unwrap();
+        assert_eq!(rom.original_swapping(), ByteSwapping::U32LittleEndian);
+        assert_eq!(rom.bootcode(), &native[BOOTCODE_START..BOOTCODE_END]);
+        assert_eq!(rom.payload(), &native[LOAD_START..]);
+        assert_eq!(rom.len(), native.len());
+        assert_eq!(rom.into_bytes(), native);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn save_applies_header_edits_and_swapping() {
+        let mut rom = Rom::from_bytes(test_image()).unwrap();
+        rom.header_mut().load_addr = 0x80000400;
+
+        let mut saved: Vec<u8> = Vec::new();
+        rom.save(&mut saved, ByteSwapping::U16LittleEndian).unwrap();
+
+        let reloaded = Rom::from_bytes(saved).unwrap();
+        assert_eq!(reloaded.original_swapping(), ByteSwapping::U16LittleEndian);
+        assert_eq!(reloaded.header().load_addr, 0x80000400);
+        assert_eq!(reloaded.payload(), rom.payload());
+    }
+
+    #[test]
+    fn finds_strings_in_swapped_payloads() {
+        let mut native = test_image();
+        native[LOAD_START..].fill(0);
+        native[LOAD_START + 0x20..LOAD_START + 0x2c].copy_from_slice(b"Hello, N64!\0");
+        let mut swapped = native.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut swapped).unwrap();
+
+        let rom = Rom::from_bytes(swapped).unwrap();
+        let found: Vec<_> = rom.strings(StringOptions::default()).collect();
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].offset, found[0].as_ascii()), (LOAD_START + 0x20, Some("Hello, N64!")));
+    }
+
+    #[test]
+    fn replaces_bootcode() {
+        let mut donor = crate::tests::test_cart();
+        crate::tests::stamp_cic(&mut donor, CicVariant::Cic6103);
+        let bootcode = crate::extract_bootcode(&donor).unwrap();
+
+        let mut rom = Rom::from_bytes(test_image()).unwrap();
+        assert_eq!(rom.ipl3(), None);
+        rom.set_bootcode(&bootcode);
+        assert_eq!(rom.bootcode(), &bootcode[..]);
+        assert_eq!(rom.ipl3(), Some(Ipl3::Retail(CicVariant::Cic6103)));
+    }
+
+    #[test]
+    fn honors_nonstandard_layouts() {
+        let layout = RomLayout { bootcode_len: RomLayout::STANDARD.bootcode_len + 0x1000, ..RomLayout::STANDARD };
+        let mut image = crate::tests::test_cart();
+        image.resize(ROM_LEN + 0x1000, 0);
+
+        let rom = Rom::from_bytes_with_layout(image.clone(), layout).unwrap();
+        assert_eq!(rom.layout(), &layout);
+        assert_eq!(rom.bootcode().len(), BOOTCODE_LEN as usize + 0x1000);
+        assert_eq!(rom.payload(), &image[LOAD_START + 0x1000..]);
+
+        let mut shifted = image[..LOAD_START].to_vec();
+        shifted.extend_from_slice(&image[LOAD_START + 0x1000..]);
+        assert_eq!(rom.calculate_checksum(CicVariant::Cic6102), calculate_cart_checksum_with(CicVariant::Cic6102, &shifted));
+
+        let tiny = RomLayout { bootcode_len: 16, ..RomLayout::STANDARD };
+        assert!(matches!(Rom::from_bytes_with_layout(image, tiny), Err(Error::InvalidLayout(_))));
+    }
+
+    #[test]
+    fn expands_and_relocates() {
+        let mut rom = Rom::from_bytes(crate::tests::test_cart()).unwrap();
+        let len = rom.len();
+
+        assert!(matches!(rom.expand_to(len - 4, PadFill::Byte(0)), Err(Error::InvalidArgument(_))));
+        assert!(matches!(rom.expand_to(len + 2, PadFill::Byte(0)), Err(Error::UnalignedLength { .. })));
+
+        let crcs = rom.expand_to(len + 0x1000, PadFill::Byte(0xff)).unwrap();
+        assert_eq!(rom.len(), len + 0x1000);
+        assert!(rom.data[len..].iter().all(|byte| *byte == 0xff));
+        assert_eq!((rom.header().crc1, rom.header().crc2), crcs);
+        assert_eq!(calculate_cart_checksum_with(CicVariant::Cic6102, &rom.data).unwrap(), crcs);
+
+        let moved = rom.data[LOAD_START + 0x10..LOAD_START + 0x20].to_vec();
+        let crcs = rom.relocate_region(LOAD_START + 0x10..LOAD_START + 0x20, len, 0).unwrap();
+        assert_eq!(&rom.data[len..len + 0x10], &moved[..]);
+        assert!(rom.data[LOAD_START + 0x10..LOAD_START + 0x20].iter().all(|byte| *byte == 0));
+        assert_ne!(crcs, calculate_cart_checksum_with(CicVariant::Cic6102, &crate::tests::test_cart()).unwrap());
+        assert_eq!(rom.header().crc1, crcs.0);
+
+        assert!(rom.relocate_region(0..0x10, len, 0).is_err());
+        assert!(rom.relocate_region(LOAD_START..LOAD_START + 0x10, rom.len() - 8, 0).is_err());
+    }
+
+    #[test]
+    fn relocation_may_overlap() {
+        let mut rom = Rom::from_bytes(crate::tests::test_cart()).unwrap();
+        let original = rom.payload().to_vec();
+        rom.relocate_region(LOAD_START..LOAD_START + 8, LOAD_START + 4, 0xee).unwrap();
+        assert_eq!(&rom.payload()[..4], &[0xee; 4]);
+        assert_eq!(&rom.payload()[4..12], &original[..8]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn loads_and_saves_files() {
+        let native = test_image();
+        let mut swapped = native.clone();
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut swapped).unwrap();
+
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("rs64-rom-load-{}.n64", std::process::id()));
+        let output = dir.join(format!("rs64-rom-save-{}.v64", std::process::id()));
+        std::fs::write(&input, &swapped).unwrap();
+
+        let mut rom = Rom::load(&input).unwrap();
+        assert_eq!(rom.original_swapping(), ByteSwapping::U32LittleEndian);
+        rom.header_mut().set_name("SAVED").unwrap();
+        rom.save_as(&output, ByteSwapping::U16LittleEndian).unwrap();
+
+        let saved = Rom::load(&output).unwrap();
+        assert_eq!(saved.original_swapping(), ByteSwapping::U16LittleEndian);
+        assert_eq!(saved.header(), rom.header());
+        assert_eq!(saved.payload(), &native[LOAD_START..]);
+
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        assert!(matches!(Rom::load(&input), Err(Error::Io(_))));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn maps_image_files() {
+        let native = test_image();
+        let mut swapped = native.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut swapped).unwrap();
+
+        let path = std::env::temp_dir().join(format!("rs64-rom-mmap-{}.v64", std::process::id()));
+        std::fs::write(&path, &swapped).unwrap();
+
+        let rom = Rom::open_mmap(&path).unwrap();
+        assert_eq!(rom.original_swapping(), ByteSwapping::U16LittleEndian);
+        assert_eq!(rom.payload(), &native[LOAD_START..]);
+        assert_eq!(rom.into_bytes(), native);
+
+        // The file itself is left in its original order.
+        assert_eq!(std::fs::read(&path).unwrap(), swapped);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_short_or_unknown_images() {
+        assert!(Rom::from_bytes(vec![0u8; 16]).is_err());
+        assert!(Rom::from_bytes(vec![0u8; LOAD_START]).is_err());
+    }
+}
@@ -0,0 +1,249 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::{DatEntry, Database, RomInfo};
+
+/// A collection of images deduplicated by their normalized SHA-1, so the same
+/// dump in different byte orders or under different file names is counted
+/// once.
+///
+/// `T` is whatever the caller uses to tell copies apart, typically a path.
+/// Given a [`Database`], dumps are matched against it, grouped under their
+/// No-Intro titles, and the report can list the dumps of each title the set
+/// lacks.
+#[derive(Clone, Debug)]
+pub struct RomSet<T> {
+    database: Option<Database>,
+    dumps: Vec<Dump<T>>,
+    by_sha1: BTreeMap<[u8; 20], usize>,
+    by_title: BTreeMap<String, Vec<usize>>,
+}
+
+/// One distinct image and every copy of it the set has seen.
+#[derive(Clone, Debug)]
+pub struct Dump<T> {
+    pub info: RomInfo,
+    /// The database entry with the image's hash, if any.
+    pub entry: Option<DatEntry>,
+    /// Every copy ingested, in the order they arrived.
+    pub sources: Vec<T>,
+    title: String,
+}
+
+impl<T> Dump<T> {
+    /// The No-Intro title if the dump is known, otherwise the header name.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Whether the database knows the dump or its CRCs are the ones it boots
+    /// with.
+    pub fn is_verified(&self) -> bool {
+        self.entry.is_some() || self.info.checksum.as_ref().is_some_and(|report| report.is_valid())
+    }
+
+    // Higher is better: known dumps beat verified ones, which beat anything
+    // else, with later header revisions breaking ties.
+    fn rank(&self) -> (bool, bool, u8) {
+        (self.entry.is_some(), self.is_verified(), self.info.header.version())
+    }
+}
+
+/// What [`RomSet::report`] found.
+#[derive(Debug)]
+pub struct RomSetReport<'a, T> {
+    /// Dumps ingested more than once.
+    pub duplicates: Vec<&'a Dump<T>>,
+    /// Dumps neither the database nor their checksum vouch for.
+    pub bad_dumps: Vec<&'a Dump<T>>,
+    /// Database entries sharing a title with a dump in the set, but not in
+    /// it themselves, such as other regions and revisions.
+    pub missing: Vec<&'a DatEntry>,
+}
+
+impl<T> RomSet<T> {
+    pub fn new() -> RomSet<T> {
+        RomSet { database: None, dumps: Vec::new(), by_sha1: BTreeMap::new(), by_title: BTreeMap::new() }
+    }
+
+    /// A set that matches each dump against `database`.
+    pub fn with_database(database: Database) -> RomSet<T> {
+        RomSet { database: Some(database), ..RomSet::new() }
+    }
+
+    /// Adds a copy of an image, returning whether it's one the set hadn't
+    /// seen before.
+    pub fn insert(&mut self, source: T, info: RomInfo) -> bool {
+        if let Some(index) = self.by_sha1.get(&info.hashes.sha1) {
+            self.dumps[*index].sources.push(source);
+            return false;
+        }
+
+        let entry = self.database.as_ref().and_then(|database| database.by_sha1(&info.hashes.sha1)).cloned();
+        let title = match entry {
+            Some(ref entry) => entry.title.clone(),
+            None => header_title(&info),
+        };
+
+        let index = self.dumps.len();
+        self.by_sha1.insert(info.hashes.sha1, index);
+        self.by_title.entry(title.clone()).or_default().push(index);
+        self.dumps.push(Dump { info, entry, sources: alloc::vec![source], title });
+        true
+    }
+
+    /// Number of distinct dumps.
+    pub fn len(&self) -> usize {
+        self.dumps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dumps.is_empty()
+    }
+
+    /// Every distinct dump, in the order first seen.
+    pub fn dumps(&self) -> &[Dump<T>] {
+        &self.dumps
+    }
+
+    /// The titles in the set, sorted.
+    pub fn titles(&self) -> impl Iterator<Item = &str> {
+        self.by_title.keys().map(String::as_str)
+    }
+
+    /// Every dump of a title, in the order first seen.
+    pub fn dumps_of<'a>(&'a self, title: &str) -> impl Iterator<Item = &'a Dump<T>> {
+        self.by_title.get(title).into_iter().flatten().map(move |index| &self.dumps[*index])
+    }
+
+    /// The dump to keep for a title: a database match if there is one, then
+    /// one with valid CRCs, then the latest revision.  The first seen wins
+    /// ties.
+    pub fn best(&self, title: &str) -> Option<&Dump<T>> {
+        self.dumps_of(title).fold(None, |best: Option<&Dump<T>>, dump| match best {
+            Some(best) if best.rank() >= dump.rank() => Some(best),
+            _ => Some(dump),
+        })
+    }
+
+    pub fn report(&self) -> RomSetReport<'_, T> {
+        let duplicates = self.dumps.iter().filter(|dump| dump.sources.len() > 1).collect();
+        let bad_dumps = self.dumps.iter().filter(|dump| !dump.is_verified()).collect();
+
+        let missing = match self.database {
+            Some(ref database) => {
+                database.entries().iter()
+                    .filter(|entry| self.by_title.contains_key(&entry.title))
+                    .filter(|entry| !entry.sha1.is_some_and(|sha1| self.by_sha1.contains_key(&sha1)))
+                    .collect()
+            },
+            None => Vec::new(),
+        };
+
+        RomSetReport { duplicates, bad_dumps, missing }
+    }
+}
+
+impl<T> Default for RomSet<T> {
+    fn default() -> RomSet<T> {
+        RomSet::new()
+    }
+}
+
+fn header_title(info: &RomInfo) -> String {
+    let name = info.header.name_str();
+    let name = name.trim();
+    if name.is_empty() {
+        info.header.game_id().to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+    use crate::{analyze, fix_cart_checksum, hashes, swap_cart_to, ByteSwapping, RomHeader, HEADER_END};
+
+    fn cart(name: &str, version: u8, fix: bool) -> Vec<u8> {
+        let mut cart = test_cart();
+        let mut header = RomHeader::new();
+        header.set_name(name).unwrap();
+        header.set_version(version);
+        cart[..HEADER_END].copy_from_slice(&header.to_bytes());
+        if fix {
+            fix_cart_checksum(&mut cart).unwrap();
+        }
+        cart
+    }
+
+    fn sha1_hex(cart: &[u8]) -> String {
+        hashes(cart).unwrap().sha1.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn dedups_across_byte_orders() {
+        let good = cart("GAME A", 0, true);
+        let mut swapped = good.clone();
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut swapped).unwrap();
+        let bad = cart("GAME B", 0, false);
+
+        let mut set = RomSet::new();
+        assert!(set.insert("a.z64", analyze(&good).unwrap()));
+        assert!(!set.insert("a.n64", analyze(&swapped).unwrap()));
+        assert!(set.insert("b.z64", analyze(&bad).unwrap()));
+
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.titles().collect::<Vec<_>>(), ["GAME A", "GAME B"]);
+        assert_eq!(set.dumps()[0].sources, ["a.z64", "a.n64"]);
+
+        let report = set.report();
+        assert_eq!(report.duplicates.len(), 1);
+        assert_eq!(report.duplicates[0].title(), "GAME A");
+        assert_eq!(report.bad_dumps.len(), 1);
+        assert_eq!(report.bad_dumps[0].title(), "GAME B");
+        assert!(report.missing.is_empty());
+    }
+
+    #[test]
+    fn prefers_database_matches_and_lists_missing_dumps() {
+        let usa = cart("GAME", 0, false);
+        let rev1 = cart("GAME", 1, true);
+        let dat = format!(r#"<datafile>
+            <game name="Game (USA)"><rom name="a.z64" size="{len}" crc="00000000" sha1="{usa}"/></game>
+            <game name="Game (Europe)"><rom name="b.z64" size="{len}" crc="00000001" sha1="{zero}"/></game>
+            <game name="Other (Japan)"><rom name="c.z64" size="{len}" crc="00000002" sha1="{one}"/></game>
+        </datafile>"#, len = usa.len(), usa = sha1_hex(&usa), zero = "0".repeat(40), one = "1".repeat(40));
+
+        let mut set = RomSet::with_database(Database::parse(&dat).unwrap());
+        set.insert(1, analyze(&usa).unwrap());
+        set.insert(2, analyze(&rev1).unwrap());
+
+        // The unverified USA dump is known to the database, so it wins over
+        // the checksummed but unknown revision, which files under the header
+        // name.
+        assert_eq!(set.titles().collect::<Vec<_>>(), ["GAME", "Game"]);
+        assert_eq!(set.best("Game").unwrap().sources, [1]);
+        assert_eq!(set.best("GAME").unwrap().sources, [2]);
+        assert!(set.best("Missing").is_none());
+
+        let report = set.report();
+        assert!(report.bad_dumps.is_empty());
+        assert_eq!(report.missing.len(), 1);
+        assert_eq!(report.missing[0].name, "Game (Europe)");
+    }
+
+    #[test]
+    fn best_prefers_verified_then_later_revisions() {
+        let mut set = RomSet::new();
+        set.insert("broken", analyze(&cart("GAME", 2, false)).unwrap());
+        set.insert("rev0", analyze(&cart("GAME", 0, true)).unwrap());
+        set.insert("rev1", analyze(&cart("GAME", 1, true)).unwrap());
+
+        assert_eq!(set.dumps_of("GAME").count(), 3);
+        assert_eq!(set.best("GAME").unwrap().sources, ["rev1"]);
+    }
+}
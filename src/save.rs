@@ -0,0 +1,299 @@
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{
+    resolve_swapping, swap_to_native, ByteSwapping, Confidence, GameId, RomHeader, HEADER_END, HEADER_START, LOAD_START,
+};
+
+/// Backup memory a game saves to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SaveType {
+    /// 512 byte serial EEPROM.
+    Eeprom4k,
+    /// 2 KiB serial EEPROM.
+    Eeprom16k,
+    /// 32 KiB battery backed SRAM.
+    Sram256k,
+    /// 128 KiB FlashRAM.
+    FlashRam,
+    /// Saves only to a Controller Pak in the controller.
+    ControllerPak,
+}
+
+impl SaveType {
+    /// Size of the backup memory, which is also the size of a full save
+    /// file.  A Controller Pak's is its 32 KiB of SRAM.
+    pub fn capacity(self) -> usize {
+        match self {
+            SaveType::Eeprom4k => 512,
+            SaveType::Eeprom16k => 2 * 1024,
+            SaveType::Sram256k | SaveType::ControllerPak => 32 * 1024,
+            SaveType::FlashRam => 128 * 1024,
+        }
+    }
+}
+
+impl fmt::Display for SaveType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SaveType::Eeprom4k => write!(f, "EEPROM 4K"),
+            SaveType::Eeprom16k => write!(f, "EEPROM 16K"),
+            SaveType::Sram256k => write!(f, "SRAM 256K"),
+            SaveType::FlashRam => write!(f, "FlashRAM"),
+            SaveType::ControllerPak => write!(f, "Controller Pak"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SaveGuess {
+    pub save_type: SaveType,
+    pub confidence: Confidence,
+}
+
+/// Titles whose save type is known outright, by the two character cart ID
+/// shared across regions.  EEPROM size in particular can't be seen in code.
+const KNOWN_SAVE_TYPES: &[([u8; 2], SaveType)] = &[
+    (*b"SM", SaveType::Eeprom4k),  // Super Mario 64
+    (*b"KT", SaveType::Eeprom4k),  // Mario Kart 64
+    (*b"FX", SaveType::Eeprom4k),  // Star Fox 64
+    (*b"GE", SaveType::Eeprom4k),  // GoldenEye 007
+    (*b"BK", SaveType::Eeprom4k),  // Banjo-Kazooie
+    (*b"PW", SaveType::Eeprom4k),  // Pilotwings 64
+    (*b"WR", SaveType::Eeprom4k),  // Wave Race 64
+    (*b"YS", SaveType::Eeprom16k), // Yoshi's Story
+    (*b"DO", SaveType::Eeprom16k), // Donkey Kong 64
+    (*b"B7", SaveType::Eeprom16k), // Banjo-Tooie
+    (*b"FU", SaveType::Eeprom16k), // Conker's Bad Fur Day
+    (*b"PD", SaveType::Eeprom16k), // Perfect Dark
+    (*b"JF", SaveType::Eeprom16k), // Jet Force Gemini
+    (*b"ZL", SaveType::Sram256k),  // The Legend of Zelda: Ocarina of Time
+    (*b"AL", SaveType::Sram256k),  // Super Smash Bros.
+    (*b"FZ", SaveType::Sram256k),  // F-Zero X
+    (*b"ZS", SaveType::FlashRam),  // The Legend of Zelda: Majora's Mask
+    (*b"MQ", SaveType::FlashRam),  // Paper Mario
+];
+
+/// Titles with a real time clock on the cart, by cart ID.
+const RTC_TITLES: &[[u8; 2]] = &[
+    *b"AF", // Animal Forest
+];
+
+/// Strings games show when they can't find a Controller Pak.
+const CONTROLLER_PAK_STRINGS: &[&[u8]] = &[b"Controller Pak", b"CONTROLLER PAK", b"Controller Pack", b"Memory Pak"];
+
+// `lui` with `rs` zero, matched as big endian words ignoring `rt`.
+const LUI_MASK: u32 = 0xffe0_ffff;
+/// `lui rt, 0xa801`: FlashRAM's command register is at 0x08010000 in PI
+/// domain 2, which SRAM stops short of.
+const LUI_FLASH_COMMAND: u32 = 0x3c00_a801;
+/// `lui rt, 0xa800`: the base of PI domain 2, where SRAM and FlashRAM live.
+const LUI_DOMAIN2: u32 = 0x3c00_a800;
+
+/// Guesses a game's save type, in whatever byte order the image is in: known
+/// titles by game ID first, then the addresses libultra's SRAM and FlashRAM
+/// routines touch, then Controller Pak messages.  Returns `None` for images
+/// with no sign of saving, which is also what EEPROM games look like to this
+/// scan.
+pub fn detect_save_type(rom: &[u8]) -> Option<SaveGuess> {
+    let swapping = resolve_swapping(rom)?;
+    let flip = match swapping {
+        ByteSwapping::Native => 0,
+        ByteSwapping::U16LittleEndian => 1,
+        ByteSwapping::U32LittleEndian => 3,
+    };
+    let byte_at = |pos: usize| rom[pos ^ flip];
+
+    let cart_id = native_header(swapping, rom)?.game_id().cart_id;
+
+    if let Some((_, save_type)) = KNOWN_SAVE_TYPES.iter().find(|(id, _)| *id == cart_id) {
+        return Some(SaveGuess { save_type: *save_type, confidence: Confidence::Certain });
+    }
+
+    let end = rom.len() & !3;
+    let mut domain2 = false;
+    for pos in (LOAD_START.min(end)..end).step_by(4) {
+        let word = u32::from_be_bytes([byte_at(pos), byte_at(pos + 1), byte_at(pos + 2), byte_at(pos + 3)]);
+        match word & LUI_MASK {
+            LUI_FLASH_COMMAND => {
+                return Some(SaveGuess { save_type: SaveType::FlashRam, confidence: Confidence::Medium });
+            },
+            LUI_DOMAIN2 => domain2 = true,
+            _ => {},
+        }
+    }
+    if domain2 {
+        return Some(SaveGuess { save_type: SaveType::Sram256k, confidence: Confidence::Medium });
+    }
+
+    let mentions_pak = CONTROLLER_PAK_STRINGS.iter().any(|needle| {
+        (LOAD_START..rom.len().saturating_sub(needle.len()) + 1)
+            .any(|pos| needle.iter().enumerate().all(|(ii, byte)| byte_at(pos + ii) == *byte))
+    });
+    if mentions_pak {
+        return Some(SaveGuess { save_type: SaveType::ControllerPak, confidence: Confidence::Low });
+    }
+
+    None
+}
+
+// `None` if the image is cut off before the end of its header.
+fn native_header(swapping: ByteSwapping, rom: &[u8]) -> Option<RomHeader> {
+    let mut header = [0u8; HEADER_END - HEADER_START];
+    header.copy_from_slice(rom.get(HEADER_START..HEADER_END)?);
+    swap_to_native(swapping, &mut header);
+    Some(RomHeader::from_bytes(&header))
+}
+
+/// A flashcart save database line: what a game saves to and whether it has
+/// a clock.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SaveDbEntry {
+    pub game_id: GameId,
+    /// `None` for games that save nothing to the cart.
+    pub save_type: Option<SaveType>,
+    pub rtc: bool,
+}
+
+impl SaveDbEntry {
+    /// Builds the entry for an image in any byte order, trusting only save
+    /// type guesses of at least medium confidence.  Returns `None` for images
+    /// without a recognizable header.
+    pub fn for_rom(rom: &[u8]) -> Option<SaveDbEntry> {
+        let game_id = native_header(resolve_swapping(rom)?, rom)?.game_id();
+        let save_type = detect_save_type(rom)
+            .filter(|guess| guess.confidence >= Confidence::Medium && guess.save_type != SaveType::ControllerPak)
+            .map(|guess| guess.save_type);
+        let rtc = RTC_TITLES.contains(&game_id.cart_id);
+
+        Some(SaveDbEntry { game_id, save_type, rtc })
+    }
+
+    // EverDrive 64 OS's save type numbering.
+    fn everdrive_type(&self) -> u8 {
+        match self.save_type {
+            None | Some(SaveType::ControllerPak) => 0,
+            Some(SaveType::Eeprom4k) => 1,
+            Some(SaveType::Eeprom16k) => 2,
+            Some(SaveType::Sram256k) => 3,
+            Some(SaveType::FlashRam) => 5,
+        }
+    }
+}
+
+/// Formats as an EverDrive 64 style `save_db.txt` line, such as "NSME=1" or
+/// "NAFJ=5,rtc".
+impl fmt::Display for SaveDbEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.game_id, self.everdrive_type())?;
+        if self.rtc {
+            write!(f, ",rtc")?;
+        }
+        Ok(())
+    }
+}
+
+/// Generates a `save_db.txt` for a collection, one line per game code in
+/// sorted order.  Games that save nothing to the cart and have no clock are
+/// left out, as are images without a recognizable header.
+#[cfg(feature = "alloc")]
+pub fn save_db<'a, I>(roms: I) -> String
+    where I: IntoIterator<Item = &'a [u8]>
+{
+    let mut entries: Vec<SaveDbEntry> = roms.into_iter()
+        .filter_map(SaveDbEntry::for_rom)
+        .filter(|entry| entry.save_type.is_some() || entry.rtc)
+        .collect();
+    entries.sort_by_key(|entry| entry.game_id.to_bytes());
+    entries.dedup_by_key(|entry| entry.game_id.to_bytes());
+
+    let mut db = String::new();
+    for entry in entries.iter() {
+        db.push_str(&alloc::format!("{}\n", entry));
+    }
+    db
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::{test_cart, test_cart_with};
+    use crate::swap_cart_to;
+
+    fn cart_with(code: &str, pos: usize, bytes: &[u8]) -> Vec<u8> {
+        let mut rom = test_cart_with(|header| header.set_game_id(&code.parse::<GameId>().unwrap()));
+        rom[pos..pos + bytes.len()].copy_from_slice(bytes);
+        rom
+    }
+
+    #[test]
+    fn knows_famous_titles() {
+        let rom = cart_with("NYSE", 0, &[]);
+        assert_eq!(detect_save_type(&rom), Some(SaveGuess { save_type: SaveType::Eeprom16k,
+                                                            confidence: Confidence::Certain }));
+        let rom = cart_with("NJFE", 0, &[]);
+        assert_eq!(detect_save_type(&rom).map(|guess| guess.save_type), Some(SaveType::Eeprom16k));
+    }
+
+    #[test]
+    fn finds_domain2_accesses() {
+        // lui t0, 0xa801 and lui a1, 0xa800
+        let flash = cart_with("NXXE", 0x2000, &[0x3c, 0x08, 0xa8, 0x01]);
+        let mut swapped = flash.clone();
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut swapped).unwrap();
+        assert_eq!(detect_save_type(&swapped).map(|guess| guess.save_type), Some(SaveType::FlashRam));
+
+        let sram = cart_with("NXXE", 0x2000, &[0x3c, 0x05, 0xa8, 0x00]);
+        assert_eq!(detect_save_type(&sram), Some(SaveGuess { save_type: SaveType::Sram256k,
+                                                             confidence: Confidence::Medium }));
+
+        // Unaligned lookalikes aren't instructions.
+        let unaligned = cart_with("NXXE", 0x2002, &[0x3c, 0x05, 0xa8, 0x00]);
+        assert_eq!(detect_save_type(&unaligned), None);
+    }
+
+    #[test]
+    fn finds_controller_pak_messages() {
+        let rom = cart_with("NXXE", 0x3001, b"Insert Controller Pak");
+        let mut swapped = rom.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut swapped).unwrap();
+        assert_eq!(detect_save_type(&swapped), Some(SaveGuess { save_type: SaveType::ControllerPak,
+                                                                confidence: Confidence::Low }));
+    }
+
+    #[test]
+    fn unknown_images_have_no_guess() {
+        assert_eq!(detect_save_type(&test_cart()), None);
+        assert_eq!(detect_save_type(&[0u8; 64]), None);
+        assert_eq!(detect_save_type(&[0x80, 0x37, 0x12, 0x40]), None);
+        assert_eq!(SaveDbEntry::for_rom(&test_cart()[..HEADER_END - 1]), None);
+    }
+
+    #[test]
+    fn formats_save_db_lines() {
+        let mut entry = SaveDbEntry::for_rom(&cart_with("NAFJ", 0x2000, &[0x3c, 0x08, 0xa8, 0x01])).unwrap();
+        assert_eq!(entry.to_string(), "NAFJ=5,rtc");
+        entry.rtc = false;
+        entry.save_type = None;
+        assert_eq!(entry.to_string(), "NAFJ=0");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn generates_sorted_databases() {
+        let sm64 = cart_with("NSME", 0, &[]);
+        let oot = cart_with("CZLE", 0, &[]);
+        let pak = cart_with("NXXE", 0x3001, b"Controller Pak");
+        let mut swapped_sm64 = sm64.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut swapped_sm64).unwrap();
+
+        let roms = [&sm64[..], &oot[..], &pak[..], &swapped_sm64[..], &[0u8; 16][..]];
+        assert_eq!(save_db(roms.iter().cloned()), "CZLE=3\nNSME=1\n");
+    }
+}
@@ -0,0 +1,261 @@
+//! Save files: .eep, .sra, .fla and .mpk dumps of cart backup memory and
+//! Controller Paks.
+//!
+//! Everything that reads saves off real carts, the EverDrive and 64drive,
+//! and the Wii Virtual Console store them in the byte order the console
+//! sees.  Project64 stores SRAM and FlashRAM saves as little endian 32 bit
+//! words instead, so moving those between it and anything else needs every
+//! word swapped.  EEPROM is read a byte at a time over the serial bus and
+//! Controller Paks are addressed in 32 byte blocks, so both come out the
+//! same everywhere.
+//!
+//! [`DexDriveSave`] reads and writes the DexDrive's .n64 container, which
+//! wraps a Controller Pak image with a comment for each note.  The DexDrive
+//! only ever read Controller Paks, so cart saves never come in one.
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{swap_to_native, ByteSwapping, Error, SaveType};
+
+/// Where a save file came from or is going to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SaveConvention {
+    /// The console's own byte order, as cart dumpers and flashcarts such as
+    /// the EverDrive 64 and 64drive use.
+    Console,
+    /// What Wii Virtual Console titles store, the console's order.
+    WiiVc,
+    /// Project64's word swapped SRAM and FlashRAM.
+    Project64,
+}
+
+impl SaveConvention {
+    /// Whether this convention swaps the words of a save of `save_type`.
+    pub fn swaps_words(self, save_type: SaveType) -> bool {
+        self == SaveConvention::Project64 && matches!(save_type, SaveType::Sram256k | SaveType::FlashRam)
+    }
+}
+
+impl fmt::Display for SaveConvention {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SaveConvention::Console => write!(f, "console"),
+            SaveConvention::WiiVc => write!(f, "Wii VC"),
+            SaveConvention::Project64 => write!(f, "Project64"),
+        }
+    }
+}
+
+/// Works out what a save file holds from its extension, without the dot and
+/// ignoring case, falling back to its size.  An .eep's size picks between
+/// the two EEPROMs; SRAM and Controller Paks are the same size, so only the
+/// name tells them apart.
+pub fn detect(extension: Option<&str>, len: usize) -> Option<SaveType> {
+    let by_len = match len {
+        512 => Some(SaveType::Eeprom4k),
+        0x800 => Some(SaveType::Eeprom16k),
+        0x8000 => Some(SaveType::Sram256k),
+        0x20000 => Some(SaveType::FlashRam),
+        _ => None,
+    };
+
+    let extension = match extension {
+        Some(extension) => extension.trim_start_matches('.'),
+        None => return by_len,
+    };
+    let is = |name: &str| extension.eq_ignore_ascii_case(name);
+    if is("eep") && len <= SaveType::Eeprom4k.capacity() {
+        Some(SaveType::Eeprom4k)
+    } else if is("eep") {
+        Some(SaveType::Eeprom16k)
+    } else if is("sra") {
+        Some(SaveType::Sram256k)
+    } else if is("fla") {
+        Some(SaveType::FlashRam)
+    } else if is("mpk") {
+        Some(SaveType::ControllerPak)
+    } else {
+        by_len
+    }
+}
+
+/// Converts a save of `save_type` from one convention's byte order to
+/// another's, in place.
+pub fn convert(save_type: SaveType, from: SaveConvention, to: SaveConvention, data: &mut [u8]) -> Result<(), Error> {
+    if from.swaps_words(save_type) == to.swaps_words(save_type) {
+        return Ok(());
+    }
+    if !data.len().is_multiple_of(4) {
+        return Err(Error::UnalignedLength { len: data.len(), unit: 4 });
+    }
+
+    swap_to_native(ByteSwapping::U32LittleEndian, data);
+    Ok(())
+}
+
+/// Pads a save out to its type's full size with what erased memory reads
+/// as: 0xff for EEPROM and FlashRAM, zeros for SRAM and Controller Paks.
+/// Saves already larger than their type are left alone and rejected, as
+/// cutting them down could lose data.
+#[cfg(feature = "alloc")]
+pub fn pad(save_type: SaveType, data: &mut Vec<u8>) -> Result<(), Error> {
+    let capacity = save_type.capacity();
+    if data.len() > capacity {
+        return Err(Error::InvalidSave("save is larger than its save type"));
+    }
+
+    let fill = match save_type {
+        SaveType::Eeprom4k | SaveType::Eeprom16k | SaveType::FlashRam => 0xff,
+        SaveType::Sram256k | SaveType::ControllerPak => 0x00,
+    };
+    data.resize(capacity, fill);
+    Ok(())
+}
+
+const DEXDRIVE_MAGIC: &[u8] = b"123-456-STD";
+const DEXDRIVE_HEADER_LEN: usize = 0x40;
+const DEXDRIVE_COMMENT_LEN: usize = 0x100;
+const DEXDRIVE_COMMENTS: usize = 16;
+const DEXDRIVE_PAK_START: usize = DEXDRIVE_HEADER_LEN + DEXDRIVE_COMMENTS * DEXDRIVE_COMMENT_LEN;
+/// Size of a DexDrive .n64 file.
+pub const DEXDRIVE_LEN: usize = DEXDRIVE_PAK_START + 0x8000;
+
+/// Whether `data` starts like a DexDrive container.
+pub fn is_dexdrive(data: &[u8]) -> bool {
+    data.starts_with(DEXDRIVE_MAGIC)
+}
+
+/// A DexDrive .n64 file: a Controller Pak image and the comments the
+/// DexDrive software kept for each of its 16 notes.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DexDriveSave {
+    /// The raw pak, as [`crate::pak::ControllerPak::from_bytes`] takes it.
+    pub pak: Vec<u8>,
+    /// One comment per note slot, empty where there's none.
+    pub comments: Vec<String>,
+}
+
+#[cfg(feature = "alloc")]
+impl DexDriveSave {
+    /// Wraps a raw pak image with no comments.
+    pub fn new(pak: Vec<u8>) -> Result<DexDriveSave, Error> {
+        if pak.len() != SaveType::ControllerPak.capacity() {
+            return Err(Error::InvalidSave("controller pak images are 32 KiB"));
+        }
+        Ok(DexDriveSave { pak, comments: alloc::vec![String::new(); DEXDRIVE_COMMENTS] })
+    }
+
+    pub fn parse(data: &[u8]) -> Result<DexDriveSave, Error> {
+        if !is_dexdrive(data) {
+            return Err(Error::InvalidSave("missing dexdrive magic"));
+        }
+        if data.len() != DEXDRIVE_LEN {
+            return Err(Error::InvalidSave("dexdrive files are 36928 bytes"));
+        }
+
+        let comments = data[DEXDRIVE_HEADER_LEN..DEXDRIVE_PAK_START].chunks_exact(DEXDRIVE_COMMENT_LEN)
+            .map(|comment| {
+                let len = comment.iter().position(|byte| *byte == 0).unwrap_or(comment.len());
+                String::from_utf8_lossy(&comment[..len]).into_owned()
+            })
+            .collect();
+        Ok(DexDriveSave { pak: data[DEXDRIVE_PAK_START..].to_vec(), comments })
+    }
+
+    /// The container, with comments cut to the 255 bytes that fit.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = alloc::vec![0u8; DEXDRIVE_PAK_START];
+        data[..DEXDRIVE_MAGIC.len()].copy_from_slice(DEXDRIVE_MAGIC);
+
+        for (comment, out) in self.comments.iter().zip(data[DEXDRIVE_HEADER_LEN..].chunks_exact_mut(DEXDRIVE_COMMENT_LEN)) {
+            let mut len = core::cmp::min(comment.len(), DEXDRIVE_COMMENT_LEN - 1);
+            while !comment.is_char_boundary(len) {
+                len -= 1;
+            }
+            out[..len].copy_from_slice(&comment.as_bytes()[..len]);
+        }
+
+        data.extend_from_slice(&self.pak);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_by_extension_then_size() {
+        assert_eq!(detect(Some("EEP"), 512), Some(SaveType::Eeprom4k));
+        assert_eq!(detect(Some(".eep"), 0x800), Some(SaveType::Eeprom16k));
+        assert_eq!(detect(Some("mpk"), 0x8000), Some(SaveType::ControllerPak));
+        assert_eq!(detect(Some("sav"), 0x8000), Some(SaveType::Sram256k));
+        assert_eq!(detect(None, 0x20000), Some(SaveType::FlashRam));
+        assert_eq!(detect(None, 100), None);
+    }
+
+    #[test]
+    fn converts_between_conventions() {
+        let mut sram = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+        convert(SaveType::Sram256k, SaveConvention::Console, SaveConvention::WiiVc, &mut sram).unwrap();
+        assert_eq!(sram, [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]);
+
+        convert(SaveType::Sram256k, SaveConvention::WiiVc, SaveConvention::Project64, &mut sram).unwrap();
+        assert_eq!(sram, [0x04, 0x03, 0x02, 0x01, 0x08, 0x07, 0x06, 0x05]);
+        convert(SaveType::Sram256k, SaveConvention::Project64, SaveConvention::Console, &mut sram).unwrap();
+        assert_eq!(sram, [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]);
+
+        let mut eeprom = [0x01, 0x02, 0x03, 0x04];
+        convert(SaveType::Eeprom4k, SaveConvention::Console, SaveConvention::Project64, &mut eeprom).unwrap();
+        assert_eq!(eeprom, [0x01, 0x02, 0x03, 0x04]);
+
+        let mut odd = [0u8; 6];
+        assert!(matches!(
+            convert(SaveType::FlashRam, SaveConvention::Project64, SaveConvention::Console, &mut odd),
+            Err(Error::UnalignedLength { len: 6, unit: 4 })
+        ));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn pads_to_capacity() {
+        let mut eeprom = vec![0x12; 100];
+        pad(SaveType::Eeprom4k, &mut eeprom).unwrap();
+        assert_eq!((eeprom.len(), eeprom[100]), (512, 0xff));
+
+        let mut sram = vec![0x12; 0x4000];
+        pad(SaveType::Sram256k, &mut sram).unwrap();
+        assert_eq!((sram.len(), sram[0x4000]), (0x8000, 0x00));
+
+        let mut too_big = vec![0; 0x900];
+        assert!(matches!(pad(SaveType::Eeprom16k, &mut too_big), Err(Error::InvalidSave(_))));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn round_trips_dexdrive_files() {
+        let pak = crate::pak::ControllerPak::format().into_bytes();
+        let mut save = DexDriveSave::new(pak.clone()).unwrap();
+        save.comments[1] = "Mario 64 all stars".into();
+        save.comments[2] = "x".repeat(300);
+
+        let data = save.to_bytes();
+        assert_eq!(data.len(), DEXDRIVE_LEN);
+        assert!(is_dexdrive(&data));
+
+        let parsed = DexDriveSave::parse(&data).unwrap();
+        assert_eq!(parsed.pak, pak);
+        assert_eq!(parsed.comments[0], "");
+        assert_eq!(parsed.comments[1], "Mario 64 all stars");
+        assert_eq!(parsed.comments[2].len(), 255);
+
+        assert!(matches!(DexDriveSave::parse(&pak), Err(Error::InvalidSave(_))));
+        assert!(matches!(DexDriveSave::parse(&data[..100]), Err(Error::InvalidSave(_))));
+        assert!(DexDriveSave::new(alloc::vec![0; 512]).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+use crate::{convert_swapping, fix_cart_checksum, resolve_swapping, ByteSwapping, Error};
+
+/// The 8 Mbit parts the Doctor V64 and similar backup units wrote images in.
+pub const DOCTOR_V64_PART_LEN: usize = 1024 * 1024;
+
+/// The largest bank most flashcart programmers take at once.
+pub const FLASHCART_BANK_LEN: usize = 8 * 1024 * 1024;
+
+/// How an image was spread across its parts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PartLayout {
+    /// Each part is the next run of the image.
+    Sequential,
+    /// Two equal halves, one holding the image's even bytes and the other
+    /// its odd bytes, as programmed into pairs of 8 bit chips.
+    Interleaved,
+}
+
+/// An image put back together by [`join_parts`].
+#[derive(Clone, Debug)]
+pub struct JoinedRom {
+    /// The image in native order.
+    pub data: Vec<u8>,
+    pub layout: PartLayout,
+    /// Indices into the parts given, in the order they were joined.
+    pub order: Vec<usize>,
+    /// The byte order the parts were in.
+    pub swapping: ByteSwapping,
+}
+
+/// Reassembles an image from the parts of a split dump, in any byte order,
+/// and converts it to native order.
+///
+/// The part starting with a recognizable header goes first, and the rest
+/// follow in the order given, which for the usual numbered file names is
+/// their sorted order.  Two equal parts where neither starts with a header
+/// are joined as interleaved halves.  Interleaving them the wrong way around
+/// only byte swaps each half word, so either way gives the same normalized
+/// image.
+pub fn join_parts(parts: &[&[u8]]) -> Result<JoinedRom, Error> {
+    if parts.is_empty() {
+        return Err(Error::InvalidArgument("no parts to join"));
+    }
+
+    let heads: Vec<usize> = (0..parts.len()).filter(|index| resolve_swapping(parts[*index]).is_some()).collect();
+    let (data, layout, order) = match heads[..] {
+        [head] => {
+            let order: Vec<usize> = core::iter::once(head).chain((0..parts.len()).filter(|index| *index != head)).collect();
+            let data = order.iter().flat_map(|index| parts[*index].iter().copied()).collect();
+            (data, PartLayout::Sequential, order)
+        },
+        [] if parts.len() == 2 && parts[0].len() == parts[1].len() => {
+            let joined = [[0, 1], [1, 0]].iter()
+                .map(|order| (interleave(parts[order[0]], parts[order[1]]), order))
+                .find(|(data, _)| resolve_swapping(data).is_some());
+            match joined {
+                Some((data, order)) => (data, PartLayout::Interleaved, order.to_vec()),
+                None => return Err(Error::UnknownByteSwapping),
+            }
+        },
+        [] => return Err(Error::UnknownByteSwapping),
+        _ => return Err(Error::InvalidLayout("more than one part starts with a header")),
+    };
+
+    let mut data = data;
+    let swapping = resolve_swapping(&data).ok_or(Error::UnknownByteSwapping)?;
+    convert_swapping(swapping, ByteSwapping::Native, &mut data)?;
+    Ok(JoinedRom { data, layout, order, swapping })
+}
+
+/// Splits an image the way a backup unit would: into `part_len` byte runs
+/// with the last one possibly short, or into even and odd byte halves.  The
+/// image is left in whatever byte order it's in, so convert it first to
+/// match what the target expects.
+pub fn split_parts(rom: &[u8], layout: PartLayout, part_len: usize) -> Result<Vec<Vec<u8>>, Error> {
+    match layout {
+        PartLayout::Sequential => {
+            if part_len == 0 {
+                return Err(Error::InvalidArgument("part length must be non-zero"));
+            }
+            Ok(rom.chunks(part_len).map(|part| part.to_vec()).collect())
+        },
+        PartLayout::Interleaved => {
+            if !rom.len().is_multiple_of(2) {
+                return Err(Error::UnalignedLength { len: rom.len(), unit: 2 });
+            }
+            let even = rom.iter().step_by(2).copied().collect();
+            let odd = rom.iter().skip(1).step_by(2).copied().collect();
+            Ok(alloc::vec![even, odd])
+        },
+    }
+}
+
+/// Fixes the image's CRCs, in whatever byte order it's in, then splits it
+/// into `bank_len` byte banks with the last one possibly short.  Fixing
+/// first matters because the CRCs live in the first bank but cover data
+/// that can spill into the second.
+pub fn split_banks(rom: &mut [u8], bank_len: usize) -> Result<core::slice::Chunks<'_, u8>, Error> {
+    if bank_len == 0 || !bank_len.is_multiple_of(4) {
+        return Err(Error::InvalidArgument("bank length must be a non-zero multiple of 4"));
+    }
+
+    fix_cart_checksum(rom)?;
+    Ok(rom.chunks(bank_len))
+}
+
+/// How [`write_banks`] sizes and names its files.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct BankOptions {
+    pub bank_len: usize,
+    /// File name for each bank, with `{n}` standing in for its number, or
+    /// `{n:W}` for its number zero padded to `W` digits.
+    pub pattern: String,
+    /// The number the first bank gets.
+    pub first_index: usize,
+}
+
+#[cfg(feature = "std")]
+impl BankOptions {
+    /// [`FLASHCART_BANK_LEN`] banks named by `pattern`, numbered from zero.
+    pub fn new<S: Into<String>>(pattern: S) -> BankOptions {
+        BankOptions { bank_len: FLASHCART_BANK_LEN, pattern: pattern.into(), first_index: 0 }
+    }
+
+    /// The file name of bank `index`, counting from zero.
+    pub fn bank_name(&self, index: usize) -> Result<String, Error> {
+        let number = self.first_index + index;
+        let start = self.pattern.find("{n").ok_or(Error::InvalidArgument("bank name pattern has no {n}"))?;
+        let len = self.pattern[start..].find('}').ok_or(Error::InvalidArgument("unterminated {n in bank name pattern"))?;
+
+        let number = match &self.pattern[start + 2..start + len] {
+            "" => number.to_string(),
+            spec => {
+                let width = spec.strip_prefix(':').and_then(|width| width.parse().ok())
+                    .ok_or(Error::InvalidArgument("bank number width must be {n:W}"))?;
+                format!("{:0width$}", number, width = width)
+            },
+        };
+        Ok(format!("{}{}{}", &self.pattern[..start], number, &self.pattern[start + len + 1..]))
+    }
+}
+
+/// Fixes the image's CRCs and writes it into `dir` as banks sized and named
+/// by `options`, returning the paths written in bank order.
+#[cfg(feature = "std")]
+pub fn write_banks<P: AsRef<Path>>(rom: &mut [u8], dir: P, options: &BankOptions) -> Result<Vec<PathBuf>, Error> {
+    // Catch a bad pattern before anything is written.
+    options.bank_name(0)?;
+
+    let mut paths = Vec::new();
+    for (index, bank) in split_banks(rom, options.bank_len)?.enumerate() {
+        let path = dir.as_ref().join(options.bank_name(index)?);
+        fs::write(&path, bank)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+fn interleave(even: &[u8], odd: &[u8]) -> Vec<u8> {
+    even.iter().zip(odd.iter()).flat_map(|(even, odd)| [*even, *odd]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{calculate_cart_checksum, swap_cart_to, RomHeader};
+    use crate::tests::test_cart;
+
+    #[test]
+    fn joins_sequential_parts_in_any_order() {
+        let native = test_cart();
+        let mut v64 = native.clone();
+        swap_cart_to(ByteSwapping::U16LittleEndian, &mut v64).unwrap();
+
+        let parts = split_parts(&v64, PartLayout::Sequential, 0x40000).unwrap();
+        assert!(parts.len() > 2);
+        assert_eq!(parts[0].len(), 0x40000);
+
+        let mut shuffled: Vec<&[u8]> = parts.iter().map(Vec::as_slice).collect();
+        shuffled.rotate_right(1);
+        let joined = join_parts(&shuffled).unwrap();
+        assert_eq!(joined.layout, PartLayout::Sequential);
+        assert_eq!(joined.swapping, ByteSwapping::U16LittleEndian);
+        assert_eq!(joined.order[0], 1);
+        assert_eq!(joined.data, native);
+    }
+
+    #[test]
+    fn joins_interleaved_halves() {
+        let native = test_cart();
+        let halves = split_parts(&native, PartLayout::Interleaved, 0).unwrap();
+        assert_eq!(halves[0].len(), native.len() / 2);
+
+        let joined = join_parts(&[&halves[0], &halves[1]]).unwrap();
+        assert_eq!(joined.layout, PartLayout::Interleaved);
+        assert_eq!((joined.order[0], joined.swapping), (0, ByteSwapping::Native));
+        assert_eq!(joined.data, native);
+
+        // The halves swapped read as a .v64.
+        let joined = join_parts(&[&halves[1], &halves[0]]).unwrap();
+        assert_eq!(joined.swapping, ByteSwapping::U16LittleEndian);
+        assert_eq!(joined.data, native);
+    }
+
+    #[test]
+    fn rejects_ambiguous_parts() {
+        let native = test_cart();
+        assert!(matches!(join_parts(&[]), Err(Error::InvalidArgument(_))));
+        assert!(matches!(join_parts(&[&native, &native]), Err(Error::InvalidLayout(_))));
+        assert!(matches!(join_parts(&[&[0u8; 16], &[0u8; 16]]), Err(Error::UnknownByteSwapping)));
+        assert!(matches!(split_parts(&native, PartLayout::Sequential, 0), Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn splits_banks_after_fixing_crcs() {
+        let mut rom = test_cart();
+        let banks: Vec<Vec<u8>> = split_banks(&mut rom, 0x80000).unwrap().map(<[u8]>::to_vec).collect();
+        assert_eq!(banks.len(), rom.len().div_ceil(0x80000));
+        assert_eq!(banks.concat(), rom);
+
+        let header = RomHeader::from_slice(&banks[0]).unwrap();
+        assert_eq!((header.crc1, header.crc2), calculate_cart_checksum(&rom).unwrap());
+        assert!(matches!(split_banks(&mut rom, 6), Err(Error::InvalidArgument(_))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn names_and_writes_banks() {
+        let mut options = BankOptions::new("game.{n:2}.z64");
+        options.first_index = 1;
+        assert_eq!(options.bank_name(0).unwrap(), "game.01.z64");
+        assert_eq!(BankOptions::new("part{n}").bank_name(10).unwrap(), "part10");
+        assert!(matches!(BankOptions::new("game.z64").bank_name(0), Err(Error::InvalidArgument(_))));
+        assert!(matches!(BankOptions::new("game{n:x}").bank_name(0), Err(Error::InvalidArgument(_))));
+
+        let dir = std::env::temp_dir().join(format!("rs64-rom-banks-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        options.bank_len = 0x100000;
+
+        let mut rom = test_cart();
+        let paths = write_banks(&mut rom, &dir, &options).unwrap();
+        assert_eq!(paths.len(), rom.len().div_ceil(0x100000));
+        assert_eq!(paths[1], dir.join("game.02.z64"));
+        assert_eq!(fs::read(&paths[0]).unwrap(), rom[..0x100000]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+//! `Read` and `Write` adapters that convert byte order as data passes
+//! through, for code that wants to work on native order streams regardless
+//! of what's on disk.
+
+use std::io::{self, Read, Write};
+
+use crate::{fill_chunk, resolve_swapping, swap_to_native, ByteSwapping, Error, SWAP_CHUNK_LEN};
+
+/// Wraps a reader of an image in `swapping` order and yields it in native
+/// order.
+pub struct SwappedReader<R> {
+    inner: R,
+    swapping: ByteSwapping,
+    chunk: Vec<u8>,
+    pos: usize,
+    len: usize,
+}
+
+impl<R: Read> SwappedReader<R> {
+    /// Wraps a reader whose ordering is known up front.
+    pub fn new(inner: R, swapping: ByteSwapping) -> SwappedReader<R> {
+        SwappedReader { inner, swapping, chunk: vec![0u8; SWAP_CHUNK_LEN], pos: 0, len: 0 }
+    }
+
+    /// Wraps a reader positioned at the start of an image, detecting its
+    /// ordering from the header.
+    pub fn detect(inner: R) -> Result<SwappedReader<R>, Error> {
+        let mut reader = SwappedReader::new(inner, ByteSwapping::Native);
+        reader.len = fill_chunk(&mut reader.inner, &mut reader.chunk)?;
+        reader.swapping = resolve_swapping(&reader.chunk[..reader.len]).ok_or(Error::UnknownByteSwapping)?;
+        reader.normalize_chunk()?;
+        Ok(reader)
+    }
+
+    /// The ordering of the underlying data.
+    pub fn swapping(&self) -> ByteSwapping {
+        self.swapping
+    }
+
+    /// Returns the underlying reader.  Anything already read from it but not
+    /// yet returned is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn normalize_chunk(&mut self) -> Result<(), Error> {
+        let unit = self.swapping.unit_len();
+        if !self.len.is_multiple_of(unit) {
+            return Err(Error::UnalignedLength { len: self.len, unit });
+        }
+        swap_to_native(self.swapping, &mut self.chunk[..self.len]);
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for SwappedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.len {
+            // Chunks are only short at the end of the stream, so a partial
+            // unit there means the image itself is misaligned.
+            self.len = fill_chunk(&mut self.inner, &mut self.chunk)?;
+            self.pos = 0;
+            self.normalize_chunk()?;
+        }
+
+        let count = buf.len().min(self.len - self.pos);
+        buf[..count].copy_from_slice(&self.chunk[self.pos..self.pos + count]);
+        self.pos += count;
+        Ok(count)
+    }
+}
+
+/// Wraps a writer and converts the native order data written to it into
+/// `swapping` order.  Data is buffered until a whole chunk is ready, so call
+/// [`finish`](SwappedWriter::finish) to write the tail and check that it
+/// ended on a whole unit; dropping the writer writes what it can and
+/// ignores errors.
+pub struct SwappedWriter<W: Write> {
+    inner: Option<W>,
+    swapping: ByteSwapping,
+    chunk: Vec<u8>,
+}
+
+impl<W: Write> SwappedWriter<W> {
+    pub fn new(inner: W, swapping: ByteSwapping) -> SwappedWriter<W> {
+        SwappedWriter { inner: Some(inner), swapping, chunk: Vec::with_capacity(SWAP_CHUNK_LEN) }
+    }
+
+    /// The ordering the output is written in.
+    pub fn swapping(&self) -> ByteSwapping {
+        self.swapping
+    }
+
+    /// Writes everything buffered and returns the underlying writer.  Fails
+    /// if the data written didn't end on a whole swapping unit.
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.write_units()?;
+        if !self.chunk.is_empty() {
+            let unit = self.swapping.unit_len();
+            return Err(Error::UnalignedLength { len: self.chunk.len(), unit });
+        }
+
+        let mut inner = self.inner.take().unwrap();
+        inner.flush()?;
+        Ok(inner)
+    }
+
+    // Converts and writes every whole unit in the buffer, keeping any
+    // partial one for the next write.
+    fn write_units(&mut self) -> io::Result<()> {
+        let unit = self.swapping.unit_len();
+        let whole = self.chunk.len() - self.chunk.len() % unit;
+        swap_to_native(self.swapping, &mut self.chunk[..whole]);
+        self.inner.as_mut().unwrap().write_all(&self.chunk[..whole])?;
+        self.chunk.drain(..whole);
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for SwappedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = buf.len().min(SWAP_CHUNK_LEN - self.chunk.len());
+        self.chunk.extend_from_slice(&buf[..count]);
+        if self.chunk.len() == SWAP_CHUNK_LEN {
+            self.write_units()?;
+        }
+        Ok(count)
+    }
+
+    /// Writes every whole unit buffered so far.  A trailing partial unit has
+    /// to wait for the rest of its bytes.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_units()?;
+        self.inner.as_mut().unwrap().flush()
+    }
+}
+
+impl<W: Write> Drop for SwappedWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.write_units();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+    use crate::{calculate_cart_checksum_from_reader, calculate_cart_checksum, swap_cart_to, CicVariant};
+
+    #[test]
+    fn reads_native_order() {
+        let native = test_cart();
+
+        for swapping in [ByteSwapping::Native, ByteSwapping::U16LittleEndian, ByteSwapping::U32LittleEndian].iter() {
+            let mut swapped = native.clone();
+            swap_cart_to(*swapping, &mut swapped).unwrap();
+
+            let mut reader = SwappedReader::detect(&swapped[..]).unwrap();
+            assert_eq!(reader.swapping(), *swapping);
+            let mut out = Vec::new();
+            reader.read_to_end(&mut out).unwrap();
+            assert_eq!(out, native);
+
+            let mut reader = SwappedReader::new(&swapped[..], *swapping);
+            assert_eq!(calculate_cart_checksum_from_reader(CicVariant::Cic6102, &mut reader).unwrap(),
+                       calculate_cart_checksum(&native).unwrap());
+        }
+    }
+
+    #[test]
+    fn writes_requested_order() {
+        let native = test_cart();
+
+        for swapping in [ByteSwapping::Native, ByteSwapping::U16LittleEndian, ByteSwapping::U32LittleEndian].iter() {
+            let mut expected = native.clone();
+            swap_cart_to(*swapping, &mut expected).unwrap();
+
+            // Odd sized writes split units across calls.
+            let mut writer = SwappedWriter::new(Vec::new(), *swapping);
+            for piece in native.chunks(4093) {
+                writer.write_all(piece).unwrap();
+            }
+            assert_eq!(writer.finish().unwrap(), expected);
+        }
+
+        let mut writer = SwappedWriter::new(Vec::new(), ByteSwapping::U32LittleEndian);
+        writer.write_all(&[1, 2, 3, 4, 5, 6]).unwrap();
+        writer.flush().unwrap();
+        assert!(matches!(writer.finish(), Err(Error::UnalignedLength { len: 2, unit: 4 })));
+    }
+
+    #[test]
+    fn rejects_misaligned_and_unknown_streams() {
+        let mut swapped = test_cart();
+        swap_cart_to(ByteSwapping::U32LittleEndian, &mut swapped).unwrap();
+        swapped.truncate(swapped.len() - 2);
+
+        let mut out = Vec::new();
+        let err = SwappedReader::detect(&swapped[..]).unwrap().read_to_end(&mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        assert!(matches!(SwappedReader::detect(&[0u8; 64][..]), Err(Error::UnknownByteSwapping)));
+    }
+}
@@ -0,0 +1,141 @@
+//! `strings(1)` for images: runs of printable text, found after the image
+//! is in native order so text isn't scrambled by byte swapping.
+//!
+//! Japanese releases keep their text in Shift-JIS, whose two byte
+//! characters and half width katakana are accepted alongside ASCII unless
+//! turned off.  Whether a run really is text is left to the caller; a
+//! higher minimum length cuts down on the noise from code and graphics.
+
+#[cfg(feature = "encoding")]
+use alloc::string::String;
+
+/// What [`strings`] accepts as text.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StringOptions {
+    /// The fewest characters a run needs to be reported.
+    pub min_len: usize,
+    pub shift_jis: bool,
+}
+
+impl Default for StringOptions {
+    fn default() -> StringOptions {
+        StringOptions { min_len: 4, shift_jis: true }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum StringEncoding {
+    Ascii,
+    /// At least one character is outside ASCII.
+    ShiftJis,
+}
+
+/// A run of text found by [`strings`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RomString<'a> {
+    pub offset: usize,
+    pub bytes: &'a [u8],
+    pub encoding: StringEncoding,
+    /// Length in characters, counting a two byte character once.
+    pub chars: usize,
+}
+
+impl<'a> RomString<'a> {
+    /// The text, if it's plain ASCII.
+    pub fn as_ascii(&self) -> Option<&'a str> {
+        match self.encoding {
+            StringEncoding::Ascii => core::str::from_utf8(self.bytes).ok(),
+            StringEncoding::ShiftJis => None,
+        }
+    }
+
+    /// The text as Unicode, however it's encoded.
+    #[cfg(feature = "encoding")]
+    pub fn decode(&self) -> String {
+        encoding_rs::SHIFT_JIS.decode_without_bom_handling(self.bytes).0.into_owned()
+    }
+}
+
+/// Iterator over the runs of text in a buffer; see [`strings`].
+pub struct Strings<'a> {
+    data: &'a [u8],
+    options: StringOptions,
+    position: usize,
+    // Offset of `data` in the image, added to every string's offset.
+    pub(crate) base: usize,
+}
+
+/// Finds the runs of text in a native byte order buffer, with offsets into
+/// it.  Use [`Rom::strings`](crate::Rom::strings) for a loaded image.
+pub fn strings(data: &[u8], options: StringOptions) -> Strings<'_> {
+    Strings { data, options, position: 0, base: 0 }
+}
+
+impl Strings<'_> {
+    // The length of the character at `at` and whether it's outside ASCII.
+    fn char_at(&self, at: usize) -> Option<(usize, bool)> {
+        let byte = *self.data.get(at)?;
+        match byte {
+            0x20..=0x7e | b'\t' | b'\n' | b'\r' => Some((1, false)),
+            _ if !self.options.shift_jis => None,
+            // Half width katakana.
+            0xa1..=0xdf => Some((1, true)),
+            0x81..=0x9f | 0xe0..=0xef => match self.data.get(at + 1) {
+                Some(0x40..=0x7e) | Some(0x80..=0xfc) => Some((2, true)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Iterator for Strings<'a> {
+    type Item = RomString<'a>;
+
+    fn next(&mut self) -> Option<RomString<'a>> {
+        while self.position < self.data.len() {
+            let start = self.position;
+            let (mut end, mut chars, mut wide) = (start, 0, false);
+            while let Some((len, is_wide)) = self.char_at(end) {
+                end += len;
+                chars += 1;
+                wide |= is_wide;
+            }
+
+            self.position = core::cmp::max(end, start + 1);
+            if chars > 0 && chars >= self.options.min_len {
+                let encoding = if wide { StringEncoding::ShiftJis } else { StringEncoding::Ascii };
+                return Some(RomString { offset: self.base + start, bytes: &self.data[start..end], encoding, chars });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "ZELDA" then ゼルダ in Shift-JIS, between binary.
+    const DATA: &[u8] = b"\x00\x01ZELDA\x00\xff\x83\x5b\x83\x8b\x83\x5f\x00ab\x00";
+
+    #[test]
+    fn finds_ascii_and_shift_jis() {
+        let found: Vec<RomString> = strings(DATA, StringOptions { min_len: 3, shift_jis: true }).collect();
+        assert_eq!(found.len(), 2);
+        assert_eq!((found[0].offset, found[0].as_ascii()), (2, Some("ZELDA")));
+        assert_eq!(found[1].offset, 9);
+        assert_eq!((found[1].encoding, found[1].chars, found[1].bytes.len()), (StringEncoding::ShiftJis, 3, 6));
+        assert_eq!(found[1].as_ascii(), None);
+        #[cfg(feature = "encoding")]
+        assert_eq!(found[1].decode(), "ゼルダ");
+    }
+
+    #[test]
+    fn respects_the_options() {
+        let ascii_only: Vec<RomString> = strings(DATA, StringOptions { min_len: 2, shift_jis: false }).collect();
+        assert_eq!(ascii_only.iter().map(|found| found.as_ascii().unwrap()).collect::<Vec<_>>(), ["ZELDA", "ab"]);
+        assert_eq!(strings(DATA, StringOptions::default()).count(), 1);
+        assert_eq!(strings(DATA, StringOptions { min_len: 0, shift_jis: true }).count(), 3);
+    }
+}
@@ -0,0 +1,143 @@
+use core::fmt;
+
+use crate::{Error, RomHeader, DEFAULT_CART_TIMING, DEFAULT_CLOCK_RATE};
+
+/// The bits of the clock rate word the IPL3 reads; the low nibble is
+/// ignored, and retail carts leave it set.
+pub const CLOCK_RATE_MASK: u32 = 0xffff_fff0;
+
+/// The PI bus timing for cart domain 1 that the first header word holds.
+/// The boot code reads the header's first word with slow, safe timing, then
+/// programs the `PI_BSD_DOM1_*` registers from it before reading anything
+/// else.  Fields are kept as the raw register values; the cycle counts the
+/// PI actually waits are one more than each.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PiTiming {
+    /// The top byte, 0x80 on every known cart.  It isn't a timing value,
+    /// but byte order detection leans on it.
+    pub marker: u8,
+    /// Cycles between pulses, the high nibble of byte 1 (`PI_BSD_DOM1_RLS`).
+    pub release: u8,
+    /// Page size as log2 of the size in words, the low nibble of byte 1
+    /// (`PI_BSD_DOM1_PGS`).
+    pub page_size: u8,
+    /// Cycles each read pulse lasts, byte 2 (`PI_BSD_DOM1_PWD`).
+    pub pulse_width: u8,
+    /// Cycles from address to first data, byte 3 (`PI_BSD_DOM1_LAT`).
+    pub latency: u8,
+}
+
+impl PiTiming {
+    pub fn from_word(word: u32) -> PiTiming {
+        let bytes = word.to_be_bytes();
+        PiTiming {
+            marker: bytes[0],
+            release: bytes[1] >> 4,
+            page_size: bytes[1] & 0x0f,
+            pulse_width: bytes[2],
+            latency: bytes[3],
+        }
+    }
+
+    /// The header word, with `release` and `page_size` cut to a nibble each.
+    pub fn to_word(&self) -> u32 {
+        let byte1 = (self.release & 0x0f) << 4 | (self.page_size & 0x0f);
+        u32::from_be_bytes([self.marker, byte1, self.pulse_width, self.latency])
+    }
+
+    /// Bytes per page, within which reads keep going without a new latency.
+    pub fn page_bytes(&self) -> usize {
+        4 << (self.page_size & 0x0f)
+    }
+}
+
+impl Default for PiTiming {
+    fn default() -> PiTiming {
+        PiTiming::from_word(DEFAULT_CART_TIMING)
+    }
+}
+
+impl fmt::Display for PiTiming {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "latency {}, pulse width {}, release {}, {} byte pages",
+               u16::from(self.latency) + 1, u16::from(self.pulse_width) + 1, self.release + 1, self.page_bytes())
+    }
+}
+
+impl RomHeader {
+    /// `cart_timing` decoded.
+    pub fn pi_timing(&self) -> PiTiming {
+        PiTiming::from_word(self.cart_timing)
+    }
+
+    pub fn set_pi_timing(&mut self, timing: &PiTiming) {
+        self.cart_timing = timing.to_word();
+    }
+
+    /// The CPU count register rate in Hz that `clock_rate` asks for, or
+    /// `None` when its masked value is zero and the console's default
+    /// applies, as it does on nearly every cart.
+    pub fn clock_rate_override(&self) -> Option<u32> {
+        match self.clock_rate & CLOCK_RATE_MASK {
+            0 => None,
+            rate => Some(rate),
+        }
+    }
+
+    pub fn uses_default_clock_rate(&self) -> bool {
+        self.clock_rate_override().is_none()
+    }
+
+    /// Stores a clock rate override, or the retail `0x0000000f` for `None`.
+    /// The ignored low nibble is kept set like retail carts have it, so a
+    /// rate that isn't a multiple of 16 would be silently changed and is
+    /// rejected instead.
+    pub fn set_clock_rate_override(&mut self, rate: Option<u32>) -> Result<(), Error> {
+        self.clock_rate = match rate {
+            None => DEFAULT_CLOCK_RATE,
+            Some(0) => return Err(Error::InvalidHeader("a zero clock rate means the default")),
+            Some(rate) if rate & !CLOCK_RATE_MASK != 0 => {
+                return Err(Error::InvalidHeader("clock rate overrides must be multiples of 16"));
+            },
+            Some(rate) => rate | !CLOCK_RATE_MASK,
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_retail_timing() {
+        let timing = RomHeader::new().pi_timing();
+        assert_eq!(timing, PiTiming { marker: 0x80, release: 3, page_size: 7, pulse_width: 0x12, latency: 0x40 });
+        assert_eq!(timing.page_bytes(), 512);
+        assert_eq!(timing.to_string(), "latency 65, pulse width 19, release 4, 512 byte pages");
+    }
+
+    #[test]
+    fn encodes_into_the_header() {
+        let mut header = RomHeader::new();
+        let timing = PiTiming { latency: 0x05, release: 2, ..PiTiming::default() };
+        header.set_pi_timing(&timing);
+        assert_eq!(header.cart_timing, 0x80271205);
+        assert_eq!(PiTiming::from_word(header.cart_timing), timing);
+    }
+
+    #[test]
+    fn masks_the_clock_rate() {
+        let mut header = RomHeader::new();
+        assert!(header.uses_default_clock_rate());
+
+        header.set_clock_rate_override(Some(46_875_008)).unwrap();
+        assert_eq!(header.clock_rate, 46_875_008 | 0xf);
+        assert_eq!(header.clock_rate_override(), Some(46_875_008));
+
+        assert!(matches!(header.set_clock_rate_override(Some(46_875_000)), Err(Error::InvalidHeader(_))));
+        assert!(matches!(header.set_clock_rate_override(Some(0)), Err(Error::InvalidHeader(_))));
+        header.set_clock_rate_override(None).unwrap();
+        assert_eq!(header.clock_rate, DEFAULT_CLOCK_RATE);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::BOOTCODE_END;
+
+/// Shortest tail worth checking for a mirror of the data before it; smaller
+/// repeats are as likely to be level data as address decoding.
+const MIN_MIRROR_LEN: usize = 0x10000;
+
+/// What an overdump's tail was filled with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TailPadding {
+    /// A run of a single byte, normally 0x00 or 0xff.
+    Fill(u8),
+    /// A repeat of the data before it, from the dumper reading past the end
+    /// of the ROM chips.
+    Mirror,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrimReport {
+    /// Length of the image without the padding, rounded up to a whole word.
+    pub content_len: usize,
+    /// The outermost padding found, if any.
+    pub padding: Option<TailPadding>,
+}
+
+/// How an image's length compares to the size it's known to have.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DumpSize {
+    Exact,
+    Underdump { missing: u64 },
+    /// Longer than expected, but only by padding, so safe to trim.
+    Overdump { extra: u64 },
+    /// Longer than expected with content past the expected end.
+    Mismatch,
+}
+
+/// Scans the tail of an image (in any byte order) for fill and mirrored data
+/// to find its likely real size.  Never trims into the bootcode.
+pub fn detect_trim(buffer: &[u8]) -> TrimReport {
+    let mut len = buffer.len();
+    let mut padding = None;
+
+    loop {
+        let found = mirror_len(&buffer[..len]).map(|mirror| (len - mirror, TailPadding::Mirror))
+            .or_else(|| fill_len(&buffer[..len]).map(|(fill, byte)| (len - fill, TailPadding::Fill(byte))));
+
+        match found {
+            Some((content_len, kind)) if content_len < len => {
+                len = content_len;
+                padding = padding.or(Some(kind));
+            },
+            _ => break,
+        }
+    }
+
+    TrimReport { content_len: len, padding }
+}
+
+/// The image without any tail padding.
+pub fn trimmed(buffer: &[u8]) -> &[u8] {
+    &buffer[..detect_trim(buffer).content_len]
+}
+
+/// Compares an image's length against the size it's known to have, such as
+/// a database entry's.
+pub fn check_dump_size(buffer: &[u8], expected_len: u64) -> DumpSize {
+    let len = buffer.len() as u64;
+
+    if len == expected_len {
+        DumpSize::Exact
+    } else if len < expected_len {
+        DumpSize::Underdump { missing: expected_len - len }
+    } else if (detect_trim(buffer).content_len as u64) <= expected_len {
+        DumpSize::Overdump { extra: len - expected_len }
+    } else {
+        DumpSize::Mismatch
+    }
+}
+
+// Length of the largest power of two tail that starts on a multiple of its
+// own size and repeats the data right before it.  Uniform tails are left for
+// `fill_len`.
+fn mirror_len(buffer: &[u8]) -> Option<usize> {
+    let len = buffer.len();
+    if len / 2 == 0 {
+        return None;
+    }
+
+    let mut tail = 1usize << (usize::BITS - 1 - (len / 2).leading_zeros());
+    while tail >= MIN_MIRROR_LEN {
+        let start = len - tail;
+        if start >= BOOTCODE_END
+            && start.is_multiple_of(tail)
+            && buffer[start..] == buffer[start - tail..start]
+            && !buffer[start..].iter().all(|byte| *byte == buffer[start])
+        {
+            return Some(tail);
+        }
+        tail /= 2;
+    }
+    None
+}
+
+// Length and byte of the run at the end of the buffer, down to a whole word
+// and never into the bootcode.
+fn fill_len(buffer: &[u8]) -> Option<(usize, u8)> {
+    let byte = *buffer.last()?;
+    if byte != 0x00 && byte != 0xff {
+        return None;
+    }
+
+    let floor = BOOTCODE_END.min(buffer.len());
+    let run = buffer[floor..].iter().rev().take_while(|b| **b == byte).count();
+    let content_len = (buffer.len() - run + 3) & !3;
+
+    if content_len >= buffer.len() {
+        None
+    } else {
+        Some((buffer.len() - content_len, byte))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::test_cart;
+
+    #[test]
+    fn detects_fill() {
+        let mut rom = test_cart();
+        let content_len = rom.len();
+        rom.resize(content_len + 0x1234, 0xff);
+
+        let report = detect_trim(&rom);
+        assert_eq!(report, TrimReport { content_len, padding: Some(TailPadding::Fill(0xff)) });
+        assert_eq!(trimmed(&rom).len(), content_len);
+
+        // Runs that end mid word keep the rest of the word.
+        let mut rom = test_cart();
+        rom[content_len - 2..].copy_from_slice(&[0, 0]);
+        rom.resize(content_len + 8, 0);
+        assert_eq!(detect_trim(&rom).content_len, content_len);
+    }
+
+    #[test]
+    fn detects_mirrors() {
+        let mut rom = test_cart();
+        rom.truncate(0x18_0000.min(rom.len()));
+        rom.resize(0x18_0000, 0x11);
+        for (ii, byte) in rom.iter_mut().enumerate().skip(0x10_0000) {
+            *byte = (ii.wrapping_mul(2654435761) >> 13) as u8;
+        }
+
+        // Pad 1.5 MiB out to 2 MiB the way an overdump of a 12/16 chip pair
+        // does, with the last half megabyte repeating the one before it.
+        let mut overdump = rom.clone();
+        overdump.extend_from_within(0x10_0000..0x18_0000);
+        let report = detect_trim(&overdump);
+        assert_eq!(report, TrimReport { content_len: rom.len(), padding: Some(TailPadding::Mirror) });
+
+        // Doubled images halve, then the fill underneath is still found.
+        let mut filled = test_cart();
+        let content_len = filled.len();
+        filled.resize(0x20_0000, 0);
+        filled.extend_from_within(..);
+        let report = detect_trim(&filled);
+        assert_eq!(report, TrimReport { content_len, padding: Some(TailPadding::Mirror) });
+    }
+
+    #[test]
+    fn leaves_clean_images_alone() {
+        let rom = test_cart();
+        assert_eq!(detect_trim(&rom), TrimReport { content_len: rom.len(), padding: None });
+
+        let blank = [0xffu8; BOOTCODE_END + 16];
+        assert_eq!(detect_trim(&blank).content_len, BOOTCODE_END);
+        assert_eq!(detect_trim(&[]).content_len, 0);
+    }
+
+    #[test]
+    fn checks_against_known_sizes() {
+        let mut rom = test_cart();
+        let content_len = rom.len() as u64;
+        assert_eq!(check_dump_size(&rom, content_len), DumpSize::Exact);
+        assert_eq!(check_dump_size(&rom, content_len + 4), DumpSize::Underdump { missing: 4 });
+        assert_eq!(check_dump_size(&rom, content_len - 4), DumpSize::Mismatch);
+
+        rom.resize(rom.len() + 0x100, 0xff);
+        assert_eq!(check_dump_size(&rom, content_len), DumpSize::Overdump { extra: 0x100 });
+    }
+}
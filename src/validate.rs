@@ -0,0 +1,228 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{
+    calculate_cart_checksum_with_params, identify_bootcode, Bootcode, ChecksumParams, CicVariant, CountryCode, RomHeader,
+    RomLayout, BOOTCODE_END, BOOTCODE_LEN, BOOTCODE_START, CART_SIZES, DEFAULT_CART_TIMING, SC64_MAX_ROM_LEN,
+};
+
+/// Where in RDRAM, through either unmapped segment, a payload can be loaded.
+const LOAD_WINDOWS: [(u32, u32); 2] = [(0x8000_0000, 0x8080_0000), (0xa000_0000, 0xa080_0000)];
+
+/// How much a [`Diagnostic`] matters.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    /// Unusual, but the image may well boot.
+    Warning,
+    /// The image likely won't boot, or isn't what it claims to be.
+    Error,
+}
+
+/// A problem found by [`Rom::validate`](crate::Rom::validate).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Diagnostic {
+    /// The PI timing word isn't the usual `0x80371240`.
+    BadMagic { found: u32 },
+    /// The stored CRCs aren't the ones `variant`'s IPL3 expects.  Reported
+    /// for the known IPL3's CIC, or for every CIC when the bootcode is
+    /// unknown and none match.
+    CrcMismatch { variant: CicVariant, expected_crc1: u32, expected_crc2: u32 },
+    /// The bootcode isn't a known IPL3.
+    UnknownBootcode,
+    /// The load address is outside RDRAM or not word aligned.
+    ImplausibleLoadAddress(u32),
+    /// The name holds bytes other than printable ASCII and padding; usually
+    /// a Japanese title, but sometimes corruption.
+    NonAsciiName,
+    UnknownCountryCode(u8),
+    /// The image ends before the region the IPL3 checksums.
+    Truncated { len: usize, needed: usize },
+    /// The length isn't a whole number of 32 bit words.
+    OddLength(usize),
+    /// The image is larger than the biggest retail cart, so it only runs
+    /// from a flashcart with extended ROM, or, past
+    /// [`SC64_MAX_ROM_LEN`], not at all.
+    Oversized(usize),
+}
+
+impl Diagnostic {
+    pub fn severity(&self) -> Severity {
+        match *self {
+            Diagnostic::BadMagic { .. } |
+            Diagnostic::CrcMismatch { .. } |
+            Diagnostic::ImplausibleLoadAddress(_) |
+            Diagnostic::Truncated { .. } => Severity::Error,
+            Diagnostic::Oversized(len) if len > SC64_MAX_ROM_LEN => Severity::Error,
+            Diagnostic::UnknownBootcode |
+            Diagnostic::NonAsciiName |
+            Diagnostic::UnknownCountryCode(_) |
+            Diagnostic::OddLength(_) |
+            Diagnostic::Oversized(_) => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Diagnostic::BadMagic { found } => write!(f, "unexpected PI timing word 0x{:08x}", found),
+            Diagnostic::CrcMismatch { variant, expected_crc1, expected_crc2 } => {
+                write!(f, "CRCs don't match {}: expected 0x{:08x} 0x{:08x}", variant, expected_crc1, expected_crc2)
+            },
+            Diagnostic::UnknownBootcode => write!(f, "unknown bootcode"),
+            Diagnostic::ImplausibleLoadAddress(addr) => write!(f, "implausible load address 0x{:08x}", addr),
+            Diagnostic::NonAsciiName => write!(f, "name is not plain ASCII"),
+            Diagnostic::UnknownCountryCode(code) => write!(f, "unknown country code 0x{:02x}", code),
+            Diagnostic::Truncated { len, needed } => write!(f, "image truncated: {} bytes, needs {}", len, needed),
+            Diagnostic::OddLength(len) => write!(f, "length {} is not a multiple of 4", len),
+            Diagnostic::Oversized(len) => write!(f, "{} bytes is larger than any retail cart", len),
+        }
+    }
+}
+
+// `data` is the native order image; its header bytes may be stale, so the
+// fields come from `header`.
+pub(crate) fn diagnose(header: &RomHeader, data: &[u8], layout: &RomLayout) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if header.cart_timing != DEFAULT_CART_TIMING {
+        diagnostics.push(Diagnostic::BadMagic { found: header.cart_timing });
+    }
+
+    let load_addr = header.load_addr;
+    let in_window = LOAD_WINDOWS.iter().any(|&(start, end)| load_addr >= start && load_addr < end);
+    if !in_window || !load_addr.is_multiple_of(4) {
+        diagnostics.push(Diagnostic::ImplausibleLoadAddress(load_addr));
+    }
+
+    let name_ok = header.name.iter().all(|byte| byte.is_ascii_graphic() || *byte == b' ' || *byte == 0);
+    if !name_ok {
+        diagnostics.push(Diagnostic::NonAsciiName);
+    }
+
+    if let CountryCode::Unknown(code) = header.country() {
+        diagnostics.push(Diagnostic::UnknownCountryCode(code));
+    }
+
+    if !data.len().is_multiple_of(4) {
+        diagnostics.push(Diagnostic::OddLength(data.len()));
+    }
+    if data.len() > CART_SIZES[CART_SIZES.len() - 1] {
+        diagnostics.push(Diagnostic::Oversized(data.len()));
+    }
+
+    let mut bootcode: Bootcode = [0u8; BOOTCODE_LEN as usize];
+    bootcode.copy_from_slice(&data[BOOTCODE_START..BOOTCODE_END]);
+    let ipl3 = identify_bootcode(&bootcode);
+    if ipl3.is_none() {
+        diagnostics.push(Diagnostic::UnknownBootcode);
+    }
+
+    if data.len() < layout.checksum_end() {
+        diagnostics.push(Diagnostic::Truncated { len: data.len(), needed: layout.checksum_end() });
+        return diagnostics;
+    }
+
+    let mismatch = |variant: CicVariant| {
+        let params = ChecksumParams::for_layout(variant, layout);
+        let (expected_crc1, expected_crc2) = calculate_cart_checksum_with_params(&params, data).ok()?;
+        if (expected_crc1, expected_crc2) == (header.crc1, header.crc2) {
+            None
+        } else {
+            Some(Diagnostic::CrcMismatch { variant, expected_crc1, expected_crc2 })
+        }
+    };
+
+    match ipl3 {
+        Some(ipl3) if ipl3.verifies_crcs() => diagnostics.extend(mismatch(ipl3.cic())),
+        Some(_) => {},
+        None => {
+            let mismatches: Vec<Diagnostic> = CicVariant::ALL.iter().filter_map(|variant| mismatch(*variant)).collect();
+            if mismatches.len() == CicVariant::ALL.len() {
+                diagnostics.extend(mismatches);
+            }
+        },
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::{stamp_cic, test_cart};
+    use crate::{fix_cart_checksum, Rom, HEADER_END, HEADER_START, ROM_LEN};
+
+    fn good_cart() -> Vec<u8> {
+        let mut cart = test_cart();
+        let mut header = RomHeader::new();
+        header.load_addr = 0x80000400;
+        header.set_name("VALID").unwrap();
+        header.set_game_id(&"NVAE".parse().unwrap());
+        cart[HEADER_START..HEADER_END].copy_from_slice(&header.to_bytes());
+        stamp_cic(&mut cart, CicVariant::Cic6102);
+        fix_cart_checksum(&mut cart).unwrap();
+        cart
+    }
+
+    #[test]
+    fn clean_image_has_no_diagnostics() {
+        assert_eq!(Rom::from_bytes(good_cart()).unwrap().validate(), vec![]);
+    }
+
+    #[test]
+    fn reports_header_problems() {
+        let mut rom = Rom::from_bytes(good_cart()).unwrap();
+        rom.header_mut().load_addr = 0x00001000;
+        rom.header_mut().name[0] = 0xb1;
+        rom.header_mut().country_code = 0x0100;
+        rom.header_mut().crc1 ^= 1;
+
+        let diagnostics = rom.validate();
+        assert_eq!(diagnostics.len(), 4);
+        assert!(diagnostics.contains(&Diagnostic::ImplausibleLoadAddress(0x00001000)));
+        assert!(diagnostics.contains(&Diagnostic::NonAsciiName));
+        assert!(diagnostics.contains(&Diagnostic::UnknownCountryCode(0x01)));
+        assert_eq!(diagnostics.iter().filter(|diagnostic| diagnostic.severity() == Severity::Error).count(), 2);
+        assert!(diagnostics.iter().any(|diagnostic| matches!(diagnostic,
+            Diagnostic::CrcMismatch { variant: CicVariant::Cic6102, .. })));
+    }
+
+    #[test]
+    fn reports_every_cic_for_unknown_bootcode() {
+        let mut cart = good_cart();
+        cart[BOOTCODE_START] ^= 0xff;
+
+        // The 6102's CRCs don't cover the bootcode, so they still match.
+        let mut rom = Rom::from_bytes(cart).unwrap();
+        assert_eq!(rom.validate(), vec![Diagnostic::UnknownBootcode]);
+
+        rom.header_mut().crc2 ^= 1;
+        let diagnostics = rom.validate();
+        assert_eq!(diagnostics[0], Diagnostic::UnknownBootcode);
+        assert_eq!(diagnostics.len(), 1 + CicVariant::ALL.len());
+    }
+
+    #[test]
+    fn reports_oversized_images() {
+        let mut cart = good_cart();
+        cart.resize(CART_SIZES[CART_SIZES.len() - 1] + 0x1000, 0);
+        assert_eq!(Rom::from_bytes(cart.clone()).unwrap().validate(), vec![Diagnostic::Oversized(cart.len())]);
+        assert_eq!(Diagnostic::Oversized(cart.len()).severity(), Severity::Warning);
+        assert_eq!(Diagnostic::Oversized(SC64_MAX_ROM_LEN + 4).severity(), Severity::Error);
+    }
+
+    #[test]
+    fn reports_truncated_images() {
+        let mut cart = good_cart();
+        cart.truncate(ROM_LEN / 2 + 2);
+
+        let diagnostics = Rom::from_bytes(cart).unwrap().validate();
+        assert!(diagnostics.contains(&Diagnostic::OddLength(ROM_LEN / 2 + 2)));
+        assert!(diagnostics.contains(&Diagnostic::Truncated { len: ROM_LEN / 2 + 2, needed: ROM_LEN }));
+        assert_eq!(Diagnostic::Truncated { len: 4, needed: 8 }.to_string(), "image truncated: 4 bytes, needs 8");
+    }
+}
@@ -0,0 +1,162 @@
+//! wasm-bindgen wrappers for browser based ROM tools, so images can be
+//! inspected and fixed entirely client side.
+//!
+//! Byte orders are named by their usual file extensions: `"z64"`, `"v64"`
+//! and `"n64"`.  Errors surface to JavaScript as thrown `Error`s carrying
+//! this crate's messages.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{ByteSwapping, Error, RomInfo};
+
+/// What [`analyze`] found out about an image.
+#[wasm_bindgen(js_name = RomInfo)]
+pub struct JsRomInfo {
+    info: RomInfo,
+}
+
+#[wasm_bindgen(js_class = RomInfo)]
+impl JsRomInfo {
+    /// The header's name with trailing padding removed.
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.info.header.name_str().trim_end().to_string()
+    }
+
+    #[wasm_bindgen(getter, js_name = gameCode)]
+    pub fn game_code(&self) -> String {
+        self.info.header.game_id().to_string()
+    }
+
+    /// The image's byte order as a file extension.
+    #[wasm_bindgen(getter)]
+    pub fn swapping(&self) -> String {
+        self.info.swapping.swapping.file_extension().to_string()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn ipl3(&self) -> Option<String> {
+        self.info.ipl3.map(|ipl3| ipl3.to_string())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn cic(&self) -> Option<String> {
+        self.info.cic.map(|cic| cic.to_string())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn platform(&self) -> String {
+        self.info.platform.to_string()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn country(&self) -> String {
+        self.info.country.to_string()
+    }
+
+    #[wasm_bindgen(getter, js_name = tvStandard)]
+    pub fn tv_standard(&self) -> Option<String> {
+        self.info.tv_standard.map(|standard| standard.to_string())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn version(&self) -> u8 {
+        self.info.header.version()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.info.size
+    }
+
+    #[wasm_bindgen(getter, js_name = cartSize)]
+    pub fn cart_size(&self) -> Option<usize> {
+        self.info.cart_size
+    }
+
+    #[wasm_bindgen(getter, js_name = saveType)]
+    pub fn save_type(&self) -> Option<String> {
+        self.info.save.as_ref().map(|save| save.save_type.to_string())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn crc1(&self) -> u32 {
+        self.info.header.crc1
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn crc2(&self) -> u32 {
+        self.info.header.crc2
+    }
+
+    /// Whether the stored CRCs are the ones the image boots with, or
+    /// `undefined` if it's too short to checksum.
+    #[wasm_bindgen(getter, js_name = checksumValid)]
+    pub fn checksum_valid(&self) -> Option<bool> {
+        self.info.checksum.as_ref().map(|report| report.is_valid())
+    }
+}
+
+/// Fingerprints an image in any byte order.
+#[wasm_bindgen]
+pub fn analyze(data: &[u8]) -> Result<JsRomInfo, JsError> {
+    crate::analyze(data).map(|info| JsRomInfo { info }).map_err(js_error)
+}
+
+/// Converts the image in place to the byte order named by `extension`.
+#[wasm_bindgen(js_name = swapCartTo)]
+pub fn swap_cart_to(data: &mut [u8], extension: &str) -> Result<(), JsError> {
+    let swapping = ByteSwapping::from_file_extension(extension).ok_or_else(|| JsError::new("unknown byte order"))?;
+    crate::swap_cart_to(swapping, data).map_err(js_error)
+}
+
+/// Recalculates and stores the image's CRCs in place, returning the new
+/// `[crc1, crc2]`.
+#[wasm_bindgen(js_name = fixCartChecksum)]
+pub fn fix_cart_checksum(data: &mut [u8]) -> Result<Box<[u32]>, JsError> {
+    let (crc1, crc2) = crate::fix_cart_checksum(data).map_err(|err| js_error(err.into()))?;
+    Ok(Box::new([crc1, crc2]))
+}
+
+fn js_error(err: Error) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::{stamp_cic, test_cart};
+    use crate::CicVariant;
+
+    // Only the success paths can run off wasm; building a `JsError` calls
+    // into JavaScript.
+
+    #[test]
+    fn analyzes_images() {
+        let mut cart = test_cart();
+        stamp_cic(&mut cart, CicVariant::Cic6102);
+        crate::swap_cart_to(ByteSwapping::U16LittleEndian, &mut cart).unwrap();
+
+        let info = analyze(&cart).unwrap();
+        assert_eq!(info.swapping(), "v64");
+        assert_eq!(info.cic().as_deref(), Some("CIC-NUS-6102"));
+        assert_eq!(info.size(), cart.len());
+        assert_eq!(info.checksum_valid(), Some(false));
+    }
+
+    #[test]
+    fn swaps_and_fixes_images() {
+        let mut cart = test_cart();
+        stamp_cic(&mut cart, CicVariant::Cic6102);
+        let expected = crate::calculate_cart_checksum(&cart).unwrap();
+
+        swap_cart_to(&mut cart, ".n64").unwrap();
+        assert_eq!(&*fix_cart_checksum(&mut cart).unwrap(), &[expected.0, expected.1]);
+        swap_cart_to(&mut cart, "Z64").unwrap();
+        assert_eq!(analyze(&cart).unwrap().checksum_valid(), Some(true));
+    }
+}